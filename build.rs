@@ -0,0 +1,14 @@
+use std::env;
+
+/// Exposes the target triple and the enabled cargo features to `--version`
+fn main() {
+    let target = env::var("TARGET").unwrap_or_default();
+    println!("cargo:rustc-env=RGREP_TARGET={}", target);
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|name| name.to_lowercase().replace('_', "-")))
+        .filter(|name| name != "default")
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=RGREP_FEATURES={}", features.join(","));
+}
@@ -0,0 +1,15 @@
+//! Arbitrary command lines, one argument per line of the input
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rgrep::args::CommandArgs;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let mut args = vec![String::from("rgrep")];
+    // these print and exit the process
+    let exits = ["-h", "--help", "-V", "--version"];
+    args.extend(text.lines().filter(|arg| !exits.contains(arg)).map(String::from));
+    let _ = CommandArgs::parse_from(args);
+});
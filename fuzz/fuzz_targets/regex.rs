@@ -0,0 +1,24 @@
+//! A pattern, then a NUL byte, then a haystack. Matches have to be within the haystack and agree with
+//! each other.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rgrep::matcher::regex::RegexMatcher;
+use rgrep::matcher::Matcher;
+
+fuzz_target!(|data: &[u8]| {
+    let split = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    let pattern = String::from_utf8_lossy(&data[..split]);
+    let haystack = data.get(split + 1..).unwrap_or(&[]);
+    let matcher = match RegexMatcher::new(&pattern, false) {
+        Ok(matcher) => matcher,
+        Err(_) => return,
+    };
+    let mut previous_end = 0;
+    for found in matcher.find_iter(haystack) {
+        assert!(previous_end <= found.start && found.start <= found.end && found.end <= haystack.len());
+        assert_eq!(matcher.captures_at(haystack, found.start).and_then(|captures| captures.get(0)), Some(found));
+        previous_end = found.end;
+    }
+});
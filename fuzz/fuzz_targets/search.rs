@@ -0,0 +1,22 @@
+//! A pattern, then a NUL byte, then an input. Every line the searcher reports has to match.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rgrep::matcher::{self, MatcherOptions};
+use rgrep::search::{BinaryDetection, Input, SearcherBuilder};
+
+fuzz_target!(|data: &[u8]| {
+    let split = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    let pattern = String::from_utf8_lossy(&data[..split]).into_owned();
+    let input = data.get(split + 1..).unwrap_or(&[]);
+    let matcher = match matcher::build(&[pattern], &MatcherOptions::default()) {
+        Ok(matcher) => matcher,
+        Err(_) => return,
+    };
+    let searcher = SearcherBuilder::new().binary_detection(BinaryDetection::Text).build();
+    for found in searcher.matches(matcher.as_ref(), Input::Reader("input", Box::new(input))) {
+        let found = found.unwrap();
+        assert!(matcher.is_match(&found.line));
+    }
+});
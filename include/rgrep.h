@@ -0,0 +1,93 @@
+/*
+ * C interface to rgrep's matcher and searcher, implemented in src/ffi.rs.
+ *
+ * Build the library with the ffi feature, as a shared or static library in target/release:
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *     cargo rustc --release --lib --features ffi --crate-type staticlib
+ *
+ * Example:
+ *
+ *     char *error = NULL;
+ *     rgrep_matcher *matcher = rgrep_matcher_new("fo+", RGREP_IGNORE_CASE, &error);
+ *     if (matcher == NULL) {
+ *         fprintf(stderr, "%s\n", error);
+ *         rgrep_string_free(error);
+ *         return 2;
+ *     }
+ *     rgrep_matches *matches = rgrep_search(matcher, buffer, length, &error);
+ *     rgrep_match match;
+ *     while (rgrep_matches_next(matches, &match)) {
+ *         printf("%llu: %.*s\n", (unsigned long long)match.line_number, (int)match.line_len, match.line);
+ *     }
+ *     rgrep_matches_free(matches);
+ *     rgrep_matcher_free(matcher);
+ */
+
+#ifndef RGREP_H
+#define RGREP_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Letters match regardless of case */
+#define RGREP_IGNORE_CASE (1u << 0)
+/* Patterns are plain strings, not regular expressions */
+#define RGREP_FIXED_STRINGS (1u << 1)
+/* Patterns can match across lines */
+#define RGREP_MULTILINE (1u << 2)
+
+/* Compiled patterns and the settings to search with */
+typedef struct rgrep_matcher rgrep_matcher;
+
+/* Everything one search found */
+typedef struct rgrep_matches rgrep_matches;
+
+/* One match in a line. `line` stays valid until the matches are freed. */
+typedef struct rgrep_match {
+    uint64_t line_number;
+    /* Offset of the line in the buffer, after decoding */
+    uint64_t offset;
+    /* The line without its terminator, not NUL-terminated */
+    const uint8_t *line;
+    size_t line_len;
+    /* The match, relative to the start of the line */
+    size_t start;
+    size_t end;
+} rgrep_match;
+
+/*
+ * Compiles `pattern`, one pattern per line, with RGREP_* flags. Returns NULL on error and, if `error` is not
+ * NULL, stores a message there that is released with rgrep_string_free.
+ */
+rgrep_matcher *rgrep_matcher_new(const char *pattern, uint32_t flags, char **error);
+
+void rgrep_matcher_free(rgrep_matcher *matcher);
+
+/*
+ * Searches the `len` bytes at `haystack`, NUL bytes included. The buffer is not needed anymore once this returns.
+ * Returns NULL on error, like rgrep_matcher_new.
+ */
+rgrep_matches *rgrep_search(const rgrep_matcher *matcher, const uint8_t *haystack, size_t len, char **error);
+
+/*
+ * Stores the next match in `out` and returns true, or returns false once there are no more.
+ * The lines of a match that spans several lines are each returned as a whole.
+ */
+bool rgrep_matches_next(rgrep_matches *matches, rgrep_match *out);
+
+void rgrep_matches_free(rgrep_matches *matches);
+
+/* Releases an error message */
+void rgrep_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RGREP_H */
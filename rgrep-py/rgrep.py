@@ -0,0 +1,197 @@
+"""Python bindings for rgrep, a thin layer over the C interface declared in include/rgrep.h.
+
+Build the shared library first:
+
+    cargo rustc --release --lib --features ffi --crate-type cdylib
+
+The library is looked up at $RGREP_LIBRARY if it is set, and otherwise next to this module, where a package
+can bundle it, then in target/release and target/debug of the repository this module is in. See README.md.
+
+    import rgrep
+
+    pattern = rgrep.compile(r"fn \\w+", ignore_case=True)
+    for match in pattern.search_dir("src"):
+        print(match.path, match.line_number, match.text)
+"""
+
+import ctypes
+import os
+import sys
+from dataclasses import dataclass
+
+__all__ = ["Match", "Pattern", "RgrepError", "compile"]
+
+_IGNORE_CASE = 1 << 0
+_FIXED_STRINGS = 1 << 1
+_MULTILINE = 1 << 2
+
+
+class RgrepError(Exception):
+    """A pattern did not compile, or a search failed"""
+
+
+@dataclass(frozen=True)
+class Match:
+    """One match in a line"""
+
+    path: str
+    line_number: int
+    # offset of the line in the input, after decoding
+    offset: int
+    # the line without its terminator
+    line: bytes
+    # the match, relative to the start of the line
+    start: int
+    end: int
+
+    @property
+    def text(self):
+        """The line as text, with invalid UTF-8 replaced"""
+        return self.line.decode("utf-8", "replace")
+
+    @property
+    def matched(self):
+        """The bytes that matched"""
+        return self.line[self.start:self.end]
+
+
+class _RawMatch(ctypes.Structure):
+    _fields_ = [
+        ("line_number", ctypes.c_uint64),
+        ("offset", ctypes.c_uint64),
+        ("line", ctypes.POINTER(ctypes.c_uint8)),
+        ("line_len", ctypes.c_size_t),
+        ("start", ctypes.c_size_t),
+        ("end", ctypes.c_size_t),
+    ]
+
+
+def _library_name():
+    if sys.platform == "win32":
+        return "rgrep.dll"
+    if sys.platform == "darwin":
+        return "librgrep.dylib"
+    return "librgrep.so"
+
+
+def _candidates():
+    """Where the library is looked for, in order"""
+    if os.environ.get("RGREP_LIBRARY"):
+        # a library that was asked for by name is not swapped for another one
+        return [os.environ["RGREP_LIBRARY"]]
+    here = os.path.dirname(os.path.abspath(__file__))
+    candidates = [os.path.join(here, _library_name())]
+    for profile in ("release", "debug"):
+        candidates.append(os.path.join(os.path.dirname(here), "target", profile, _library_name()))
+    return candidates
+
+
+def _load():
+    candidates = _candidates()
+    for candidate in candidates:
+        if os.path.exists(candidate):
+            break
+    else:
+        if os.environ.get("RGREP_LIBRARY"):
+            raise RgrepError("RGREP_LIBRARY is set to {}, which does not exist".format(candidates[0]))
+        raise RgrepError("could not find {} in {}; build it with"
+                         " `cargo rustc --release --lib --features ffi --crate-type cdylib`"
+                         " in the rgrep repository, then copy it next to rgrep.py or set RGREP_LIBRARY to its path"
+                         .format(_library_name(), ", ".join(candidates)))
+
+    try:
+        library = ctypes.CDLL(candidate)
+    except OSError as error:
+        raise RgrepError("could not load {}: {}".format(candidate, error)) from error
+    error = ctypes.POINTER(ctypes.c_void_p)
+    library.rgrep_matcher_new.argtypes = [ctypes.c_char_p, ctypes.c_uint32, error]
+    library.rgrep_matcher_new.restype = ctypes.c_void_p
+    library.rgrep_matcher_free.argtypes = [ctypes.c_void_p]
+    library.rgrep_matcher_free.restype = None
+    library.rgrep_search.argtypes = [ctypes.c_void_p, ctypes.c_char_p, ctypes.c_size_t, error]
+    library.rgrep_search.restype = ctypes.c_void_p
+    library.rgrep_matches_next.argtypes = [ctypes.c_void_p, ctypes.POINTER(_RawMatch)]
+    library.rgrep_matches_next.restype = ctypes.c_bool
+    library.rgrep_matches_free.argtypes = [ctypes.c_void_p]
+    library.rgrep_matches_free.restype = None
+    # messages are plain pointers, c_char_p would copy them and lose the pointer that has to be freed
+    library.rgrep_string_free.argtypes = [ctypes.c_void_p]
+    library.rgrep_string_free.restype = None
+    return library
+
+
+_library = None
+
+
+def _lib():
+    global _library
+    if _library is None:
+        _library = _load()
+    return _library
+
+
+def _check(result, error):
+    if result:
+        return result
+    message = ctypes.cast(error, ctypes.c_char_p).value
+    _lib().rgrep_string_free(error)
+    raise RgrepError(message.decode("utf-8", "replace") if message else "unknown error")
+
+
+class Pattern:
+    """Compiled patterns. `pattern` holds one pattern per line, like `rgrep -e` given several times."""
+
+    def __init__(self, pattern, ignore_case=False, fixed_strings=False, multiline=False):
+        flags = 0
+        if ignore_case:
+            flags |= _IGNORE_CASE
+        if fixed_strings:
+            flags |= _FIXED_STRINGS
+        if multiline:
+            flags |= _MULTILINE
+        error = ctypes.c_void_p()
+        self._matcher = _check(_lib().rgrep_matcher_new(pattern.encode("utf-8"), flags, ctypes.byref(error)), error)
+
+    def __del__(self):
+        if getattr(self, "_matcher", None):
+            _lib().rgrep_matcher_free(self._matcher)
+            self._matcher = None
+
+    def search(self, data, path="buffer"):
+        """Returns the matches in `data`, bytes or str, as a list of Match reported under `path`"""
+        if isinstance(data, str):
+            data = data.encode("utf-8")
+        error = ctypes.c_void_p()
+        matches = _check(_lib().rgrep_search(self._matcher, data, len(data), ctypes.byref(error)), error)
+        found = []
+        try:
+            raw = _RawMatch()
+            while _lib().rgrep_matches_next(matches, ctypes.byref(raw)):
+                line = ctypes.string_at(raw.line, raw.line_len)
+                found.append(Match(path, raw.line_number, raw.offset, line, raw.start, raw.end))
+        finally:
+            _lib().rgrep_matches_free(matches)
+        return found
+
+    def search_file(self, path):
+        """Returns the matches in the file at `path`"""
+        with open(path, "rb") as file:
+            return self.search(file.read(), os.fspath(path))
+
+    def search_dir(self, path):
+        """Yields the matches in every file under `path`, in the order of the file names.
+        Files that cannot be read are skipped."""
+        for directory, directories, files in os.walk(path):
+            directories.sort()
+            for name in sorted(files):
+                file_path = os.path.join(directory, name)
+                try:
+                    found = self.search_file(file_path)
+                except OSError:
+                    continue
+                yield from found
+
+
+def compile(pattern, ignore_case=False, fixed_strings=False, multiline=False):
+    """Compiles `pattern` into a Pattern; raises RgrepError if it is not a valid regular expression"""
+    return Pattern(pattern, ignore_case=ignore_case, fixed_strings=fixed_strings, multiline=multiline)
@@ -0,0 +1,53 @@
+"""Tests of the bindings, against the library rgrep.py loads; build it first as README.md says"""
+
+import os
+import unittest
+from unittest import mock
+
+import rgrep
+
+TREE = os.path.join(os.path.dirname(os.path.dirname(os.path.abspath(__file__))), "tests", "golden", "tree")
+
+
+class SearchTest(unittest.TestCase):
+    def test_search(self):
+        found = rgrep.compile(r"fo\w+|dog").search("The quick brown fox\nthe lazy dog.\n")
+        self.assertEqual([(match.line_number, match.matched) for match in found], [(1, b"fox"), (2, b"dog")])
+        self.assertEqual(found[1].offset, 20)
+        self.assertEqual(found[1].text, "the lazy dog.")
+        self.assertEqual(found[0].path, "buffer")
+
+    def test_flags(self):
+        self.assertEqual(len(rgrep.compile("FOX", ignore_case=True).search("fox\nFox\n")), 2)
+        self.assertEqual(len(rgrep.compile("a.c", fixed_strings=True).search("abc\na.c\n")), 1)
+        self.assertEqual(len(rgrep.compile(r"fox\njumps", multiline=True).search("fox\njumps\n")), 2)
+
+    def test_bytes(self):
+        found = rgrep.compile("b").search(b"a\x00b\n\xff b\n")
+        self.assertEqual([match.line for match in found], [b"a\x00b", b"\xff b"])
+
+    def test_files(self):
+        found = rgrep.compile("quick").search_file(os.path.join(TREE, "poem.txt"))
+        self.assertEqual([match.line_number for match in found], [1, 4])
+        found = list(rgrep.compile("lazy dog").search_dir(TREE))
+        self.assertIn(os.path.join(TREE, "poem.txt"), [match.path for match in found])
+
+    def test_invalid_pattern(self):
+        with self.assertRaisesRegex(rgrep.RgrepError, "unclosed group"):
+            rgrep.compile("(a")
+
+
+class LibraryTest(unittest.TestCase):
+    def test_missing_library(self):
+        with mock.patch.dict(os.environ, {"RGREP_LIBRARY": "/nonexistent/librgrep.so"}):
+            with self.assertRaisesRegex(rgrep.RgrepError, "RGREP_LIBRARY is set to /nonexistent/librgrep.so"):
+                rgrep._load()
+
+    def test_not_built(self):
+        with mock.patch.dict(os.environ, {"RGREP_LIBRARY": ""}), mock.patch("os.path.exists", return_value=False):
+            with self.assertRaisesRegex(rgrep.RgrepError, "could not find .*--features ffi --crate-type cdylib"):
+                rgrep._load()
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,1974 @@
+use std::io::{self, IsTerminal};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+pub use crate::help::print_help;
+use crate::log;
+use crate::matcher::{builtin_pattern, Engine, MatcherOptions, MatchesPerLine, Syntax, BUILTIN_PATTERNS};
+use crate::output;
+use crate::search::{BinaryDetection, DirectoryAction, Encoding, Metrics, Order, Preprocessor, Region};
+use crate::sink::{ColorChoice, ColorSpec, Theme};
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandArgs {
+    pub files: Vec<String>,
+    /// `files` were listed by rgrep, like those of --changed-since, rather than named by the user: the ones that
+    /// are removed before they are read are skipped, not reported as errors
+    pub files_listed: bool,
+    /// Patterns to search for; a line matches if any of them matches
+    pub patterns: Vec<String>,
+    /// Names of the built-in patterns among `patterns`, for `--builtin`
+    pub builtins: Vec<String>,
+    /// Only report inputs in which every pattern matches
+    pub all_match: bool,
+    /// Stop reading an input at the first line that does not match after a matching one
+    pub stop_on_nonmatch: bool,
+    pub after_context: u32,
+    pub before_context: u32,
+    /// Print the paragraph every match is in as its context
+    pub paragraph: bool,
+    /// Print this many bytes around every match instead of whole matching lines
+    pub context_bytes: Option<u32>,
+    /// Print the line of the function or section every match is in before it
+    pub show_function: bool,
+    pub ignore_case: bool,
+    /// Patterns are plain strings instead of regular expressions
+    pub fixed_strings: bool,
+    /// The syntax of the patterns, `-G` or `-E`; `None` for that of the regex engine, or basic regular expressions
+    /// with `--posix`
+    pub syntax: Option<Syntax>,
+    /// What runs regular expressions
+    pub engine: Engine,
+    /// Which matches in a line are colored and printed, all of them or only the first
+    pub matches: MatchesPerLine,
+    /// Behave like POSIX grep in the options rgrep has of it: basic regular expressions, options only before the
+    /// first operand, and none of what rgrep does on top by default. It is not a drop-in `grep`, which also has
+    /// `-f`, `-l`, `-q`, `-v` and `-x`, and takes short options clustered, like `-in`.
+    pub posix: bool,
+    /// Match whole inputs at once, so matches can span lines
+    pub multiline: bool,
+    pub binary: BinaryDetection,
+    pub encoding: Encoding,
+    /// Man page or shell completion script to print instead of searching, empty if none
+    pub generate: String,
+    pub log_level: log::Level,
+    /// Print counters about the search when it ends
+    pub stats: bool,
+    /// Also count the time and bytes every input took, for --stats
+    pub stats_per_file: bool,
+    /// Print a line with the counts of --stats and the time taken on stderr at the end
+    pub summary: bool,
+    /// Do not show the progress of long searches on stderr
+    pub no_progress: bool,
+    /// Do not report inputs that cannot be read, only exit with status 2
+    pub no_messages: bool,
+    /// Bytes of an input held in memory at most, `None` for no limit
+    pub max_memory: Option<u64>,
+    /// Receives counters about every input as it is searched, for programs running searches; no option sets it
+    pub metrics: Option<Arc<dyn Metrics>>,
+    /// File recording the inputs searched so far, to go on from there when run again; empty for none
+    pub checkpoint: String,
+    /// Name printed for standard input, empty for the default
+    pub label: String,
+    /// Separator printed between the components of paths instead of the one they have
+    pub path_separator: Option<char>,
+    pub color: ColorChoice,
+    /// Colors to start from, which `GREP_COLORS` and `colors` change
+    pub theme: Theme,
+    /// Changes to the colors, `--colors`, applied in order after `GREP_COLORS`
+    pub colors: Vec<ColorSpec>,
+    /// Print the name of every input above its lines; `None` to do so when standard output is a terminal
+    pub heading: Option<bool>,
+    /// Add the number of matching lines of every input to its heading
+    pub heading_counts: bool,
+    /// Print the number of matching lines instead of the lines
+    pub count: bool,
+    /// Print how many lines and matches every pattern had instead of the lines
+    pub count_per_pattern: bool,
+    /// Leave inputs without matches out of counts
+    pub no_include_zero: bool,
+    /// Print results as JSON lines
+    pub json: bool,
+    /// Add the matches in every line to --json output, with their columns in UTF-16 code units too
+    pub utf16_columns: bool,
+    /// Add the groups of every match to --json and --byte-offsets output
+    pub captures: bool,
+    /// Matching lines printed per input at most, `None` for all of them
+    pub max_shown: Option<u32>,
+    /// Start printed lines with their line number
+    pub line_number: bool,
+    /// Columns line numbers are right-aligned in, 0 to print them as they are, or under headings, to align them to the
+    /// digits of the line count of their input
+    pub line_number_width: u32,
+    /// Print the column of the first match in matching lines after their line number, or of every match with -o
+    pub column: bool,
+    /// Print the byte offsets and text of every match instead of the lines
+    pub byte_offsets: bool,
+    /// Print every distinct match once instead of the lines, with how often it was found along with --count
+    pub unique: bool,
+    /// Print every match on a line of its own instead of the lines
+    pub only_matching: bool,
+    /// Text matches are replaced with in printed lines, `None` to print them as they are
+    pub replace: Option<String>,
+    /// Also write the replacements back to the files that matched
+    pub write_replace: bool,
+    /// Keep a copy of every file rewritten by --write-replace, named after it with `.bak` added
+    pub backup: bool,
+    /// Print what --write-replace would change as a diff instead of writing it
+    pub dry_run: bool,
+    /// Browse the results in a terminal UI instead of printing them
+    pub interactive: bool,
+    /// Keep running after the search and search the lines added to the files, and with -d recurse, the files created
+    pub watch: bool,
+    /// Answer searches sent on standard input or `socket` until stopped, instead of searching once
+    pub daemon: bool,
+    /// Unix socket --daemon listens on, empty for standard input
+    pub socket: String,
+    /// Connections to `socket` answered at once, `None` for [`daemon::MAX_CONNECTIONS`](crate::daemon::MAX_CONNECTIONS)
+    pub max_connections: Option<u32>,
+    /// Answer searches sent on standard input as JSON objects until stopped, for editor plugins
+    pub editor_server: bool,
+    /// Range of git revisions to search the files of instead of the working tree, empty for none
+    pub git_rev: String,
+    /// Only search the files changed since this git commit, empty to search all of them
+    pub changed_since: String,
+    /// `HOST:PATH` to run the search on with ssh, empty to search here
+    pub remote: String,
+    /// Only search files modified after this time
+    pub newer_than: Option<SystemTime>,
+    /// Only search files modified before this time
+    pub older_than: Option<SystemTime>,
+    /// What to do with inputs that are directories
+    pub directories: DirectoryAction,
+    /// Only search files whose names match one of these wildcard patterns, if there are any
+    pub include: Vec<String>,
+    /// Do not search files whose names match one of these wildcard patterns
+    pub exclude: Vec<String>,
+    /// Do not search the files found in directories that look minified or generated
+    pub skip_minified: bool,
+    /// Search the directories found that virtual file systems like procfs are mounted on
+    pub no_skip_virtual: bool,
+    /// Do not search `.git` directories, and what `.gitignore` and `.ignore` files ignore, in the directories walked
+    pub gitignore: bool,
+    /// Search a file again when it is given again, or reached through another link
+    pub no_dedupe: bool,
+    pub order: Order,
+    /// Commands whose output is searched instead of the files they are for
+    pub pre: Vec<Preprocessor>,
+    /// Only match in this part of source files, `None` to match anywhere
+    pub only: Option<Region>,
+}
+
+impl CommandArgs {
+    /// Parses a command line the way the binary does, starting with the command name, but without reading
+    /// the config file, `RGREP_OPTIONS` or process arguments. `--help`, `--version` and `--builtin list` are
+    /// [`Error::Help`], [`Error::Version`] and [`Error::ListBuiltins`], for the caller to print what they ask for.
+    ///
+    /// ```
+    /// use rgrep::args::CommandArgs;
+    /// use rgrep::error::Error;
+    ///
+    /// let command_args = CommandArgs::parse_from(["rgrep", "-i", "foo", "a.txt"]).unwrap();
+    /// assert!(command_args.ignore_case);
+    /// assert_eq!(command_args.patterns, ["foo"]);
+    /// assert_eq!(command_args.files, ["a.txt"]);
+    /// assert!(matches!(CommandArgs::parse_from(["rgrep", "foo", "--help"]), Err(Error::Help)));
+    /// ```
+    pub fn parse_from<I, T>(args: I) -> Result<CommandArgs>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut command_args = CommandArgs::default();
+        parse_args(args.into_iter().map(Into::into).collect(), &mut command_args)?;
+        Ok(command_args)
+    }
+
+    /// How to compile the patterns
+    pub fn matcher_options(&self) -> MatcherOptions {
+        let syntax = self.syntax.unwrap_or(if self.posix { Syntax::Basic } else { Syntax::Perl });
+        MatcherOptions {
+            fixed_strings: self.fixed_strings,
+            ignore_case: self.ignore_case,
+            syntax,
+            engine: self.engine,
+            matches: self.matches,
+        }
+    }
+
+    /// What POSIX grep does where rgrep has defaults of its own, for `--posix`: no colors unless asked for, no
+    /// headings, no progress, and a file named twice is searched twice
+    fn posix_defaults(&mut self) {
+        if self.color == ColorChoice::Auto {
+            self.color = ColorChoice::Never;
+        }
+        self.heading.get_or_insert(false);
+        self.no_progress = true;
+        self.no_dedupe = true;
+    }
+}
+
+/// Builds [`CommandArgs`] in code, for programs that call [`search::run`](crate::search::run) without a
+/// command line. Every setting starts out like rgrep without options.
+///
+/// ```
+/// use rgrep::args::CommandArgsBuilder;
+///
+/// let command_args = CommandArgsBuilder::new().pattern("foo").file("a.txt").context(1).count(true).build();
+/// assert_eq!(command_args.after_context, 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CommandArgsBuilder {
+    args: CommandArgs,
+}
+
+impl CommandArgsBuilder {
+    pub fn new() -> CommandArgsBuilder {
+        CommandArgsBuilder::default()
+    }
+
+    pub fn build(&self) -> CommandArgs {
+        self.args.clone()
+    }
+
+    /// Adds a pattern, like `-e`
+    pub fn pattern(&mut self, pattern: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.patterns.push(pattern.into());
+        self
+    }
+
+    /// Adds the built-in pattern `name`, like `--builtin`; returns `None` if there is none by that name
+    pub fn builtin(&mut self, name: &str) -> Option<&mut CommandArgsBuilder> {
+        self.args.patterns.push(String::from(builtin_pattern(name)?.pattern));
+        self.args.builtins.push(String::from(name));
+        Some(self)
+    }
+
+    pub fn all_match(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.all_match = yes;
+        self
+    }
+
+    pub fn stop_on_nonmatch(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.stop_on_nonmatch = yes;
+        self
+    }
+
+    /// Adds a file to search, `-` for standard input
+    pub fn file(&mut self, file: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.files.push(file.into());
+        self
+    }
+
+    pub fn files_listed(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.files_listed = yes;
+        self
+    }
+
+    pub fn after_context(&mut self, lines: u32) -> &mut CommandArgsBuilder {
+        self.args.after_context = lines;
+        self
+    }
+
+    pub fn before_context(&mut self, lines: u32) -> &mut CommandArgsBuilder {
+        self.args.before_context = lines;
+        self
+    }
+
+    /// Sets both the after and before context
+    pub fn context(&mut self, lines: u32) -> &mut CommandArgsBuilder {
+        self.after_context(lines).before_context(lines)
+    }
+
+    pub fn paragraph(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.paragraph = yes;
+        self
+    }
+
+    pub fn context_bytes(&mut self, bytes: Option<u32>) -> &mut CommandArgsBuilder {
+        self.args.context_bytes = bytes;
+        self
+    }
+
+    pub fn show_function(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.show_function = yes;
+        self
+    }
+
+    pub fn ignore_case(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.ignore_case = yes;
+        self
+    }
+
+    pub fn fixed_strings(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.fixed_strings = yes;
+        self
+    }
+
+    /// Like `-G` with `Syntax::Basic` and `-E` with `Syntax::Extended`
+    pub fn syntax(&mut self, syntax: Syntax) -> &mut CommandArgsBuilder {
+        self.args.syntax = Some(syntax);
+        self
+    }
+
+    pub fn engine(&mut self, engine: Engine) -> &mut CommandArgsBuilder {
+        self.args.engine = engine;
+        self
+    }
+
+    pub fn matches(&mut self, matches: MatchesPerLine) -> &mut CommandArgsBuilder {
+        self.args.matches = matches;
+        self
+    }
+
+    /// Like `--posix`, which also turns off colors, headings, progress and searching files once
+    pub fn posix(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.posix = yes;
+        if yes {
+            self.args.posix_defaults();
+        }
+        self
+    }
+
+    pub fn multiline(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.multiline = yes;
+        self
+    }
+
+    pub fn binary_detection(&mut self, binary: BinaryDetection) -> &mut CommandArgsBuilder {
+        self.args.binary = binary;
+        self
+    }
+
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut CommandArgsBuilder {
+        self.args.encoding = encoding;
+        self
+    }
+
+    pub fn stats(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.stats = yes;
+        self
+    }
+
+    pub fn stats_per_file(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.stats_per_file = yes;
+        self
+    }
+
+    pub fn summary(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.summary = yes;
+        self
+    }
+
+    pub fn no_progress(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.no_progress = yes;
+        self
+    }
+
+    pub fn no_messages(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.no_messages = yes;
+        self
+    }
+
+    pub fn max_memory(&mut self, bytes: u64) -> &mut CommandArgsBuilder {
+        self.args.max_memory = Some(bytes);
+        self
+    }
+
+    /// Reports every input searched, and every one that could not be, to `metrics`, like
+    /// [`SearcherBuilder::metrics`](crate::search::SearcherBuilder::metrics)
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut CommandArgsBuilder {
+        self.args.metrics = Some(metrics);
+        self
+    }
+
+    pub fn checkpoint(&mut self, path: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.checkpoint = path.into();
+        self
+    }
+
+    /// Name printed for standard input
+    pub fn label(&mut self, label: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.label = label.into();
+        self
+    }
+
+    pub fn path_separator(&mut self, separator: Option<char>) -> &mut CommandArgsBuilder {
+        self.args.path_separator = separator;
+        self
+    }
+
+    pub fn color(&mut self, color: ColorChoice) -> &mut CommandArgsBuilder {
+        self.args.color = color;
+        self
+    }
+
+    pub fn theme(&mut self, theme: Theme) -> &mut CommandArgsBuilder {
+        self.args.theme = theme;
+        self
+    }
+
+    /// Like `--colors SPEC`, can be called again for more
+    pub fn colors(&mut self, spec: ColorSpec) -> &mut CommandArgsBuilder {
+        self.args.colors.push(spec);
+        self
+    }
+
+    pub fn heading(&mut self, heading: Option<bool>) -> &mut CommandArgsBuilder {
+        self.args.heading = heading;
+        self
+    }
+
+    pub fn heading_counts(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.heading_counts = yes;
+        self
+    }
+
+    pub fn count(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.count = yes;
+        self
+    }
+
+    pub fn count_per_pattern(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.count_per_pattern = yes;
+        self
+    }
+
+    pub fn no_include_zero(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.no_include_zero = yes;
+        self
+    }
+
+    pub fn json(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.json = yes;
+        self
+    }
+
+    pub fn max_shown(&mut self, lines: Option<u32>) -> &mut CommandArgsBuilder {
+        self.args.max_shown = lines;
+        self
+    }
+
+    pub fn captures(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.captures = yes;
+        self
+    }
+
+    pub fn unique(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.unique = yes;
+        self
+    }
+
+    pub fn only_matching(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.only_matching = yes;
+        self
+    }
+
+    pub fn line_number(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.line_number = yes;
+        self
+    }
+
+    pub fn line_number_width(&mut self, width: u32) -> &mut CommandArgsBuilder {
+        self.args.line_number_width = width;
+        self
+    }
+
+    pub fn column(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.column = yes;
+        self
+    }
+
+    pub fn utf16_columns(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.utf16_columns = yes;
+        self
+    }
+
+    pub fn byte_offsets(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.byte_offsets = yes;
+        self
+    }
+
+    /// Replaces matches with `replacement` in printed lines, like `--replace`
+    pub fn replace(&mut self, replacement: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.replace = Some(replacement.into());
+        self
+    }
+
+    pub fn write_replace(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.write_replace = yes;
+        self
+    }
+
+    pub fn backup(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.backup = yes;
+        self
+    }
+
+    pub fn dry_run(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.dry_run = yes;
+        self
+    }
+
+    pub fn interactive(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.interactive = yes;
+        self
+    }
+
+    pub fn watch(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.watch = yes;
+        self
+    }
+
+    pub fn daemon(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.daemon = yes;
+        self
+    }
+
+    pub fn socket(&mut self, path: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.socket = path.into();
+        self
+    }
+
+    pub fn max_connections(&mut self, connections: u32) -> &mut CommandArgsBuilder {
+        self.args.max_connections = Some(connections);
+        self
+    }
+
+    pub fn editor_server(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.editor_server = yes;
+        self
+    }
+
+    pub fn git_rev(&mut self, range: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.git_rev = range.into();
+        self
+    }
+
+    pub fn changed_since(&mut self, commit: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.changed_since = commit.into();
+        self
+    }
+
+    pub fn remote(&mut self, remote: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.remote = remote.into();
+        self
+    }
+
+    pub fn newer_than(&mut self, time: Option<SystemTime>) -> &mut CommandArgsBuilder {
+        self.args.newer_than = time;
+        self
+    }
+
+    pub fn older_than(&mut self, time: Option<SystemTime>) -> &mut CommandArgsBuilder {
+        self.args.older_than = time;
+        self
+    }
+
+    /// Like `-d ACTION`
+    pub fn directories(&mut self, action: DirectoryAction) -> &mut CommandArgsBuilder {
+        self.args.directories = action;
+        self
+    }
+
+    /// Like `--include GLOB`, can be called again for more
+    pub fn include(&mut self, glob: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.include.push(glob.into());
+        self
+    }
+
+    /// Like `--exclude GLOB`, can be called again for more
+    pub fn exclude(&mut self, glob: impl Into<String>) -> &mut CommandArgsBuilder {
+        self.args.exclude.push(glob.into());
+        self
+    }
+
+    pub fn skip_minified(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.skip_minified = yes;
+        self
+    }
+
+    pub fn no_skip_virtual(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.no_skip_virtual = yes;
+        self
+    }
+
+    pub fn gitignore(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.gitignore = yes;
+        self
+    }
+
+    pub fn no_dedupe(&mut self, yes: bool) -> &mut CommandArgsBuilder {
+        self.args.no_dedupe = yes;
+        self
+    }
+
+    pub fn order(&mut self, order: Order) -> &mut CommandArgsBuilder {
+        self.args.order = order;
+        self
+    }
+
+    pub fn pre(&mut self, preprocessor: Preprocessor) -> &mut CommandArgsBuilder {
+        self.args.pre.push(preprocessor);
+        self
+    }
+
+    pub fn only(&mut self, region: Option<Region>) -> &mut CommandArgsBuilder {
+        self.args.only = region;
+        self
+    }
+}
+
+/// What an option does when it is given
+pub enum OptionKind {
+    /// Takes no value. Receives false when negated with the `--no-` prefix.
+    Flag(fn(&mut CommandArgs, bool)),
+    /// Takes a value, either as the next argument or after `=`. Returns the reason when the value is invalid.
+    Value(fn(&mut CommandArgs, &str) -> std::result::Result<(), String>),
+    /// Takes no value and cannot be negated; parsing stops with the error it makes, e.g. [`Error::Help`] to print
+    /// the help instead of searching.
+    Action(fn() -> Error),
+}
+
+pub struct OptionSpec {
+    /// Short name without the dash, empty if none
+    pub short: &'static str,
+    /// Long name without the dashes
+    pub long: &'static str,
+    /// Name of the value in help, empty unless the kind is `Value`
+    pub value_name: &'static str,
+    pub default_value: &'static str,
+    pub description: &'static str,
+    /// Values the option accepts, empty if it accepts anything
+    pub possible_values: &'static [&'static str],
+    pub kind: OptionKind,
+}
+
+impl OptionSpec {
+    pub fn takes_value(&self) -> bool {
+        matches!(self.kind, OptionKind::Value(_))
+    }
+
+    pub fn negatable(&self) -> bool {
+        matches!(self.kind, OptionKind::Flag(_))
+    }
+
+    /// Long form as shown in help, e.g. `--after-context=NUM`
+    pub fn long_form(&self) -> String {
+        if self.takes_value() {
+            format!("--{}={}", self.long, self.value_name)
+        } else {
+            format!("--{}", self.long)
+        }
+    }
+}
+
+pub struct Category {
+    pub name: &'static str,
+    /// Longer explanation shown under the category name in help and the man page
+    pub description: &'static str,
+    pub options: &'static [OptionSpec],
+}
+
+pub const USAGE: &str = "Usage: rgrep [OPTION..] PATTERN [FILE..]\n       rgrep [OPTION..] -e PATTERN.. [FILE..]";
+
+/// Kinds accepted by --generate
+const GENERATE_KINDS: &[&str] = &["man", "complete-bash", "complete-zsh", "complete-fish", "complete-powershell"];
+
+/// Every option rgrep understands. Parsing, help, the man page and shell completions are all derived from it.
+pub const CATEGORIES: &[Category] = &[
+    Category {
+        name: "Pattern selection and interpretation",
+        description: "Patterns are regular expressions, matched against every line, unless -F is given. \
+                      A line is selected if any of the patterns matches it.",
+        options: &[
+            OptionSpec {
+                short: "i",
+                long: "ignore-case",
+                value_name: "",
+                default_value: "false",
+                description: "ignore case distinctions in patterns and data",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.ignore_case = value),
+            },
+            OptionSpec {
+                short: "F",
+                long: "fixed-strings",
+                value_name: "",
+                default_value: "false",
+                description: "PATTERNS are strings, not regular expressions",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.fixed_strings = value),
+            },
+            OptionSpec {
+                short: "G",
+                long: "basic-regexp",
+                value_name: "",
+                default_value: "false",
+                description: "PATTERNS are basic regular expressions, like those of grep and sed, with \\( \\) \\{ \\} \\|",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| set_syntax(args, Syntax::Basic, value)),
+            },
+            OptionSpec {
+                short: "E",
+                long: "extended-regexp",
+                value_name: "",
+                default_value: "false",
+                description: "PATTERNS are extended regular expressions, like those of grep -E and awk",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| set_syntax(args, Syntax::Extended, value)),
+            },
+            OptionSpec {
+                short: "",
+                long: "engine",
+                value_name: "ENGINE",
+                default_value: "auto",
+                description: "run regular expressions on the pikevm, in time linear in the input, or on the backtracker, which also matches backreferences like \\1 but can be very slow; auto takes the backtracker only for patterns with backreferences",
+                possible_values: Engine::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.engine = Engine::from_name(value).ok_or("unknown engine")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "matches",
+                value_name: "WHICH",
+                default_value: "all",
+                description: "color, print with -o and replace all the matches in every line, or only the first; the lines that match, and the column of --column, are the same",
+                possible_values: MatchesPerLine::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.matches = MatchesPerLine::from_name(value).ok_or("unknown choice of matches")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "e",
+                long: "regexp",
+                value_name: "PATTERN",
+                default_value: "none",
+                description: "use PATTERN for matching, can be repeated; all operands are then files",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.patterns.push(String::from(value));
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "builtin",
+                value_name: "NAME",
+                default_value: "none",
+                description: "use the built-in regular expression NAME for matching, like -e; --builtin list shows them all",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    let builtin = builtin_pattern(value)
+                        .ok_or_else(|| String::from("no such built-in pattern, --builtin list shows them"))?;
+                    args.patterns.push(String::from(builtin.pattern));
+                    args.builtins.push(String::from(value));
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "all-match",
+                value_name: "",
+                default_value: "false",
+                description: "only report inputs in which every pattern matches somewhere, not necessarily on the same line",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.all_match = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "stop-on-nonmatch",
+                value_name: "",
+                default_value: "false",
+                description: "stop reading an input at the first line that does not match after one that did",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.stop_on_nonmatch = value),
+            },
+            OptionSpec {
+                short: "U",
+                long: "multiline",
+                value_name: "",
+                default_value: "false",
+                description: "match whole inputs instead of single lines, so matches can span lines",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.multiline = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "only",
+                value_name: "PART",
+                default_value: "anywhere",
+                description: "only match in the comments, string literals or code of source files, known by their extension",
+                possible_values: Region::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.only = Some(Region::from_name(value).ok_or("unknown part of source files")?);
+                    Ok(())
+                }),
+            }
+        ],
+    },
+    Category {
+        name: "File and directory selection",
+        description: "Inputs containing a NUL byte are binary. Matching lines in them are not printed, \
+                      only a line saying that the input matches.",
+        options: &[
+            OptionSpec {
+                short: "a",
+                long: "text",
+                value_name: "",
+                default_value: "false",
+                description: "search binary inputs as if they were text, same as --binary-files=text",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| {
+                    args.binary = if value { BinaryDetection::Text } else { BinaryDetection::Report }
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "binary-files",
+                value_name: "TYPE",
+                default_value: "binary",
+                description: "report matches in binary inputs (binary), search them as text (text) or skip them (without-match)",
+                possible_values: BinaryDetection::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.binary = BinaryDetection::from_name(value).ok_or("unknown binary files type")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "encoding",
+                value_name: "ENC",
+                default_value: "auto",
+                description: "decode inputs as ENC; auto recognizes UTF-16 by its byte order mark",
+                possible_values: Encoding::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.encoding = Encoding::from_name(value).ok_or("unknown encoding")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "git-rev",
+                value_name: "RANGE",
+                default_value: "none",
+                description: "search the files of every commit git rev-list lists for RANGE, e.g. v1.0..HEAD; FILEs are paths in them",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.git_rev = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "changed-since",
+                value_name: "REF",
+                default_value: "none",
+                description: "only search the files, under FILEs if any, that changed since git commit REF or are new",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.changed_since = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "remote",
+                value_name: "HOST:PATH",
+                default_value: "none",
+                description: "run the search in directory PATH of HOST with ssh, and print the results here",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.remote = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "newer-than",
+                value_name: "WHEN",
+                default_value: "none",
+                description: "only search files modified after WHEN, a time ago like 2d, 3h or 30m, or a UTC date like 2024-05-01 or 2024-05-01T12:30",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.newer_than = Some(parse_time(value)?);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "older-than",
+                value_name: "WHEN",
+                default_value: "none",
+                description: "only search files last modified before WHEN, given like for --newer-than",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.older_than = Some(parse_time(value)?);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "d",
+                long: "directories",
+                value_name: "ACTION",
+                default_value: "read",
+                description: "what to do with FILEs that are directories: read them like files, which fails, skip them, or recurse into them and search the files in them, and the current directory if there are no FILEs",
+                possible_values: DirectoryAction::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.directories = DirectoryAction::from_name(value).ok_or("unknown action")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "r",
+                long: "recursive",
+                value_name: "",
+                default_value: "false",
+                description: "search the files in directories and the directories in them, like -d recurse; without FILEs, search the current directory",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| {
+                    args.directories = if value { DirectoryAction::Recurse } else { DirectoryAction::Read }
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "include",
+                value_name: "GLOB",
+                default_value: "none",
+                description: "only search files whose base name matches GLOB, with * ? and [...] like a shell; can be repeated, then any of them",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.include.push(String::from(value));
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "exclude",
+                value_name: "GLOB",
+                default_value: "none",
+                description: "skip files whose base name matches GLOB, even if they match --include; can be repeated",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.exclude.push(String::from(value));
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "skip-minified",
+                value_name: "",
+                default_value: "false",
+                description: "with -r, skip the files found that look minified or generated: .min.js, .min.css and source maps, and files with very long lines at their start",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.skip_minified = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "skip-virtual",
+                value_name: "",
+                default_value: "true",
+                description: "with -r, skip the directories found that virtual file systems like /proc, /sys, /dev and FUSE mounts are on, on Linux; --no-skip-virtual to search them",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.no_skip_virtual = !value),
+            },
+            OptionSpec {
+                short: "",
+                long: "gitignore",
+                value_name: "",
+                default_value: "false",
+                description: "with -r, skip .git directories and what the .gitignore and .ignore files of the directories walked ignore, like git does",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.gitignore = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "dedupe",
+                value_name: "",
+                default_value: "true",
+                description: "search a file once when it is given twice or through hard or symbolic links to it; --no-dedupe to search every name",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.no_dedupe = !value),
+            },
+            OptionSpec {
+                short: "",
+                long: "order",
+                value_name: "ORDER",
+                default_value: "as-completed",
+                description: "search inputs in the order they are given and print the results of each as soon as they are found, or with sorted, in the order of their names, for output that compares with diff; the matches and exit status are the same",
+                possible_values: Order::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.order = Order::from_name(value).ok_or("unknown order")?;
+                    Ok(())
+                }),
+            }
+        ],
+    },
+    Category {
+        name: "Preprocessing",
+        description: "Files that are not text can be searched as the text a command prints for them, e.g. PDFs with --pre='pdf=pdftotext -q {} -', word processor documents with --pre='docx,odt,rtf=pandoc -t plain' and e-books with --pre='epub,mobi=ebook-convert {} /dev/stdout'. With the documents feature, .docx, .pptx, .xlsx, .odt, .ods, .odp and .epub files are searched as their text without a command.",
+        options: &[
+            OptionSpec {
+                short: "",
+                long: "pre",
+                value_name: "[EXT,..=]COMMAND",
+                default_value: "none",
+                description: "search what COMMAND prints for files ending in .EXT, or all files, instead of them; {} is the file, added last otherwise. Can be repeated",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.pre.push(Preprocessor::parse(value)?);
+                    Ok(())
+                }),
+            }
+        ],
+    },
+    Category {
+        name: "General output control",
+        description: "By default every matching line is printed. These options print something else instead.",
+        options: &[
+            OptionSpec {
+                short: "c",
+                long: "count",
+                value_name: "",
+                default_value: "false",
+                description: "print only a count of matching lines per input",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.count = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "count-per-pattern",
+                value_name: "",
+                default_value: "false",
+                description: "print LINES:MATCHES:PATTERN for every pattern per input, and in total for several inputs",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.count_per_pattern = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "include-zero",
+                value_name: "",
+                default_value: "true",
+                description: "count inputs without matches too, as 0; --no-include-zero leaves them out",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.no_include_zero = !value),
+            },
+            OptionSpec {
+                short: "",
+                long: "max-shown",
+                value_name: "NUM",
+                default_value: "none",
+                description: "print only the first NUM matching lines of every input, then how many more there are",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.max_shown = Some(parse_number(value)?);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "n",
+                long: "line-number",
+                value_name: "",
+                default_value: "false",
+                description: "print the line number of every line before it",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.line_number = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "line-number-width",
+                value_name: "NUM",
+                default_value: "0",
+                description: "right-align line numbers in NUM columns, at most 20, so the lines after them line up; with --heading, as many as the line count of each file has digits by default",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.line_number_width = parse_number(value)?;
+                    if args.line_number_width > MAX_LINE_NUMBER_WIDTH {
+                        let largest = "the digits of the largest line number";
+                        return Err(format!("expected at most {}, {}", MAX_LINE_NUMBER_WIDTH, largest));
+                    }
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "column",
+                value_name: "",
+                default_value: "false",
+                description: "print the column of the first match in every matching line after its line number, which it turns on, in bytes from 1; with -o the column of every match, and with --matches all still of the first",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.column = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "json",
+                value_name: "",
+                default_value: "false",
+                description: "print results as JSON, one object per line for every input, match and context line, and a summary with the totals and exit status at the end",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.json = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "utf16-columns",
+                value_name: "",
+                default_value: "false",
+                description: "give the matches in every line of --json output, with columns in UTF-16 code units as well as bytes, as the Language Server Protocol counts them",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.utf16_columns = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "captures",
+                value_name: "",
+                default_value: "false",
+                description: "add the spans and text of all the groups of every match to --json and --byte-offsets output, which --json gives by name for named groups without it",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.captures = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "byte-offsets",
+                value_name: "",
+                default_value: "false",
+                description: "print every match as START:END:TEXT, the offsets of its first byte and of the byte after it in the input",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.byte_offsets = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "unique",
+                value_name: "",
+                default_value: "false",
+                description: "print every distinct match once, in the order they are found; with -c as COUNT:TEXT once done",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.unique = value),
+            },
+            OptionSpec {
+                short: "o",
+                long: "only-matching",
+                value_name: "",
+                default_value: "false",
+                description: "print only the text of every match in matching lines, each on a line of its own with the prefixes of its line",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.only_matching = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "interactive",
+                value_name: "",
+                default_value: "false",
+                description: "browse the matches in a terminal UI; typing filters them and Enter opens one in $EDITOR",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.interactive = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "watch",
+                value_name: "",
+                default_value: "false",
+                description: "keep running and print matches in lines added to the files, and in files created with -r, until interrupted",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.watch = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "replace",
+                value_name: "TEXT",
+                default_value: "none",
+                description: "print matching lines with every match replaced by TEXT; $1, ${name} and $$ insert groups and a $",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.replace = Some(String::from(value));
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "write-replace",
+                value_name: "",
+                default_value: "false",
+                description: "also write the replacements of --replace to the files, replacing each at once",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.write_replace = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "backup",
+                value_name: "",
+                default_value: "false",
+                description: "keep the original of every file --write-replace changes as FILE.bak",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.backup = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "dry-run",
+                value_name: "",
+                default_value: "false",
+                description: "print what --write-replace would change as a unified diff for patch, instead of writing it",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.dry_run = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "color",
+                value_name: "WHEN",
+                default_value: "auto",
+                description: "color matches, names and line numbers, the matches of every pattern in a color of its own; auto does when standard output is a terminal and TERM is not dumb",
+                possible_values: ColorChoice::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.color = ColorChoice::from_name(value).ok_or("unknown color choice")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "theme",
+                value_name: "NAME",
+                default_value: "default",
+                description: "start from the colors of a theme, which GREP_COLORS and --colors change",
+                possible_values: Theme::NAMES,
+                kind: OptionKind::Value(|args, value| {
+                    args.theme = Theme::from_name(value).ok_or("unknown theme")?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "colors",
+                value_name: "SPEC",
+                default_value: "none",
+                description: "change a color, with PART:fg:COLOR, PART:bg:COLOR, PART:style:STYLE or PART:none; PART is match, path, line or separator, STYLE is bold, italic, underline or reverse, or one of them after no; can be repeated",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.colors.push(ColorSpec::parse(value)?);
+                    Ok(())
+                }),
+            }
+        ],
+    },
+    Category {
+        name: "Output line prefix control",
+        description: "When more than one input is searched, every printed line starts with the name of its input.",
+        options: &[
+            OptionSpec {
+                short: "",
+                long: "label",
+                value_name: "NAME",
+                default_value: "(standard input)",
+                description: "use NAME as the name of standard input in output",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.label = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "path-separator",
+                value_name: "SEPARATOR",
+                default_value: "/",
+                description: "print paths with SEPARATOR, one character, between their components, like / on Windows",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    let mut chars = value.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(separator), None) => args.path_separator = Some(separator),
+                        _ => return Err(String::from("expected a single character")),
+                    }
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "heading",
+                value_name: "",
+                default_value: "auto",
+                description: "print the name of every input once above its lines instead; the default on a terminal",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.heading = Some(value)),
+            },
+            OptionSpec {
+                short: "",
+                long: "heading-counts",
+                value_name: "",
+                default_value: "off",
+                description: "add the number of matching lines to every heading, printing the lines of an input once it has been searched",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.heading_counts = value),
+            }
+        ],
+    },
+    Category {
+        name: "Context control",
+        description: "Context lines are printed around matching lines, prefixed with - instead of :. Groups of lines that are not adjacent are separated by a line containing --.",
+        options: &[
+            OptionSpec {
+                short: "A",
+                long: "after-context",
+                value_name: "NUM",
+                default_value: "0",
+                description: "print NUM lines of trailing context",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.after_context = parse_number(value)?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "B",
+                long: "before-context",
+                value_name: "NUM",
+                default_value: "0",
+                description: "print NUM lines of leading context",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.before_context = parse_number(value)?;
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "paragraph",
+                value_name: "",
+                default_value: "false",
+                description: "print the whole paragraph every match is in, up to the blank lines around it, instead of NUM lines",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.paragraph = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "context-bytes",
+                value_name: "NUM",
+                default_value: "none",
+                description: "print only the matches in matching lines and NUM bytes around each of them, for files with very long lines",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.context_bytes = Some(parse_number(value)?);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "p",
+                long: "show-function",
+                value_name: "",
+                default_value: "false",
+                description: "print the line of the function, Markdown section or indented block each match is in, prefixed with =",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.show_function = value),
+            }
+        ],
+    },
+    Category {
+        name: "Daemon mode",
+        description: "With --daemon, rgrep keeps running and answers searches, keeping the files and patterns it used in memory. Every request is a line of arguments as they would follow rgrep, and is answered with --json events, then {\"type\":\"done\",\"data\":{\"status\":N}} with the exit status. With --editor-server, every request is a JSON object instead, like {\"id\":1,\"pattern\":\"fn main\",\"paths\":[\"src\"],\"flags\":[\"-i\"]}, and its id is given back in the done event.",
+        options: &[
+            OptionSpec {
+                short: "",
+                long: "daemon",
+                value_name: "",
+                default_value: "false",
+                description: "answer searches read from standard input, or --socket, one per line, until stopped",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.daemon = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "socket",
+                value_name: "PATH",
+                default_value: "none",
+                description: "with --daemon, answer searches sent to the Unix socket PATH instead of standard input",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.socket = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "max-connections",
+                value_name: "NUM",
+                default_value: "64",
+                description: "with --socket, answer at most NUM connections at once and let the others wait, so the daemon stays within its limit of open files",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    match parse_number(value)? {
+                        0 => return Err(String::from("expected at least 1")),
+                        connections => args.max_connections = Some(connections),
+                    }
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "editor-server",
+                value_name: "",
+                default_value: "false",
+                description: "answer searches read from standard input as JSON objects, one per line, until stopped, for editor plugins",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.editor_server = value),
+            }
+        ],
+    },
+    Category {
+        name: "Miscellaneous",
+        description: "Options that print information about rgrep itself, instead of or in addition to searching.",
+        options: &[
+            OptionSpec {
+                short: "",
+                long: "generate",
+                value_name: "KIND",
+                default_value: "none",
+                description: "print a man page or shell completion script and exit",
+                possible_values: GENERATE_KINDS,
+                kind: OptionKind::Value(|args, value| {
+                    args.generate = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "stats",
+                value_name: "",
+                default_value: "false",
+                description: "print counts of matches, files and bytes searched after the results",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.stats = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "stats-per-file",
+                value_name: "",
+                default_value: "false",
+                description: "with --stats, also print the time, bytes and strategy of every input, as \"files\" in --json",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.stats_per_file = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "summary",
+                value_name: "",
+                default_value: "false",
+                description: "print a line like \"3 matching lines in 2 files (searched 10 files in 0.1s)\" on stderr at the end",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.summary = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "progress",
+                value_name: "",
+                default_value: "true",
+                description: "show files searched and matches so far on stderr while a long search runs, if it is a terminal; --no-progress to never",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.no_progress = !value),
+            },
+            OptionSpec {
+                short: "s",
+                long: "no-messages",
+                value_name: "",
+                default_value: "false",
+                description: "do not print errors about inputs that cannot be read, nor how many were skipped; the exit status is still 2",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.no_messages = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "max-memory",
+                value_name: "SIZE",
+                default_value: "none",
+                description: "hold at most SIZE bytes of an input in memory, with a K, M or G suffix for KiB, MiB or GiB; only the start of a longer line is searched, with a warning, and a larger input with --multiline is an error of that input instead of running out of memory",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.max_memory = Some(parse_size(value)?);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "checkpoint",
+                value_name: "FILE",
+                default_value: "none",
+                description: "record every input in FILE once it is searched, and skip the inputs already in it, so a stopped search goes on where it was when run again with the same patterns; FILE is removed when everything was searched",
+                possible_values: &[],
+                kind: OptionKind::Value(|args, value| {
+                    args.checkpoint = String::from(value);
+                    Ok(())
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "posix",
+                value_name: "",
+                default_value: "false",
+                description: "behave like POSIX grep in its options -E, -F, -c, -e, -i, -n and -s: patterns are basic regular expressions, options end at the first operand, the config file and RGREP_OPTIONS are not read, and nothing is colored, headed, shown as progress or searched once when named twice; -f, -l, -q, -v and -x are not there, and short options are given apart with their values, like -i -n -A 1",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| args.posix = value),
+            },
+            OptionSpec {
+                short: "",
+                long: "debug",
+                value_name: "",
+                default_value: "false",
+                description: "print which config, inputs and search strategy are used, with timings, to stderr",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| {
+                    args.log_level = if value { args.log_level.max(log::Level::Debug) } else { log::Level::Off }
+                }),
+            },
+            OptionSpec {
+                short: "",
+                long: "trace",
+                value_name: "",
+                default_value: "false",
+                description: "like --debug, and also report every line that is matched",
+                possible_values: &[],
+                kind: OptionKind::Flag(|args, value| {
+                    args.log_level = if value { log::Level::Trace } else { args.log_level.min(log::Level::Debug) }
+                }),
+            },
+            OptionSpec {
+                short: "V",
+                long: "version",
+                value_name: "",
+                default_value: "false",
+                description: "print version, features and target information and exit",
+                possible_values: &[],
+                kind: OptionKind::Action(|| Error::Version),
+            },
+            OptionSpec {
+                short: "h",
+                long: "help",
+                value_name: "",
+                default_value: "false",
+                description: "print this help and exit",
+                possible_values: &[],
+                kind: OptionKind::Action(|| Error::Help),
+            }
+        ],
+    }
+];
+
+/// Columns of `--line-number-width` at most, enough for any `u64`
+const MAX_LINE_NUMBER_WIDTH: u32 = 20;
+
+/// Sets the syntax of -G and -E, or turns it off with their `--no-` form, only if it is the one of that flag, so
+/// `-E --no-basic-regexp` is still extended
+fn set_syntax(args: &mut CommandArgs, syntax: Syntax, value: bool) {
+    if value {
+        args.syntax = Some(syntax);
+    } else if args.syntax == Some(syntax) {
+        args.syntax = None;
+    }
+}
+
+fn parse_number(value: &str) -> std::result::Result<u32, String> {
+    value.parse::<u32>().map_err(|_| String::from("expected a non-negative number"))
+}
+
+/// Parses a number of bytes like `512K` or `2G`, with powers of 1024 for the suffixes K, M and G
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let invalid = || String::from("expected a number of bytes like 4096, 512K, 64M or 2G");
+    let (number, unit) = match value.strip_suffix(['K', 'k', 'M', 'm', 'G', 'g']) {
+        Some(number) => (number, value[number.len()..].to_ascii_uppercase()),
+        None => (value, String::new()),
+    };
+    let shift = match unit.as_str() {
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        _ => 0,
+    };
+    let bytes = number.parse::<u64>().map_err(|_| invalid())?.checked_mul(1 << shift).ok_or_else(invalid)?;
+    if bytes == 0 {
+        return Err(String::from("expected at least 1 byte"));
+    }
+    Ok(bytes)
+}
+
+/// Parses the value of --newer-than and --older-than: a duration before now like `2d`, with a unit of `s`, `m`,
+/// `h`, `d` or `w`, or a UTC date like `2024-05-01`, with a time like `2024-05-01T12:30:00` or `2024-05-01 12:30`
+fn parse_time(value: &str) -> std::result::Result<SystemTime, String> {
+    let invalid = || String::from("expected a duration like 2d or 3h, or a date like 2024-05-01");
+    if let Some(unit) = value.chars().last().filter(|unit| unit.is_ascii_alphabetic()) {
+        let seconds = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let count = value[..value.len() - 1].parse::<u64>().map_err(|_| invalid())?;
+        let ago = Duration::from_secs(count.checked_mul(seconds).ok_or_else(invalid)?);
+        return SystemTime::now().checked_sub(ago).ok_or_else(invalid);
+    }
+    let (date, time) = value.split_once(['T', ' ']).unwrap_or((value, "00:00:00"));
+    let numbers = |text: &str, separator: char| -> Option<Vec<u64>> {
+        text.split(separator).map(|number| number.parse::<u64>().ok()).collect()
+    };
+    let date = numbers(date, '-').filter(|date| date.len() == 3).ok_or_else(invalid)?;
+    let time = numbers(time, ':').filter(|time| (2..=3).contains(&time.len())).ok_or_else(invalid)?;
+    let (year, month, day) = (date[0], date[1], date[2]);
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    if time[0] > 23 || time[1] > 59 || time.get(2).is_some_and(|&second| second > 60) {
+        return Err(invalid());
+    }
+    let seconds = days_since_epoch(year, month, day) * 24 * 60 * 60 + time[0] * 60 * 60 + time[1] * 60;
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds + time.get(2).copied().unwrap_or(0)))
+}
+
+/// Days from 1970-01-01 to a date of the Gregorian calendar from 1970 on
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // counted from March, so the leap day comes last in a year
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Prints the version, features and target of `--version`
+pub fn print_version() {
+    let features = match env!("RGREP_FEATURES") {
+        "" => "none",
+        features => features,
+    };
+    output::print(format!("rgrep {}\n", env!("CARGO_PKG_VERSION")).as_str());
+    output::print(format!("features: {}\n", features).as_str());
+    output::print(format!("target: {}\n", env!("RGREP_TARGET")).as_str());
+}
+
+/// Prints the built-in patterns of `--builtin`, for `--builtin list`
+pub fn print_builtin_patterns() {
+    let mut list = String::new();
+    for builtin in BUILTIN_PATTERNS {
+        list.push_str(&format!("{:<8}{}, e.g. {}\n", builtin.name, builtin.description, builtin.example));
+        list.push_str(&format!("        {}\n", builtin.pattern));
+    }
+    output::print(&list);
+}
+
+/// All options, regardless of category
+fn all_options() -> impl Iterator<Item = &'static OptionSpec> {
+    CATEGORIES.iter().flat_map(|category| category.options.iter())
+}
+
+/// Finds the option for an argument starting with a dash, e.g. `-A` or `--no-ignore-case`.
+/// Returns the option and whether it was negated.
+fn find_option(name: &str) -> Result<(&'static OptionSpec, bool)> {
+    let found = if let Some(long) = name.strip_prefix("--") {
+        all_options()
+            .find(|option| option.long == long)
+            .map(|option| (option, false))
+            .or_else(|| {
+                let negated = long.strip_prefix("no-")?;
+                all_options().find(|option| option.long == negated && option.negatable()).map(|option| (option, true))
+            })
+    } else {
+        let short = name.trim_start_matches('-');
+        all_options().find(|option| !option.short.is_empty() && option.short == short).map(|option| (option, false))
+    };
+    found.ok_or_else(|| Error::UnknownOption { option: String::from(name), suggestion: suggest_option(name) })
+}
+
+/// The positions of the operands in `args`, a command line starting with the command name, before a `--`, other
+/// than the pattern: the operands that [`parse_args`] takes for files. Options and their values are not operands,
+/// and neither is the first operand unless patterns are given with -e or --builtin.
+pub(crate) fn file_operands(args: &[String]) -> Vec<usize> {
+    let mut operands = Vec::new();
+    let mut patterns_given = false;
+    let mut index = 1;
+    while index < args.len() && args[index] != "--" {
+        let arg = &args[index];
+        if arg.starts_with('-') && arg != "-" {
+            let (name, inline_value) = match arg.find('=') {
+                Some(position) if arg.starts_with("--") => (&arg[..position], Some(&arg[position + 1..])),
+                _ => (arg.as_str(), None),
+            };
+            // unknown options are reported by parse_args
+            if let Ok((option, _)) = find_option(name) {
+                patterns_given |= option.long == "regexp" || option.long == "builtin";
+                if option.takes_value() && inline_value.is_none() {
+                    index += 1;
+                }
+            }
+        } else {
+            operands.push(index);
+        }
+        index += 1;
+    }
+    if !patterns_given && !operands.is_empty() {
+        operands.remove(0);
+    }
+    operands
+}
+
+/// Levenshtein distance between two strings, counted in chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances from the processed prefix of a to every prefix of b
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Closest spelling of a known option, if any is close enough to be a likely typo
+fn suggest_option(name: &str) -> Option<String> {
+    // short options are a single character, so anything is "close" to them; only suggest long ones
+    if !name.starts_with("--") {
+        return None;
+    }
+    let max_distance = (name.len() / 3).max(1);
+    let mut candidates = Vec::new();
+    for option in all_options() {
+        candidates.push(format!("--{}", option.long));
+        if option.negatable() {
+            candidates.push(format!("--no-{}", option.long));
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn apply_value(option: &OptionSpec, name: &str, value: &str, command_args: &mut CommandArgs) -> Result<()> {
+    let invalid = |reason: String| Error::InvalidValue { option: String::from(name), value: String::from(value), reason };
+    if option.long == "builtin" && value == "list" {
+        return Err(Error::ListBuiltins);
+    }
+    if !option.possible_values.is_empty() && !option.possible_values.contains(&value) {
+        return Err(invalid(format!("expected one of {}", option.possible_values.join(", "))));
+    }
+    match option.kind {
+        OptionKind::Value(parse) => parse(command_args, value).map_err(invalid),
+        _ => Err(Error::UnexpectedValue { option: String::from(name) }),
+    }
+}
+
+pub fn parse_args(args: Vec<String>, command_args: &mut CommandArgs) -> Result<()> {
+    // made true after a bare `--`; everything after it is a positional argument.
+    let mut options_ended = false;
+    // the first one is the pattern, unless patterns were given with -e
+    let mut positionals = Vec::new();
+
+    // start from 1; so, skip the first argument which is the command name
+    let mut index = 1;
+    while index < args.len() {
+        let arg = &args[index];
+
+        if !options_ended && arg == "--" {
+            options_ended = true;
+        } else if !options_ended && arg.starts_with('-') && arg != "-" {
+            // long options can carry their value after an = sign
+            let (name, inline_value) = match arg.find('=') {
+                Some(position) if arg.starts_with("--") => (&arg[..position], Some(&arg[position + 1..])),
+                _ => (arg.as_str(), None),
+            };
+            let (option, negated) = find_option(name)?;
+            match (&option.kind, inline_value) {
+                (_, Some(value)) => apply_value(option, name, value, command_args)?,
+                (OptionKind::Flag(set), None) => set(command_args, !negated),
+                (OptionKind::Action(stop), None) => return Err(stop()),
+                (OptionKind::Value(_), None) => {
+                    if index + 1 >= args.len() {
+                        return Err(Error::MissingValue { option: String::from(name) });
+                    }
+                    index += 1;
+                    apply_value(option, name, &args[index], command_args)?;
+                }
+            }
+        } else {
+            positionals.push(arg.clone());
+            // POSIX utilities take options before operands only, so `grep foo -i` searches a file named -i
+            options_ended |= command_args.posix;
+        }
+
+        index += 1
+    }
+
+    let mut positionals = positionals.into_iter();
+    if command_args.patterns.is_empty() {
+        command_args.patterns.extend(positionals.next());
+    }
+    command_args.files.extend(positionals);
+    if command_args.posix {
+        command_args.posix_defaults();
+    }
+
+    Ok(())
+}
+
+/// Checks that the options of `command_args` can be used together, and that each does something with the others;
+/// returns the message to stop with if not. [`parse_args`] takes options one at a time, this looks at all of them.
+///
+/// ```
+/// use rgrep::args::{self, CommandArgs};
+///
+/// let command_args = CommandArgs::parse_from(["rgrep", "-n", "--line-number-width", "4", "foo"]).unwrap();
+/// assert!(args::validate(&command_args).is_ok());
+/// let command_args = CommandArgs::parse_from(["rgrep", "--dry-run", "foo"]).unwrap();
+/// let message = args::validate(&command_args).unwrap_err();
+/// assert!(message.starts_with("--dry-run shows what --write-replace would change"));
+/// ```
+pub fn validate(command_args: &CommandArgs) -> std::result::Result<(), String> {
+    let message = match () {
+        // the manual and completions need no pattern
+        _ if !command_args.generate.is_empty() => return Ok(()),
+        _ if !command_args.socket.is_empty() && !command_args.daemon => {
+            "--socket is where --daemon listens, it does nothing without it"
+        }
+        _ if command_args.max_connections.is_some() && command_args.socket.is_empty() => {
+            "--max-connections limits the connections to --socket, it does nothing without it"
+        }
+        _ if command_args.directories == DirectoryAction::Recurse && !command_args.git_rev.is_empty() => {
+            "--git-rev searches the files of a commit, it cannot be used with -d recurse"
+        }
+        _ if !command_args.checkpoint.is_empty() && (command_args.watch || !command_args.git_rev.is_empty()) => {
+            "--checkpoint records the files searched, it cannot be used with --watch or --git-rev"
+        }
+        _ if command_args.editor_server && command_args.daemon => {
+            "--editor-server and --daemon answer searches in different ways, they cannot be used together"
+        }
+        // they check the searches sent to them as they come
+        _ if command_args.daemon || command_args.editor_server => return Ok(()),
+        _ if command_args.patterns.is_empty() => "no pattern given",
+        _ if command_args.write_replace && command_args.replace.is_none() => {
+            "--write-replace needs the text to replace matches with, given with --replace"
+        }
+        _ if command_args.interactive && !io::stdout().is_terminal() => {
+            "--interactive needs standard output to be a terminal"
+        }
+        _ if command_args.dry_run && !command_args.write_replace => {
+            "--dry-run shows what --write-replace would change, it does nothing without it"
+        }
+        _ if command_args.write_replace && !matches!(command_args.encoding, Encoding::Auto | Encoding::Utf8) => {
+            "--write-replace only rewrites files that are not decoded, with --encoding=auto or utf-8"
+        }
+        _ if command_args.stats_per_file && !command_args.stats => {
+            "--stats-per-file adds to --stats, it does nothing without it"
+        }
+        _ if command_args.captures && !command_args.json && !command_args.byte_offsets => {
+            "--captures adds to --json and --byte-offsets output, it does nothing without them"
+        }
+        _ if command_args.utf16_columns && !command_args.json => {
+            "--utf16-columns adds to --json output, it does nothing without it"
+        }
+        _ if command_args.unique
+            && (command_args.json || command_args.byte_offsets || command_args.count_per_pattern) => {
+            "--unique is an output format, not for --json, --byte-offsets or --count-per-pattern"
+        }
+        _ if command_args.unique && (command_args.replace.is_some() || command_args.multiline) => {
+            "--unique prints the text of matches in lines, it cannot be used with --replace or --multiline"
+        }
+        _ if command_args.only_matching
+            && (command_args.json || command_args.count || command_args.count_per_pattern) => {
+            "-o prints the matches in lines, not with --json, --count or --count-per-pattern"
+        }
+        _ if command_args.only_matching && (command_args.byte_offsets || command_args.unique) => {
+            "--byte-offsets and --unique already print only the matches, they cannot be used with -o"
+        }
+        _ if command_args.only_matching && (command_args.multiline || command_args.context_bytes.is_some()) => {
+            "-o prints the matches in every line, it cannot be used with --multiline or --context-bytes"
+        }
+        _ if command_args.column
+            && (command_args.json || command_args.count || command_args.count_per_pattern || command_args.unique) => {
+            "--column adds to the prefix of printed lines, not to --json, --count, --count-per-pattern or --unique"
+        }
+        _ if command_args.column && (command_args.byte_offsets || command_args.multiline) => {
+            "--column is where the match is in its line, it cannot be used with --byte-offsets or --multiline"
+        }
+        _ if command_args.line_number_width > 0 && !command_args.line_number && !command_args.column => {
+            "--line-number-width aligns the numbers of -n, it does nothing without it"
+        }
+        _ if command_args.no_include_zero && !command_args.count && !command_args.count_per_pattern => {
+            "--no-include-zero leaves inputs out of counts, it does nothing without --count"
+        }
+        _ if command_args.count_per_pattern
+            && (command_args.json || command_args.count || command_args.byte_offsets) => {
+            "--count-per-pattern is an output format of its own, not for --json, --count or --byte-offsets"
+        }
+        _ if command_args.count_per_pattern && command_args.multiline => {
+            "--count-per-pattern counts matches in lines, it cannot be used with --multiline"
+        }
+        _ if command_args.byte_offsets && (command_args.json || command_args.count) => {
+            "--byte-offsets is an output format of its own, it cannot be used with --json or --count"
+        }
+        _ if command_args.byte_offsets && (command_args.replace.is_some() || !command_args.remote.is_empty()) => {
+            "--byte-offsets reports where matches are in files, so not with --replace or --remote"
+        }
+        _ if !command_args.builtins.is_empty() && command_args.fixed_strings => {
+            "--builtin patterns are regular expressions, they cannot be used with -F"
+        }
+        _ if !command_args.builtins.is_empty() && (command_args.posix || command_args.syntax.is_some()) => {
+            "--builtin patterns are not POSIX regular expressions, so not with -G, -E or --posix"
+        }
+        _ if command_args.paragraph && (command_args.after_context > 0 || command_args.before_context > 0) => {
+            "--paragraph is the context of matches, so not with -A or -B"
+        }
+        _ if command_args.context_bytes.is_some()
+            && (command_args.json || command_args.count || command_args.count_per_pattern || command_args.unique) => {
+            "--context-bytes shortens the lines printed, so not with --json, --count or --unique"
+        }
+        _ if command_args.context_bytes.is_some()
+            && (command_args.byte_offsets || command_args.replace.is_some()) => {
+            "--context-bytes cannot be used with --byte-offsets or --replace"
+        }
+        _ if command_args.all_match && command_args.multiline => {
+            "--all-match looks for every pattern in the matching lines, it cannot be used with --multiline"
+        }
+        _ if command_args.write_replace && command_args.only.is_some() => {
+            "--write-replace replaces matches anywhere in files, it cannot be used with --only"
+        }
+        _ if command_args.watch
+            && (command_args.files.is_empty() || command_args.files.contains(&String::from("-"))) => {
+            "--watch needs files to watch, standard input cannot be watched"
+        }
+        _ if command_args.watch && command_args.interactive => {
+            "--watch and --interactive cannot be used together"
+        }
+        _ if !command_args.git_rev.is_empty() && command_args.files.iter().any(|file| file == "-") => {
+            "--git-rev searches files in commits, standard input cannot be one of them"
+        }
+        _ if !command_args.git_rev.is_empty() && (command_args.write_replace || command_args.watch) => {
+            "--git-rev searches commits, which --write-replace and --watch cannot change or watch"
+        }
+        _ if !command_args.changed_since.is_empty() && !command_args.git_rev.is_empty() => {
+            "--changed-since and --git-rev cannot be used together"
+        }
+        _ if !command_args.changed_since.is_empty() && command_args.files.iter().any(|file| file == "-") => {
+            "--changed-since picks files git knows about, standard input cannot be one of them"
+        }
+        _ if !command_args.remote.is_empty()
+            && (command_args.write_replace || command_args.interactive || command_args.daemon) => {
+            "--remote only prints results, it cannot be used with --write-replace or --interactive"
+        }
+        // the remote rgrep prints --json, which these are not for
+        _ if !command_args.remote.is_empty()
+            && (command_args.count_per_pattern || command_args.context_bytes.is_some() || command_args.unique) => {
+            "--remote cannot be used with --count-per-pattern, --context-bytes or --unique"
+        }
+        _ if !command_args.remote.is_empty() && command_args.only_matching => "--remote cannot be used with -o",
+        _ if command_args.files.is_empty() && !command_args.remote.is_empty() && io::stdin().is_terminal() => {
+            "no files given and standard input is a terminal"
+        }
+        _ => return Ok(()),
+    };
+    Err(String::from(message))
+}
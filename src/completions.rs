@@ -0,0 +1,146 @@
+use crate::args::{Category, OptionSpec};
+
+/// Returns the completion script for the given --generate kind
+pub fn generate(kind: &str, categories: &[Category]) -> String {
+    let specs: Vec<&OptionSpec> = categories.iter().flat_map(|category| category.options.iter()).collect();
+    match kind {
+        "complete-bash" => bash(&specs),
+        "complete-zsh" => zsh(&specs),
+        "complete-fish" => fish(&specs),
+        "complete-powershell" => powershell(&specs),
+        _ => unreachable!("unknown completion kind {}", kind),
+    }
+}
+
+/// All spellings of an option, including the negated form of flags
+fn words(spec: &OptionSpec) -> Vec<String> {
+    let mut words = Vec::new();
+    if !spec.short.is_empty() {
+        words.push(format!("-{}", spec.short));
+    }
+    words.push(format!("--{}", spec.long));
+    if spec.negatable() {
+        words.push(format!("--no-{}", spec.long));
+    }
+    words
+}
+
+fn bash(specs: &[&OptionSpec]) -> String {
+    let all_words: Vec<String> = specs.iter().flat_map(|spec| words(spec)).collect();
+    let mut script = String::from("_rgrep() {\n");
+    script.push_str("    local cur prev\n");
+    script.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    script.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    script.push_str("    case \"$prev\" in\n");
+    for spec in specs.iter().filter(|spec| spec.takes_value()) {
+        let names: Vec<String> = words(spec);
+        if spec.possible_values.is_empty() {
+            script.push_str(format!("        {})\n            return\n            ;;\n", names.join("|")).as_str());
+        } else {
+            script.push_str(
+                format!("        {})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return\n            ;;\n",
+                        names.join("|"),
+                        spec.possible_values.join(" ")
+                ).as_str());
+        }
+    }
+    script.push_str("    esac\n");
+    script.push_str("    if [[ \"$cur\" == -* ]]; then\n");
+    script.push_str(format!("        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n", all_words.join(" ")).as_str());
+    script.push_str("    else\n");
+    script.push_str("        COMPREPLY=($(compgen -f -- \"$cur\"))\n");
+    script.push_str("    fi\n");
+    script.push_str("}\n");
+    script.push_str("complete -o default -F _rgrep rgrep\n");
+    script
+}
+
+/// Escapes a description for use inside a single quoted zsh `_arguments` spec
+fn zsh_description(description: &str) -> String {
+    description.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]").replace(':', "\\:")
+}
+
+fn zsh(specs: &[&OptionSpec]) -> String {
+    let mut script = String::from("#compdef rgrep\n\n");
+    script.push_str("_arguments -s \\\n");
+    for spec in specs {
+        let description = zsh_description(spec.description);
+        let action = if !spec.takes_value() {
+            String::from("")
+        } else if spec.possible_values.is_empty() {
+            format!(":{}:", spec.value_name)
+        } else {
+            format!(":{}:({})", spec.value_name, spec.possible_values.join(" "))
+        };
+        if !spec.short.is_empty() {
+            let suffix = if spec.takes_value() { "+" } else { "" };
+            script.push_str(format!("  '-{}{}[{}]{}' \\\n", spec.short, suffix, description, action).as_str());
+        }
+        let suffix = if spec.takes_value() { "=" } else { "" };
+        script.push_str(format!("  '--{}{}[{}]{}' \\\n", spec.long, suffix, description, action).as_str());
+        if spec.negatable() {
+            script.push_str(format!("  '--no-{}[do not {}]' \\\n", spec.long, description).as_str());
+        }
+    }
+    script.push_str("  '*:file:_files'\n");
+    script
+}
+
+fn fish(specs: &[&OptionSpec]) -> String {
+    let mut script = String::from("");
+    for spec in specs {
+        let description = spec.description.replace('\'', "\\'");
+        let mut line = String::from("complete -c rgrep");
+        if !spec.short.is_empty() {
+            line.push_str(format!(" -s {}", spec.short).as_str());
+        }
+        line.push_str(format!(" -l {}", spec.long).as_str());
+        if spec.takes_value() && spec.possible_values.is_empty() {
+            line.push_str(" -r");
+        } else if spec.takes_value() {
+            line.push_str(format!(" -x -a '{}'", spec.possible_values.join(" ")).as_str());
+        }
+        line.push_str(format!(" -d '{}'\n", description).as_str());
+        script.push_str(line.as_str());
+        if spec.negatable() {
+            script.push_str(format!("complete -c rgrep -l no-{} -d 'do not {}'\n", spec.long, description).as_str());
+        }
+    }
+    script
+}
+
+fn powershell(specs: &[&OptionSpec]) -> String {
+    let mut script = String::from("using namespace System.Management.Automation\n\n");
+    script.push_str("Register-ArgumentCompleter -Native -CommandName rgrep -ScriptBlock {\n");
+    script.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    script.push_str("    $prev = $commandAst.CommandElements | Where-Object { $_.Extent.EndOffset -lt $cursorPosition } | Select-Object -Last 1\n");
+    script.push_str("    $completions = switch ([string]$prev) {\n");
+    for spec in specs.iter().filter(|spec| !spec.possible_values.is_empty()) {
+        let names: Vec<String> = words(spec).iter().map(|word| format!("'{}'", word)).collect();
+        script.push_str(format!("        {{ $_ -in {} }} {{\n", names.join(", ")).as_str());
+        for value in spec.possible_values {
+            script.push_str(
+                format!("            [CompletionResult]::new('{value}', '{value}', [CompletionResultType]::ParameterValue, '{value}')\n",
+                        value = value
+                ).as_str());
+        }
+        script.push_str("            break\n");
+        script.push_str("        }\n");
+    }
+    script.push_str("        default {\n");
+    for spec in specs {
+        let description = spec.description.replace('\'', "''");
+        for word in words(spec) {
+            script.push_str(
+                format!("            [CompletionResult]::new('{word}', '{word}', [CompletionResultType]::ParameterName, '{desc}')\n",
+                        word = word,
+                        desc = description
+                ).as_str());
+        }
+    }
+    script.push_str("        }\n");
+    script.push_str("    }\n");
+    script.push_str("    $completions | Where-Object { $_.CompletionText -like \"$wordToComplete*\" }\n");
+    script.push_str("}\n");
+    script
+}
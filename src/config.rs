@@ -0,0 +1,185 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::args;
+use crate::error::{Error, Result};
+
+/// Environment variable pointing at an explicit config file. An empty value disables config loading.
+const CONFIG_ENV: &str = "RGREP_CONFIG";
+
+/// Environment variable holding default arguments, split like a shell would.
+const OPTIONS_ENV: &str = "RGREP_OPTIONS";
+
+/// Standard config locations, in lookup order. The first one that exists is used.
+fn default_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        paths.push(PathBuf::from(dir).join("rgrep").join("config"));
+    }
+    if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        let home = PathBuf::from(home);
+        paths.push(home.join(".config").join("rgrep").join("config"));
+        paths.push(home.join(".rgreprc"));
+    }
+    paths
+}
+
+/// Config files contain one argument per line. Blank lines and lines starting with `#` are ignored, and so are
+/// presets.
+fn parse_config(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && parse_preset(line).is_none())
+        .map(String::from)
+        .collect()
+}
+
+/// Splits a `NAME = ARGUMENTS` line of the config file, a preset that `@NAME` on the command line stands for.
+/// Names are made of letters, digits, `_` and `-`, and do not start with a `-` like options do.
+fn parse_preset(line: &str) -> Option<(&str, &str)> {
+    let (name, args) = line.split_once('=')?;
+    let name = name.trim();
+    let is_name = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if is_name {
+        Some((name, args.trim()))
+    } else {
+        None
+    }
+}
+
+/// Returns the config file to read, if any
+pub fn config_path() -> Option<PathBuf> {
+    match env::var_os(CONFIG_ENV) {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_paths().into_iter().find(|path| path.is_file()),
+    }
+}
+
+/// Returns the arguments from the config file, to be placed before the command line arguments.
+/// A missing file in a standard location is not an error, a missing `RGREP_CONFIG` file is.
+pub fn load_args() -> Result<Vec<String>> {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(parse_config(&contents)),
+        Err(source) => Err(Error::Config { path, source }),
+    }
+}
+
+/// Replaces every `@NAME` argument before a `--` with the arguments of the preset `NAME` in the config file,
+/// e.g. `loose = -i -n` makes `rgrep todo @loose src` search `src` ignoring case, with line numbers.
+/// `args` start with the command name. Only arguments where a file or option could be are presets; the values of
+/// options, like `-e @Override`, and the pattern, like in `rgrep @Override src`, are left as they are.
+pub fn expand_presets(args: Vec<String>) -> Result<Vec<String>> {
+    let presets: Vec<usize> =
+        args::file_operands(&args).into_iter().filter(|index| args[*index].starts_with('@')).collect();
+    if presets.is_empty() {
+        return Ok(args);
+    }
+    let contents = match config_path() {
+        Some(path) => fs::read_to_string(&path).map_err(|source| Error::Config { path, source })?,
+        None => String::new(),
+    };
+    let mut expanded = Vec::with_capacity(args.len());
+    for (index, arg) in args.into_iter().enumerate() {
+        let name = match arg.strip_prefix('@') {
+            Some(name) if presets.contains(&index) => name,
+            _ => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+        let preset = contents.lines().filter_map(|line| parse_preset(line.trim())).find(|(found, _)| *found == name);
+        let invalid = |reason: String| Error::Preset { name: String::from(name), reason };
+        let words = match preset {
+            Some((_, words)) => split_shell_words(words).map_err(invalid)?,
+            None => return Err(invalid(String::from("no such preset in the config file"))),
+        };
+        expanded.extend(words);
+    }
+    Ok(expanded)
+}
+
+/// Splits a string into words following the POSIX shell quoting rules: whitespace separates words,
+/// single quotes are taken literally, double quotes allow `\` escapes of `"`, `\`, `$` and `` ` ``,
+/// and an unquoted backslash escapes the next character.
+/// Returns why the input cannot be split, e.g. an unterminated quote.
+pub fn split_shell_words(input: &str) -> std::result::Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    // a word can be empty but still present, e.g. ''
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("Unterminated single quote")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(String::from("Unterminated double quote")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("Unterminated double quote")),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err(String::from("Trailing backslash")),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Returns the arguments from `RGREP_OPTIONS`, to be placed after the config file arguments
+/// and before the command line arguments.
+pub fn env_args() -> Result<Vec<String>> {
+    let invalid = |reason: &str| Error::Environment { variable: OPTIONS_ENV, reason: String::from(reason) };
+    match env::var(OPTIONS_ENV) {
+        Ok(value) => split_shell_words(&value).map_err(|reason| invalid(&reason)),
+        Err(env::VarError::NotPresent) => Ok(Vec::new()),
+        Err(env::VarError::NotUnicode(_)) => Err(invalid("not valid unicode")),
+    }
+}
@@ -0,0 +1,449 @@
+//! `--daemon`: answers searches sent to it one after the other, keeping file contents and compiled patterns in
+//! memory between them, so an editor plugin does not pay for starting a process and reading every file each time.
+//!
+//! Requests are read from standard input, or from every connection to the Unix socket given with `--socket`.
+//! A request is one line holding the arguments of a search as they would follow `rgrep` on a command line:
+//!
+//! ```text
+//! -i "fn main" src/main.rs src/lib.rs
+//! ```
+//!
+//! The answer is the results as `--json` prints them, ending with their summary, errors as
+//! `{"type":"error","data":{"message":...}}` events, and last `{"type":"done","data":{"status":N}}` with the exit
+//! status rgrep would have had. Files are always named in the request, relative to the directory the daemon runs
+//! in; a file is read again when its size or modification time changed. The options of a request work the way
+//! they do on the command line, except those that change the output from `--json`, like `-c` or `--heading`,
+//! which a request cannot use.
+//!
+//! `--editor-server` answers the same way on standard output, to requests read from standard input that are JSON
+//! objects instead, which editor plugins write more easily than quoted arguments:
+//!
+//! ```text
+//! {"id":7,"pattern":"fn main","paths":["src/main.rs","src/lib.rs"],"flags":["-i"]}
+//! ```
+//!
+//! `pattern` is one pattern and `patterns` several, `paths` the files to search, or directories with `-d recurse`
+//! among the `flags`, which are the options as they would be on a command line. Only `flags` can be left out. The
+//! `id`, a number or a string, comes back in the done event, as `{"type":"done","data":{"id":7,"status":N}}`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::args::{CommandArgs, OptionKind, CATEGORIES};
+use crate::config::split_shell_words;
+use crate::error::{Error, Result};
+use crate::json::{self, Json};
+use crate::matcher::{self, Matcher};
+use crate::search::{self, Stats};
+use crate::signal;
+use crate::sink::{self, FileSummary, JsonSink, ReplaceSink, Sink, SinkLine, ToJson};
+
+/// File contents kept in memory at most; the cache starts over when it would grow beyond that
+const MAX_CACHED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Compiled patterns kept at most; like the files, they start over when there would be more
+pub const MAX_CACHED_MATCHERS: usize = 64;
+
+/// Connections to the socket answered at once by default. Each holds a few files open while it is answered; the
+/// ones beyond wait to be accepted.
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// Options a request cannot use, because they print and exit, do not answer with results, or change output that
+/// is always `--json`
+const REFUSED_OPTIONS: &[&str] = &[
+    "daemon",
+    "editor-server",
+    "socket",
+    "max-connections",
+    "checkpoint",
+    "interactive",
+    "watch",
+    "write-replace",
+    "generate",
+    "git-rev",
+    "changed-since",
+    "remote",
+    "pre",
+    "only",
+    "summary",
+    "count",
+    "count-per-pattern",
+    "include-zero",
+    "unique",
+    "only-matching",
+    "column",
+    "byte-offsets",
+    "context-bytes",
+    "max-shown",
+    "heading",
+    "heading-counts",
+    "color",
+    "colors",
+    "theme",
+    "path-separator",
+];
+
+struct CachedFile {
+    len: u64,
+    modified: Option<SystemTime>,
+    contents: Arc<[u8]>,
+}
+
+/// What is kept warm between requests, shared by all connections
+#[derive(Default)]
+struct Cache {
+    files: HashMap<PathBuf, CachedFile>,
+    /// Sum of the lengths of the cached files
+    cached_bytes: u64,
+    /// Compiled patterns by the options they were compiled with
+    matchers: HashMap<String, Arc<dyn Matcher>>,
+}
+
+impl Cache {
+    fn file(&mut self, path: &str) -> io::Result<Arc<[u8]>> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified().ok();
+        if let Some(cached) = self.files.get(&PathBuf::from(path)) {
+            if cached.len == metadata.len() && cached.modified == modified {
+                return Ok(Arc::clone(&cached.contents));
+            }
+        }
+        let contents: Arc<[u8]> = fs::read(path)?.into();
+        let len = contents.len() as u64;
+        if self.cached_bytes + len > MAX_CACHED_BYTES {
+            debug!("file cache is full, starting over");
+            self.files.clear();
+            self.cached_bytes = 0;
+        }
+        if len <= MAX_CACHED_BYTES {
+            let cached = CachedFile { len, modified, contents: Arc::clone(&contents) };
+            let replaced = self.files.insert(PathBuf::from(path), cached);
+            self.cached_bytes = self.cached_bytes + len - replaced.map_or(0, |replaced| replaced.len);
+        }
+        Ok(contents)
+    }
+
+    fn matcher(&mut self, command_args: &CommandArgs) -> Result<Arc<dyn Matcher>> {
+        let key = format!("{:?} {:?}", command_args.patterns, command_args.matcher_options());
+        if let Some(matcher) = self.matchers.get(&key) {
+            return Ok(Arc::clone(matcher));
+        }
+        let matcher: Arc<dyn Matcher> = matcher::build(&command_args.patterns, &command_args.matcher_options())?.into();
+        if self.matchers.len() == MAX_CACHED_MATCHERS {
+            debug!("matcher cache is full, starting over");
+            self.matchers.clear();
+        }
+        self.matchers.insert(key, Arc::clone(&matcher));
+        Ok(matcher)
+    }
+}
+
+/// How requests are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A line of arguments, for --daemon
+    Arguments,
+    /// A JSON object, for --editor-server
+    Json,
+}
+
+/// `--json` output, with errors written as events instead of to stderr, so clients get them with the results
+struct ResponseSink<W: Write> {
+    json: JsonSink<'static, W>,
+    /// The id of an --editor-server request, as JSON, given back when it is done
+    id: Option<String>,
+}
+
+impl<W: Write> ResponseSink<W> {
+    fn done(&mut self, status: i32) -> io::Result<()> {
+        let id = self.id.as_ref().map_or_else(String::new, |id| format!("\"id\":{},", id));
+        self.json.event("done", &format!("{{{}\"status\":{}}}", id, status))?;
+        self.json.flush()
+    }
+}
+
+impl<W: Write> Sink for ResponseSink<W> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.json.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.json.matched(line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.json.context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.json.enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.json.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.json.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.json.event("error", &error.to_json())
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.json.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.json.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.json.flush()
+    }
+}
+
+/// Answers the requests read from `requests`, written in `format`, on `out` until there are no more. Only errors
+/// writing the answers are returned.
+fn serve<R: BufRead, W: Write>(requests: R, mut out: W, cache: &Mutex<Cache>, format: Format) -> io::Result<()> {
+    for request in requests.lines() {
+        let request = request?;
+        if request.trim().is_empty() {
+            continue;
+        }
+        debug!("request: {}", request);
+        let mut sink = ResponseSink { json: JsonSink::new(&mut out), id: None };
+        let words = match format {
+            Format::Arguments => split_shell_words(&request),
+            Format::Json => {
+                let (id, words) = json_request(&request);
+                sink.id = id;
+                words
+            }
+        };
+        let status = match words.and_then(parse_request) {
+            Ok(command_args) => match answer(&command_args, cache, &mut sink) {
+                Ok(status) => status,
+                Err(err) if err.is_broken_pipe() => return Ok(()),
+                Err(Error::Io { path: None, source }) => return Err(source),
+                Err(err) => {
+                    sink.error(&err)?;
+                    2
+                }
+            },
+            Err(reason) => {
+                sink.json.error_message(&reason)?;
+                2
+            }
+        };
+        sink.done(status)?;
+        if signal::interrupted() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The id of an --editor-server request, as JSON, and the arguments it stands for, or why it cannot be answered
+fn json_request(request: &str) -> (Option<String>, std::result::Result<Vec<String>, String>) {
+    let members = match json::parse(request) {
+        Some(Json::Object(members)) => members,
+        _ => return (None, Err(String::from("a request has to be a JSON object"))),
+    };
+    // even a request that cannot be answered gets its id back
+    let id = members.iter().find(|(name, _)| name == "id").and_then(|(_, id)| match id {
+        Json::Number(number) => Some(number.to_string()),
+        Json::String(text) => Some(sink::json_string(text)),
+        _ => None,
+    });
+    (id, request_words(&members))
+}
+
+/// The arguments the members of an --editor-server request stand for
+fn request_words(members: &[(String, Json)]) -> std::result::Result<Vec<String>, String> {
+    let (mut patterns, mut paths, mut flags) = (Vec::new(), Vec::new(), Vec::new());
+    for (name, value) in members {
+        match (name.as_str(), value) {
+            ("id", Json::Number(_) | Json::String(_)) => {}
+            ("id", _) => return Err(String::from("\"id\" has to be a number or a string")),
+            ("pattern", Json::String(pattern)) => patterns.push(pattern.clone()),
+            ("pattern", _) => return Err(String::from("\"pattern\" has to be a string")),
+            ("patterns", _) => patterns.extend(strings(name, value)?),
+            ("paths", _) => paths = strings(name, value)?,
+            ("flags", _) => flags = strings(name, value)?,
+            _ => return Err(format!("a request cannot have \"{}\"", name)),
+        }
+    }
+    if flags.iter().any(|flag| flag == "--") {
+        return Err(String::from("\"flags\" cannot end the options with --, the paths go in \"paths\""));
+    }
+    // a pattern given with = cannot be taken for an option, and paths after -- cannot either
+    let mut words = flags;
+    words.extend(patterns.iter().map(|pattern| format!("--regexp={}", pattern)));
+    words.push(String::from("--"));
+    words.extend(paths);
+    Ok(words)
+}
+
+/// The strings in `value`, the member `name` of an --editor-server request, which has to be an array of them
+fn strings(name: &str, value: &Json) -> std::result::Result<Vec<String>, String> {
+    let strings = value.array().and_then(|values| values.iter().map(Json::string).collect::<Option<Vec<_>>>());
+    let strings = strings.ok_or_else(|| format!("\"{}\" has to be an array of strings", name))?;
+    Ok(strings.into_iter().map(String::from).collect())
+}
+
+/// The search the arguments of a request ask for, or why it cannot be answered
+fn parse_request(words: Vec<String>) -> std::result::Result<CommandArgs, String> {
+    let options: Vec<&String> = words.iter().take_while(|word| *word != "--").collect();
+    if let Some(option) = options.iter().find(|word| refused(word)) {
+        return Err(format!("{} cannot be used in a request", option));
+    }
+    // listing the built-in patterns prints them and exits, like --help
+    let lists_builtins = options.windows(2).any(|pair| pair[0] == "--builtin" && pair[1] == "list");
+    if lists_builtins || options.iter().any(|word| *word == "--builtin=list") {
+        return Err(String::from("--builtin list cannot be used in a request"));
+    }
+    let command_args =
+        CommandArgs::parse_from(std::iter::once(String::from("rgrep")).chain(words)).map_err(|err| err.to_string())?;
+    if command_args.patterns.is_empty() {
+        return Err(String::from("no pattern given"));
+    }
+    if command_args.files.is_empty() || command_args.files.iter().any(|file| file == "-") {
+        return Err(String::from("a request has to name the files to search"));
+    }
+    Ok(command_args)
+}
+
+/// Searches like rgrep would with `command_args`, returns the exit status it would have had
+fn answer<W: Write>(command_args: &CommandArgs, cache: &Mutex<Cache>, sink: &mut ResponseSink<W>) -> Result<i32> {
+    let matcher = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).matcher(command_args)?;
+    let mut replace_sink;
+    let sink: &mut dyn Sink = match &command_args.replace {
+        Some(replacement) => {
+            replace_sink = ReplaceSink::new(&mut *sink, matcher.as_ref(), replacement.as_str());
+            &mut replace_sink
+        }
+        None => sink,
+    };
+    let contents = |file: &str| cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).file(file);
+    let summary = search::run_with_contents(command_args, matcher.as_ref(), sink, &contents)?;
+    Ok(summary.status())
+}
+
+/// Whether a request argument is an option a request cannot use, given by its long or its short name, or turned
+/// off like `--no-heading`
+fn refused(word: &str) -> bool {
+    let name = word.split('=').next().unwrap_or(word);
+    let long = name.strip_prefix("--");
+    CATEGORIES.iter().flat_map(|category| category.options.iter()).any(|option| {
+        (matches!(option.kind, OptionKind::Action(_)) || REFUSED_OPTIONS.contains(&option.long))
+            && (long == Some(option.long)
+                || long.and_then(|long| long.strip_prefix("no-")) == Some(option.long)
+                || !option.short.is_empty() && name.strip_prefix('-') == Some(option.short))
+    })
+}
+
+/// Answers requests from standard input, or from connections to the Unix socket at `socket`, until standard input
+/// ends or Ctrl-C is pressed. At most `max_connections` connections are answered at the same time.
+pub fn run(socket: Option<&str>, max_connections: usize) -> Result<()> {
+    let cache = Arc::new(Mutex::new(Cache::default()));
+    match socket {
+        None => {
+            let out = io::BufWriter::new(io::stdout().lock());
+            serve(io::stdin().lock(), out, &cache, Format::Arguments).map_err(Error::write)
+        }
+        Some(path) => listen(path, cache, max_connections),
+    }
+}
+
+/// Answers requests written as JSON objects, read from standard input, until it ends or Ctrl-C is pressed
+pub fn run_editor_server() -> Result<()> {
+    let cache = Mutex::new(Cache::default());
+    let out = io::BufWriter::new(io::stdout().lock());
+    serve(io::stdin().lock(), out, &cache, Format::Json).map_err(Error::write)
+}
+
+#[cfg(unix)]
+fn listen(path: &str, cache: Arc<Mutex<Cache>>, max_connections: usize) -> Result<()> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    /// EMFILE and ENFILE, the same on Linux, macOS and the BSDs
+    const OUT_OF_FILES: [i32; 2] = [24, 23];
+
+    let listener = match UnixListener::bind(path) {
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => match UnixStream::connect(path) {
+            Ok(_) => Err(io::Error::new(io::ErrorKind::AddrInUse, "another rgrep --daemon is listening on it")),
+            // left behind by a daemon that did not stop cleanly
+            Err(_) => fs::remove_file(path).and_then(|_| UnixListener::bind(path)),
+        },
+        result => result,
+    }
+    .map_err(|err| Error::read(path, err))?;
+    // accepting does not block, so Ctrl-C is noticed
+    listener.set_nonblocking(true).map_err(|err| Error::read(path, err))?;
+    debug!("listening on {}", path);
+
+    let open_connections = Arc::new(AtomicUsize::new(0));
+    // whether accepting failed for want of file descriptors, so that is said once and not every time it is retried
+    let mut out_of_files = false;
+    let result = loop {
+        if signal::interrupted() {
+            break Ok(());
+        }
+        // the connections beyond the limit wait in the backlog of the socket
+        if open_connections.load(Ordering::SeqCst) >= max_connections {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                out_of_files = false;
+                let cache = Arc::clone(&cache);
+                let open_connections = Arc::clone(&open_connections);
+                open_connections.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let requests = stream.try_clone().and_then(|stream| {
+                        stream.set_nonblocking(false)?;
+                        Ok(io::BufReader::new(stream))
+                    });
+                    let served = requests.and_then(|requests| serve(requests, &stream, &cache, Format::Arguments));
+                    if let Err(err) = served {
+                        debug!("connection closed: {}", err);
+                    }
+                    drop(stream);
+                    open_connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(100)),
+            // waiting for connections to close frees file descriptors again, so the daemon keeps going
+            Err(err) if err.raw_os_error().is_some_and(|code| OUT_OF_FILES.contains(&code)) => {
+                if !out_of_files {
+                    let open = open_connections.load(Ordering::SeqCst);
+                    eprintln!(
+                        "rgrep: {}: out of file descriptors with {} connections open, waiting for some to close; \
+                         lower --max-connections or raise the limit with ulimit -n",
+                        path, open
+                    );
+                    out_of_files = true;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(err) => break Err(Error::read(path, err)),
+        }
+    };
+    let _ = fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+fn listen(path: &str, _cache: Arc<Mutex<Cache>>, _max_connections: usize) -> Result<()> {
+    Err(Error::read(path, io::Error::new(io::ErrorKind::Unsupported, "Unix sockets are not supported here")))
+}
@@ -0,0 +1,105 @@
+//! The error type shared by argument parsing, matcher construction and searching.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::matcher::regex;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// An option rgrep does not know, with the closest known spelling if one is close enough
+    UnknownOption { option: String, suggestion: Option<String> },
+    /// An option that takes a value was the last argument
+    MissingValue { option: String },
+    /// An option that takes no value was given one with `--option=value`
+    UnexpectedValue { option: String },
+    /// An option got a value it does not accept; `reason` says what it expected
+    InvalidValue { option: String, value: String, reason: String },
+    /// An environment variable with default options could not be used
+    Environment { variable: &'static str, reason: String },
+    /// The config file could not be read
+    Config { path: PathBuf, source: io::Error },
+    /// An `@NAME` argument names a preset the config file does not have, or one that cannot be split into words
+    Preset { name: String, reason: String },
+    /// A pattern is not a valid regular expression
+    Pattern(regex::Error),
+    /// Reading an input or writing the output failed. `path` is the input as it is named in the output,
+    /// `None` when writing the output failed.
+    Io { path: Option<PathBuf>, source: io::Error },
+    /// The search was stopped by Ctrl-C or its [`CancellationToken`](crate::search::CancellationToken)
+    Interrupted,
+    /// Options that cannot be used together, or one that does nothing without another, which
+    /// [`args::validate`](crate::args::validate) tells
+    Usage(String),
+    /// The command line asks for `--help` instead of a search, which [`args::print_help`](crate::args::print_help)
+    /// prints
+    Help,
+    /// The command line asks for `--version`, which [`args::print_version`](crate::args::print_version) prints
+    Version,
+    /// The command line asks for `--builtin list`, which
+    /// [`args::print_builtin_patterns`](crate::args::print_builtin_patterns) prints
+    ListBuiltins,
+}
+
+impl Error {
+    /// An error reading the input called `path`
+    pub fn read(path: &str, source: io::Error) -> Error {
+        Error::Io { path: Some(PathBuf::from(path)), source }
+    }
+
+    /// An error writing the output
+    pub fn write(source: io::Error) -> Error {
+        Error::Io { path: None, source }
+    }
+
+    /// The reader of the output went away, e.g. `rgrep foo | head`. Not worth reporting.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, Error::Io { path: None, source } if source.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOption { option, suggestion: Some(suggestion) } => {
+                write!(f, "Unexpected option {}, did you mean {}?", option, suggestion)
+            }
+            Error::UnknownOption { option, suggestion: None } => write!(f, "Unexpected option {}", option),
+            Error::MissingValue { option } => write!(f, "Option {} requires value but no value is passed", option),
+            Error::UnexpectedValue { option } => write!(f, "Option {} does not take a value", option),
+            Error::InvalidValue { option, value, reason } => {
+                write!(f, "Option {} got invalid value: {} ({})", option, value, reason)
+            }
+            Error::Environment { variable, reason } => write!(f, "Could not parse {}: {}", variable, reason),
+            Error::Config { path, source } => write!(f, "Could not read config file {}: {}", path.display(), source),
+            Error::Preset { name, reason } => write!(f, "Cannot use preset @{}: {}", name, reason),
+            Error::Pattern(err) => err.fmt(f),
+            Error::Io { path: Some(path), source } => write!(f, "{}: {}", path.display(), source),
+            Error::Io { path: None, source } => source.fmt(f),
+            Error::Interrupted => write!(f, "interrupted"),
+            Error::Usage(message) => message.fmt(f),
+            Error::Help => write!(f, "--help prints the help instead of searching"),
+            Error::Version => write!(f, "--version prints the version instead of searching"),
+            Error::ListBuiltins => write!(f, "--builtin list prints the built-in patterns instead of searching"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config { source, .. } | Error::Io { source, .. } => Some(source),
+            Error::Pattern(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Error {
+        Error::Pattern(err)
+    }
+}
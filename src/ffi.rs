@@ -0,0 +1,204 @@
+//! A C interface to the matcher and the searcher, declared in `include/rgrep.h`.
+//!
+//! C owns nothing it did not allocate: every object handed out here is released with the matching
+//! `rgrep_*_free` function. Inputs are buffers in memory; C programs read files themselves.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+use crate::error::Result;
+use crate::matcher::{self, Matcher, MatcherOptions};
+use crate::search::{BinaryDetection, Input, LineMatch, Searcher, SearcherBuilder};
+
+/// Letters match regardless of case
+pub const RGREP_IGNORE_CASE: u32 = 1;
+/// Patterns are plain strings, not regular expressions
+pub const RGREP_FIXED_STRINGS: u32 = 1 << 1;
+/// Patterns can match across lines
+pub const RGREP_MULTILINE: u32 = 1 << 2;
+
+/// Name the buffer is searched under
+const BUFFER_NAME: &str = "buffer";
+
+/// `rgrep_matcher`: compiled patterns and the settings to search with
+pub struct FfiMatcher {
+    matcher: Box<dyn Matcher>,
+    searcher: Searcher,
+}
+
+/// `rgrep_matches`: everything one search found, handed out one match at a time
+pub struct FfiMatches {
+    lines: Vec<LineMatch>,
+    /// Line and span `rgrep_matches_next` returns next
+    line: usize,
+    span: usize,
+}
+
+/// `rgrep_match`: one match in a line. `line` stays valid until the matches are freed.
+#[repr(C)]
+pub struct FfiMatch {
+    pub line_number: u64,
+    /// Offset of the line in the buffer, after decoding
+    pub offset: u64,
+    pub line: *const u8,
+    pub line_len: usize,
+    /// The match, relative to the start of the line
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Compiles `pattern`, one pattern per line, with `RGREP_*` flags. Returns null on error and, if `error` is not
+/// null, stores a message there that is released with `rgrep_string_free`.
+///
+/// # Safety
+///
+/// `pattern` must be a NUL-terminated string and `error` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_matcher_new(
+    pattern: *const c_char,
+    flags: u32,
+    error: *mut *mut c_char,
+) -> *mut FfiMatcher {
+    if pattern.is_null() {
+        set_error(error, "pattern is null");
+        return ptr::null_mut();
+    }
+    let pattern = match CStr::from_ptr(pattern).to_str() {
+        Ok(pattern) => pattern,
+        Err(_) => {
+            set_error(error, "pattern is not valid UTF-8");
+            return ptr::null_mut();
+        }
+    };
+    let patterns: Vec<String> = pattern.lines().map(String::from).collect();
+    let options = MatcherOptions {
+        fixed_strings: flags & RGREP_FIXED_STRINGS != 0,
+        ignore_case: flags & RGREP_IGNORE_CASE != 0,
+        ..MatcherOptions::default()
+    };
+    match matcher::build(&patterns, &options) {
+        Ok(matcher) => {
+            // the caller chose the buffer, so binary data is searched like the rest instead of being left out
+            let searcher = SearcherBuilder::new()
+                .binary_detection(BinaryDetection::Text)
+                .multi_line(flags & RGREP_MULTILINE != 0)
+                .build();
+            Box::into_raw(Box::new(FfiMatcher { matcher, searcher }))
+        }
+        Err(err) => {
+            set_error(error, &err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `matcher` must be null or returned by `rgrep_matcher_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_matcher_free(matcher: *mut FfiMatcher) {
+    if !matcher.is_null() {
+        drop(Box::from_raw(matcher));
+    }
+}
+
+/// Searches the `len` bytes at `haystack`, NUL bytes included. The buffer is not needed anymore once this returns.
+/// Returns null on error, like `rgrep_matcher_new`.
+///
+/// # Safety
+///
+/// `matcher` must come from `rgrep_matcher_new`, `haystack` must be valid for `len` bytes (or null if `len` is 0)
+/// and `error` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_search(
+    matcher: *const FfiMatcher,
+    haystack: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut FfiMatches {
+    if matcher.is_null() {
+        set_error(error, "matcher is null");
+        return ptr::null_mut();
+    }
+    let matcher = &*matcher;
+    let haystack = if len == 0 { &[][..] } else { slice::from_raw_parts(haystack, len) };
+    match search(matcher, haystack) {
+        Ok(lines) => Box::into_raw(Box::new(FfiMatches { lines, line: 0, span: 0 })),
+        Err(err) => {
+            set_error(error, &err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+fn search(matcher: &FfiMatcher, haystack: &[u8]) -> Result<Vec<LineMatch>> {
+    let input = Input::Reader(BUFFER_NAME, Box::new(haystack));
+    matcher.searcher.matches(matcher.matcher.as_ref(), input).collect()
+}
+
+/// Stores the next match in `out` and returns true, or returns false once there are no more.
+/// The lines of a match that spans several lines are each returned as a whole.
+///
+/// # Safety
+///
+/// `matches` must come from `rgrep_search` and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_matches_next(matches: *mut FfiMatches, out: *mut FfiMatch) -> bool {
+    if matches.is_null() || out.is_null() {
+        return false;
+    }
+    let matches = &mut *matches;
+    while let Some(line) = matches.lines.get(matches.line) {
+        let span = match line.spans.get(matches.span) {
+            Some(span) => Some((span.start, span.end)),
+            None if line.spans.is_empty() && matches.span == 0 => Some((0, line.line.len())),
+            None => None,
+        };
+        if let Some((start, end)) = span {
+            matches.span += 1;
+            *out = FfiMatch {
+                line_number: line.line_number as u64,
+                offset: line.offset,
+                line: line.line.as_ptr(),
+                line_len: line.line.len(),
+                start,
+                end,
+            };
+            return true;
+        }
+        matches.line += 1;
+        matches.span = 0;
+    }
+    false
+}
+
+/// # Safety
+///
+/// `matches` must be null or returned by `rgrep_search` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_matches_free(matches: *mut FfiMatches) {
+    if !matches.is_null() {
+        drop(Box::from_raw(matches));
+    }
+}
+
+/// Releases an error message
+///
+/// # Safety
+///
+/// `string` must be null or a message stored by one of the functions above, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rgrep_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn set_error(error: *mut *mut c_char, message: &str) {
+    if !error.is_null() {
+        let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+        *error = message.into_raw();
+    }
+}
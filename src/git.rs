@@ -0,0 +1,233 @@
+//! `--git-rev`: searches the files of every commit in a range of git revisions instead of the working tree, like
+//! `git grep` given each of the commits, to find when something was added or removed.
+//!
+//! The repository is read with the `git` command: `git rev-list` lists the commits, `git ls-tree` the files of
+//! each, and one `git cat-file --batch` reads their contents. A file is named `COMMIT:PATH` in the output.
+//!
+//! `--changed-since` uses git too, to only search the files that were changed since a commit.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::args::CommandArgs;
+use crate::error::{Error, Result};
+use crate::matcher::{self, Matcher};
+use crate::search::{self, AllMatchSink, Stats, Summary};
+use crate::signal;
+use crate::sink::Sink;
+use crate::walk::Walk;
+
+/// Hex digits commits are shortened to in the names of files
+const COMMIT_DIGITS: usize = 12;
+
+/// Runs `git` with `args`, returns what it printed, or why it failed
+pub(crate) fn git(args: &[&str]) -> std::result::Result<Vec<u8>, String> {
+    debug!("running git {}", args.join(" "));
+    let output = Command::new("git")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("could not run git: {}", err))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(String::from(message.lines().next().unwrap_or("git failed")));
+    }
+    Ok(output.stdout)
+}
+
+/// Makes what git said about the value of `option` the reason it is invalid
+fn invalid<'a>(option: &'a str, value: &'a str) -> impl Fn(String) -> Error + Copy + 'a {
+    move |reason| Error::InvalidValue { option: String::from(option), value: String::from(value), reason }
+}
+
+/// The files under `paths`, or the working directory if there are none, that differ from `since` in the working
+/// tree or are new and not ignored, for `--changed-since`. Deleted files are left out; the paths are relative to
+/// the working directory.
+pub fn changed_files(since: &str, paths: &[String]) -> Result<Vec<String>> {
+    let invalid = invalid("--changed-since", since);
+    // a commit starting with `-` is not taken for an option
+    let mut changed =
+        vec!["diff", "--name-only", "--relative", "--diff-filter=d", "-z", "--end-of-options", since, "--"];
+    changed.extend(paths.iter().map(String::as_str));
+    let mut untracked = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+    untracked.extend(paths.iter().map(String::as_str));
+    let mut files = git(&changed).map_err(invalid)?;
+    files.extend(git(&untracked).map_err(invalid)?);
+    let mut files: Vec<String> = files
+        .split(|&byte| byte == 0)
+        .filter(|file| !file.is_empty())
+        .map(|file| String::from_utf8_lossy(file).into_owned())
+        .collect();
+    files.sort();
+    files.dedup();
+    debug!("{} files changed since {}", files.len(), since);
+    Ok(files)
+}
+
+/// A file in a commit
+struct TreeFile {
+    blob: String,
+    path: String,
+}
+
+/// The files in `commit` under the paths given as files, paths relative to the working directory like
+/// `git ls-tree` prints them
+fn tree_files(commit: &str, paths: &[String]) -> std::result::Result<Vec<TreeFile>, String> {
+    // after --end-of-options, ls-tree would take `--` for a path
+    let mut args = vec!["ls-tree", "-r", "-z", "--end-of-options", commit];
+    args.extend(paths.iter().map(String::as_str));
+    let listing = git(&args)?;
+    // every entry is "MODE TYPE OBJECT\tPATH\0"; submodules are commits, not blobs
+    Ok(listing
+        .split(|&byte| byte == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (info, path) = entry.split_once('\t')?;
+            let mut info = info.split(' ').skip(1);
+            match (info.next(), info.next()) {
+                (Some("blob"), Some(blob)) => Some(TreeFile { blob: String::from(blob), path: String::from(path) }),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// A `git cat-file --batch` reading blobs one after the other
+struct Blobs {
+    process: Child,
+    requests: ChildStdin,
+    contents: BufReader<ChildStdout>,
+}
+
+impl Blobs {
+    fn start() -> io::Result<Blobs> {
+        let mut process = Command::new("git")
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let requests = process.stdin.take().expect("stdin is piped");
+        let contents = BufReader::new(process.stdout.take().expect("stdout is piped"));
+        Ok(Blobs { process, requests, contents })
+    }
+
+    fn read(&mut self, blob: &str) -> io::Result<Vec<u8>> {
+        writeln!(self.requests, "{}", blob)?;
+        self.requests.flush()?;
+        // "OBJECT TYPE SIZE\n", then the contents and a "\n", or "OBJECT missing\n"
+        let mut header = String::new();
+        self.contents.read_line(&mut header)?;
+        let size = header
+            .split_whitespace()
+            .nth(2)
+            .and_then(|size| size.parse::<usize>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("git cat-file: {}", header.trim())))?;
+        let mut contents = vec![0; size + 1];
+        self.contents.read_exact(&mut contents)?;
+        contents.truncate(size);
+        Ok(contents)
+    }
+}
+
+impl Drop for Blobs {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Searches the files of every commit `git rev-list` lists for the range given with `--git-rev`, newest first.
+/// File arguments limit the search to those paths, and `--include` and `--exclude` to the files whose names they
+/// select; `--all-match` works like in the working tree. Errors reading a file are reported to the sink; a range
+/// git does not understand is an error.
+pub fn run<W: Write>(command_args: &CommandArgs, matcher: &dyn Matcher, out: W) -> Result<Summary> {
+    let range = command_args.git_rev.as_str();
+    let invalid = invalid("--git-rev", range);
+    // revisions starting with `-` are not taken for options, like `--output=FILE`
+    let mut args = vec!["rev-list", "--end-of-options"];
+    args.extend(range.split_whitespace());
+    args.push("--");
+    let commits = git(&args).map_err(invalid)?;
+    let commits: Vec<&str> = std::str::from_utf8(&commits).unwrap_or_default().lines().collect();
+    debug!("searching {} commits of {}", commits.len(), range);
+
+    let mut sink = search::output_sink(command_args, matcher, out);
+    let searcher = search::searcher(command_args);
+    let mut blobs = Blobs::start().map_err(|err| invalid(format!("could not run git: {}", err)))?;
+    // most files are the same in many commits; once one did not match, it is not read again
+    let mut without_matches = HashSet::new();
+    let mut summary = Summary { matched: false, had_errors: false, skipped: 0, interrupted: false };
+    let mut stats = Stats::default();
+    let started = std::time::Instant::now();
+    // the files of a commit are left out by name the way the files of the working tree are
+    let names = Walk::of(command_args);
+    let all_match_patterns = if command_args.all_match {
+        let options = command_args.matcher_options();
+        matcher::build_each(&command_args.patterns, &options).expect("the patterns compiled together")
+    } else {
+        Vec::new()
+    };
+
+    'commits: for commit in commits {
+        let files = tree_files(commit, &command_args.files).map_err(invalid)?;
+        for file in files {
+            if signal::interrupted() {
+                summary.interrupted = true;
+                break 'commits;
+            }
+            if without_matches.contains(&file.blob) || !names.selects(&file.path) {
+                continue;
+            }
+            let name = format!("{}:{}", &commit[..COMMIT_DIGITS.min(commit.len())], file.path);
+            let mut all_match = None;
+            let file_sink: &mut dyn Sink = if command_args.all_match {
+                all_match.insert(AllMatchSink::new(sink.as_mut(), &all_match_patterns))
+            } else {
+                sink.as_mut()
+            };
+            let result = blobs
+                .read(&file.blob)
+                .map_err(|err| Error::read(&name, err))
+                .and_then(|contents| searcher.search_slice(matcher, &contents, &name, &mut *file_sink));
+            // with --all-match, a file without every pattern counts as one without matches
+            let missed_patterns = all_match.is_some_and(|all_match| !all_match.all_matched());
+            match result {
+                Ok(file_summary) => {
+                    stats.files_searched += 1;
+                    stats.bytes_searched += file_summary.bytes_searched;
+                    let matched_lines = if missed_patterns { 0 } else { file_summary.matched_lines };
+                    stats.matched_lines += matched_lines;
+                    if matched_lines > 0 {
+                        stats.files_with_matches += 1;
+                        summary.matched = true;
+                    } else {
+                        without_matches.insert(file.blob);
+                    }
+                }
+                Err(err @ Error::Io { path: Some(_), .. }) => {
+                    if !command_args.no_messages {
+                        sink.error(&err).map_err(Error::write)?;
+                    }
+                    summary.had_errors = true;
+                    summary.skipped += 1;
+                    stats.errors += 1;
+                    // git cat-file is out of step with the requests after a failed read
+                    blobs = Blobs::start().map_err(|err| invalid(format!("could not run git: {}", err)))?;
+                }
+                Err(Error::Interrupted) => {
+                    summary.interrupted = true;
+                    break 'commits;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    stats.elapsed = started.elapsed();
+    if command_args.stats {
+        sink.stats(&stats).map_err(Error::write)?;
+    }
+    sink.finish(&stats, summary.status()).map_err(Error::write)?;
+    sink.flush().map_err(Error::write)?;
+    Ok(summary)
+}
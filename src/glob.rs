@@ -0,0 +1,210 @@
+//! Shell wildcards: `*` for any text, `?` for any character and `[...]` for one of a set of characters.
+//!
+//! Only the shells of Unix expand them in arguments. On Windows, cmd.exe and PowerShell pass `*.rs` on as it is,
+//! so rgrep expands the file arguments itself there with [`expand`].
+
+use std::fs;
+
+/// A part of a wildcard pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyText,
+    /// `[a-z_]`, or `[!a-z_]` and `[^a-z_]` when `negated`
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+/// A compiled wildcard pattern, matched against whole names
+///
+/// ```
+/// use rgrep::glob::Glob;
+///
+/// let glob = Glob::new("*.[ch]", false);
+/// assert!(glob.is_match("main.c"));
+/// assert!(!glob.is_match("main.rs"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    tokens: Vec<Token>,
+    ignore_case: bool,
+}
+
+impl Glob {
+    /// Compiles `pattern`. A `[` without a closing `]` is taken literally, like shells do.
+    pub fn new(pattern: &str, ignore_case: bool) -> Glob {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut at = 0;
+        while at < chars.len() {
+            let token = match chars[at] {
+                '*' => Token::AnyText,
+                '?' => Token::AnyChar,
+                '[' => match parse_class(&chars[at + 1..]) {
+                    Some((token, len)) => {
+                        at += len;
+                        token
+                    }
+                    None => Token::Literal('['),
+                },
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+            at += 1;
+        }
+        Glob { tokens, ignore_case }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        // where the last `*` was and the character it was tried to end before, to go back to on a mismatch
+        let mut star: Option<(usize, usize)> = None;
+        let (mut token, mut at) = (0, 0);
+        while at < name.len() {
+            match self.tokens.get(token) {
+                Some(Token::AnyText) => {
+                    star = Some((token, at));
+                    token += 1;
+                    continue;
+                }
+                Some(matched) if self.matches_char(matched, name[at]) => {
+                    token += 1;
+                    at += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match star {
+                // the `*` takes one more character
+                Some((star_token, star_at)) => {
+                    star = Some((star_token, star_at + 1));
+                    token = star_token + 1;
+                    at = star_at + 1;
+                }
+                None => return false,
+            }
+        }
+        self.tokens[token..].iter().all(|token| *token == Token::AnyText)
+    }
+
+    fn matches_char(&self, token: &Token, c: char) -> bool {
+        let fold = |c: char| if self.ignore_case { c.to_lowercase().next().unwrap_or(c) } else { c };
+        match token {
+            Token::Literal(literal) => fold(*literal) == fold(c),
+            Token::AnyChar => true,
+            Token::AnyText => false,
+            Token::Class { negated, ranges } => {
+                let in_range = |c: char| ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+                let found = in_range(c) || self.ignore_case && (in_range(fold(c)) || c.to_uppercase().any(in_range));
+                found != *negated
+            }
+        }
+    }
+}
+
+/// Parses the class after a `[`, returns it and how many characters it took with its `]`
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut at = if negated { 1 } else { 0 };
+    let mut ranges = Vec::new();
+    // a `]` right at the start is one of the characters
+    let mut first = true;
+    loop {
+        let c = *chars.get(at)?;
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, at + 1));
+        }
+        first = false;
+        match (chars.get(at + 1), chars.get(at + 2)) {
+            (Some('-'), Some(&high)) if high != ']' => {
+                ranges.push((c, high));
+                at += 3;
+            }
+            _ => {
+                ranges.push((c, c));
+                at += 1;
+            }
+        }
+    }
+}
+
+/// Whether `text` has `*`, `?` or `[` in it
+pub fn has_wildcards(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || cfg!(windows) && c == '\\'
+}
+
+/// The paths of the files and directories `pattern` matches, sorted, with wildcards in any of its components.
+/// Patterns without wildcards, and patterns that match nothing, are returned as they are, so that opening them
+/// fails with the name the user gave. Names are compared ignoring case on Windows, like its file systems do.
+/// Symbolic links, and junctions and other reparse points of Windows, are followed into like directories: a
+/// pattern goes one level deeper per component, so a link that loops back cannot make the expansion endless.
+///
+/// ```
+/// let files = rgrep::glob::expand("src/*.rs");
+/// assert!(files.contains(&String::from("src/main.rs")));
+/// ```
+pub fn expand(pattern: &str) -> Vec<String> {
+    let first = match pattern.find(['*', '?', '[']) {
+        Some(first) => first,
+        None => return vec![String::from(pattern)],
+    };
+    // the part before the component with the first wildcard is kept as it is, e.g. `C:\` or `\\server\share\`
+    let split = pattern[..first].rfind(is_separator).map_or(0, |separator| separator + 1);
+    let (base, rest) = pattern.split_at(split);
+    let components: Vec<&str> = rest.split(is_separator).filter(|component| !component.is_empty()).collect();
+    let separator = if cfg!(windows) && pattern.contains('\\') { '\\' } else { '/' };
+    let mut found = Vec::new();
+    let base = if base.is_empty() { None } else { Some(String::from(base)) };
+    expand_into(base, &components, separator, &mut found);
+    if found.is_empty() {
+        return vec![String::from(pattern)];
+    }
+    found.sort();
+    found
+}
+
+/// Adds the paths in `directory`, the current one if `None`, that `components` match to `found`
+fn expand_into(directory: Option<String>, components: &[&str], separator: char, found: &mut Vec<String>) {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let join = |name: &str| match &directory {
+        None => String::from(name),
+        Some(directory) if directory.ends_with(is_separator) => format!("{}{}", directory, name),
+        Some(directory) => format!("{}{}{}", directory, separator, name),
+    };
+    let add = |path: String, found: &mut Vec<String>| {
+        if rest.is_empty() {
+            found.push(path);
+        } else if fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+            expand_into(Some(path), rest, separator, found);
+        }
+    };
+    if !has_wildcards(component) {
+        let path = join(component);
+        if fs::symlink_metadata(&path).is_ok() {
+            add(path, found);
+        }
+        return;
+    }
+    let glob = Glob::new(component, cfg!(windows));
+    let entries = match fs::read_dir(directory.as_deref().unwrap_or(".")) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| glob.is_match(name))
+        .collect();
+    names.sort();
+    for name in names {
+        add(join(&name), found);
+    }
+}
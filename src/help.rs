@@ -0,0 +1,120 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::args::{CATEGORIES, USAGE};
+use crate::config::split_shell_words;
+use crate::output;
+use crate::terminal;
+
+/// Column at which option descriptions start
+const DESCRIPTION_COLUMN: usize = 30;
+
+/// Help is never wrapped wider than this, even on very wide terminals
+const MAX_WIDTH: usize = 100;
+
+const OVERVIEW: &[&str] = &[
+    "Search for PATTERNS in each FILE. With no FILE, or when FILE is -, read standard input; \
+     with no FILE and -r, or a terminal as standard input, search the current directory.",
+    "Example: rgrep -i 'hello world' menu.h main.c",
+    "Options may appear anywhere; arguments after -- are never treated as options. \
+     Use -e PATTERN to give patterns that start with a dash, or several patterns. \
+     Flags can be negated with a no- prefix, e.g. --no-ignore-case.",
+    "Default options are read from $RGREP_CONFIG or ~/.rgreprc, one per line, \
+     and from $RGREP_OPTIONS, split like a shell command line.",
+    "A config file line NAME = ARGUMENTS defines a preset: @NAME on the command line, where a file or an option \
+     could be, stands for the ARGUMENTS, e.g. loose = -i -n makes rgrep todo @loose src search src ignoring case, \
+     with line numbers. The pattern and the values of options are never presets.",
+];
+
+/// Pushes `text` wrapped to `width`, with every line indented by `indent` spaces
+fn push_wrapped(help_string: &mut String, text: &str, indent: usize, width: usize) {
+    for line in terminal::wrap(text, width.saturating_sub(indent).max(20)) {
+        help_string.push_str(format!("{:indent$}{}\n", "", line, indent = indent).as_str());
+    }
+}
+
+fn render(width: usize) -> String {
+    let mut help_string = String::from("");
+
+    help_string.push_str(USAGE);
+    help_string.push('\n');
+    for paragraph in OVERVIEW {
+        push_wrapped(&mut help_string, paragraph, 0, width);
+    }
+    help_string.push('\n');
+
+    for category in CATEGORIES {
+        help_string.push_str(format!("{}:\n", category.name).as_str());
+        push_wrapped(&mut help_string, category.description, 2, width);
+        help_string.push('\n');
+        for option in category.options {
+            let short = if option.short.is_empty() {
+                String::from("    ")
+            } else {
+                format!("-{}, ", option.short)
+            };
+            let forms = format!("  {}{}", short, option.long_form());
+            let values = if option.possible_values.is_empty() {
+                String::from("")
+            } else {
+                format!("; one of {}", option.possible_values.join(", "))
+            };
+            let description = format!("{} (default {}{})", option.description, option.default_value, values);
+
+            let mut lines = terminal::wrap(&description, width.saturating_sub(DESCRIPTION_COLUMN).max(20)).into_iter();
+            if forms.len() + 2 <= DESCRIPTION_COLUMN {
+                let first = lines.next().unwrap_or_default();
+                help_string.push_str(format!("{:<column$}{}\n", forms, first, column = DESCRIPTION_COLUMN).as_str());
+            } else {
+                help_string.push_str(format!("{}\n", forms).as_str());
+            }
+            for line in lines {
+                help_string.push_str(format!("{:column$}{}\n", "", line, column = DESCRIPTION_COLUMN).as_str());
+            }
+        }
+        help_string.push('\n');
+    }
+    help_string
+}
+
+/// Writes the help through $PAGER, returns false if no pager could be started
+fn page(help_string: &str) -> bool {
+    let pager = env::var("PAGER").unwrap_or_else(|_| String::from("less"));
+    let words = match split_shell_words(&pager) {
+        Ok(words) if !words.is_empty() => words,
+        _ => return false,
+    };
+    let mut command = Command::new(&words[0]);
+    command.args(&words[1..]).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // quit if the help fits after all, keep colors, do not clear the screen on exit
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => return false,
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // the user may quit the pager before reading everything, that is not an error
+        let _ = stdin.write_all(help_string.as_bytes());
+    }
+    let _ = child.wait();
+    true
+}
+
+/// Prints the help text of `--help`, through a pager if it does not fit on the terminal
+pub fn print_help() {
+    let size = if io::stdout().is_terminal() {
+        Some(terminal::size().unwrap_or(terminal::Size { columns: 80, rows: 24 }))
+    } else {
+        None
+    };
+    let width = size.as_ref().map(|size| size.columns).unwrap_or(80).min(MAX_WIDTH);
+    let help_string = render(width);
+
+    let too_long = matches!(size, Some(size) if help_string.lines().count() >= size.rows);
+    if !(too_long && page(&help_string)) {
+        output::print(&help_string);
+    }
+}
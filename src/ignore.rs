@@ -0,0 +1,216 @@
+//! Rules of `.gitignore` files, and of files written like them such as `.ignore`, and which paths they ignore.
+//!
+//! Rules are matched the way git matches them. A rule without a `/` other than at its end matches names at any
+//! depth below the directory of its file, and one with a `/` matches paths from there. `*`, `?` and `[...]` do not
+//! match `/`; a `**` component matches any number of directories, and a `/**` at the end everything inside. A `/`
+//! at the end makes a rule match only directories, and a `!` in front makes it include again what earlier rules
+//! ignored. The last rule that matches decides, and nothing inside an ignored directory can be included again.
+//! `#` starts a comment, and a backslash keeps a `#` or `!` at the start, or a space at the end, as it is.
+//!
+//! rgrep searches what it is given, like grep, and only reads these files with `--gitignore`, see
+//! [`walk`](crate::walk); this is also for tools that want to know which files git leaves out.
+//!
+//! ```
+//! use rgrep::ignore::Ignore;
+//!
+//! let mut ignore = Ignore::new();
+//! ignore.add_rules("target/\n*.log\n!keep.log\n", ".gitignore");
+//! ignore.add_rules("/generated.rs\n", "src/.gitignore");
+//! assert!(ignore.is_ignored("target/debug/rgrep", false));
+//! assert!(ignore.is_ignored("logs/run.log", false));
+//! assert!(!ignore.is_ignored("logs/keep.log", false));
+//! assert!(ignore.is_ignored("src/generated.rs", false));
+//! assert!(!ignore.is_ignored("src/parser/generated.rs", false));
+//!
+//! let rule = ignore.matched("build.log", false).unwrap();
+//! assert_eq!((rule.source.as_str(), rule.line_number, rule.pattern.as_str()), (".gitignore", 2, "*.log"));
+//! ```
+
+use std::fs;
+
+use crate::error::{Error, Result};
+use crate::glob::Glob;
+
+/// A component of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Component {
+    /// `**`
+    AnyDirectories,
+    Name(Glob),
+}
+
+/// A rule of an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// The line of the rule as it was written, without the spaces at its end
+    pub pattern: String,
+    /// The file the rule is from, as it was given
+    pub source: String,
+    /// The number of its line in it, starting at 1
+    pub line_number: usize,
+    /// It started with `!`, so what it matches is not ignored
+    pub negated: bool,
+    /// It ended with `/`, so it only matches directories
+    pub directory_only: bool,
+    /// The directory of the file, which paths the rule matches are in, with a `/` at its end unless it is empty
+    base: String,
+    /// The rule has a `/` before its end, so it matches paths from the base rather than names
+    anchored: bool,
+    components: Vec<Component>,
+}
+
+impl Rule {
+    /// Parses the line `line_number` of the file at `source`, `None` if it is blank or a comment
+    fn parse(line: &str, source: &str, line_number: usize) -> Option<Rule> {
+        let pattern = trim_end(line);
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+        let (negated, rest) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => {
+                let escaped = pattern.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!']));
+                (false, escaped.unwrap_or(pattern))
+            }
+        };
+        let (directory_only, rest) = match rest.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let anchored = rest.contains('/');
+        let rest = rest.strip_prefix('/').unwrap_or(rest);
+        if rest.is_empty() {
+            return None;
+        }
+        let components = rest
+            .split('/')
+            .map(|component| match component {
+                "**" => Component::AnyDirectories,
+                // an escaped space at the end stays a space
+                name => Component::Name(Glob::new(&name.replace("\\ ", " "), false)),
+            })
+            .collect();
+        let base = match source.rfind(is_separator) {
+            Some(end) => source[..=end].replace('\\', "/"),
+            None => String::new(),
+        };
+        let base = String::from(base.trim_start_matches("./"));
+        let pattern = String::from(pattern);
+        let source = String::from(source);
+        Some(Rule { pattern, source, line_number, negated, directory_only, base, anchored, components })
+    }
+
+    /// Whether the rule matches `path`, given from the directory all rules are relative to
+    fn is_match(&self, path: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        let rest = match path.strip_prefix(self.base.as_str()) {
+            Some(rest) if !rest.is_empty() => rest,
+            _ => return false,
+        };
+        let names: Vec<&str> = rest.split('/').collect();
+        if self.anchored {
+            matches(&self.components, &names)
+        } else {
+            // a name, at any depth
+            let name = names.last().expect("split gives at least one part");
+            matches(&self.components, &[name])
+        }
+    }
+}
+
+/// Whether `components` match all of `names`
+fn matches(components: &[Component], names: &[&str]) -> bool {
+    match components.split_first() {
+        None => names.is_empty(),
+        // at the end, everything inside, but not the directory itself
+        Some((Component::AnyDirectories, [])) => !names.is_empty(),
+        Some((Component::AnyDirectories, rest)) => (0..=names.len()).any(|skipped| matches(rest, &names[skipped..])),
+        Some((Component::Name(glob), rest)) => {
+            names.split_first().is_some_and(|(name, names)| glob.is_match(name) && matches(rest, names))
+        }
+    }
+}
+
+/// `line` without the spaces at its end that are not escaped, and without its `\r`
+fn trim_end(line: &str) -> &str {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || cfg!(windows) && c == '\\'
+}
+
+/// The rules of ignore files, which tell whether paths are ignored. Paths are given from one directory, and so
+/// are the files the rules are from, so the rules of `src/.gitignore` only match what is in `src`.
+#[derive(Debug, Clone, Default)]
+pub struct Ignore {
+    rules: Vec<Rule>,
+}
+
+impl Ignore {
+    pub fn new() -> Ignore {
+        Ignore::default()
+    }
+
+    /// Reads the rules of the ignore file at `path`. Files in deeper directories have to be added after those
+    /// in the directories above them, so their rules come later and win.
+    pub fn add_file(&mut self, path: &str) -> Result<()> {
+        let text = fs::read_to_string(path).map_err(|err| Error::read(path, err))?;
+        self.add_rules(&text, path);
+        Ok(())
+    }
+
+    /// Adds the rules in `text`, one per line, as the contents of an ignore file at `source`
+    pub fn add_rules(&mut self, text: &str, source: &str) {
+        let rules = text.lines().enumerate().filter_map(|(index, line)| Rule::parse(line, source, index + 1));
+        self.rules.extend(rules);
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rule that decides about `path`, a directory if `is_dir`: the last one that matches it, or one that
+    /// ignores a directory it is in. A rule with [`negated`](Rule::negated) means it is not ignored because of
+    /// it; `None` means no rule is about it.
+    ///
+    /// ```
+    /// use rgrep::ignore::Ignore;
+    ///
+    /// let mut ignore = Ignore::new();
+    /// ignore.add_rules("docs/**/*.html\nbuild/\n!build/keep.txt\n", ".gitignore");
+    /// assert!(ignore.is_ignored("docs/index.html", false));
+    /// assert!(ignore.is_ignored("docs/api/v1/index.html", false));
+    /// assert!(!ignore.is_ignored("build", false));
+    /// // the directory is ignored, so what is in it cannot be included again
+    /// assert_eq!(ignore.matched("build/keep.txt", false).unwrap().pattern, "build/");
+    /// ```
+    pub fn matched(&self, path: &str, is_dir: bool) -> Option<&Rule> {
+        let path = path.replace(is_separator, "/");
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        // the directories it is in first, since nothing in an ignored one is included again
+        let directories = path.match_indices('/').map(|(end, _)| &path[..end]);
+        for directory in directories {
+            if let Some(rule) = self.last_match(directory, true).filter(|rule| !rule.negated) {
+                return Some(rule);
+            }
+        }
+        self.last_match(path, is_dir)
+    }
+
+    /// Whether a rule ignores `path`, a directory if `is_dir`
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.matched(path, is_dir).is_some_and(|rule| !rule.negated)
+    }
+
+    fn last_match(&self, path: &str, is_dir: bool) -> Option<&Rule> {
+        self.rules.iter().rev().find(|rule| rule.is_match(path, is_dir))
+    }
+}
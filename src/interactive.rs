@@ -0,0 +1,511 @@
+//! `--interactive`: a terminal UI to browse the results of a search.
+//!
+//! The search runs to the end first, collecting the matching lines and some context around them. The matches are
+//! then listed grouped by file, with the lines around the selected one in a preview below. Typing filters the list
+//! to matches whose line or file name contains the text, and Enter opens the selected match in `$VISUAL` or
+//! `$EDITOR` at its line. With several patterns, the matches of each are highlighted in a color of their own.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::args::CommandArgs;
+use crate::config::split_shell_words;
+use crate::error::{Error, Result};
+use crate::matcher::{self, Matcher, MultiMatcher};
+use crate::search::{self, Summary};
+use crate::sink::{Sink, SinkLine};
+use crate::terminal::{self, RawMode};
+
+/// Where keys are read from and the UI is drawn, whatever standard input is
+const TTY: &str = "/dev/tty";
+
+/// Lines collected before and after every match, for the preview
+const PREVIEW_CONTEXT: u32 = 5;
+
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+const CLEAR_LINE: &str = "\x1b[2K";
+const RESET: &str = "\x1b[0m";
+/// Styles of the matches of the first pattern, the second and so on, starting over after the last one
+const MATCH_STYLES: &[&str] = &["\x1b[1;31m", "\x1b[1;32m", "\x1b[1;33m", "\x1b[1;34m", "\x1b[1;36m"];
+const MATCH_STYLE_OFF: &str = "\x1b[22;39m";
+const HEADER_STYLE: &str = "\x1b[1;35m";
+const SELECTED_STYLE: &str = "\x1b[7m";
+const DIMMED_STYLE: &str = "\x1b[2m";
+
+/// An input with the lines collected from it
+struct Collected {
+    path: String,
+    /// Lines by number, with whether they matched
+    lines: BTreeMap<usize, (bool, Vec<u8>)>,
+}
+
+/// Collects what a search finds, to be browsed once it is done
+#[derive(Default)]
+struct CollectSink {
+    inputs: Vec<Collected>,
+    /// Every match as the input it is in and its line number, 0 for a binary input
+    matches: Vec<(usize, usize)>,
+    /// Inputs that could not be read, shown after the UI closes
+    errors: Vec<String>,
+}
+
+impl CollectSink {
+    fn add_line(&mut self, line: &SinkLine, matched: bool) {
+        if let Some(input) = self.inputs.last_mut() {
+            input.lines.insert(line.line_number, (matched, line.line.to_vec()));
+        }
+        if matched {
+            self.matches.push((self.inputs.len() - 1, line.line_number));
+        }
+    }
+}
+
+impl Sink for CollectSink {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inputs.push(Collected { path: String::from(path), lines: BTreeMap::new() });
+        Ok(())
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.add_line(line, true);
+        Ok(())
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.add_line(line, false);
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        // there are no lines to show, only that the input matches
+        if !self.inputs.is_empty() {
+            self.matches.push((self.inputs.len() - 1, 0));
+        }
+        Ok(())
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.errors.push(error.to_string());
+        Ok(())
+    }
+}
+
+/// Searches like [`search::run`], then lets the user browse the matches until they quit. Standard output has to
+/// be a terminal; keys are read from the controlling terminal, so standard input can still be searched.
+pub fn run(command_args: &CommandArgs, matcher: &dyn Matcher) -> Result<Summary> {
+    let mut collect_args = command_args.clone();
+    collect_args.after_context = collect_args.after_context.max(PREVIEW_CONTEXT);
+    collect_args.before_context = collect_args.before_context.max(PREVIEW_CONTEXT);
+    collect_args.count = false;
+    let mut sink = CollectSink::default();
+    let summary = search::run_with_sink(&collect_args, matcher, &mut sink)?;
+
+    if !sink.matches.is_empty() && !summary.interrupted {
+        let patterns = MultiMatcher::new(matcher::build_each(&command_args.patterns, &command_args.matcher_options())?);
+        let tty = OpenOptions::new().read(true).write(true).open(TTY).map_err(|err| Error::read(TTY, err))?;
+        let mut browser = Browser::new(&sink, &patterns, tty);
+        browser.run().map_err(Error::write)?;
+    }
+    for error in &sink.errors {
+        eprintln!("rgrep: {}", error);
+    }
+    Ok(summary)
+}
+
+/// A key that does something in the browser
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Enter,
+    Backspace,
+    ClearFilter,
+    Quit,
+    Text(String),
+}
+
+impl Key {
+    /// Decodes what one read from the terminal returned, `None` for keys that do nothing
+    fn parse(input: &[u8]) -> Option<Key> {
+        Some(match input {
+            b"\x1b[A" | b"\x1bOA" | [0x10] | [0x0b] => Key::Up,
+            b"\x1b[B" | b"\x1bOB" | [0x0e] => Key::Down,
+            b"\x1b[5~" => Key::PageUp,
+            b"\x1b[6~" => Key::PageDown,
+            b"\r" | b"\n" => Key::Enter,
+            [0x7f] | [0x08] => Key::Backspace,
+            [0x15] => Key::ClearFilter,
+            // Esc, Ctrl-C, Ctrl-D
+            [0x1b] | [0x03] | [0x04] => Key::Quit,
+            _ if input.first().is_some_and(|&byte| byte >= 0x20 && byte != 0x7f) => {
+                let text = std::str::from_utf8(input).ok()?;
+                Key::Text(text.chars().filter(|c| !c.is_control()).collect())
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// A row of the match list
+enum Row {
+    /// The name of an input, above its matches
+    Header(usize),
+    /// The nth of the matches the filter lets through
+    Match(usize),
+}
+
+struct Browser<'s> {
+    sink: &'s CollectSink,
+    /// The patterns apart, to highlight the matches of each in its own style
+    patterns: &'s MultiMatcher,
+    tty: File,
+    /// Typed text the matches are filtered by
+    filter: String,
+    /// Indexes into `sink.matches` of the matches the filter lets through
+    visible: Vec<usize>,
+    /// Index into `visible` of the selected match
+    selected: usize,
+    /// First row of the list on screen
+    top: usize,
+    /// Shown in the status line until the next key, e.g. when the editor failed
+    message: Option<String>,
+}
+
+impl<'s> Browser<'s> {
+    fn new(sink: &'s CollectSink, patterns: &'s MultiMatcher, tty: File) -> Browser<'s> {
+        let filter = String::new();
+        let mut browser =
+            Browser { sink, patterns, tty, filter, visible: Vec::new(), selected: 0, top: 0, message: None };
+        browser.apply_filter();
+        browser
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        let mut raw_mode = Some(RawMode::enable(&self.tty)?);
+        self.tty.write_all(ENTER_SCREEN.as_bytes())?;
+        let result = self.browse(&mut raw_mode);
+        // the terminal is given back however browsing ended
+        drop(raw_mode);
+        self.tty.write_all(LEAVE_SCREEN.as_bytes())?;
+        self.tty.flush()?;
+        result
+    }
+
+    fn browse(&mut self, raw_mode: &mut Option<RawMode>) -> io::Result<()> {
+        let mut input = [0; 64];
+        loop {
+            self.draw()?;
+            let read = self.tty.read(&mut input)?;
+            if read == 0 {
+                return Ok(());
+            }
+            let key = match Key::parse(&input[..read]) {
+                Some(key) => key,
+                None => continue,
+            };
+            self.message = None;
+            let page = self.list_height().max(1);
+            match key {
+                Key::Up => self.selected = self.selected.saturating_sub(1),
+                Key::Down => self.selected = (self.selected + 1).min(self.visible.len().saturating_sub(1)),
+                Key::PageUp => self.selected = self.selected.saturating_sub(page),
+                Key::PageDown => self.selected = (self.selected + page).min(self.visible.len().saturating_sub(1)),
+                Key::Enter => {
+                    // the editor gets the terminal as it normally is
+                    raw_mode.take();
+                    self.tty.write_all(LEAVE_SCREEN.as_bytes())?;
+                    self.tty.flush()?;
+                    let opened = self.open_selected();
+                    *raw_mode = Some(RawMode::enable(&self.tty)?);
+                    self.tty.write_all(ENTER_SCREEN.as_bytes())?;
+                    if let Err(message) = opened {
+                        self.message = Some(message);
+                    }
+                }
+                Key::Backspace => {
+                    self.filter.pop();
+                    self.apply_filter();
+                }
+                Key::ClearFilter => {
+                    self.filter.clear();
+                    self.apply_filter();
+                }
+                Key::Quit => return Ok(()),
+                Key::Text(text) => {
+                    self.filter.push_str(&text);
+                    self.apply_filter();
+                }
+            }
+        }
+    }
+
+    /// Keeps the matches whose line or input name contains the filter, ignoring case, and selects the first one
+    fn apply_filter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        let sink = self.sink;
+        self.visible = (0..sink.matches.len())
+            .filter(|&index| {
+                let (input, line_number) = sink.matches[index];
+                let input = &sink.inputs[input];
+                filter.is_empty()
+                    || input.path.to_lowercase().contains(&filter)
+                    || input
+                        .lines
+                        .get(&line_number)
+                        .is_some_and(|(_, line)| String::from_utf8_lossy(line).to_lowercase().contains(&filter))
+            })
+            .collect();
+        self.selected = 0;
+        self.top = 0;
+    }
+
+    fn size(&self) -> (usize, usize) {
+        terminal::size().map(|size| (size.columns, size.rows)).unwrap_or((80, 24))
+    }
+
+    /// Lines of the preview, without the line above it with the name of the selected input
+    fn preview_height(&self) -> usize {
+        let (_, rows) = self.size();
+        if rows < 8 {
+            0
+        } else {
+            (2 * PREVIEW_CONTEXT as usize + 1).min(rows / 3)
+        }
+    }
+
+    fn list_height(&self) -> usize {
+        let (_, rows) = self.size();
+        let preview = self.preview_height();
+        rows.saturating_sub(1 + if preview > 0 { preview + 1 } else { 0 })
+    }
+
+    /// The list as rows, with where the selected match is in it
+    fn rows(&self) -> (Vec<Row>, usize) {
+        let mut rows = Vec::new();
+        let mut selected_row = 0;
+        let mut previous_input = None;
+        for (position, &index) in self.visible.iter().enumerate() {
+            let (input, _) = self.sink.matches[index];
+            if previous_input != Some(input) {
+                rows.push(Row::Header(input));
+                previous_input = Some(input);
+            }
+            if position == self.selected {
+                selected_row = rows.len();
+            }
+            rows.push(Row::Match(position));
+        }
+        (rows, selected_row)
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let (columns, _) = self.size();
+        let height = self.list_height();
+        let (rows, selected_row) = self.rows();
+        // the header of the first match of an input is kept in view with it
+        let wanted_top = if selected_row > 0 && matches!(rows[selected_row - 1], Row::Header(_)) {
+            selected_row - 1
+        } else {
+            selected_row
+        };
+        if wanted_top < self.top {
+            self.top = wanted_top;
+        } else if selected_row >= self.top + height {
+            self.top = selected_row + 1 - height;
+        }
+
+        let mut screen = String::from("\x1b[H");
+        for row in rows.iter().skip(self.top).take(height) {
+            screen.push_str(CLEAR_LINE);
+            match *row {
+                Row::Header(input) => {
+                    screen.push_str(HEADER_STYLE);
+                    screen.push_str(&fit(&self.sink.inputs[input].path, columns));
+                }
+                Row::Match(position) => {
+                    let (input, line_number) = self.sink.matches[self.visible[position]];
+                    let selected = position == self.selected;
+                    if selected {
+                        screen.push_str(SELECTED_STYLE);
+                    }
+                    let prefix = format!("{:>6}: ", line_number);
+                    screen.push_str(&prefix);
+                    let text = self.line_text(input, line_number);
+                    screen.push_str(&self.styled(&text, columns.saturating_sub(prefix.len()), !selected));
+                }
+            }
+            screen.push_str(RESET);
+            screen.push_str("\r\n");
+        }
+        for _ in rows.len().saturating_sub(self.top).min(height)..height {
+            screen.push_str(CLEAR_LINE);
+            screen.push_str("\r\n");
+        }
+        self.draw_preview(&mut screen, columns);
+
+        let status = match &self.message {
+            Some(message) => format!("  {}", message),
+            None => format!(
+                "  {}/{} matches  Up/Down move  Enter opens in $EDITOR  Esc quits",
+                self.visible.len(),
+                self.sink.matches.len()
+            ),
+        };
+        let prompt = format!("> {}", self.filter);
+        screen.push_str(CLEAR_LINE);
+        screen.push_str(&prompt);
+        screen.push_str(DIMMED_STYLE);
+        screen.push_str(&fit(&status, columns.saturating_sub(prompt.chars().count())));
+        screen.push_str(RESET);
+        // the cursor goes where typed text appears
+        screen.push_str(&format!("\r\x1b[{}C\x1b[?25h", prompt.chars().count()));
+        self.tty.write_all(screen.as_bytes())?;
+        self.tty.flush()
+    }
+
+    fn draw_preview(&self, screen: &mut String, columns: usize) {
+        let height = self.preview_height();
+        if height == 0 {
+            return;
+        }
+        let selected = self.visible.get(self.selected).map(|&index| self.sink.matches[index]);
+        let title = match selected {
+            Some((input, 0)) => format!("-- {} ", self.sink.inputs[input].path),
+            Some((input, line_number)) => format!("-- {}:{} ", self.sink.inputs[input].path, line_number),
+            None => String::from("-- no matches "),
+        };
+        screen.push_str(CLEAR_LINE);
+        screen.push_str(DIMMED_STYLE);
+        // filled by hand, `format!` panics on widths above `u16::MAX`
+        let rule = "-".repeat(columns.saturating_sub(title.chars().count()));
+        screen.push_str(&fit(&format!("{}{}", title, rule), columns));
+        screen.push_str(RESET);
+        screen.push_str("\r\n");
+
+        let mut lines = Vec::new();
+        match selected {
+            Some((input, 0)) => lines.push(format!("Binary file {} matches", self.sink.inputs[input].path)),
+            Some((input, line_number)) => {
+                let half = height / 2;
+                let first = line_number.saturating_sub(half).max(1);
+                for (number, (matched, line)) in self.sink.inputs[input].lines.range(first..first + height) {
+                    let separator = if *matched { ':' } else { '-' };
+                    let prefix = format!("{:>6}{} ", number, separator);
+                    let text = String::from_utf8_lossy(line).into_owned();
+                    let styled = if *matched {
+                        self.styled(&text, columns.saturating_sub(prefix.len()), true)
+                    } else {
+                        fit(&text, columns.saturating_sub(prefix.len()))
+                    };
+                    lines.push(format!("{}{}", prefix, styled));
+                }
+            }
+            None => {}
+        }
+        for index in 0..height {
+            screen.push_str(CLEAR_LINE);
+            if let Some(line) = lines.get(index) {
+                screen.push_str(line);
+            }
+            screen.push_str(RESET);
+            screen.push_str("\r\n");
+        }
+    }
+
+    fn line_text(&self, input: usize, line_number: usize) -> String {
+        if line_number == 0 {
+            return String::from("binary file matches");
+        }
+        match self.sink.inputs[input].lines.get(&line_number) {
+            Some((_, line)) => String::from_utf8_lossy(line).into_owned(),
+            None => String::new(),
+        }
+    }
+
+    /// The matches in `text` with the index of the pattern of each, from left to right
+    fn pattern_matches(&self, text: &[u8]) -> Vec<(usize, matcher::Match)> {
+        let mut matches = Vec::new();
+        let mut at = 0;
+        while at <= text.len() {
+            let (pattern, found) = match self.patterns.find_pattern_at(text, at) {
+                Some(found) => found,
+                None => break,
+            };
+            // an empty match must not be found again at the same place
+            at = if found.is_empty() { found.end + 1 } else { found.end };
+            if !found.is_empty() {
+                matches.push((pattern, found));
+            }
+        }
+        matches
+    }
+
+    /// `text` cut to `width` columns, with the matches in it highlighted if `highlight` is set
+    fn styled(&self, text: &str, width: usize, highlight: bool) -> String {
+        let matches = if highlight { self.pattern_matches(text.as_bytes()) } else { Vec::new() };
+        let mut styled = String::new();
+        for (used, (at, c)) in text.char_indices().enumerate() {
+            if used == width {
+                break;
+            }
+            if matches.iter().any(|(_, found)| found.end == at) {
+                styled.push_str(MATCH_STYLE_OFF);
+            }
+            if let Some((pattern, _)) = matches.iter().find(|(_, found)| found.start == at) {
+                styled.push_str(MATCH_STYLES[pattern % MATCH_STYLES.len()]);
+            }
+            styled.push(printable(c));
+        }
+        styled
+    }
+
+    /// Opens the selected match in the editor and waits for it, returns what to tell the user if that failed
+    fn open_selected(&self) -> std::result::Result<(), String> {
+        let (input, line_number) = match self.visible.get(self.selected) {
+            Some(&index) => self.sink.matches[index],
+            None => return Ok(()),
+        };
+        let path = &self.sink.inputs[input].path;
+        if !Path::new(path).is_file() {
+            return Err(format!("{} is not a file that can be opened", path));
+        }
+        let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or_else(|_| String::from("vi"));
+        let words = split_shell_words(&editor).map_err(|reason| format!("cannot run $EDITOR: {}", reason))?;
+        let (program, editor_args) = words.split_first().ok_or("$EDITOR is empty")?;
+        let mut command = Command::new(program);
+        command.args(editor_args);
+        if line_number > 0 {
+            command.arg(format!("+{}", line_number));
+        }
+        let stdin = self.tty.try_clone().map_err(|err| err.to_string())?;
+        let status = command
+            .arg(path)
+            .stdin(Stdio::from(stdin))
+            .status()
+            .map_err(|err| format!("cannot run {}: {}", program, err))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {}", program, status))
+        }
+    }
+}
+
+/// `text` cut to `width` characters, with control characters made visible
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).map(printable).collect()
+}
+
+fn printable(c: char) -> char {
+    match c {
+        '\t' => ' ',
+        c if c.is_control() => '?',
+        c => c,
+    }
+}
@@ -0,0 +1,202 @@
+//! Just enough of a JSON reader for the events rgrep prints, read back by `--remote`, and the requests of
+//! `--editor-server`.
+
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(member, _)| member == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn string(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// The bytes of `{"text":...}` or `{"bytes":...}`
+    pub(crate) fn data(&self) -> Option<Vec<u8>> {
+        match (self.get("text"), self.get("bytes")) {
+            (Some(text), _) => Some(text.string()?.as_bytes().to_vec()),
+            (None, Some(bytes)) => decode_base64(bytes.string()?),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Reads `text`, which has to be one JSON value with nothing but whitespace around it
+pub(crate) fn parse(text: &str) -> Option<Json> {
+    let mut parser = Parser { text: text.as_bytes(), at: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    Some(value).filter(|_| parser.at == parser.text.len())
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.at).is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.at += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.text.get(self.at) == Some(&byte);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Option<Json> {
+        let found = self.text[self.at..].starts_with(keyword.as_bytes());
+        self.at += keyword.len();
+        Some(value).filter(|_| found)
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match self.text.get(self.at)? {
+            b'{' => {
+                self.at += 1;
+                let mut members = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let name = self.string()?;
+                        if !self.eat(b':') {
+                            return None;
+                        }
+                        members.push((name, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Object(members))
+            }
+            b'[' => {
+                self.at += 1;
+                let mut values = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        values.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Array(values))
+            }
+            b'"' => self.string().map(Json::String),
+            b't' => self.keyword("true", Json::Bool(true)),
+            b'f' => self.keyword("false", Json::Bool(false)),
+            b'n' => self.keyword("null", Json::Null),
+            _ => {
+                let start = self.at;
+                while self.text.get(self.at).is_some_and(|byte| b"+-.eE0123456789".contains(byte)) {
+                    self.at += 1;
+                }
+                std::str::from_utf8(&self.text[start..self.at]).ok()?.parse().ok().map(Json::Number)
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.text.get(self.at) != Some(&b'"') {
+            return None;
+        }
+        self.at += 1;
+        let mut text = Vec::new();
+        loop {
+            match *self.text.get(self.at)? {
+                b'"' => break,
+                b'\\' => {
+                    self.at += 1;
+                    match *self.text.get(self.at)? {
+                        b'n' => text.push(b'\n'),
+                        b'r' => text.push(b'\r'),
+                        b't' => text.push(b'\t'),
+                        b'b' => text.push(8),
+                        b'f' => text.push(12),
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // a surrogate pair for a character outside the Basic Multilingual Plane
+                            if (0xd800..0xdc00).contains(&code) && self.text[self.at + 1..].starts_with(b"\\u") {
+                                self.at += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.checked_sub(0xdc00)?);
+                            }
+                            let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                            text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => text.push(other),
+                    }
+                }
+                byte => text.push(byte),
+            }
+            self.at += 1;
+        }
+        self.at += 1;
+        String::from_utf8(text).ok()
+    }
+
+    /// Reads the 4 hex digits after `\u`, leaving `at` on the last one
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.at + 1..self.at + 5)?;
+        self.at += 4;
+        u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+    }
+}
+
+/// Decodes standard base64 with padding
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.trim_end_matches('=').as_bytes().chunks(4) {
+        let mut value = 0u32;
+        for (index, digit) in chunk.iter().enumerate() {
+            let digit = ALPHABET.iter().position(|known| known == digit)? as u32;
+            value |= digit << (18 - 6 * index);
+        }
+        bytes.extend_from_slice(&value.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(bytes)
+}
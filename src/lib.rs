@@ -0,0 +1,92 @@
+//! rgrep searches files for lines matching patterns, like grep.
+//!
+//! The `rgrep` binary is a thin wrapper around this library: it builds a [`CommandArgs`](args::CommandArgs)
+//! from the command line with [`parse_args`](args::parse_args), builds a [`Matcher`](matcher::Matcher)
+//! for the patterns and hands both to [`search::run`]. Other programs can do the same without spawning a process:
+//!
+//! ```no_run
+//! use rgrep::args::CommandArgs;
+//! use rgrep::matcher::{self, MatcherOptions};
+//!
+//! let command_args = CommandArgs {
+//!     patterns: vec![String::from("TODO|FIXME")],
+//!     files: vec![String::from("src/main.rs")],
+//!     ..CommandArgs::default()
+//! };
+//! let matcher = matcher::build(&command_args.patterns, &MatcherOptions::default()).unwrap();
+//! let mut out = Vec::new();
+//! let summary = rgrep::search::run(&command_args, matcher.as_ref(), &mut out).unwrap();
+//! println!("matched: {}", summary.matched);
+//! ```
+//!
+//! Everything that is about the command line is behind the default `cli` feature. Without it, the crate is
+//! the matcher, the [`Searcher`](search::Searcher) and the [sinks](sink), which only need inputs to read from.
+//! That is how it builds for `wasm32-unknown-unknown`, where there is no file system, process or terminal:
+//!
+//! ```text
+//! cargo build --lib --no-default-features --features std --target wasm32-unknown-unknown
+//! ```
+//!
+//! Inputs are then given as readers, e.g. `Input::Reader("buffer", Box::new(bytes))` for a byte slice.
+//!
+//! The [`matcher`] module only needs `alloc`. Without the `std` feature, which is a default one as well, the
+//! crate is `no_std` and has nothing else, for targets without an operating system:
+//!
+//! ```text
+//! cargo rustc --lib --no-default-features --crate-type rlib --target thumbv7em-none-eabihf
+//! ```
+//!
+//! Without `std`, [`matcher::build`] is not there either; build [`LiteralMatcher`](matcher::LiteralMatcher),
+//! [`LiteralSet`](matcher::LiteralSet) or [`RegexMatcher`](matcher::RegexMatcher) directly.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+#[macro_use]
+pub mod log;
+
+#[cfg(feature = "cli")]
+pub mod args;
+#[cfg(feature = "cli")]
+pub mod completions;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "cli")]
+pub mod daemon;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "cli")]
+pub mod git;
+#[cfg(feature = "cli")]
+pub mod glob;
+#[cfg(feature = "cli")]
+pub mod ignore;
+#[cfg(feature = "cli")]
+mod help;
+#[cfg(feature = "cli")]
+pub mod interactive;
+#[cfg(feature = "cli")]
+mod json;
+#[cfg(feature = "cli")]
+pub mod man;
+pub mod matcher;
+#[cfg(feature = "cli")]
+pub mod output;
+#[cfg(feature = "cli")]
+pub mod remote;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod signal;
+#[cfg(feature = "std")]
+pub mod sink;
+#[cfg(feature = "cli")]
+mod terminal;
+#[cfg(feature = "cli")]
+pub mod watch;
+#[cfg(feature = "cli")]
+pub mod walk;
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// How much internal diagnostics to print to stderr
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Off = 0,
+    /// Decisions rgrep makes: config files, inputs, search strategies, timings
+    Debug = 1,
+    /// Everything in debug plus per-line details
+    Trace = 2,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Sets the level and starts the clock that log timestamps are relative to
+pub fn init(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+    START.get_or_init(Instant::now);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// Prints a log line with the time since `init`, the level and the module it came from
+pub fn write(level: Level, module: &str, message: std::fmt::Arguments) {
+    let elapsed = START.get_or_init(Instant::now).elapsed();
+    let level = match level {
+        Level::Trace => "TRACE",
+        _ => "DEBUG",
+    };
+    let module = module.strip_prefix("rgrep::").unwrap_or(module);
+    eprintln!("[{:>9.3}ms {} {}] {}", elapsed.as_secs_f64() * 1000.0, level, module, message);
+}
+
+/// Logs at debug level, see [`Level::Debug`](crate::log::Level::Debug)
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            $crate::log::write($crate::log::Level::Debug, module_path!(), format_args!($($arg)*));
+        }
+    };
+}
+
+/// Logs at trace level, see [`Level::Trace`](crate::log::Level::Trace)
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Trace) {
+            $crate::log::write($crate::log::Level::Trace, module_path!(), format_args!($($arg)*));
+        }
+    };
+}
@@ -1,239 +1,189 @@
 use std::env;
-use std::process::{exit};
-
-#[derive(Debug)]
-struct CommandArgs {
-    files: Vec<String>,
-    query: String,
-    after_context: u32,
-    before_context: u32,
-    ignore_case: bool,
-}
-
-fn option_error_string(option: &str, value: &str) -> String {
-    format!(
-        "Option {option} got invalid value: {value}",
-        option = option,
-        value = value
-    )
+use std::io::{self, IsTerminal};
+use std::process::exit;
+
+use rgrep::args::{self, parse_args, CommandArgs, CATEGORIES, USAGE};
+use rgrep::error::Error;
+use rgrep::matcher;
+use rgrep::search::DirectoryAction;
+use rgrep::{
+    completions, config, daemon, debug, git, glob, interactive, log, man, output, remote, search, signal, watch,
+};
+
+/// Prints a short usage hint for a command line that cannot be searched and exits with status 2
+fn usage_error(message: &str) -> ! {
+    eprintln!("rgrep: {}", message);
+    eprintln!("{}", USAGE);
+    eprintln!("Try 'rgrep --help' for more information.");
+    exit(2);
 }
 
-struct Option<'a> {
-    short_form: &'a str,
-    long_form: &'a str,
-    default_value: &'a str,
-    description: &'a str,
+/// `file` with the separator of Windows, so that `src/main.rs` and `src\main.rs` are printed the same way.
+/// UNC paths like `\\server\share\a.txt` and paths longer than MAX_PATH need nothing more, the standard library
+/// opens them with a `\\?\` prefix. Paths that already have it are left alone, `/` is part of a name in them.
+fn windows_separators(file: &str) -> String {
+    if file.starts_with(r"\\?\") {
+        String::from(file)
+    } else {
+        file.replace('/', "\\")
+    }
 }
 
-struct Category<'a> {
-    name: &'a str,
-    options: Vec<Option<'a>>,
-}
+fn main() {
+    let mut command_args = CommandArgs::default();
 
-fn print_help() {
-    let categories = vec![
-        Category {
-            name: "Pattern selection and interpretation",
-            options: vec![
-                Option {
-                    short_form: "-i",
-                    long_form: "--ignore-case",
-                    default_value: "false",
-                    description: "ignore case distinctions in patterns and data",
-                }
-            ],
-        },
-        Category {
-            name: "Context control",
-            options: vec![
-                Option {
-                    short_form: "-A",
-                    long_form: "--after-context=NUM",
-                    default_value: "0",
-                    description: "print NUM lines of trailing context",
-                },
-                Option {
-                    short_form: "-B",
-                    long_form: "--before-context=NUM",
-                    default_value: "0",
-                    description: "print NUM lines of leading context",
-                }
-            ],
+    let args: Vec<String> = env::args().collect();
+    let mut args = match config::expand_presets(args) {
+        Ok(args) => args,
+        Err(x) => {
+            eprintln!("{}", x);
+            exit(2);
         }
-    ];
-
-
-    let mut help_string = String::from("");
-
-    help_string.push_str("Usage: rgrep [OPTION..] PATTERN FILE [FILE..]\n");
-    help_string.push_str("Search for PATTERNS in eacn FILE.\n");
-    help_string.push_str("Example: rgrep -i 'hello world' menu.h main.c\n");
-    help_string.push_str("\n");
-
-    for category in &categories {
-        help_string.push_str(format!("{}:\n", category.name).as_str());
-        for option in &category.options {
-            help_string.push_str(
-                format!("  {short}, {long}  {desc}(default {default})\n",
-                        short = option.short_form,
-                        long = option.long_form,
-                        desc = option.description,
-                        default = option.default_value
-                ).as_str());
+    };
+    // --remote sends the arguments given here, not the defaults of this machine
+    let command_line = args[1..].to_vec();
+    // a script that asks for POSIX grep gets it, whatever the defaults of the user running it are
+    let posix = command_line.iter().take_while(|arg| *arg != "--").any(|arg| arg == "--posix");
+    // default arguments go right after the command name, so the command line can override them.
+    // config file comes first, so RGREP_OPTIONS can override it in turn.
+    let defaults = if posix {
+        Ok((Vec::new(), Vec::new()))
+    } else {
+        config::load_args().and_then(|config_args| Ok((config_args, config::env_args()?)))
+    };
+    let (config_args, env_args) = match defaults {
+        Ok(defaults) => defaults,
+        Err(x) => {
+            eprintln!("{}", x);
+            exit(2);
         }
-        help_string.push_str("\n");
+    };
+    args.splice(1..1, config_args.iter().chain(env_args.iter()).cloned());
+    let args_result = parse_args(args, &mut command_args);
+    log::init(command_args.log_level);
+    if let Some(path) = config::config_path() {
+        debug!("config file {} added arguments {:?}", path.display(), config_args);
     }
-    println!("{}", help_string);
-}
-
-/// Flags are options that do not take a value
-fn parse_flag(option: &str, command_args: &mut CommandArgs) -> Result<(), String> {
-    match option {
-        "i" | "ignore-case" => {
-            command_args.ignore_case = true
-        }
-        _ => {
-            return Err(format!("Unexpected flag {option}", option = option));
-        }
+    if !env_args.is_empty() {
+        debug!("RGREP_OPTIONS added arguments {:?}", env_args);
     }
-    Ok(())
-}
-
-/// Options take values
-fn parse_non_flag(option: &str, value: &str, command_args: &mut CommandArgs) -> Result<(), String> {
-    match option {
-        "A" | "after-context" => {
-            let result = value.parse::<u32>();
-            match result {
-                Err(_) => return Err(option_error_string(option, value)),
-                Ok(v) => command_args.after_context = v
-            }
-        }
-        "B" | "before-context" => {
-            let result = value.parse::<u32>();
-            match result {
-                Err(_) => return Err(option_error_string(option, value)),
-                Ok(v) => command_args.before_context = v
-            }
-        }
-        _ => {
-            return Err(format!("Unexpected option {option}", option = option));
-        }
+    // cmd.exe and PowerShell pass wildcards on to the programs they start, instead of the files they match
+    if cfg!(windows) {
+        let files = command_args.files.iter().flat_map(|file| glob::expand(file));
+        command_args.files = files.map(|file| windows_separators(&file)).collect();
     }
-    Ok(())
-}
-
-/// Whether or not option is not flag
-fn requires_value(option: &str) -> Result<bool, String> {
-    return match option {
-        "-A" | "--after-context" => {
-            Ok(true)
-        }
-        "-B" | "--before-context" => {
-            Ok(true)
+    match args_result {
+        Err(Error::Help) => {
+            args::print_help();
+            exit(0);
         }
-        "-i" | "--ignore-case" => {
-            Ok(false)
+        Err(Error::Version) => {
+            args::print_version();
+            exit(0);
         }
-        "-h" | "--help" => {
-            print_help();
+        Err(Error::ListBuiltins) => {
+            args::print_builtin_patterns();
             exit(0);
         }
-        _ => {
-            Err(format!("Unexpected option {}", option))
+        Err(x) => {
+            eprintln!("{}", x);
+            exit(2);
         }
-    };
-}
-
-enum OptionType {
-    FLAG = 0,
-    NONFLAG = 1,
-}
-
-/// Returns error or option type parsed
-fn parse_nonflag_or_flag(argument: &str, args_length: usize, index: usize, args: &Vec<String>, command_args: &mut CommandArgs) -> Result<OptionType, String> {
-    let requires_value = requires_value(argument)?;
-    if requires_value && index + 1 < args_length { // have at least one more argument
-        parse_non_flag(argument, args[index + 1].as_str(), command_args)?;
-        Ok(OptionType::NONFLAG)
-    } else if !requires_value {
-        parse_flag(argument, command_args)?;
-        Ok(OptionType::FLAG)
+        Ok(_) => {}
+    }
+    if let Err(message) = args::validate(&command_args) {
+        usage_error(&message);
+    }
+    if !command_args.generate.is_empty() {
+        match command_args.generate.as_str() {
+            "man" => output::print(&man::generate(CATEGORIES)),
+            kind => output::print(&completions::generate(kind, CATEGORIES)),
+        }
+    } else if command_args.daemon || command_args.editor_server {
+        serve(&command_args);
     } else {
-        return Err(format!("Option {} requires value but no value is passed", argument));
+        run(command_args, &command_line);
     }
 }
 
-
-fn parse_args(args: Vec<String>, command_args: &mut CommandArgs) -> Result<(), String> {
-    // made true after query parsing finished.
-    let mut query_parsed = false;
-
-    // start from 1; so, skip the first argument which is the command name
-    let mut index = 1;
-    while index < args.len() {
-        let arg = &args[index];
-
-        if query_parsed {
-            command_args.files.push(arg.clone());
-        } else {
-            if arg.starts_with("--") { // long option
-                let split: Vec<&str> = arg.split('=').collect();
-                match split.len() {
-                    // does not have = sign, we need to take two values
-                    1 => {
-                        match parse_nonflag_or_flag(arg, args.len(), index, &args, command_args)? {
-                            OptionType::NONFLAG => index += 1,
-                            OptionType::FLAG => {}
-                        }
-                    }
-                    // has one = sign
-                    2 => {
-                        parse_non_flag(split[0], split[1], command_args)?;
-                    }
-                    // has more than one = error
-                    _ => return Err(format!("Option {} has more than one equal sign", arg))
-                }
-            } else if arg.starts_with('-') {
-                match parse_nonflag_or_flag(arg, args.len(), index, &args, command_args)? {
-                    OptionType::NONFLAG => index += 1,
-                    OptionType::FLAG => {}
-                }
-            } else { // parse query
-                command_args.query = arg.clone();
-                query_parsed = true;
-            }
+/// Answers searches with --daemon or --editor-server until stopped
+fn serve(command_args: &CommandArgs) -> ! {
+    signal::install();
+    let socket = Some(command_args.socket.as_str()).filter(|socket| !socket.is_empty());
+    let max_connections = command_args.max_connections.map_or(daemon::MAX_CONNECTIONS, |max| max as usize);
+    let result = if command_args.editor_server {
+        daemon::run_editor_server()
+    } else {
+        daemon::run(socket, max_connections)
+    };
+    match result {
+        Ok(()) if signal::interrupted() => exit(signal::INTERRUPTED_STATUS),
+        Ok(()) => exit(0),
+        Err(err) if err.is_broken_pipe() => exit(0),
+        Err(err) => {
+            eprintln!("rgrep: {}", err);
+            exit(2);
         }
-
-        index += 1
     }
-
-    Ok(())
 }
 
-fn main() {
-    let mut command_args: CommandArgs = CommandArgs {
-        files: Vec::new(),
-        query: String::from(""),
-        after_context: 0,
-        before_context: 0,
-        ignore_case: false,
+/// Searches the way `command_args` say, `command_line` being the arguments they were parsed from
+fn run(mut command_args: CommandArgs, command_line: &[String]) -> ! {
+    // there is nothing to read on a terminal, so the files here are searched, like with -r
+    if command_args.files.is_empty()
+        && command_args.git_rev.is_empty()
+        && command_args.changed_since.is_empty()
+        && io::stdin().is_terminal()
+    {
+        command_args.directories = DirectoryAction::Recurse;
+    }
+    let matcher = match matcher::build(&command_args.patterns, &command_args.matcher_options()) {
+        Ok(matcher) => matcher,
+        Err(err) => {
+            eprintln!("rgrep: {}", err);
+            exit(2);
+        }
     };
-
-    let args: Vec<String> = env::args().collect();
-    let args_result = parse_args(args, &mut command_args);
-    match args_result {
-        Err(x) => {
-            eprintln!("{}", x);
-            exit(1);
+    // on a remote machine, the remote rgrep asks git
+    if !command_args.changed_since.is_empty() && command_args.remote.is_empty() {
+        match git::changed_files(&command_args.changed_since, &command_args.files) {
+            // nothing changed, so nothing matches
+            Ok(files) if files.is_empty() => exit(1),
+            Ok(files) => {
+                command_args.files = files;
+                command_args.files_listed = true;
+            }
+            Err(err) => {
+                eprintln!("rgrep: {}", err);
+                exit(2);
+            }
         }
-        Ok(_) => { // start operation
-            println!("Argument A is {}", command_args.after_context);
-            println!("Argument B is {}", command_args.before_context);
-            println!("Argument i is {}", command_args.ignore_case);
-            println!("Will search {query} in files {:?}",
-                     command_args.files,
-                     query = command_args.query);
+    }
+    signal::install();
+    let stdout = io::stdout();
+    let result = if !command_args.remote.is_empty() {
+        remote::run(&command_args, matcher.as_ref(), command_line, io::BufWriter::new(stdout.lock()))
+    } else if command_args.interactive {
+        interactive::run(&command_args, matcher.as_ref())
+    } else if !command_args.git_rev.is_empty() {
+        git::run(&command_args, matcher.as_ref(), io::BufWriter::new(stdout.lock()))
+    } else if command_args.watch {
+        watch::run(&command_args, matcher.as_ref(), io::BufWriter::new(stdout.lock()))
+    } else {
+        search::run(&command_args, matcher.as_ref(), io::BufWriter::new(stdout.lock()))
+    };
+    // one line for all of them, after the errors of each, which can scroll out of sight
+    let skipped = result.as_ref().map_or(0, |summary| summary.skipped);
+    if skipped > 1 && !command_args.no_messages {
+        eprintln!("rgrep: skipped {} inputs that could not be read", skipped);
+    }
+    match result {
+        Ok(summary) => exit(summary.status()),
+        // the reader of our output is gone, e.g. `rgrep foo | head`; nothing left to do
+        Err(err) if err.is_broken_pipe() => exit(0),
+        Err(err) => {
+            eprintln!("rgrep: {}", err);
+            exit(2);
         }
     }
 }
@@ -0,0 +1,96 @@
+use crate::args::Category;
+
+/// Escapes text for roff: backslashes and dashes are special, and so are leading dots and quotes
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Formats an option form like `--after-context=NUM` as bold name and italic value
+fn option_form(form: &str) -> String {
+    match form.find('=') {
+        Some(index) => format!("\\fB{}\\fR=\\fI{}\\fR", escape(&form[..index]), escape(&form[index + 1..])),
+        None => format!("\\fB{}\\fR", escape(form)),
+    }
+}
+
+/// Returns a man page in roff format documenting every option
+pub fn generate(categories: &[Category]) -> String {
+    let mut page = String::from("");
+    page.push_str(format!(".TH RGREP 1 \"\" \"rgrep {}\" \"User Commands\"\n", env!("CARGO_PKG_VERSION")).as_str());
+    page.push_str(".SH NAME\n");
+    page.push_str("rgrep \\- search for patterns in files\n");
+    page.push_str(".SH SYNOPSIS\n");
+    page.push_str(".B rgrep\n");
+    page.push_str("[\\fIOPTION\\fR...] \\fIPATTERN\\fR [\\fIFILE\\fR...]\n");
+    page.push_str(".br\n");
+    page.push_str(".B rgrep\n");
+    page.push_str("[\\fIOPTION\\fR...] \\fB\\-e\\fR \\fIPATTERN\\fR... [\\fIFILE\\fR...]\n");
+    page.push_str(".SH DESCRIPTION\n");
+    page.push_str(".B rgrep\n");
+    page.push_str("searches for \\fIPATTERN\\fR in each \\fIFILE\\fR.\n");
+    page.push_str("Options may appear anywhere on the command line;\n");
+    page.push_str("arguments after \\fB\\-\\-\\fR are never treated as options.\n");
+    page.push_str("Flags can be negated with a \\fBno\\-\\fR prefix, e.g. \\fB\\-\\-no\\-ignore\\-case\\fR.\n");
+    page.push_str(".SH OPTIONS\n");
+    for category in categories {
+        page.push_str(format!(".SS \"{}\"\n", escape(category.name)).as_str());
+        page.push_str(format!("{}\n", escape(category.description)).as_str());
+        for option in category.options {
+            page.push_str(".TP\n");
+            let mut forms = Vec::new();
+            if !option.short.is_empty() {
+                forms.push(option_form(format!("-{}", option.short).as_str()));
+            }
+            forms.push(option_form(option.long_form().as_str()));
+            if option.negatable() {
+                forms.push(option_form(format!("--no-{}", option.long).as_str()));
+            }
+            page.push_str(format!("{}\n", forms.join(", ")).as_str());
+            page.push_str(format!("{}.\n", escape(option.description)).as_str());
+            page.push_str(format!("Default: {}.\n", escape(option.default_value)).as_str());
+            if !option.possible_values.is_empty() {
+                let values: Vec<String> = option.possible_values.iter().map(|value| format!("\\fB{}\\fR", escape(value))).collect();
+                page.push_str(format!("One of {}.\n", values.join(", ")).as_str());
+            }
+        }
+    }
+    page.push_str(".SH EXIT STATUS\n");
+    page.push_str("0 if a line matched, 1 if no line matched, 2 if an error occurred,\n");
+    page.push_str("and 130 if the search was interrupted with Ctrl\\-C.\n");
+    page.push_str(".SH JSON OUTPUT\n");
+    page.push_str("With \\fB\\-\\-json\\fR every event is a JSON object on a line of its own, with its type as ");
+    page.push_str("\\fBtype\\fR and its data as \\fBdata\\fR: \\fBbegin\\fR, \\fBmatch\\fR, \\fBcontext\\fR, ");
+    page.push_str("\\fBbinary_match\\fR and \\fBend\\fR for the inputs, \\fBerror\\fR for those that cannot be ");
+    page.push_str("searched, and last \\fBsummary\\fR, with the totals, the exit status as \\fBstatus\\fR and the ");
+    let version = crate::sink::JSON_VERSION;
+    page.push_str(format!("version of the output as \\fBversion\\fR, which is {}.\n", version).as_str());
+    page.push_str(".PP\n");
+    page.push_str("Later releases only add to the output of a version: new event types, and new members in the data ");
+    page.push_str("of events, which readers have to leave alone. Events and members keep their names and meaning, ");
+    page.push_str("and a change that would break that comes with a new version.\n");
+    page.push_str(".SH ENVIRONMENT\n");
+    page.push_str(".TP\n");
+    page.push_str(".B RGREP_CONFIG\n");
+    page.push_str("Path of the config file to read default options from. An empty value disables the config file.\n");
+    page.push_str(".TP\n");
+    page.push_str(".B RGREP_OPTIONS\n");
+    page.push_str("Default options, split like a shell command line. They override the config file.\n");
+    page.push_str(".TP\n");
+    page.push_str(".B GREP_COLORS\n");
+    page.push_str("Colors of grep, like \\fBms=01;31:fn=35\\fR: \\fBms\\fR, \\fBmc\\fR and \\fBmt\\fR for matches ");
+    page.push_str("in matching lines, context lines and both, \\fBfn\\fR for paths, \\fBln\\fR for line numbers ");
+    page.push_str("and \\fBse\\fR for separators.\n");
+    page.push_str(".SH FILES\n");
+    page.push_str(".TP\n");
+    page.push_str("\\fI$XDG_CONFIG_HOME/rgrep/config\\fR, \\fI~/.config/rgrep/config\\fR, \\fI~/.rgreprc\\fR\n");
+    page.push_str("Config file with one option per line. Blank lines and lines starting with # are ignored.\n");
+    page.push_str("A line NAME = ARGUMENTS defines a preset instead: \\fB@NAME\\fR on the command line is replaced by ");
+    page.push_str("the ARGUMENTS, split like a shell command line, where a file or an option could be; ");
+    page.push_str("the pattern and the values of options are never presets.\n");
+    page
+}
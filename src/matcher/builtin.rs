@@ -0,0 +1,76 @@
+//! Regular expressions for common kinds of text, picked by name with `--builtin`.
+//!
+//! They are written for the regex engine of rgrep, which has no look-around, so they are bounded by `\b` and
+//! character classes instead. They aim to find what is meant in logs and source files without many false hits,
+//! not to validate; e.g. `email` does not check that domains exist or how long their labels are.
+
+/// A named regular expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinPattern {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub pattern: &'static str,
+    /// Text the pattern matches as a whole, shown by `--builtin list`
+    pub example: &'static str,
+}
+
+/// Every built-in pattern, in the order `--builtin list` shows them
+pub const BUILTIN_PATTERNS: &[BuiltinPattern] = &[
+    BuiltinPattern {
+        name: "email",
+        description: "email addresses",
+        pattern: r"\b[A-Za-z0-9._%+-]+@(?:[A-Za-z0-9-]+\.)+[A-Za-z]{2,}\b",
+        example: "jane.doe+news@mail.example.org",
+    },
+    BuiltinPattern {
+        name: "url",
+        description: "http and https URLs, without punctuation that follows them",
+        pattern: r#"\bhttps?://[A-Za-z0-9.-]*[A-Za-z0-9](?::[0-9]+)?(?:[/?#](?:[^\s<>"']*[^\s<>"'.,;:!?)\]])?)?"#,
+        example: "https://example.org:8080/docs/search?q=fox#results",
+    },
+    BuiltinPattern {
+        name: "ipv4",
+        description: "IPv4 addresses, every part from 0 to 255",
+        pattern: concat!(
+            r"\b(?:(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\.){3}",
+            r"(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\b"
+        ),
+        example: "192.168.0.255",
+    },
+    BuiltinPattern {
+        name: "mac",
+        description: "MAC addresses, with : or - between the bytes",
+        pattern: r"\b[0-9A-Fa-f]{2}(?:[:-][0-9A-Fa-f]{2}){5}\b",
+        example: "00:1a:2b:3c:4d:5e",
+    },
+    BuiltinPattern {
+        name: "uuid",
+        description: "UUIDs in their 8-4-4-4-12 form",
+        pattern: r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b",
+        example: "123e4567-e89b-12d3-a456-426614174000",
+    },
+    BuiltinPattern {
+        name: "date",
+        description: "ISO 8601 dates, YYYY-MM-DD",
+        pattern: r"\b[0-9]{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])\b",
+        example: "2024-02-29",
+    },
+    BuiltinPattern {
+        name: "hex",
+        description: "hexadecimal numbers starting with 0x",
+        pattern: r"\b0[xX][0-9A-Fa-f]+\b",
+        example: "0xDEADBEEF",
+    },
+];
+
+/// The built-in pattern called `name`
+///
+/// ```
+/// use rgrep::matcher::builtin_pattern;
+///
+/// assert_eq!(builtin_pattern("date").unwrap().example, "2024-02-29");
+/// assert!(builtin_pattern("phone").is_none());
+/// ```
+pub fn builtin_pattern(name: &str) -> Option<&'static BuiltinPattern> {
+    BUILTIN_PATTERNS.iter().find(|builtin| builtin.name == name)
+}
@@ -0,0 +1,95 @@
+//! Only the first match of every line, for `--matches first`.
+//!
+//! The matcher rather than the printers leaves out the other matches, so colors, `--byte-offsets`, `--unique`,
+//! `--replace` and the matches of `--json` all agree on them, like `sed` without `g` replaces only the first.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+
+use super::{Captures, Match, Matcher};
+
+/// Which matches in a line are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchesPerLine {
+    #[default]
+    All,
+    /// Only the first, the leftmost one
+    First,
+}
+
+impl MatchesPerLine {
+    /// Names accepted by `--matches`
+    pub const NAMES: &'static [&'static str] = &["all", "first"];
+
+    pub fn from_name(name: &str) -> Option<MatchesPerLine> {
+        Some(match name {
+            "all" => MatchesPerLine::All,
+            "first" => MatchesPerLine::First,
+            _ => return None,
+        })
+    }
+}
+
+/// Finds only the first match of another matcher in every line, the one it finds from the start of the line.
+/// Lines that match are the same, since their first match is still found. In a haystack of many lines, a line
+/// that a match from an earlier one reaches into may have none of its own.
+///
+/// ```
+/// use rgrep::matcher::{self, FirstPerLine, LiteralMatcher, Match};
+///
+/// let matcher = FirstPerLine::new(Box::new(LiteralMatcher::new(b"o", false)));
+/// let found: Vec<Match> = matcher::find_iter(&matcher, b"foo\nbar\nboo").collect();
+/// assert_eq!(found, [Match::new(1, 2), Match::new(9, 10)]);
+/// ```
+pub struct FirstPerLine {
+    inner: Box<dyn Matcher>,
+}
+
+impl FirstPerLine {
+    pub fn new(inner: Box<dyn Matcher>) -> FirstPerLine {
+        FirstPerLine { inner }
+    }
+
+    /// Whether `found`, searched for from `at`, is the first match on its line. If not, returns where the
+    /// next line starts to go on from there, or `None` if there is none.
+    fn first_on_line(&self, haystack: &[u8], found: Match, at: usize) -> Result<(), Option<usize>> {
+        let line_start = haystack[..found.start].iter().rposition(|byte| *byte == b'\n').map_or(0, |end| end + 1);
+        // nothing was searched for before `found` on its line yet
+        if line_start >= at || self.inner.find_at(haystack, line_start).is_some_and(|first| first.start == found.start)
+        {
+            return Ok(());
+        }
+        Err(haystack[found.start..].iter().position(|byte| *byte == b'\n').map(|end| found.start + end + 1))
+    }
+}
+
+impl Matcher for FirstPerLine {
+    fn find_at(&self, haystack: &[u8], mut at: usize) -> Option<Match> {
+        loop {
+            let found = self.inner.find_at(haystack, at)?;
+            match self.first_on_line(haystack, found, at) {
+                Ok(()) => return Some(found),
+                Err(next_line) => at = next_line?,
+            }
+        }
+    }
+
+    fn captures_at(&self, haystack: &[u8], mut at: usize) -> Option<Captures> {
+        loop {
+            let captures = self.inner.captures_at(haystack, at)?;
+            match self.first_on_line(haystack, captures.get(0)?, at) {
+                Ok(()) => return Some(captures),
+                Err(next_line) => at = next_line?,
+            }
+        }
+    }
+
+    fn gave_up(&self) -> bool {
+        self.inner.gave_up()
+    }
+
+    fn strategy(&self) -> String {
+        format!("first match per line of {}", self.inner.strategy())
+    }
+}
@@ -0,0 +1,208 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{Captures, Match, Matcher};
+
+/// Folds a byte for comparison, ASCII only
+fn fold(byte: u8, ignore_case: bool) -> u8 {
+    if ignore_case {
+        byte.to_ascii_lowercase()
+    } else {
+        byte
+    }
+}
+
+/// Searches for a single string. Case is folded for ASCII letters only.
+pub struct LiteralMatcher {
+    needle: Vec<u8>,
+    ignore_case: bool,
+}
+
+impl LiteralMatcher {
+    pub fn new(needle: &[u8], ignore_case: bool) -> LiteralMatcher {
+        let needle = needle.iter().map(|byte| fold(*byte, ignore_case)).collect();
+        LiteralMatcher { needle, ignore_case }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Match> {
+        let first = match self.needle.first() {
+            Some(first) => *first,
+            None => return Some(Match::new(at, at)),
+        };
+        let last_start = haystack.len().checked_sub(self.needle.len())?;
+        let mut start = at;
+        while start <= last_start {
+            // skip ahead to the next candidate first byte, then compare the rest
+            let offset = haystack[start..=last_start]
+                .iter()
+                .position(|byte| fold(*byte, self.ignore_case) == first)?;
+            start += offset;
+            let candidate = &haystack[start..start + self.needle.len()];
+            let equal = if self.ignore_case {
+                candidate.eq_ignore_ascii_case(&self.needle)
+            } else {
+                candidate == self.needle.as_slice()
+            };
+            if equal {
+                return Some(Match::new(start, start + self.needle.len()));
+            }
+            start += 1;
+        }
+        None
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Captures> {
+        let found = self.find_at(haystack, at)?;
+        Some(Captures::new(vec![Some(found)], vec![None]))
+    }
+
+    fn strategy(&self) -> String {
+        String::from("literal")
+    }
+}
+
+/// State of the Aho-Corasick automaton
+struct State {
+    /// Trie edges, sorted by byte
+    next: Vec<(u8, usize)>,
+    /// Longest proper suffix of this state that is also a state
+    fail: usize,
+    /// Patterns ending here, including those ending at states reached through `fail`
+    outputs: Vec<usize>,
+}
+
+impl State {
+    fn edge(&self, byte: u8) -> Option<usize> {
+        self.next.binary_search_by_key(&byte, |(edge, _)| *edge).ok().map(|index| self.next[index].1)
+    }
+}
+
+/// Searches for any of several strings at once with an Aho-Corasick automaton, in a single pass over
+/// the haystack. The leftmost match wins, ties go to the pattern that comes first.
+pub struct LiteralSet {
+    states: Vec<State>,
+    lengths: Vec<usize>,
+    max_length: usize,
+    /// Index of an empty pattern, which matches everywhere
+    empty: Option<usize>,
+    ignore_case: bool,
+}
+
+impl LiteralSet {
+    pub fn new<P: AsRef<[u8]>>(patterns: &[P], ignore_case: bool) -> LiteralSet {
+        let root = State { next: Vec::new(), fail: 0, outputs: Vec::new() };
+        let mut states = vec![root];
+        let mut lengths = Vec::new();
+        let mut empty = None;
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            lengths.push(pattern.len());
+            if pattern.is_empty() {
+                empty = empty.or(Some(index));
+                continue;
+            }
+            let mut state = 0;
+            for byte in pattern {
+                let byte = fold(*byte, ignore_case);
+                state = match states[state].edge(byte) {
+                    Some(next) => next,
+                    None => {
+                        let next = states.len();
+                        states.push(State { next: Vec::new(), fail: 0, outputs: Vec::new() });
+                        let edges = &mut states[state].next;
+                        let position = edges.binary_search_by_key(&byte, |(edge, _)| *edge).unwrap_err();
+                        edges.insert(position, (byte, next));
+                        next
+                    }
+                };
+            }
+            states[state].outputs.push(index);
+        }
+
+        // breadth first, so fail links always point to states that are already done
+        let mut queue = alloc::collections::VecDeque::new();
+        let root_edges = states[0].next.clone();
+        for (_, child) in root_edges {
+            queue.push_back(child);
+        }
+        while let Some(state) = queue.pop_front() {
+            let edges = states[state].next.clone();
+            for (byte, child) in edges {
+                let mut fail = states[state].fail;
+                let child_fail = loop {
+                    if let Some(next) = states[fail].edge(byte) {
+                        break next;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = states[fail].fail;
+                };
+                states[child].fail = child_fail;
+                let inherited = states[child_fail].outputs.clone();
+                states[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        let max_length = lengths.iter().copied().max().unwrap_or(0);
+        LiteralSet { states, lengths, max_length, empty, ignore_case }
+    }
+
+    /// Leftmost match at or after `at` and the index of its pattern
+    pub fn find_pattern_at(&self, haystack: &[u8], at: usize) -> Option<(usize, Match)> {
+        let mut best: Option<(usize, Match)> = self.empty.map(|index| (index, Match::new(at, at)));
+        let mut state = 0;
+        for (position, byte) in haystack.iter().enumerate().skip(at) {
+            if let Some((_, found)) = best {
+                // nothing that ends from here on can start before the best match
+                if position >= found.start + self.max_length {
+                    break;
+                }
+            }
+            let byte = fold(*byte, self.ignore_case);
+            state = loop {
+                if let Some(next) = self.states[state].edge(byte) {
+                    break next;
+                }
+                if state == 0 {
+                    break 0;
+                }
+                state = self.states[state].fail;
+            };
+            for &pattern in &self.states[state].outputs {
+                let found = Match::new(position + 1 - self.lengths[pattern], position + 1);
+                let better = match best {
+                    None => true,
+                    Some((best_pattern, best)) => {
+                        found.start < best.start || (found.start == best.start && pattern < best_pattern)
+                    }
+                };
+                if better {
+                    best = Some((pattern, found));
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Matcher for LiteralSet {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Match> {
+        self.find_pattern_at(haystack, at).map(|(_, found)| found)
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Captures> {
+        let found = self.find_at(haystack, at)?;
+        Some(Captures::new(vec![Some(found)], vec![None]))
+    }
+
+    fn strategy(&self) -> String {
+        format!("aho-corasick over {} literals", self.lengths.len())
+    }
+}
@@ -0,0 +1,365 @@
+//! Matching patterns against bytes.
+//!
+//! The searcher and printer only talk to the [`Matcher`] trait, so the engine behind it can be a plain
+//! literal search, a regex, or a set of patterns. [`build`] picks the cheapest one that can handle the patterns.
+//! Other crates can implement [`Matcher`] as well, to search with an engine rgrep does not have.
+
+mod builtin;
+mod first;
+mod literal;
+pub mod posix;
+pub mod regex;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::error::Result;
+
+pub use self::builtin::{builtin_pattern, BuiltinPattern, BUILTIN_PATTERNS};
+pub use self::first::{FirstPerLine, MatchesPerLine};
+pub use self::literal::{LiteralMatcher, LiteralSet};
+pub use self::posix::Syntax;
+pub use self::regex::{Engine, RegexMatcher};
+
+/// Location of a match in a haystack, as byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Match {
+    pub fn new(start: usize, end: usize) -> Match {
+        Match { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Spans of the groups of a match. Group 0 is the whole match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    spans: Vec<Option<Match>>,
+    names: Vec<Option<String>>,
+}
+
+impl Captures {
+    /// `names` has one entry per group, `None` for unnamed ones
+    pub fn new(spans: Vec<Option<Match>>, names: Vec<Option<String>>) -> Captures {
+        Captures { spans, names }
+    }
+
+    /// Span of group `index`, `None` if the group did not take part in the match
+    pub fn get(&self, index: usize) -> Option<Match> {
+        self.spans.get(index).copied().flatten()
+    }
+
+    /// Span of the group called `name`
+    pub fn name(&self, name: &str) -> Option<Match> {
+        let index = self.names.iter().position(|group| group.as_deref() == Some(name))?;
+        self.get(index)
+    }
+
+    /// Number of groups, including group 0
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Names of all groups, in order, `None` for unnamed ones
+    pub fn names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Appends `replacement` to `out` with `$N`, `${N}`, `$name` and `${name}` replaced by what the group matched
+    /// in `haystack`, and `$$` by `$`. Groups that do not exist or did not take part in the match are empty.
+    pub fn expand(&self, haystack: &[u8], replacement: &str, out: &mut Vec<u8>) {
+        let mut rest = replacement;
+        while let Some(dollar) = rest.find('$') {
+            out.extend_from_slice(&rest.as_bytes()[..dollar]);
+            rest = &rest[dollar + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                out.push(b'$');
+                rest = after;
+                continue;
+            }
+            let (group, after) = match rest.strip_prefix('{') {
+                Some(braced) => match braced.find('}') {
+                    Some(end) => (&braced[..end], &braced[end + 1..]),
+                    None => ("", rest),
+                },
+                None => {
+                    let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            if group.is_empty() {
+                // not a reference, the dollar is taken literally
+                out.push(b'$');
+                continue;
+            }
+            let span = match group.parse::<usize>() {
+                Ok(index) => self.get(index),
+                Err(_) => self.name(group),
+            };
+            if let Some(span) = span {
+                out.extend_from_slice(&haystack[span.start..span.end]);
+            }
+            rest = after;
+        }
+        out.extend_from_slice(rest.as_bytes());
+    }
+}
+
+/// Appends `haystack` to `out` with every match replaced by `replacement`, expanded with
+/// [`Captures::expand`]. Returns the number of matches replaced.
+pub fn replace_all(matcher: &dyn Matcher, haystack: &[u8], replacement: &str, out: &mut Vec<u8>) -> usize {
+    let mut replaced = 0;
+    let mut copied = 0;
+    let mut at = 0;
+    while at <= haystack.len() {
+        let captures = match matcher.captures_at(haystack, at) {
+            Some(captures) => captures,
+            None => break,
+        };
+        let found = match captures.get(0) {
+            Some(found) => found,
+            None => break,
+        };
+        // an empty match must not be found again at the same place, and one right after a match is not
+        // replaced, like sed does: `x*` replaces `fox` with `XfXoX`
+        at = if found.is_empty() { found.end + 1 } else { found.end };
+        if found.is_empty() && replaced > 0 && found.start == copied {
+            continue;
+        }
+        out.extend_from_slice(&haystack[copied..found.start]);
+        captures.expand(haystack, replacement, out);
+        copied = found.end;
+        replaced += 1;
+    }
+    out.extend_from_slice(&haystack[copied..]);
+    replaced
+}
+
+/// A compiled pattern, or set of patterns, that can be searched for in bytes.
+///
+/// Offsets are byte offsets into the haystack. `find_at` and `captures_at` start searching at `at` but still
+/// look at the bytes before it for context, so `^` and `\b` behave the same as when searching the whole haystack.
+pub trait Matcher: Send + Sync {
+    /// Leftmost match starting at or after `at`
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Match>;
+
+    /// Leftmost match starting at or after `at`, with the spans of all groups
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Captures>;
+
+    fn find(&self, haystack: &[u8]) -> Option<Match> {
+        self.find_at(haystack, 0)
+    }
+
+    fn captures(&self, haystack: &[u8]) -> Option<Captures> {
+        self.captures_at(haystack, 0)
+    }
+
+    fn is_match(&self, haystack: &[u8]) -> bool {
+        self.find(haystack).is_some()
+    }
+
+    /// All non-overlapping matches, from left to right
+    fn find_iter<'m, 'h>(&'m self, haystack: &'h [u8]) -> FindIter<'m, 'h>
+    where
+        Self: Sized,
+    {
+        FindIter { matcher: self, haystack, at: 0 }
+    }
+
+    /// Whether a search gave up since this was last asked, because it would have taken too long, e.g. a backtracking
+    /// one on a pattern like `(a|aa)*\1c`. It found no match then. Matchers shared by threads answer for all of them.
+    fn gave_up(&self) -> bool {
+        false
+    }
+
+    /// Short description of the engine, for diagnostics
+    fn strategy(&self) -> String;
+}
+
+/// Iterator over the matches of a [`Matcher`] in a haystack, see [`Matcher::find_iter`] and [`find_iter`]
+pub struct FindIter<'m, 'h> {
+    matcher: &'m dyn Matcher,
+    haystack: &'h [u8],
+    at: usize,
+}
+
+impl<'m, 'h> Iterator for FindIter<'m, 'h> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        if self.at > self.haystack.len() {
+            return None;
+        }
+        let found = self.matcher.find_at(self.haystack, self.at)?;
+        // an empty match must not be found again at the same place
+        self.at = if found.is_empty() { found.end + 1 } else { found.end };
+        Some(found)
+    }
+}
+
+/// Same as [`Matcher::find_iter`], for matchers behind a `dyn` reference
+pub fn find_iter<'m, 'h>(matcher: &'m dyn Matcher, haystack: &'h [u8]) -> FindIter<'m, 'h> {
+    FindIter { matcher, haystack, at: 0 }
+}
+
+/// Iterator over the matches of a [`Matcher`] in a haystack with the spans of their groups, see [`captures_iter`]
+pub struct CapturesIter<'m, 'h> {
+    matcher: &'m dyn Matcher,
+    haystack: &'h [u8],
+    at: usize,
+}
+
+impl<'m, 'h> Iterator for CapturesIter<'m, 'h> {
+    type Item = Captures;
+
+    fn next(&mut self) -> Option<Captures> {
+        if self.at > self.haystack.len() {
+            return None;
+        }
+        let captures = self.matcher.captures_at(self.haystack, self.at)?;
+        let found = captures.get(0)?;
+        self.at = if found.is_empty() { found.end + 1 } else { found.end };
+        Some(captures)
+    }
+}
+
+/// The same matches as [`find_iter`], each with the spans of all groups
+pub fn captures_iter<'m, 'h>(matcher: &'m dyn Matcher, haystack: &'h [u8]) -> CapturesIter<'m, 'h> {
+    CapturesIter { matcher, haystack, at: 0 }
+}
+
+/// Several matchers searched together. The leftmost match wins, ties go to the matcher that comes first.
+pub struct MultiMatcher {
+    matchers: Vec<Box<dyn Matcher>>,
+}
+
+impl MultiMatcher {
+    pub fn new(matchers: Vec<Box<dyn Matcher>>) -> MultiMatcher {
+        MultiMatcher { matchers }
+    }
+
+    /// Leftmost match starting at or after `at`, with the index of the matcher that found it, e.g. to tell which
+    /// of the patterns given to [`build_each`] matched
+    pub fn find_pattern_at(&self, haystack: &[u8], at: usize) -> Option<(usize, Match)> {
+        let mut best: Option<(usize, Match)> = None;
+        for (index, matcher) in self.matchers.iter().enumerate() {
+            if let Some(found) = matcher.find_at(haystack, at) {
+                if best.is_none_or(|(_, best)| found.start < best.start) {
+                    best = Some((index, found));
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Matcher for MultiMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Match> {
+        self.find_pattern_at(haystack, at).map(|(_, found)| found)
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Captures> {
+        let (index, _) = self.find_pattern_at(haystack, at)?;
+        self.matchers[index].captures_at(haystack, at)
+    }
+
+    fn gave_up(&self) -> bool {
+        // every matcher is asked, so none keeps an old answer
+        let answers: Vec<bool> = self.matchers.iter().map(|matcher| matcher.gave_up()).collect();
+        answers.contains(&true)
+    }
+
+    fn strategy(&self) -> String {
+        let strategies: Vec<String> = self.matchers.iter().map(|matcher| matcher.strategy()).collect();
+        format!("any of [{}]", strategies.join(", "))
+    }
+}
+
+/// How patterns should be interpreted
+#[derive(Debug, Clone, Default)]
+pub struct MatcherOptions {
+    /// Patterns are plain strings, not regular expressions
+    pub fixed_strings: bool,
+    pub ignore_case: bool,
+    /// The syntax regular expressions are written in
+    pub syntax: Syntax,
+    /// What runs regular expressions
+    pub engine: Engine,
+    /// Which matches in a line are found
+    pub matches: MatchesPerLine,
+}
+
+/// Whether a pattern means the same thing as a regex and as a plain string
+#[cfg(feature = "std")]
+fn is_plain(pattern: &str) -> bool {
+    !pattern.chars().any(|c| "\\.+*?()|[]{}^$".contains(c))
+}
+
+/// Builds the cheapest matcher for the patterns: plain strings are searched for directly,
+/// anything else is compiled as a regular expression.
+#[cfg(feature = "std")]
+pub fn build(patterns: &[String], options: &MatcherOptions) -> Result<Box<dyn Matcher>> {
+    if options.matches == MatchesPerLine::First {
+        let all = build(patterns, &MatcherOptions { matches: MatchesPerLine::All, ..options.clone() })?;
+        return Ok(Box::new(FirstPerLine::new(all)));
+    }
+    if options.syntax != Syntax::Perl && !options.fixed_strings {
+        let translated: Vec<String> = patterns
+            .iter()
+            .map(|pattern| posix::translate(pattern, options.syntax))
+            .collect::<core::result::Result<_, _>>()?;
+        // errors of the engine point into the translations, which the user did not write
+        return build(&translated, &MatcherOptions { syntax: Syntax::Perl, ..options.clone() }).map_err(|err| match err {
+            crate::error::Error::Pattern(err) => match translated.iter().position(|pattern| *pattern == err.pattern) {
+                Some(index) => crate::error::Error::Pattern(posix::locate(err, &patterns[index], options.syntax)),
+                None => crate::error::Error::Pattern(err),
+            },
+            err => err,
+        });
+    }
+    let literal = options.fixed_strings || patterns.iter().all(|pattern| is_plain(pattern));
+    // literal search only folds ASCII case, other scripts need the regex engine's case folding
+    let folds_ascii = !options.ignore_case || patterns.iter().all(|pattern| pattern.is_ascii());
+    if literal && folds_ascii {
+        return Ok(match patterns {
+            [pattern] => Box::new(LiteralMatcher::new(pattern.as_bytes(), options.ignore_case)),
+            _ => Box::new(LiteralSet::new(patterns, options.ignore_case)),
+        });
+    }
+
+    let mut matchers: Vec<Box<dyn Matcher>> = Vec::new();
+    for pattern in patterns {
+        let source = if literal { regex::escape(pattern) } else { pattern.clone() };
+        let matcher = RegexMatcher::with_engine(&source, options.ignore_case, options.engine)?;
+        matchers.push(Box::new(matcher));
+    }
+    Ok(match matchers.len() {
+        1 => matchers.remove(0),
+        _ => Box::new(MultiMatcher::new(matchers)),
+    })
+}
+
+/// Builds a matcher for every pattern on its own, to be searched together in a [`MultiMatcher`]. It finds the
+/// same matches as [`build`] with all the patterns, and can tell which pattern each is of.
+#[cfg(feature = "std")]
+pub fn build_each(patterns: &[String], options: &MatcherOptions) -> Result<Vec<Box<dyn Matcher>>> {
+    patterns.iter().map(|pattern| build(std::slice::from_ref(pattern), options)).collect()
+}
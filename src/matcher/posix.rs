@@ -0,0 +1,463 @@
+//! The regular expressions of POSIX grep, basic and extended, translated to the syntax of the [regex](super::regex)
+//! engine.
+//!
+//! In basic regular expressions `\(`, `\)`, `\{` and `\}` group and count, and `+`, `?`, `|`, `(`, `)`, `{` and `}`
+//! are plain characters; extended ones are the other way around. A `*` that has nothing to repeat is a plain
+//! character in both. Brackets take `\` literally, `]` first in them as a member, classes like `[:alpha:]`, and
+//! collating symbols and equivalence classes of single characters, like `[.-.]` and `[=e=]`, or of the names POSIX
+//! gives the characters of the portable character set, like `[.hyphen.]`. Both have
+//! backreferences `\1` to `\9`. Like GNU grep, both also have `\w`, `\W`, `\s`, `\S`, `\b`, `\B`, `\<` and `\>`,
+//! and basic ones have `\+`, `\?` and `\|`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::regex::Error;
+
+/// Which syntax patterns are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// The syntax of the regex engine, the common Perl-like one
+    #[default]
+    Perl,
+    /// POSIX basic regular expressions, those of `grep` and `sed`
+    Basic,
+    /// POSIX extended regular expressions, those of `grep -E` and `awk`
+    Extended,
+}
+
+/// Characters that stand for something else in the syntax of the engine, outside of classes
+const ENGINE_SPECIAL: &str = "\\.+*?()|[]{}^$#&-~";
+
+/// Characters that stand for something else in the classes of the engine
+const ENGINE_CLASS_SPECIAL: &str = "\\[]^-&~";
+
+/// `pattern`, written in `syntax`, in the syntax of the regex engine. Errors point into `pattern`.
+///
+/// ```
+/// use rgrep::matcher::posix::{translate, Syntax};
+///
+/// assert_eq!(translate(r"\(ab\)*c+", Syntax::Basic).unwrap(), r"(ab)*c\+");
+/// assert_eq!(translate("(ab)*c+", Syntax::Extended).unwrap(), "(ab)*c+");
+/// ```
+pub fn translate(pattern: &str, syntax: Syntax) -> Result<String, Error> {
+    translate_with_origins(pattern, syntax).map(|(translated, _)| translated)
+}
+
+/// `error`, which the engine found in the translation of `pattern` to its syntax, pointing into `pattern` instead
+///
+/// ```
+/// use rgrep::matcher::posix::{locate, translate, Syntax};
+/// use rgrep::matcher::RegexMatcher;
+///
+/// let translated = translate(r"x\(a", Syntax::Basic).unwrap();
+/// let error = RegexMatcher::new(&translated, false).err().unwrap();
+/// let error = locate(error, r"x\(a", Syntax::Basic);
+/// assert_eq!((error.pattern.as_str(), error.position, error.length), (r"x\(a", 1, 2));
+/// ```
+pub fn locate(error: Error, pattern: &str, syntax: Syntax) -> Error {
+    let origins = match translate_with_origins(pattern, syntax) {
+        Ok((translated, origins)) if translated == error.pattern => origins,
+        _ => return error,
+    };
+    let end = pattern.chars().count();
+    let first = origins.get(error.position).map_or(end, |origin| origin.0);
+    let last = origins.get(error.position + error.length.max(1) - 1).map_or(end + 1, |origin| origin.1);
+    Error { position: first, length: last - first, pattern: String::from(pattern), ..error }
+}
+
+/// The translation of `pattern`, with the span of the chars of `pattern` every char of it was translated from
+fn translate_with_origins(pattern: &str, syntax: Syntax) -> Result<(String, Vec<(usize, usize)>), Error> {
+    if syntax == Syntax::Perl {
+        let origins = (0..pattern.chars().count()).map(|at| (at, at + 1)).collect();
+        return Ok((String::from(pattern), origins));
+    }
+    let mut translator = Translator {
+        pattern,
+        chars: pattern.chars().collect(),
+        position: 0,
+        basic: syntax == Syntax::Basic,
+        out: String::with_capacity(pattern.len()),
+        repeatable: false,
+        repeated: false,
+        origins: Vec::new(),
+    };
+    translator.translate()?;
+    Ok((translator.out, translator.origins))
+}
+
+struct Translator<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    position: usize,
+    basic: bool,
+    out: String,
+    /// What was translated last can be repeated, so a `*` after it repeats it rather than being a character
+    repeatable: bool,
+    /// What was translated last is a repetition, which the engine would make lazy with a `?` after it
+    repeated: bool,
+    /// Span of the chars of the pattern every char of `out` was translated from
+    origins: Vec<(usize, usize)>,
+}
+
+impl Translator<'_> {
+    fn error_at(&self, message: &str, position: usize) -> Error {
+        Error { message: String::from(message), position, length: 1, pattern: String::from(self.pattern) }
+    }
+
+    fn error_span(&self, message: &str, position: usize, length: usize) -> Error {
+        Error { length, ..self.error_at(message, position) }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek_at_is(self.position, text)
+    }
+
+    fn peek_at_is(&self, position: usize, text: &str) -> bool {
+        (position..).zip(text.chars()).all(|(position, c)| self.chars.get(position) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        Some(c)
+    }
+
+    fn literal(&mut self, c: char) {
+        if ENGINE_SPECIAL.contains(c) {
+            self.out.push('\\');
+        }
+        self.out.push(c);
+        self.repeatable = true;
+        self.repeated = false;
+    }
+
+    /// Something that matches, and can be repeated
+    fn atom(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.repeatable = true;
+        self.repeated = false;
+    }
+
+    /// Something after which a `*` has nothing to repeat: the start of the pattern, a group or a branch, or `^`
+    fn boundary(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.repeatable = false;
+        self.repeated = false;
+    }
+
+    /// A repetition operator, `*`, `+`, `?` or a counted one like `{2,3}`, or the character if there is nothing to
+    /// repeat
+    fn repetition(&mut self, operator: &str, c: char) {
+        if !self.repeatable {
+            self.literal(c);
+            return;
+        }
+        // `a+?` is `(a+)?`, not the lazy `a+` of the engine; repeating once or not at all is the same
+        let operator = if self.repeated && operator == "?" { "{0,1}" } else { operator };
+        self.out.push_str(operator);
+        self.repeated = true;
+    }
+
+    fn translate(&mut self) -> Result<(), Error> {
+        while let Some(c) = self.next() {
+            let start = self.position - 1;
+            let translated = self.out.len();
+            match c {
+                '\\' => self.escape(start)?,
+                '[' => self.bracket(start)?,
+                '.' => self.atom("."),
+                '*' => self.repetition("*", '*'),
+                // in basic ones, an anchor only at the start of the pattern or a group or branch
+                '^' if self.basic && self.repeatable => self.literal('^'),
+                '^' => self.boundary("^"),
+                '$' if self.basic && !self.at_end_of_branch() => self.literal('$'),
+                '$' => self.atom("$"),
+                _ if self.basic => self.literal(c),
+                '(' | '|' => self.boundary(c.encode_utf8(&mut [0; 4])),
+                ')' => self.atom(")"),
+                '+' | '?' => self.repetition(c.encode_utf8(&mut [0; 4]), c),
+                '{' if !self.repeatable => self.literal('{'),
+                '{' => match self.interval("}") {
+                    Some(interval) => self.repetition(&interval, '{'),
+                    // not a count, so a plain brace
+                    None => self.literal('{'),
+                },
+                c => self.literal(c),
+            }
+            let chars = self.out[translated..].chars().count();
+            self.origins.extend(core::iter::repeat_n((start, self.position), chars));
+        }
+        Ok(())
+    }
+
+    /// Whether the end of the pattern, a group or a branch is next
+    fn at_end_of_branch(&self) -> bool {
+        self.position == self.chars.len() || self.peek_is("\\)") || self.peek_is("\\|")
+    }
+
+    /// Translates an escape, after the backslash at `start`
+    fn escape(&mut self, start: usize) -> Result<(), Error> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err(self.error_at("trailing backslash", start)),
+        };
+        match c {
+            '(' if self.basic => self.boundary("("),
+            '|' if self.basic => self.boundary("|"),
+            ')' if self.basic => self.atom(")"),
+            '+' | '?' if self.basic => self.repetition(c.encode_utf8(&mut [0; 4]), c),
+            '{' if self.basic && !self.repeatable => self.literal('{'),
+            '{' if self.basic => match self.interval("\\}") {
+                Some(interval) => self.repetition(&interval, '{'),
+                None => return Err(self.error_at("invalid interval, expected \\{N\\}, \\{N,\\} or \\{N,M\\}", start)),
+            },
+            'w' | 'W' | 's' | 'S' => self.atom(&['\\', c].iter().collect::<String>()),
+            'b' | 'B' | '<' | '>' => self.boundary(&['\\', c].iter().collect::<String>()),
+            '1'..='9' => self.atom(&['\\', c].iter().collect::<String>()),
+            c => self.literal(c),
+        }
+        Ok(())
+    }
+
+    /// Reads a count like `2,3` up to `close`, after the opening brace, and returns it for the engine. Returns
+    /// `None`, reading nothing, if there is none.
+    fn interval(&mut self, close: &str) -> Option<String> {
+        let start = self.position;
+        let digits = |translator: &mut Translator| {
+            let begin = translator.position;
+            while translator.peek().is_some_and(|c| c.is_ascii_digit()) {
+                translator.position += 1;
+            }
+            translator.chars[begin..translator.position].iter().collect::<String>()
+        };
+        let min = digits(self);
+        let max = if self.peek() == Some(',') {
+            self.position += 1;
+            Some(digits(self))
+        } else {
+            None
+        };
+        if (min.is_empty() && max.as_deref().is_none_or(str::is_empty)) || !self.peek_is(close) {
+            self.position = start;
+            return None;
+        }
+        self.position += close.chars().count();
+        // `{,3}` is, as in GNU grep, up to 3 times
+        let min = if min.is_empty() { String::from("0") } else { min };
+        Some(match max {
+            Some(max) => alloc::format!("{{{},{}}}", min, max),
+            None => alloc::format!("{{{}}}", min),
+        })
+    }
+
+    /// Translates a bracket expression, after the `[` at `start`
+    fn bracket(&mut self, start: usize) -> Result<(), Error> {
+        let mut class = String::from("[");
+        if self.peek() == Some('^') {
+            self.position += 1;
+            class.push('^');
+        }
+        let mut first = true;
+        loop {
+            let item_start = self.position;
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err(self.error_at("unclosed bracket expression", start)),
+            };
+            // a `]` right after the opening bracket is a member
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            if c == '[' && self.peek() == Some(':') {
+                // the engine has the same classes, with the same syntax
+                match self.chars[self.position + 1..].windows(2).position(|end| end == [':', ']']) {
+                    Some(length) => {
+                        class.extend(&self.chars[item_start..self.position + length + 3]);
+                        self.position += length + 3;
+                        continue;
+                    }
+                    None => return Err(self.error_at("unclosed character class, expected [:NAME:]", item_start)),
+                }
+            }
+            let (low, equivalence) = match c {
+                '[' if matches!(self.peek(), Some('=' | '.')) => self.collating(item_start)?,
+                c => (c, false),
+            };
+            // a range, unless the dash is the last thing in the brackets
+            if self.peek() != Some('-') || self.chars.get(self.position + 1).is_none_or(|c| *c == ']') {
+                push_member(&mut class, low);
+                continue;
+            }
+            self.position += 1;
+            let high_start = self.position;
+            let high = match self.next() {
+                Some('[') if matches!(self.peek(), Some('=' | '.')) => self.collating(high_start)?,
+                Some('[') if self.peek() == Some(':') => (':', true),
+                Some(high) => (high, false),
+                None => return Err(self.error_at("unclosed bracket expression", start)),
+            };
+            let length = self.position - item_start;
+            if equivalence || high.1 {
+                let message = "a range goes from one character to another, not from or to a class";
+                return Err(self.error_span(message, item_start, length));
+            }
+            if high.0 < low {
+                let message = "invalid range, the start is larger than the end";
+                return Err(self.error_span(message, item_start, length));
+            }
+            push_member(&mut class, low);
+            class.push('-');
+            push_member(&mut class, high.0);
+        }
+        class.push(']');
+        self.atom(&class);
+        Ok(())
+    }
+
+    /// Reads a collating symbol like `[.-.]` or an equivalence class like `[=e=]`, after its `[` at `start`, and
+    /// returns its character and whether it is an equivalence class. Only single characters are collating
+    /// elements, given as they are or by their name in [`COLLATING_NAMES`], and every character is only equivalent
+    /// to itself, as in the C locale.
+    fn collating(&mut self, start: usize) -> Result<(char, bool), Error> {
+        let kind = self.next().expect("checked by the caller");
+        let (close, what) = if kind == '=' { ("=]", "equivalence class") } else { (".]", "collating symbol") };
+        let begin = self.position;
+        let length = match (begin..self.chars.len()).find(|&at| self.peek_at_is(at, close)) {
+            Some(end) => end - begin,
+            None => {
+                let message = alloc::format!("unclosed {}, expected [{}C{}]", what, kind, close);
+                return Err(self.error_span(&message, start, self.chars.len() - start));
+            }
+        };
+        self.position = begin + length + 2;
+        let name: String = self.chars[begin..begin + length].iter().collect();
+        match self.chars[begin..begin + length] {
+            [c] => Ok((c, kind == '=')),
+            [] => Err(self.error_span(&alloc::format!("empty {}", what), start, self.position - start)),
+            _ => match COLLATING_NAMES.iter().find(|(known, _)| *known == name) {
+                Some(&(_, c)) => Ok((c, kind == '=')),
+                None => {
+                    let message = alloc::format!(
+                        "unknown {} {:?}, only single characters and the names of those of the portable character \
+                         set are supported",
+                        what, name
+                    );
+                    Err(self.error_span(&message, start, self.position - start))
+                }
+            },
+        }
+    }
+}
+
+/// The names POSIX gives the characters of the portable character set, for collating symbols like `[.hyphen.]`.
+/// Letters and digits are only named by themselves, apart from the digits, which have their names too.
+pub const COLLATING_NAMES: &[(&str, char)] = &[
+    ("NUL", '\0'),
+    ("SOH", '\u{1}'),
+    ("STX", '\u{2}'),
+    ("ETX", '\u{3}'),
+    ("EOT", '\u{4}'),
+    ("ENQ", '\u{5}'),
+    ("ACK", '\u{6}'),
+    ("alert", '\u{7}'),
+    ("BEL", '\u{7}'),
+    ("backspace", '\u{8}'),
+    ("BS", '\u{8}'),
+    ("tab", '\t'),
+    ("HT", '\t'),
+    ("newline", '\n'),
+    ("LF", '\n'),
+    ("vertical-tab", '\u{b}'),
+    ("VT", '\u{b}'),
+    ("form-feed", '\u{c}'),
+    ("FF", '\u{c}'),
+    ("carriage-return", '\r'),
+    ("CR", '\r'),
+    ("SO", '\u{e}'),
+    ("SI", '\u{f}'),
+    ("DLE", '\u{10}'),
+    ("DC1", '\u{11}'),
+    ("DC2", '\u{12}'),
+    ("DC3", '\u{13}'),
+    ("DC4", '\u{14}'),
+    ("NAK", '\u{15}'),
+    ("SYN", '\u{16}'),
+    ("ETB", '\u{17}'),
+    ("CAN", '\u{18}'),
+    ("EM", '\u{19}'),
+    ("SUB", '\u{1a}'),
+    ("ESC", '\u{1b}'),
+    ("IS4", '\u{1c}'),
+    ("FS", '\u{1c}'),
+    ("IS3", '\u{1d}'),
+    ("GS", '\u{1d}'),
+    ("IS2", '\u{1e}'),
+    ("RS", '\u{1e}'),
+    ("IS1", '\u{1f}'),
+    ("US", '\u{1f}'),
+    ("space", ' '),
+    ("exclamation-mark", '!'),
+    ("quotation-mark", '"'),
+    ("number-sign", '#'),
+    ("dollar-sign", '$'),
+    ("percent-sign", '%'),
+    ("ampersand", '&'),
+    ("apostrophe", '\''),
+    ("left-parenthesis", '('),
+    ("right-parenthesis", ')'),
+    ("asterisk", '*'),
+    ("plus-sign", '+'),
+    ("comma", ','),
+    ("hyphen", '-'),
+    ("hyphen-minus", '-'),
+    ("period", '.'),
+    ("full-stop", '.'),
+    ("slash", '/'),
+    ("solidus", '/'),
+    ("zero", '0'),
+    ("one", '1'),
+    ("two", '2'),
+    ("three", '3'),
+    ("four", '4'),
+    ("five", '5'),
+    ("six", '6'),
+    ("seven", '7'),
+    ("eight", '8'),
+    ("nine", '9'),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("less-than-sign", '<'),
+    ("equals-sign", '='),
+    ("greater-than-sign", '>'),
+    ("question-mark", '?'),
+    ("commercial-at", '@'),
+    ("left-square-bracket", '['),
+    ("backslash", '\\'),
+    ("reverse-solidus", '\\'),
+    ("right-square-bracket", ']'),
+    ("circumflex", '^'),
+    ("circumflex-accent", '^'),
+    ("underscore", '_'),
+    ("low-line", '_'),
+    ("grave-accent", '`'),
+    ("left-brace", '{'),
+    ("left-curly-bracket", '{'),
+    ("vertical-line", '|'),
+    ("right-brace", '}'),
+    ("right-curly-bracket", '}'),
+    ("tilde", '~'),
+    ("DEL", '\u{7f}'),
+];
+
+fn push_member(class: &mut String, c: char) {
+    if ENGINE_CLASS_SPECIAL.contains(c) {
+        class.push('\\');
+    }
+    class.push(c);
+}
@@ -0,0 +1,158 @@
+//! Backtracker: runs a program depth first, trying the alternatives of every split in priority order and
+//! going back to the next one when a path fails. This finds the same leftmost-first matches as the
+//! [`pikevm`](super::pikevm) and can also match backreferences, but takes exponential time on some patterns,
+//! so it only runs programs that have backreferences. It gives up after a number of steps proportional to the
+//! length of the haystack, and never takes more than [`MAX_STEPS`].
+
+use alloc::vec::Vec;
+
+use super::compile::{Inst, Program};
+use super::pikevm::{assertion_holds, decode};
+
+enum Frame {
+    /// Tries `pc` at `position`, with the splits on the path to it up to `path`
+    Explore { pc: usize, position: usize, path: usize },
+    /// Puts a capture slot back when the path that set it failed
+    Restore(usize, Option<usize>),
+}
+
+/// Steps a search may take for every byte of the haystack before it gives up. Enough for the quadratic searches
+/// of patterns like `(\w+) \1` on lines of ordinary length, not for exponential ones.
+const STEPS_PER_BYTE: usize = 10_000;
+
+/// Steps a search takes at most, however long the haystack is, so it gives up within a fraction of a second
+const MAX_STEPS: usize = 10_000_000;
+
+/// A search took more steps than it may for its haystack, see [`STEPS_PER_BYTE`] and [`MAX_STEPS`]
+#[derive(Debug)]
+pub struct TooExpensive;
+
+/// Searches for the leftmost-first match starting at or after `at`. On a match, fills `slots` with the
+/// capture positions and returns true.
+pub fn search(
+    program: &Program,
+    haystack: &[u8],
+    at: usize,
+    slots: &mut [Option<usize>],
+) -> Result<bool, TooExpensive> {
+    let mut stack = Vec::new();
+    let mut path = Vec::new();
+    let mut budget = (haystack.len() + 1).saturating_mul(STEPS_PER_BYTE).min(MAX_STEPS);
+    let mut start = at;
+    loop {
+        slots.iter_mut().for_each(|slot| *slot = None);
+        if run(program, haystack, start, slots, &mut stack, &mut path, &mut budget)? {
+            return Ok(true);
+        }
+        match decode(haystack, start) {
+            Some((_, width)) => start += width,
+            None => return Ok(false),
+        }
+    }
+}
+
+/// Whether the program matches starting exactly at `start`, taking one of the `budget` steps left for every
+/// instruction
+fn run(
+    program: &Program,
+    haystack: &[u8],
+    start: usize,
+    slots: &mut [Option<usize>],
+    stack: &mut Vec<Frame>,
+    path: &mut Vec<(usize, usize)>,
+    budget: &mut usize,
+) -> Result<bool, TooExpensive> {
+    stack.clear();
+    stack.push(Frame::Explore { pc: 0, position: start, path: 0 });
+    while let Some(frame) = stack.pop() {
+        let (mut pc, mut position) = match frame {
+            Frame::Explore { pc, position, path: length } => {
+                path.truncate(length);
+                (pc, position)
+            }
+            Frame::Restore(slot, value) => {
+                slots[slot] = value;
+                continue;
+            }
+        };
+        loop {
+            *budget = budget.checked_sub(1).ok_or(TooExpensive)?;
+            // the width of the char at `position` if it is one `accepts`
+            let char_width = |accepts: &dyn Fn(char) -> bool| {
+                decode(haystack, position).filter(|(c, _)| accepts(*c)).map(|(_, width)| width)
+            };
+            let advanced = match &program.insts[pc] {
+                Inst::Match => return Ok(true),
+                Inst::Char(expected) => char_width(&|c| c == *expected),
+                Inst::Class(class) => char_width(&|c| class.matches(c)),
+                Inst::Any { newline } => char_width(&|c| *newline || c != '\n'),
+                Inst::Assert(assertion) => Some(0).filter(|_| assertion_holds(*assertion, haystack, position)),
+                Inst::Backref { group, case_insensitive } => {
+                    backref(haystack, position, slots, *group, *case_insensitive)
+                }
+                Inst::Save(slot) => {
+                    stack.push(Frame::Restore(*slot, slots[*slot]));
+                    slots[*slot] = Some(position);
+                    Some(0)
+                }
+                Inst::Jmp(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Inst::Split(first, second) => {
+                    // coming back to a split without having matched anything since is a repetition of nothing,
+                    // which would go on forever; positions only grow along a path, so only its end is checked
+                    let mut here = path.iter().rev().take_while(|(_, at)| *at == position);
+                    let looped = here.any(|(split, _)| *split == pc);
+                    if looped {
+                        None
+                    } else {
+                        path.push((pc, position));
+                        stack.push(Frame::Explore { pc: *second, position, path: path.len() });
+                        pc = *first;
+                        continue;
+                    }
+                }
+            };
+            match advanced {
+                Some(width) => {
+                    position += width;
+                    pc += 1;
+                }
+                None => break,
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// The length of the text group `group` matched if it is at `position` again, `None` if it is not there or the
+/// group did not match
+fn backref(
+    haystack: &[u8],
+    position: usize,
+    slots: &[Option<usize>],
+    group: usize,
+    case_insensitive: bool,
+) -> Option<usize> {
+    let (start, end) = match (slots[group * 2], slots[group * 2 + 1]) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return None,
+    };
+    let captured = &haystack[start..end];
+    if !case_insensitive {
+        return haystack[position..].starts_with(captured).then_some(captured.len());
+    }
+    // the same chars up to case, which may take other widths in the haystack
+    let (mut expected, mut at) = (start, position);
+    while expected < end {
+        let (want, want_width) = decode(haystack, expected)?;
+        let (found, found_width) = decode(haystack, at)?;
+        if want != found && !want.to_lowercase().eq(found.to_lowercase()) {
+            return None;
+        }
+        expected += want_width;
+        at += found_width;
+    }
+    Some(at - position)
+}
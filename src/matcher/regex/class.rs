@@ -0,0 +1,177 @@
+//! Character classes: sets of chars given as ranges and builtin classes like `\d` and `[:alpha:]`.
+
+use alloc::vec::Vec;
+
+/// Classes that are tested with char properties instead of listing their ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// `\d`, any Unicode decimal digit
+    Digit,
+    /// `\w`, alphanumeric chars and the underscore
+    Word,
+    /// `\s`, Unicode whitespace
+    Space,
+    /// `[:alpha:]`
+    Alpha,
+    /// `[:alnum:]`
+    Alnum,
+    /// `[:upper:]`
+    Upper,
+    /// `[:lower:]`
+    Lower,
+    /// `[:upper:]` and `[:lower:]` when matching case insensitively, both are any char that has case
+    Cased,
+    /// `[:punct:]`, visible chars that are neither letters nor digits
+    Punct,
+    /// `[:blank:]`, whitespace that does not end lines
+    Blank,
+    /// `[:cntrl:]`
+    Cntrl,
+    /// `[:graph:]`, visible chars
+    Graph,
+    /// `[:print:]`, visible chars and the space
+    Print,
+    /// `[:xdigit:]`, ASCII hex digits
+    Xdigit,
+}
+
+/// Names of the POSIX classes like `[:alpha:]`, and what they are
+const POSIX_CLASSES: &[(&str, Builtin)] = &[
+    ("alpha", Builtin::Alpha),
+    ("alnum", Builtin::Alnum),
+    ("digit", Builtin::Digit),
+    ("upper", Builtin::Upper),
+    ("lower", Builtin::Lower),
+    ("space", Builtin::Space),
+    ("blank", Builtin::Blank),
+    ("punct", Builtin::Punct),
+    ("cntrl", Builtin::Cntrl),
+    ("graph", Builtin::Graph),
+    ("print", Builtin::Print),
+    ("xdigit", Builtin::Xdigit),
+    ("word", Builtin::Word),
+];
+
+impl Builtin {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Builtin::Digit => c.is_numeric(),
+            Builtin::Word => is_word_char(c),
+            Builtin::Space => c.is_whitespace(),
+            Builtin::Alpha => c.is_alphabetic(),
+            Builtin::Alnum => c.is_alphanumeric(),
+            Builtin::Upper => c.is_uppercase(),
+            Builtin::Lower => c.is_lowercase(),
+            Builtin::Cased => c.is_uppercase() || c.is_lowercase(),
+            Builtin::Punct => is_graph(c) && !c.is_alphanumeric(),
+            Builtin::Blank => c.is_whitespace() && !is_line_break(c),
+            Builtin::Cntrl => c.is_control(),
+            Builtin::Graph => is_graph(c),
+            Builtin::Print => is_graph(c) || c == ' ',
+            Builtin::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+
+    /// The POSIX class with `name`, like `alpha` for `[:alpha:]`
+    pub fn posix(name: &str) -> Option<Builtin> {
+        POSIX_CLASSES.iter().find(|(posix, _)| *posix == name).map(|(_, builtin)| *builtin)
+    }
+}
+
+fn is_graph(c: char) -> bool {
+    !c.is_whitespace() && !c.is_control()
+}
+
+fn is_line_break(c: char) -> bool {
+    matches!(c, '\n' | '\x0B' | '\x0C' | '\r' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+/// Whether a char is part of a word for `\w` and `\b`
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Ranges larger than this are not case folded char by char; they are too big to be about case anyway
+const MAX_FOLDED_RANGE: u32 = 0x3000;
+
+#[derive(Debug, Clone, Default)]
+pub struct Class {
+    /// Inclusive ranges, sorted and merged once the class is finished
+    ranges: Vec<(char, char)>,
+    /// Builtin classes and whether they are negated, like `\D`
+    builtins: Vec<(Builtin, bool)>,
+    pub negated: bool,
+}
+
+impl Class {
+    pub fn new() -> Class {
+        Class::default()
+    }
+
+    pub fn push_range(&mut self, low: char, high: char) {
+        self.ranges.push((low, high));
+    }
+
+    pub fn push_builtin(&mut self, builtin: Builtin, negated: bool) {
+        self.builtins.push((builtin, negated));
+    }
+
+    /// Adds the other case of every char when `case_insensitive`, then sorts and merges the ranges.
+    /// Must be called once all items are pushed.
+    pub fn finish(mut self, case_insensitive: bool) -> Class {
+        if case_insensitive {
+            for (builtin, _) in &mut self.builtins {
+                if matches!(builtin, Builtin::Upper | Builtin::Lower) {
+                    *builtin = Builtin::Cased;
+                }
+            }
+            let mut folded = Vec::new();
+            for &(low, high) in &self.ranges {
+                if high as u32 - low as u32 > MAX_FOLDED_RANGE {
+                    continue;
+                }
+                for c in low..=high {
+                    for variant in c.to_lowercase().chain(c.to_uppercase()) {
+                        if variant != c {
+                            folded.push((variant, variant));
+                        }
+                    }
+                }
+            }
+            self.ranges.extend(folded);
+        }
+
+        self.ranges.sort();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(self.ranges.len());
+        for (low, high) in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if low as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
+        self.ranges = merged;
+        self
+    }
+
+    /// Whether the class is exactly one char, so it can be matched as a literal
+    pub fn is_single_char(&self) -> bool {
+        !self.negated && self.builtins.is_empty() && matches!(self.ranges.as_slice(), [(low, high)] if low == high)
+    }
+
+    pub fn matches(&self, c: char) -> bool {
+        let in_ranges = self
+            .ranges
+            .binary_search_by(|&(low, high)| {
+                if high < c {
+                    core::cmp::Ordering::Less
+                } else if low > c {
+                    core::cmp::Ordering::Greater
+                } else {
+                    core::cmp::Ordering::Equal
+                }
+            })
+            .is_ok();
+        let in_builtins = || self.builtins.iter().any(|&(builtin, negated)| builtin.matches(c) != negated);
+        (in_ranges || in_builtins()) != self.negated
+    }
+}
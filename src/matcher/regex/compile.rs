@@ -0,0 +1,163 @@
+//! Compiles a [`Node`] tree into a program for the [`pikevm`](super::pikevm), or the
+//! [`backtrack`](super::backtrack)er when it has backreferences.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::class::Class;
+use super::parse::{Assertion, Error, Node};
+
+/// Programs larger than this are refused, searching them would be too slow
+const MAX_INSTRUCTIONS: usize = 1_000_000;
+
+#[derive(Debug, Clone)]
+pub enum Inst {
+    Char(char),
+    Class(Class),
+    Any { newline: bool },
+    Assert(Assertion),
+    /// Records the current position in a capture slot
+    Save(usize),
+    /// Continues at both targets, the first one has priority
+    Split(usize, usize),
+    Jmp(usize),
+    /// Matches the text capture group `group` matched, only run by the [`backtrack`](super::backtrack)er
+    Backref { group: usize, case_insensitive: bool },
+    Match,
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub insts: Vec<Inst>,
+    /// Number of capture slots, two per group
+    pub slots: usize,
+}
+
+/// Compiles the tree, with the whole match recorded as group 0
+pub fn compile(node: &Node, groups: usize, pattern: &str) -> Result<Program, Error> {
+    let mut compiler = Compiler { insts: Vec::new() };
+    compiler.push(Inst::Save(0));
+    compiler.node(node);
+    compiler.push(Inst::Save(1));
+    compiler.push(Inst::Match);
+    if compiler.insts.len() > MAX_INSTRUCTIONS {
+        return Err(Error {
+            message: String::from("pattern is too large after expanding repetitions"),
+            position: 0,
+            length: pattern.chars().count(),
+            pattern: String::from(pattern),
+        });
+    }
+    Ok(Program { insts: compiler.insts, slots: groups * 2 })
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+
+    fn pc(&self) -> usize {
+        self.insts.len()
+    }
+
+    fn node(&mut self, node: &Node) {
+        // keep going past the limit cheaply, compile reports it
+        if self.insts.len() > MAX_INSTRUCTIONS {
+            return;
+        }
+        match node {
+            Node::Empty => {}
+            Node::Literal(c) => {
+                self.push(Inst::Char(*c));
+            }
+            Node::Class(class) => {
+                self.push(Inst::Class(class.clone()));
+            }
+            Node::Any { newline } => {
+                self.push(Inst::Any { newline: *newline });
+            }
+            Node::Assertion(assertion) => {
+                self.push(Inst::Assert(*assertion));
+            }
+            Node::Backref { group, case_insensitive } => {
+                self.push(Inst::Backref { group: *group, case_insensitive: *case_insensitive });
+            }
+            Node::Group { index: Some(index), node } => {
+                self.push(Inst::Save(index * 2));
+                self.node(node);
+                self.push(Inst::Save(index * 2 + 1));
+            }
+            Node::Group { index: None, node } => self.node(node),
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.node(node);
+                }
+            }
+            Node::Alternate(branches) => {
+                let mut jumps = Vec::new();
+                for (index, branch) in branches.iter().enumerate() {
+                    if index + 1 == branches.len() {
+                        self.node(branch);
+                    } else {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.node(branch);
+                        jumps.push(self.push(Inst::Jmp(0)));
+                        self.insts[split] = Inst::Split(split + 1, self.pc());
+                    }
+                }
+                let end = self.pc();
+                for jump in jumps {
+                    self.insts[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat { node, min, max, greedy } => {
+                match max {
+                    // the last required copy loops back onto itself, so an empty iteration cannot repeat
+                    None if *min > 0 => {
+                        for _ in 1..*min {
+                            self.node(node);
+                        }
+                        let start = self.pc();
+                        self.node(node);
+                        let split = self.push(Inst::Split(0, 0));
+                        self.insts[split] = self.split(start, split + 1, *greedy);
+                    }
+                    None => {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.node(node);
+                        self.push(Inst::Jmp(split));
+                        self.insts[split] = self.split(split + 1, self.pc(), *greedy);
+                    }
+                    Some(max) => {
+                        for _ in 0..*min {
+                            self.node(node);
+                        }
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0)));
+                            self.node(node);
+                        }
+                        let end = self.pc();
+                        for split in splits {
+                            self.insts[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// A split between repeating again and moving on, in the order the greediness asks for
+    fn split(&self, again: usize, done: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(again, done)
+        } else {
+            Inst::Split(done, again)
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! A regular expression engine for byte haystacks.
+//!
+//! The syntax follows the common Perl-like dialect: `.`, classes like `[a-z]`, `\d` and `[[:alpha:]]`, anchors,
+//! `\b`, `\<` and `\>` for the start and end of words, groups (capturing, non-capturing and named),
+//! alternation, greedy and lazy repetition, and the inline flags `i`, `m`, `s`, `x` and `U`. Haystacks are
+//! decoded as UTF-8, invalid bytes match as U+FFFD. Classes take the collating symbols and equivalence classes
+//! of POSIX for single chars, like `[[.-.]]` and `[[=e=]]`, which only stand for that char as in the C locale.
+//!
+//! Multi-line mode is on by default: `^` and `$` match at the start and end of every line, `\A` and `\z`
+//! only at the ends of the haystack.
+//!
+//! Backreferences `\1` to `\9` match the text a group matched again. Patterns without them run on a Pike VM,
+//! which takes time linear in the haystack; patterns with them need a backtracker, see [`Engine`].
+
+mod backtrack;
+mod class;
+mod compile;
+mod parse;
+mod pikevm;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{Captures, Match, Matcher};
+
+pub use self::parse::Error;
+
+/// Escapes every regex metacharacter, so the result matches `text` literally
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// What runs compiled patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// The Pike VM, or the backtracker for patterns with backreferences
+    #[default]
+    Auto,
+    /// The Pike VM, in time linear in the haystack; backreferences are refused
+    PikeVm,
+    /// The backtracker, which can take exponential time on patterns like `(a*)*b`; it gives up after a time
+    /// proportional to the haystack, see [`Matcher::gave_up`]
+    Backtrack,
+}
+
+impl Engine {
+    /// Names accepted by `--engine`
+    pub const NAMES: &'static [&'static str] = &["auto", "pikevm", "backtrack"];
+
+    pub fn from_name(name: &str) -> Option<Engine> {
+        Some(match name {
+            "auto" => Engine::Auto,
+            "pikevm" => Engine::PikeVm,
+            "backtrack" => Engine::Backtrack,
+            _ => return None,
+        })
+    }
+}
+
+/// A compiled regular expression
+pub struct RegexMatcher {
+    program: compile::Program,
+    /// Run by the backtracker instead of the Pike VM
+    backtrack: bool,
+    group_names: Vec<Option<String>>,
+    pattern: String,
+    /// A backtracking search gave up since [`Matcher::gave_up`] was last asked
+    gave_up: AtomicBool,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<RegexMatcher, Error> {
+        RegexMatcher::with_engine(pattern, case_insensitive, Engine::Auto)
+    }
+
+    /// Compiles `pattern` to run on `engine`
+    pub fn with_engine(pattern: &str, case_insensitive: bool, engine: Engine) -> Result<RegexMatcher, Error> {
+        let parsed = parse::parse(pattern, case_insensitive)?;
+        let backtrack = match (engine, parsed.first_backref) {
+            (Engine::PikeVm, Some(position)) => {
+                let message = "backreferences need the backtracking engine, with --engine auto or backtrack";
+                let pattern = String::from(pattern);
+                return Err(Error { message: String::from(message), position, length: 2, pattern });
+            }
+            (Engine::Auto, backref) => backref.is_some(),
+            (engine, _) => engine == Engine::Backtrack,
+        };
+        let program = compile::compile(&parsed.node, parsed.group_names.len(), pattern)?;
+        let (group_names, pattern, gave_up) = (parsed.group_names, String::from(pattern), AtomicBool::new(false));
+        Ok(RegexMatcher { program, backtrack, group_names, pattern, gave_up })
+    }
+
+    fn search(&self, haystack: &[u8], at: usize, earliest: bool) -> Option<Vec<Option<usize>>> {
+        if at > haystack.len() {
+            return None;
+        }
+        let mut slots = vec![None; self.program.slots];
+        let found = if self.backtrack {
+            backtrack::search(&self.program, haystack, at, &mut slots).unwrap_or_else(|_| {
+                self.gave_up.store(true, Ordering::Relaxed);
+                false
+            })
+        } else {
+            pikevm::search(&self.program, haystack, at, &mut slots, earliest)
+        };
+        if found {
+            Some(slots)
+        } else {
+            None
+        }
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Match> {
+        let slots = self.search(haystack, at, false)?;
+        Some(Match::new(slots[0]?, slots[1]?))
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Captures> {
+        let slots = self.search(haystack, at, false)?;
+        let spans = slots
+            .chunks(2)
+            .map(|pair| match pair {
+                [Some(start), Some(end)] => Some(Match::new(*start, *end)),
+                _ => None,
+            })
+            .collect();
+        Some(Captures::new(spans, self.group_names.clone()))
+    }
+
+    fn is_match(&self, haystack: &[u8]) -> bool {
+        self.search(haystack, 0, true).is_some()
+    }
+
+    fn gave_up(&self) -> bool {
+        self.gave_up.swap(false, Ordering::Relaxed)
+    }
+
+    fn strategy(&self) -> String {
+        let engine = if self.backtrack { "backtracker" } else { "pike vm" };
+        format!("regex ({}, {} instructions) for {:?}", engine, self.program.insts.len(), self.pattern)
+    }
+}
@@ -0,0 +1,586 @@
+//! Parses regex syntax into a [`Node`] tree. Flags like `(?i)` are applied while parsing,
+//! so the tree itself has no flags left in it.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::class::{Builtin, Class};
+
+/// Zero width conditions on the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assertion {
+    /// `^`, unless multi-line mode is turned off with `(?-m)`
+    StartLine,
+    /// `$`, unless multi-line mode is turned off with `(?-m)`
+    EndLine,
+    /// `\A`, or `^` outside multi-line mode
+    StartText,
+    /// `\z`, or `$` outside multi-line mode
+    EndText,
+    /// `\b`
+    WordBoundary,
+    /// `\B`
+    NotWordBoundary,
+    /// `\<`, the start of a word
+    WordStart,
+    /// `\>`, the end of a word
+    WordEnd,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Empty,
+    Literal(char),
+    Class(Class),
+    /// `.`, `newline` is whether it also matches `\n`
+    Any { newline: bool },
+    Assertion(Assertion),
+    /// `index` is the capture group number, `None` for non-capturing groups
+    Group { index: Option<usize>, node: Box<Node> },
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32>, greedy: bool },
+    /// `\1` to `\9`, the text capture group `group` matched last
+    Backref { group: usize, case_insensitive: bool },
+}
+
+/// Syntax error, with the char offset in the pattern where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    pub position: usize,
+    /// Chars from `position` on that the error is about, underlined when displayed; 1 for a single spot
+    pub length: usize,
+    pub pattern: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "regex parse error:")?;
+        writeln!(f, "    {}", self.pattern)?;
+        let marker = "^".repeat(self.length.max(1));
+        writeln!(f, "    {}{}", " ".repeat(self.position), marker)?;
+        write!(f, "error: {}", self.message)
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Counted repetitions larger than this are refused, they would make huge programs
+const MAX_REPEAT: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Flags {
+    case_insensitive: bool,
+    multi_line: bool,
+    dot_matches_newline: bool,
+    /// `x`: whitespace is ignored and `#` starts a comment
+    ignore_whitespace: bool,
+    /// `U`: `*` is lazy and `*?` is greedy
+    swap_greed: bool,
+}
+
+/// Result of parsing: the tree and the names of the capture groups, index 0 being the whole match
+pub struct Parsed {
+    pub node: Node,
+    pub group_names: Vec<Option<String>>,
+    /// Where the first backreference is, if there is one
+    pub first_backref: Option<usize>,
+}
+
+pub fn parse(pattern: &str, case_insensitive: bool) -> Result<Parsed, Error> {
+    let mut parser = Parser {
+        pattern,
+        chars: pattern.chars().collect(),
+        position: 0,
+        group_names: vec![None],
+        first_backref: None,
+    };
+    // lines are searched one at a time unless the whole input is searched at once, and then `^` and `$`
+    // should still match at the start and end of every line
+    let mut flags = Flags { case_insensitive, multi_line: true, ..Flags::default() };
+    let node = parser.parse_alternation(&mut flags, 0)?;
+    if parser.position < parser.chars.len() {
+        // parse_alternation only stops early at a `)`
+        return Err(parser.error("unopened group"));
+    }
+    Ok(Parsed { node, group_names: parser.group_names, first_backref: parser.first_backref })
+}
+
+struct Parser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    position: usize,
+    group_names: Vec<Option<String>>,
+    first_backref: Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        self.error_at(message, self.position)
+    }
+
+    fn error_at(&self, message: &str, position: usize) -> Error {
+        self.error_span(message, position, 1)
+    }
+
+    fn error_span(&self, message: &str, position: usize, length: usize) -> Error {
+        Error { message: String::from(message), position, length, pattern: String::from(self.pattern) }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        (self.position..).zip(text.chars()).all(|(position, c)| self.chars.get(position) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips whitespace and comments in `x` mode
+    fn skip_ignored(&mut self, flags: &Flags) {
+        if !flags.ignore_whitespace {
+            return;
+        }
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.position += 1;
+            } else if c == '#' {
+                while let Some(c) = self.next() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses up to the end of the pattern or the `)` closing the current group, which is not consumed.
+    /// Inline flags like `(?i)` change `flags` up to the end of the current group.
+    fn parse_alternation(&mut self, flags: &mut Flags, depth: usize) -> Result<Node, Error> {
+        let mut branches = vec![self.parse_concat(flags, depth)?];
+        while self.eat('|') {
+            branches.push(self.parse_concat(flags, depth)?);
+        }
+        Ok(if branches.len() == 1 { branches.remove(0) } else { Node::Alternate(branches) })
+    }
+
+    fn parse_concat(&mut self, flags: &mut Flags, depth: usize) -> Result<Node, Error> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_ignored(flags);
+            match self.peek() {
+                None | Some('|') => break,
+                Some(')') if depth > 0 => break,
+                Some(')') => return Err(self.error("unopened group")),
+                _ => {}
+            }
+            let atom = match self.parse_atom(flags, depth)? {
+                Some(atom) => atom,
+                // a flag group like (?i), which changes flags but matches nothing
+                None => continue,
+            };
+            let node = self.parse_repetitions(atom, flags)?;
+            nodes.push(node);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_repetitions(&mut self, mut node: Node, flags: &Flags) -> Result<Node, Error> {
+        loop {
+            self.skip_ignored(flags);
+            let (min, max) = match self.peek() {
+                Some('*') => {
+                    self.position += 1;
+                    (0, None)
+                }
+                Some('+') => {
+                    self.position += 1;
+                    (1, None)
+                }
+                Some('?') => {
+                    self.position += 1;
+                    (0, Some(1))
+                }
+                Some('{') => match self.parse_counted()? {
+                    Some(range) => range,
+                    // not a valid repetition, so the brace is taken literally
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            let lazy = self.eat('?');
+            node = Node::Repeat { node: Box::new(node), min, max, greedy: lazy == flags.swap_greed };
+        }
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}`. Returns None, consuming nothing, if the brace does not start one.
+    fn parse_counted(&mut self) -> Result<Option<(u32, Option<u32>)>, Error> {
+        let start = self.position;
+        self.position += 1;
+        let read_number = |parser: &mut Parser| -> Option<u32> {
+            let begin = parser.position;
+            while matches!(parser.peek(), Some(c) if c.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            let digits: String = parser.chars[begin..parser.position].iter().collect();
+            digits.parse().ok()
+        };
+        let min = match read_number(self) {
+            Some(min) => min,
+            None => {
+                self.position = start;
+                return Ok(None);
+            }
+        };
+        let max = if self.eat(',') {
+            if self.peek() == Some('}') {
+                None
+            } else {
+                match read_number(self) {
+                    Some(max) => Some(max),
+                    None => {
+                        self.position = start;
+                        return Ok(None);
+                    }
+                }
+            }
+        } else {
+            Some(min)
+        };
+        if !self.eat('}') {
+            self.position = start;
+            return Ok(None);
+        }
+        if min.max(max.unwrap_or(0)) > MAX_REPEAT {
+            return Err(self.error_at("repetition count is too large", start));
+        }
+        if matches!(max, Some(max) if max < min) {
+            return Err(self.error_at("invalid repetition range, the minimum is larger than the maximum", start));
+        }
+        Ok(Some((min, max)))
+    }
+
+    /// Parses one atom. Returns None for a flag group like `(?i)`, which only changes `flags`.
+    fn parse_atom(&mut self, flags: &mut Flags, depth: usize) -> Result<Option<Node>, Error> {
+        let start = self.position;
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Ok(Some(Node::Empty)),
+        };
+        let node = match c {
+            '(' => return self.parse_group(flags, depth, start),
+            '[' => Node::Class(self.parse_class(flags, start)?),
+            '.' => Node::Any { newline: flags.dot_matches_newline },
+            '^' => Node::Assertion(if flags.multi_line { Assertion::StartLine } else { Assertion::StartText }),
+            '$' => Node::Assertion(if flags.multi_line { Assertion::EndLine } else { Assertion::EndText }),
+            '\\' => self.parse_escape(flags, start)?,
+            '*' | '+' | '?' => return Err(self.error_at("repetition operator missing expression", start)),
+            c => literal(c, flags),
+        };
+        Ok(Some(node))
+    }
+
+    fn parse_group(&mut self, flags: &mut Flags, depth: usize, start: usize) -> Result<Option<Node>, Error> {
+        let mut inner_flags = *flags;
+        let index = if self.eat('?') {
+            if self.eat(':') {
+                None
+            } else if self.peek_is("P<") || self.peek_is("<") {
+                self.eat('P');
+                self.eat('<');
+                let name_start = self.position;
+                while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+                    self.position += 1;
+                }
+                let name: String = self.chars[name_start..self.position].iter().collect();
+                if name.is_empty() || !self.eat('>') {
+                    return Err(self.error_at("invalid capture group name", name_start));
+                }
+                if self.group_names.iter().any(|existing| existing.as_deref() == Some(name.as_str())) {
+                    return Err(self.error_at("duplicate capture group name", name_start));
+                }
+                self.group_names.push(Some(name));
+                Some(self.group_names.len() - 1)
+            } else {
+                // flags, either (?flags) for the rest of the group or (?flags:...) for a new group
+                let mut enable = true;
+                loop {
+                    match self.next() {
+                        Some('i') => inner_flags.case_insensitive = enable,
+                        Some('m') => inner_flags.multi_line = enable,
+                        Some('s') => inner_flags.dot_matches_newline = enable,
+                        Some('x') => inner_flags.ignore_whitespace = enable,
+                        Some('U') => inner_flags.swap_greed = enable,
+                        Some('-') if enable => enable = false,
+                        Some(')') => {
+                            *flags = inner_flags;
+                            return Ok(None);
+                        }
+                        Some(':') => break,
+                        Some(_) => return Err(self.error_at("unrecognized flag", self.position - 1)),
+                        None => return Err(self.error_at("unclosed group", start)),
+                    }
+                }
+                None
+            }
+        } else {
+            self.group_names.push(None);
+            Some(self.group_names.len() - 1)
+        };
+
+        let node = self.parse_alternation(&mut inner_flags, depth + 1)?;
+        if !self.eat(')') {
+            return Err(self.error_at("unclosed group", start));
+        }
+        Ok(Some(Node::Group { index, node: Box::new(node) }))
+    }
+
+    /// Parses an escape outside of a class, after the backslash
+    fn parse_escape(&mut self, flags: &Flags, start: usize) -> Result<Node, Error> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err(self.error_at("incomplete escape sequence", start)),
+        };
+        let node = match c {
+            'b' => Node::Assertion(Assertion::WordBoundary),
+            'B' => Node::Assertion(Assertion::NotWordBoundary),
+            '<' => Node::Assertion(Assertion::WordStart),
+            '>' => Node::Assertion(Assertion::WordEnd),
+            'A' => Node::Assertion(Assertion::StartText),
+            'z' => Node::Assertion(Assertion::EndText),
+            '1'..='9' => {
+                let group = c as usize - '0' as usize;
+                if group >= self.group_names.len() {
+                    return Err(self.error_at("backreference to a group that is not opened before it", start));
+                }
+                self.first_backref.get_or_insert(start);
+                Node::Backref { group, case_insensitive: flags.case_insensitive }
+            }
+            _ => match self.parse_class_escape(c, start)? {
+                ClassEscape::Char(c) => literal(c, flags),
+                ClassEscape::Builtin(builtin, negated) => {
+                    let mut class = Class::new();
+                    class.push_builtin(builtin, negated);
+                    Node::Class(class.finish(flags.case_insensitive))
+                }
+            },
+        };
+        Ok(node)
+    }
+
+    /// Parses an escape that is valid both inside and outside of classes, after the backslash
+    fn parse_class_escape(&mut self, c: char, start: usize) -> Result<ClassEscape, Error> {
+        let escape = match c {
+            'd' => ClassEscape::Builtin(Builtin::Digit, false),
+            'D' => ClassEscape::Builtin(Builtin::Digit, true),
+            'w' => ClassEscape::Builtin(Builtin::Word, false),
+            'W' => ClassEscape::Builtin(Builtin::Word, true),
+            's' => ClassEscape::Builtin(Builtin::Space, false),
+            'S' => ClassEscape::Builtin(Builtin::Space, true),
+            't' => ClassEscape::Char('\t'),
+            'n' => ClassEscape::Char('\n'),
+            'r' => ClassEscape::Char('\r'),
+            'f' => ClassEscape::Char('\x0C'),
+            'v' => ClassEscape::Char('\x0B'),
+            'a' => ClassEscape::Char('\x07'),
+            'e' => ClassEscape::Char('\x1B'),
+            '0' => ClassEscape::Char('\0'),
+            'x' => ClassEscape::Char(self.parse_hex(start)?),
+            c if c.is_ascii_alphanumeric() => return Err(self.error_at("unrecognized escape sequence", start)),
+            c => ClassEscape::Char(c),
+        };
+        Ok(escape)
+    }
+
+    /// Parses `\xHH` or `\x{H..}`, after the `x`
+    fn parse_hex(&mut self, start: usize) -> Result<char, Error> {
+        let digits: String = if self.eat('{') {
+            let begin = self.position;
+            while matches!(self.peek(), Some(c) if c != '}') {
+                self.position += 1;
+            }
+            let digits = self.chars[begin..self.position].iter().collect();
+            if !self.eat('}') {
+                return Err(self.error_at("unclosed hex escape", start));
+            }
+            digits
+        } else {
+            let begin = self.position;
+            for _ in 0..2 {
+                self.next();
+            }
+            self.chars[begin..self.position.min(self.chars.len())].iter().collect()
+        };
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error_at("invalid hex escape", start))
+    }
+
+    /// Parses a POSIX class like `[:alpha:]` or `[:^alpha:]` in a bracketed class, after its `[`. Returns `None`,
+    /// reading nothing, if there is no `:]` to end it, and the `[` is a literal.
+    fn parse_posix_class(&mut self, start: usize) -> Result<Option<(Builtin, bool)>, Error> {
+        let begin = self.position + 1;
+        let end = match (begin..self.chars.len()).find(|&at| self.chars[at] == ']') {
+            Some(end) if self.chars[end - 1] == ':' && end - 1 > begin => end - 1,
+            _ => return Ok(None),
+        };
+        let name: String = self.chars[begin..end].iter().collect();
+        let (negated, name) = match name.strip_prefix('^') {
+            Some(name) => (true, name),
+            None => (false, name.as_str()),
+        };
+        let builtin = Builtin::posix(name).ok_or_else(|| self.error_at("unknown POSIX class", start))?;
+        self.position = end + 2;
+        Ok(Some((builtin, negated)))
+    }
+
+    /// Parses a collating symbol like `[.-.]` or an equivalence class like `[=e=]` in a bracketed class, after its
+    /// `[` at `start`, and returns its char and whether it is an equivalence class. As in the C locale, only single
+    /// chars are collating elements and every char is only equivalent to itself.
+    fn parse_collating(&mut self, start: usize) -> Result<(char, bool), Error> {
+        let kind = self.next().expect("checked by the caller");
+        let what = if kind == '=' { "equivalence class" } else { "collating symbol" };
+        let begin = self.position;
+        let end = match (begin..self.chars.len()).find(|&at| self.chars[at..].starts_with(&[kind, ']'])) {
+            Some(end) => end,
+            None => {
+                let message = format!("unclosed {}, expected [{}C{}]", what, kind, kind);
+                return Err(self.error_span(&message, start, self.chars.len() - start));
+            }
+        };
+        self.position = end + 2;
+        match self.chars[begin..end] {
+            [c] => Ok((c, kind == '=')),
+            [] => Err(self.error_span(&format!("empty {}", what), start, self.position - start)),
+            _ => {
+                let message = format!("{} of more than one char, only single ones are supported", what);
+                Err(self.error_span(&message, start, self.position - start))
+            }
+        }
+    }
+
+    /// Parses a bracketed class, after the opening bracket
+    fn parse_class(&mut self, flags: &Flags, start: usize) -> Result<Class, Error> {
+        let mut class = Class::new();
+        if self.eat('^') {
+            class.negated = true;
+        }
+        let mut first = true;
+        loop {
+            let item_start = self.position;
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err(self.error_at("unclosed character class", start)),
+            };
+            let (low, equivalence) = match c {
+                // a `]` right after the opening bracket is a literal
+                ']' if !first => break,
+                '[' if matches!(self.peek(), Some('=' | '.')) => self.parse_collating(item_start)?,
+                '[' if self.peek() == Some(':') => match self.parse_posix_class(item_start)? {
+                    Some((builtin, negated)) => {
+                        class.push_builtin(builtin, negated);
+                        first = false;
+                        continue;
+                    }
+                    None => ('[', false),
+                },
+                '\\' => {
+                    let escaped = match self.next() {
+                        Some(escaped) => escaped,
+                        None => return Err(self.error_at("incomplete escape sequence", item_start)),
+                    };
+                    match self.parse_class_escape(escaped, item_start)? {
+                        ClassEscape::Char(c) => (c, false),
+                        ClassEscape::Builtin(builtin, negated) => {
+                            class.push_builtin(builtin, negated);
+                            first = false;
+                            continue;
+                        }
+                    }
+                }
+                c => (c, false),
+            };
+            first = false;
+
+            // a range, unless the dash is the last thing in the class
+            if self.peek() == Some('-') && self.chars.get(self.position + 1).is_some_and(|c| *c != ']') {
+                self.position += 1;
+                let high_start = self.position;
+                let high = match self.next() {
+                    Some('[') if matches!(self.peek(), Some('=' | '.')) => match self.parse_collating(high_start)? {
+                        (high, false) if !equivalence => high,
+                        _ => {
+                            let message = "a range goes from one char to another, not from or to an equivalence class";
+                            return Err(self.error_span(message, item_start, self.position - item_start));
+                        }
+                    },
+                    Some('\\') => match self.next().map(|escaped| self.parse_class_escape(escaped, item_start)) {
+                        Some(Ok(ClassEscape::Char(c))) => c,
+                        Some(Err(err)) => return Err(err),
+                        _ => return Err(self.error_at("invalid range end", item_start)),
+                    },
+                    Some(c) => c,
+                    None => return Err(self.error_at("unclosed character class", start)),
+                };
+                if high < low {
+                    return Err(self.error_at("invalid range, the start is larger than the end", item_start));
+                }
+                if equivalence {
+                    let message = "a range goes from one char to another, not from or to an equivalence class";
+                    return Err(self.error_span(message, item_start, self.position - item_start));
+                }
+                class.push_range(low, high);
+            } else {
+                class.push_range(low, low);
+            }
+        }
+        Ok(class.finish(flags.case_insensitive))
+    }
+}
+
+enum ClassEscape {
+    Char(char),
+    /// A builtin class and whether it is negated, like `\D`
+    Builtin(Builtin, bool),
+}
+
+/// A literal char, or a class of its case variants when matching case insensitively
+fn literal(c: char, flags: &Flags) -> Node {
+    if !flags.case_insensitive {
+        return Node::Literal(c);
+    }
+    let mut class = Class::new();
+    class.push_range(c, c);
+    let class = class.finish(true);
+    if class.is_single_char() {
+        Node::Literal(c)
+    } else {
+        Node::Class(class)
+    }
+}
@@ -0,0 +1,220 @@
+//! Pike VM: runs a program over the haystack in a single pass, keeping at most one thread per instruction,
+//! so searching takes time linear in the haystack and never backtracks. Threads are kept in priority
+//! order, which gives the same leftmost-first matches as a backtracking engine.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::class::is_word_char;
+use super::compile::{Inst, Program};
+use super::parse::Assertion;
+
+/// Decodes the char starting at `position`. Invalid UTF-8 decodes as U+FFFD, one byte at a time.
+pub fn decode(haystack: &[u8], position: usize) -> Option<(char, usize)> {
+    let first = *haystack.get(position)?;
+    if first < 0x80 {
+        return Some((first as char, 1));
+    }
+    let width = match first {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return Some((char::REPLACEMENT_CHARACTER, 1)),
+    };
+    let decoded = haystack
+        .get(position..position + width)
+        .and_then(|bytes| core::str::from_utf8(bytes).ok())
+        .and_then(|text| text.chars().next());
+    match decoded {
+        Some(c) => Some((c, width)),
+        None => Some((char::REPLACEMENT_CHARACTER, 1)),
+    }
+}
+
+/// Decodes the char ending right before `position`
+pub fn decode_last(haystack: &[u8], position: usize) -> Option<char> {
+    if position == 0 {
+        return None;
+    }
+    // a char is at most 4 bytes, find where the last one starts
+    let mut start = position - 1;
+    while start > 0 && position - start < 4 && haystack[start] & 0xC0 == 0x80 {
+        start -= 1;
+    }
+    match decode(haystack, start) {
+        Some((c, width)) if start + width == position => Some(c),
+        _ => Some(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+fn is_word_at(c: Option<char>) -> bool {
+    c.is_some_and(is_word_char)
+}
+
+pub fn assertion_holds(assertion: Assertion, haystack: &[u8], position: usize) -> bool {
+    match assertion {
+        Assertion::StartText => position == 0,
+        Assertion::EndText => position == haystack.len(),
+        Assertion::StartLine => position == 0 || haystack[position - 1] == b'\n',
+        Assertion::EndLine => position == haystack.len() || haystack[position] == b'\n',
+        _ => {
+            let before = is_word_at(decode_last(haystack, position));
+            let after = is_word_at(decode(haystack, position).map(|(c, _)| c));
+            match assertion {
+                Assertion::WordStart => !before && after,
+                Assertion::WordEnd => before && !after,
+                _ => (before != after) == (assertion == Assertion::WordBoundary),
+            }
+        }
+    }
+}
+
+/// Set of threads, at most one per instruction, in priority order, with their capture slots
+struct Threads {
+    /// Instructions in the set, in insertion order
+    dense: Vec<usize>,
+    /// Index into `dense` for every instruction, only meaningful if it points back
+    sparse: Vec<usize>,
+    /// Capture slots of the thread at each instruction
+    slots: Vec<Option<usize>>,
+    slots_per_thread: usize,
+}
+
+impl Threads {
+    fn new(program: &Program) -> Threads {
+        Threads {
+            dense: Vec::with_capacity(program.insts.len()),
+            sparse: vec![0; program.insts.len()],
+            slots: vec![None; program.insts.len() * program.slots],
+            slots_per_thread: program.slots,
+        }
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        let index = self.sparse[pc];
+        index < self.dense.len() && self.dense[index] == pc
+    }
+
+    fn insert(&mut self, pc: usize) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    fn slots_mut(&mut self, pc: usize) -> &mut [Option<usize>] {
+        &mut self.slots[pc * self.slots_per_thread..(pc + 1) * self.slots_per_thread]
+    }
+}
+
+enum Frame {
+    Explore(usize),
+    /// Puts a capture slot back after the threads that saw the new value are added
+    Restore(usize, Option<usize>),
+}
+
+/// Adds a thread at `pc`, following jumps, splits, saves and assertions right away,
+/// so only threads waiting on a char or a match end up in the set.
+fn add_thread(
+    program: &Program,
+    threads: &mut Threads,
+    stack: &mut Vec<Frame>,
+    pc: usize,
+    haystack: &[u8],
+    position: usize,
+    slots: &mut [Option<usize>],
+) {
+    stack.push(Frame::Explore(pc));
+    while let Some(frame) = stack.pop() {
+        let pc = match frame {
+            Frame::Explore(pc) => pc,
+            Frame::Restore(slot, value) => {
+                slots[slot] = value;
+                continue;
+            }
+        };
+        if threads.contains(pc) {
+            continue;
+        }
+        threads.insert(pc);
+        match &program.insts[pc] {
+            Inst::Jmp(target) => stack.push(Frame::Explore(*target)),
+            Inst::Split(first, second) => {
+                stack.push(Frame::Explore(*second));
+                stack.push(Frame::Explore(*first));
+            }
+            Inst::Save(slot) => {
+                stack.push(Frame::Restore(*slot, slots[*slot]));
+                slots[*slot] = Some(position);
+                stack.push(Frame::Explore(pc + 1));
+            }
+            Inst::Assert(assertion) => {
+                if assertion_holds(*assertion, haystack, position) {
+                    stack.push(Frame::Explore(pc + 1));
+                }
+            }
+            Inst::Char(_) | Inst::Class(_) | Inst::Any { .. } | Inst::Match => {
+                threads.slots_mut(pc).copy_from_slice(slots);
+            }
+            // programs with backreferences are run by the backtracker
+            Inst::Backref { .. } => {}
+        }
+    }
+}
+
+/// Searches for the leftmost-first match starting at or after `at`. On a match, fills `slots` with the
+/// capture positions and returns true. With `earliest`, stops at the first match found, whatever its length.
+pub fn search(program: &Program, haystack: &[u8], at: usize, slots: &mut [Option<usize>], earliest: bool) -> bool {
+    let mut current = Threads::new(program);
+    let mut next = Threads::new(program);
+    let mut stack = Vec::new();
+    let mut scratch = vec![None; program.slots];
+    let mut matched = false;
+    let mut position = at;
+
+    loop {
+        if !matched {
+            // a new thread for a match starting here, with the lowest priority
+            scratch.iter_mut().for_each(|slot| *slot = None);
+            add_thread(program, &mut current, &mut stack, 0, haystack, position, &mut scratch);
+        }
+        if current.dense.is_empty() && matched {
+            break;
+        }
+
+        let decoded = decode(haystack, position);
+        let next_position = decoded.map_or(position, |(_, width)| position + width);
+        for index in 0..current.dense.len() {
+            let pc = current.dense[index];
+            let advance = match (&program.insts[pc], decoded) {
+                (Inst::Match, _) => {
+                    slots.copy_from_slice(current.slots_mut(pc));
+                    matched = true;
+                    if earliest {
+                        return true;
+                    }
+                    // threads after this one have lower priority, their matches would lose anyway
+                    break;
+                }
+                (Inst::Char(expected), Some((c, _))) => *expected == c,
+                (Inst::Class(class), Some((c, _))) => class.matches(c),
+                (Inst::Any { newline }, Some((c, _))) => *newline || c != '\n',
+                _ => false,
+            };
+            if advance {
+                scratch.copy_from_slice(current.slots_mut(pc));
+                add_thread(program, &mut next, &mut stack, pc + 1, haystack, next_position, &mut scratch);
+            }
+        }
+
+        if decoded.is_none() {
+            break;
+        }
+        core::mem::swap(&mut current, &mut next);
+        next.clear();
+        position = next_position;
+    }
+    matched
+}
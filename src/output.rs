@@ -0,0 +1,22 @@
+use std::io::{self, Write};
+use std::process::exit;
+
+/// Whether a write error means the reader closed the pipe, e.g. `rgrep foo | head`.
+/// That is how output normally ends in a pipeline, so it is not worth reporting.
+pub fn is_broken_pipe(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::BrokenPipe
+}
+
+/// Writes text to stdout for the commands that print and exit, like --help.
+/// Exits quietly if the pipe was closed, and with status 2 on any other error.
+pub fn print(text: &str) {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Err(err) = out.write_all(text.as_bytes()).and_then(|_| out.flush()) {
+        if is_broken_pipe(&err) {
+            exit(0);
+        }
+        eprintln!("rgrep: {}", err);
+        exit(2);
+    }
+}
@@ -0,0 +1,191 @@
+//! `--remote`: runs the search on another machine with `ssh`, and prints the results here as if they had been
+//! found here.
+//!
+//! The remote rgrep gets the same command line arguments, without `--remote`, and prints its results with
+//! `--json`; they are read back and handed to the sink the options pick, so `--count`, `--stats` and the other
+//! output options work the same, except those that cannot be used with `--json`: `--count-per-pattern`,
+//! `--context-bytes`, `--unique`, `-o` and `--byte-offsets`. Only the arguments on the command line are sent, the
+//! config file and `RGREP_OPTIONS` of the remote machine apply to it instead of the local ones.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::args::CommandArgs;
+use crate::error::{Error, Result};
+use crate::json::{self, Json};
+use crate::matcher::Matcher;
+use crate::search::{self, Stats, Summary};
+use crate::signal;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+/// Splits `HOST:PATH` into the host, which may be `user@host` or `[address]`, and the directory to search in,
+/// empty for the home directory
+fn split_remote(remote: &str) -> (&str, &str) {
+    let host_end = if remote.starts_with('[') { remote.find(']').map_or(0, |end| end + 1) } else { 0 };
+    match remote[host_end..].find(':') {
+        Some(colon) => (&remote[..host_end + colon], &remote[host_end + colon + 1..]),
+        None => (remote, ""),
+    }
+}
+
+/// Quotes `word` for the remote shell
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// The shell command running rgrep with `args` in `directory` on the remote machine
+fn remote_command(directory: &str, args: &[String]) -> String {
+    let mut command = String::new();
+    if !directory.is_empty() {
+        // ~ is only expanded when it is not quoted
+        let (home, rest) = match directory.strip_prefix("~/") {
+            Some(rest) => ("~/", rest),
+            None if directory == "~" => ("~", ""),
+            None => ("", directory),
+        };
+        let rest = if rest.is_empty() { String::new() } else { quote(rest) };
+        command.push_str(&format!("cd {}{} && ", home, rest));
+    }
+    command.push_str("rgrep --json");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--remote" {
+            args.next();
+        } else if !arg.starts_with("--remote=") {
+            command.push(' ');
+            command.push_str(&quote(arg));
+        }
+    }
+    command
+}
+
+/// Runs the search `args`, the command line arguments after the command name, on the machine `--remote` names,
+/// and writes the results to `out` like [`search::run`] does
+pub fn run<W: Write>(command_args: &CommandArgs, matcher: &dyn Matcher, args: &[String], out: W) -> Result<Summary> {
+    let (host, directory) = split_remote(&command_args.remote);
+    let invalid = |reason: String| Error::InvalidValue {
+        option: String::from("--remote"),
+        value: command_args.remote.clone(),
+        reason,
+    };
+    if host.is_empty() || host.starts_with('-') {
+        return Err(invalid(String::from("expected HOST:PATH")));
+    }
+    let command = remote_command(directory, args);
+    debug!("running {} on {}", command, host);
+    let mut ssh = Command::new("ssh")
+        .arg(host)
+        .arg(&command)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| invalid(format!("could not run ssh: {}", err)))?;
+
+    // the remote rgrep already replaced the matches
+    let local_args = CommandArgs { replace: None, ..command_args.clone() };
+    let mut sink = search::output_sink(&local_args, matcher, out);
+    let mut events = BufReader::new(ssh.stdout.take().expect("stdout is piped"));
+    let mut line = String::new();
+    // the totals of the remote search, from its summary event
+    let mut totals = Stats::default();
+    loop {
+        line.clear();
+        if events.read_line(&mut line).map_err(Error::write)? == 0 {
+            break;
+        }
+        match parse(&line) {
+            Some(event) => {
+                event.send(sink.as_mut()).map_err(Error::write)?;
+                if let Event::Summary(stats, _) = event {
+                    totals = stats;
+                }
+            }
+            None => debug!("not an event: {}", line.trim_end()),
+        }
+        // --watch sends results as they are found
+        if events.buffer().is_empty() {
+            sink.flush().map_err(Error::write)?;
+        }
+    }
+    let status = ssh.wait().map_err(Error::write)?;
+    debug!("ssh exited with {}", status);
+    let status = status.code().unwrap_or(2);
+    sink.finish(&totals, status).map_err(Error::write)?;
+    sink.flush().map_err(Error::write)?;
+    Ok(Summary {
+        matched: status == 0,
+        had_errors: status != 0 && status != 1 && status != signal::INTERRUPTED_STATUS,
+        // the rgrep on the other end says how many inputs it skipped itself
+        skipped: 0,
+        interrupted: status == signal::INTERRUPTED_STATUS || signal::interrupted(),
+    })
+}
+
+/// An event `--json` prints
+enum Event {
+    Begin(String),
+    Match(String, usize, Vec<u8>),
+    Context(String, usize, Vec<u8>),
+    BinaryMatch(String),
+    End(String, FileSummary),
+    /// The totals of the search, or its counters if `--stats` was given, and which of them
+    Summary(Stats, bool),
+}
+
+impl Event {
+    fn send(&self, sink: &mut dyn Sink) -> io::Result<()> {
+        match self {
+            Event::Begin(path) => sink.begin(path),
+            Event::Match(path, line_number, line) => {
+                sink.matched(&SinkLine { path, line_number: *line_number, offset: 0, line })
+            }
+            Event::Context(path, line_number, line) => {
+                sink.context(&SinkLine { path, line_number: *line_number, offset: 0, line })
+            }
+            Event::BinaryMatch(path) => sink.binary_match(path),
+            Event::End(path, summary) => sink.end(path, summary),
+            Event::Summary(stats, true) => sink.stats(stats),
+            Event::Summary(_, false) => Ok(()),
+        }
+    }
+}
+
+/// Reads an event from a line `--json` printed
+fn parse(line: &str) -> Option<Event> {
+    let event = json::parse(line)?;
+    let data = event.get("data")?;
+    let path = || data.get("path").and_then(Json::data).map(|path| String::from_utf8_lossy(&path).into_owned());
+    let line_number = || data.get("line_number").and_then(Json::number).map(|number| number as usize);
+    let stat = |stats: &Json, name: &str| stats.get(name).and_then(Json::number).unwrap_or(0.0);
+    match event.get("type")?.string()? {
+        "begin" => Some(Event::Begin(path()?)),
+        "match" => Some(Event::Match(path()?, line_number()?, data.get("lines")?.data()?)),
+        "context" => Some(Event::Context(path()?, line_number()?, data.get("lines")?.data()?)),
+        "binary_match" => Some(Event::BinaryMatch(path()?)),
+        "end" => {
+            let stats = data.get("stats")?;
+            let summary = FileSummary {
+                matched_lines: stat(stats, "matched_lines") as u64,
+                bytes_searched: stat(stats, "bytes_searched") as u64,
+                lines_searched: 0,
+                binary: matches!(data.get("binary"), Some(Json::Bool(true))),
+            };
+            Some(Event::End(path()?, summary))
+        }
+        "summary" => {
+            let with_stats = data.get("stats").is_some();
+            let stats = data.get("stats").or_else(|| data.get("totals"))?;
+            let counted = Stats {
+                matched_lines: stat(stats, "matched_lines") as u64,
+                files_with_matches: stat(stats, "files_with_matches") as u64,
+                files_searched: stat(stats, "files_searched") as u64,
+                bytes_searched: stat(stats, "bytes_searched") as u64,
+                errors: data.get("totals").map_or(0.0, |totals| stat(totals, "errors")) as u64,
+                elapsed: Duration::from_secs_f64(stat(stats, "elapsed_seconds").max(0.0)),
+                files: Vec::new(),
+            };
+            Some(Event::Summary(counted, with_stats))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,108 @@
+//! `--all-match`: only reports inputs in which every pattern matches somewhere.
+//!
+//! Whether an input qualifies is only known once it has been read to the end, so what the searcher reports for
+//! it is held back until then, and passed on or dropped as a whole.
+
+use std::io;
+
+use crate::error::Error;
+use crate::matcher::Matcher;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+/// A line reported for the input being searched, kept until its end
+enum Event {
+    Matched(OwnedLine),
+    Context(OwnedLine),
+    Enclosing(OwnedLine),
+    BinaryMatch,
+}
+
+struct OwnedLine {
+    line_number: usize,
+    offset: u64,
+    line: Vec<u8>,
+}
+
+impl OwnedLine {
+    fn new(line: &SinkLine) -> OwnedLine {
+        OwnedLine { line_number: line.line_number, offset: line.offset, line: line.line.to_vec() }
+    }
+
+    fn borrow<'a>(&'a self, path: &'a str) -> SinkLine<'a> {
+        SinkLine { path, line_number: self.line_number, offset: self.offset, line: &self.line }
+    }
+}
+
+/// Passes on what one input is found to contain only if every pattern matched in it; otherwise it is passed on as
+/// searched without matches
+pub(crate) struct AllMatchSink<'s> {
+    inner: &'s mut dyn Sink,
+    /// A matcher for every pattern on its own
+    patterns: &'s [Box<dyn Matcher>],
+    /// Which of the patterns matched a line so far
+    matched: Vec<bool>,
+    events: Vec<Event>,
+    /// The input had every pattern and was passed on
+    all_matched: bool,
+}
+
+impl<'s> AllMatchSink<'s> {
+    pub(crate) fn new(inner: &'s mut dyn Sink, patterns: &'s [Box<dyn Matcher>]) -> AllMatchSink<'s> {
+        let matched = vec![false; patterns.len()];
+        AllMatchSink { inner, patterns, matched, events: Vec::new(), all_matched: false }
+    }
+
+    /// Whether the input had every pattern, once it has been searched
+    pub(crate) fn all_matched(&self) -> bool {
+        self.all_matched
+    }
+}
+
+impl Sink for AllMatchSink<'_> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        for (pattern, matched) in self.patterns.iter().zip(&mut self.matched) {
+            *matched = *matched || pattern.is_match(line.line);
+        }
+        self.events.push(Event::Matched(OwnedLine::new(line)));
+        Ok(())
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.events.push(Event::Context(OwnedLine::new(line)));
+        Ok(())
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.events.push(Event::Enclosing(OwnedLine::new(line)));
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        // the search of the input stops here, so which patterns it has is not known
+        self.events.push(Event::BinaryMatch);
+        Ok(())
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.all_matched = self.matched.iter().all(|&matched| matched);
+        self.inner.begin(path)?;
+        if !self.all_matched {
+            // searched, without matches, e.g. `name:0` for -c
+            self.events.clear();
+            return self.inner.end(path, &FileSummary { matched_lines: 0, ..summary.clone() });
+        }
+        for event in std::mem::take(&mut self.events) {
+            match event {
+                Event::Matched(line) => self.inner.matched(&line.borrow(path))?,
+                Event::Context(line) => self.inner.context(&line.borrow(path))?,
+                Event::Enclosing(line) => self.inner.enclosing(&line.borrow(path))?,
+                Event::BinaryMatch => self.inner.binary_match(path)?,
+            }
+        }
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+}
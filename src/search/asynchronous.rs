@@ -0,0 +1,242 @@
+//! Searching readers that are polled instead of blocking, for programs running an async runtime.
+//!
+//! rgrep depends on no runtime. [`AsyncRead`] has the same shape as the `AsyncRead` traits of tokio and
+//! futures, so a reader from either takes a few lines to adapt.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::decode::Decoder;
+use super::searcher::{gave_up, over_memory, trim_line_end, truncated, Flow, Line, LineReader, LineState, Searcher};
+use super::BinaryDetection;
+use crate::error::{Error, Result};
+use crate::matcher::Matcher;
+use crate::sink::{FileSummary, Sink};
+
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A source of bytes that can be read without blocking
+pub trait AsyncRead {
+    /// Reads into `buf` if data is available, otherwise arranges for the task to be woken up once it is.
+    /// `Ok(0)` means the end of the input.
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+}
+
+impl AsyncRead for &[u8] {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Read::read(&mut *self, buf))
+    }
+}
+
+impl<R: AsyncRead + Unpin + ?Sized> AsyncRead for &mut R {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncRead + Unpin + ?Sized> AsyncRead for Box<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+/// Future of one read
+struct ReadChunk<'a, R: ?Sized> {
+    reader: &'a mut R,
+    buf: &'a mut [u8],
+}
+
+impl<'a, R: AsyncRead + Unpin + ?Sized> Future for ReadChunk<'a, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        Pin::new(&mut *this.reader).poll_read(cx, this.buf)
+    }
+}
+
+impl Searcher {
+    /// Same as [`Searcher::search_reader`], but waits for `reader` without blocking the thread.
+    /// Returns once the whole input was read, with what was found.
+    ///
+    /// ```
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::search::SearcherBuilder;
+    /// use rgrep::sink::CountSink;
+    ///
+    /// // a stand-in for the executor of an async runtime
+    /// fn block_on<F: Future>(future: F) -> F::Output {
+    ///     let mut future = pin!(future);
+    ///     let mut cx = Context::from_waker(Waker::noop());
+    ///     loop {
+    ///         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+    ///             return output;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let matcher = matcher::build(&[String::from("b")], &MatcherOptions::default()).unwrap();
+    /// let searcher = SearcherBuilder::new().build();
+    /// let mut sink = CountSink::new(Vec::new(), false);
+    /// let upload: &[u8] = b"a\nb\nab\n";
+    /// let summary = block_on(searcher.search_async(matcher.as_ref(), upload, "upload", &mut sink)).unwrap();
+    /// assert_eq!(summary.matched_lines, 2);
+    /// ```
+    pub async fn search_async<R, S>(&self, matcher: &dyn Matcher, reader: R, name: &str, sink: &mut S) -> Result<FileSummary>
+    where
+        R: AsyncRead + Unpin,
+        S: Sink,
+    {
+        sink.begin(name).map_err(Error::write)?;
+        let summary = self.read_async(matcher, reader, name, sink).await;
+        let summary = self.report(name, summary)?;
+        sink.end(name, &summary).map_err(Error::write)?;
+        Ok(summary)
+    }
+
+    /// Searches `reader` until its end, or until the search stops, and reports its lines to `sink`
+    async fn read_async<R, S>(
+        &self,
+        matcher: &dyn Matcher,
+        mut reader: R,
+        name: &str,
+        sink: &mut S,
+    ) -> Result<FileSummary>
+    where
+        R: AsyncRead + Unpin,
+        S: Sink,
+    {
+        let mut state = LineState::new(self, name);
+        let mut decoder = Decoder::new(self.encoding());
+        let mut chunk = vec![0; CHUNK_SIZE];
+        // decoded bytes that do not make a complete line yet, or the whole input in multi-line mode
+        let mut pending = Vec::new();
+        let mut checked_binary = false;
+        let mut line_number = 0;
+        let mut offset = 0;
+        // the start of the line was over the memory limit, so the rest of it is skipped up to its terminator
+        let mut skipping = false;
+
+        'read: loop {
+            let read = match (ReadChunk { reader: &mut reader, buf: &mut chunk }).await {
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::read(name, err)),
+            };
+            let at_end = read == 0;
+            decoder.push(&chunk[..read], at_end, &mut pending);
+            // like the blocking search, only the first block is checked for binary data up front
+            if !checked_binary && !pending.is_empty() && self.binary_detection() != BinaryDetection::Text {
+                checked_binary = true;
+                if pending.contains(&0) {
+                    debug!("{} contains binary data", name);
+                    state.summary.binary = true;
+                    if self.binary_detection() == BinaryDetection::Skip {
+                        break 'read;
+                    }
+                }
+            }
+            if self.multi_line() {
+                if let Some(limit) = self.max_memory().filter(|limit| pending.len() as u64 > *limit) {
+                    return Err(over_memory(name, "the input is", limit));
+                }
+                if at_end {
+                    let mut lines = LineReader::whole(&pending[..], self, matcher, name)?;
+                    while let Some(line) = lines.next(matcher, name)? {
+                        if self.cancelled() {
+                            self.interrupted(name, &state.summary);
+                            return Err(Error::Interrupted);
+                        }
+                        state.summary.bytes_searched += line.length as u64;
+                        state.summary.lines_searched += 1;
+                        let number = line.number;
+                        if state.line(sink, &line)? == Flow::Stop {
+                            break;
+                        }
+                        if matcher.gave_up() {
+                            return Err(gave_up(name, &format!("line {}", number)));
+                        }
+                    }
+                    break 'read;
+                }
+                continue;
+            }
+
+            let mut start = 0;
+            if skipping {
+                start = pending.iter().position(|byte| *byte == b'\n').map_or(pending.len(), |position| position + 1);
+                skipping = start == pending.len() && !pending.ends_with(b"\n");
+                state.summary.bytes_searched += start as u64;
+                offset += start as u64;
+            }
+            while start < pending.len() {
+                let end = match pending[start..].iter().position(|byte| *byte == b'\n') {
+                    Some(position) => start + position + 1,
+                    // the last line of the input has no terminator
+                    None if at_end => pending.len(),
+                    None => break,
+                };
+                if self.cancelled() {
+                    self.interrupted(name, &state.summary);
+                    return Err(Error::Interrupted);
+                }
+                line_number += 1;
+                // like in the blocking search, only as much of a line as the memory limit is searched, even if all
+                // of it was read at once
+                let limit = self.max_memory().filter(|limit| (end - start) as u64 > *limit);
+                let text = match limit {
+                    Some(limit) => {
+                        sink.error(&truncated(name, line_number, limit)).map_err(Error::write)?;
+                        &pending[start..start + limit as usize]
+                    }
+                    None => trim_line_end(&pending[start..end]),
+                };
+                let is_match = matcher.is_match(text);
+                let truncated = limit.is_some();
+                let line = Line { number: line_number, offset, text, length: end - start, is_match, truncated };
+                state.summary.bytes_searched += line.length as u64;
+                state.summary.lines_searched += 1;
+                offset += line.length as u64;
+                if state.line(sink, &line)? == Flow::Stop {
+                    break 'read;
+                }
+                if matcher.gave_up() {
+                    return Err(gave_up(name, &format!("line {}", line_number)));
+                }
+                start = end;
+            }
+            pending.drain(..start);
+            if at_end {
+                break;
+            }
+            // what is left is the start of a line, which is longer than that with its terminator, so only the start
+            // is searched
+            if let Some(limit) = self.max_memory().filter(|limit| pending.len() as u64 >= *limit) {
+                line_number += 1;
+                sink.error(&truncated(name, line_number, limit)).map_err(Error::write)?;
+                let text = &pending[..limit as usize];
+                let is_match = matcher.is_match(text);
+                let line = Line { number: line_number, offset, text, length: pending.len(), is_match, truncated: true };
+                state.summary.bytes_searched += line.length as u64;
+                state.summary.lines_searched += 1;
+                offset += line.length as u64;
+                if state.line(sink, &line)? == Flow::Stop {
+                    break 'read;
+                }
+                if matcher.gave_up() {
+                    return Err(gave_up(name, &format!("line {}", line_number)));
+                }
+                pending.clear();
+                skipping = true;
+            }
+        }
+        Ok(state.summary)
+    }
+}
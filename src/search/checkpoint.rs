@@ -0,0 +1,103 @@
+//! `--checkpoint FILE`: records every input once it has been searched, so a run that is stopped can be started
+//! again and go on with the inputs it did not get to.
+//!
+//! The file starts with a `#` line with the patterns and the options that decide what matches, and a run with
+//! others refuses to go on with it. Then it has a line per searched input, `+ NAME` if it matched and `- NAME` if
+//! it did not. Inputs that could not be read are not recorded, so they are tried again. The file is removed once a
+//! run searched everything without errors.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+
+use crate::args::CommandArgs;
+use crate::error::{Error, Result};
+
+/// The first line of the file for a search with `command_args`, which a run going on with it has to have too
+fn header(command_args: &CommandArgs) -> String {
+    format!(
+        "# rgrep --checkpoint of patterns {:?}, builtins {:?}, {:?}, all-match {}, multiline {}, binary {:?}, \
+         encoding {:?}, only {:?}, pre {:?}",
+        command_args.patterns,
+        command_args.builtins,
+        command_args.matcher_options(),
+        command_args.all_match,
+        command_args.multiline,
+        command_args.binary,
+        command_args.encoding,
+        command_args.only,
+        command_args.pre,
+    )
+}
+
+pub(crate) struct Checkpoint {
+    path: String,
+    file: File,
+    /// Inputs searched by earlier runs
+    searched: HashSet<String>,
+    /// Whether any of them matched
+    matched: bool,
+}
+
+impl Checkpoint {
+    /// Reads what earlier runs with the same patterns and options as `command_args` recorded in the file at
+    /// `path`, if there is one, and goes on recording there. A file of a search with others is an error.
+    pub(crate) fn open(path: &str, command_args: &CommandArgs) -> Result<Checkpoint> {
+        let recorded = match fs::read_to_string(path) {
+            Ok(recorded) => recorded,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::read(path, err)),
+        };
+        let header = header(command_args);
+        let mut lines = recorded.lines();
+        match lines.next() {
+            None => {}
+            Some(line) if line == header => {}
+            Some(_) => {
+                let reason = "it is the checkpoint of a search with other patterns or options, remove it to start over";
+                return Err(Error::read(path, io::Error::new(io::ErrorKind::InvalidData, reason)));
+            }
+        }
+        let mut searched = HashSet::new();
+        let mut matched = false;
+        for line in lines {
+            // a line cut short when the run was killed while writing it is not an input
+            let (mark, name) = match line.split_at_checked(2) {
+                Some((mark @ ("+ " | "- "), name)) => (mark, name),
+                _ => continue,
+            };
+            matched |= mark == "+ ";
+            searched.insert(String::from(name));
+        }
+        if !searched.is_empty() {
+            debug!("{} inputs were searched before, as {} says", searched.len(), path);
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|err| Error::read(path, err))?;
+        if recorded.is_empty() {
+            writeln!(file, "{}", header).map_err(|err| Error::read(path, err))?;
+        }
+        Ok(Checkpoint { path: String::from(path), file, searched, matched })
+    }
+
+    pub(crate) fn searched_before(&self, input: &str) -> bool {
+        self.searched.contains(input)
+    }
+
+    /// Whether an input an earlier run searched matched
+    pub(crate) fn matched_before(&self) -> bool {
+        self.matched
+    }
+
+    /// Records that `input` was searched, right away, so it is there even if rgrep is killed next
+    pub(crate) fn record(&mut self, input: &str, matched: bool) -> Result<()> {
+        let mark = if matched { '+' } else { '-' };
+        writeln!(self.file, "{} {}", mark, input).map_err(|err| Error::read(&self.path, err))
+    }
+
+    /// Removes the file, for a run that searched everything
+    pub(crate) fn finish(self) -> Result<()> {
+        let Checkpoint { path, file, .. } = self;
+        drop(file);
+        fs::remove_file(&path).map_err(|err| Error::read(&path, err))
+    }
+}
@@ -0,0 +1,253 @@
+//! Transcoding of inputs to UTF-8 before they are searched.
+
+use std::fmt;
+use std::io::{self, Read};
+
+/// How the bytes of an input are decoded before matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// UTF-16 if the input starts with a UTF-16 byte order mark, otherwise the bytes as they are.
+    /// A UTF-8 byte order mark is removed.
+    #[default]
+    Auto,
+    /// The bytes as they are, matched as UTF-8
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, every byte is one char
+    Latin1,
+}
+
+impl Encoding {
+    /// Names accepted by `--encoding`, as returned by [`Encoding::name`]
+    pub const NAMES: &'static [&'static str] = &["auto", "utf-8", "utf-16le", "utf-16be", "latin1"];
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        Some(match name {
+            "auto" => Encoding::Auto,
+            "utf-8" => Encoding::Utf8,
+            "utf-16le" => Encoding::Utf16Le,
+            "utf-16be" => Encoding::Utf16Be,
+            "latin1" => Encoding::Latin1,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Auto => "auto",
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin1",
+        }
+    }
+}
+
+/// Turns inputs of a format rgrep does not know, e.g. compressed or structured logs, into bytes to search.
+/// Registered with [`SearcherBuilder::decoder`](super::SearcherBuilder::decoder). What a decoder returns is then
+/// decoded with the [`Encoding`] of the searcher, like any input. `Searcher::search_async` does not use them.
+///
+/// ```
+/// use std::io::{self, Read};
+/// use std::sync::Arc;
+///
+/// use rgrep::matcher::{self, MatcherOptions};
+/// use rgrep::search::{InputDecoder, SearcherBuilder};
+/// use rgrep::sink::CountSink;
+///
+/// /// Files ending in .rot13 are searched for what they say, not how they are written
+/// struct Rot13;
+///
+/// impl InputDecoder for Rot13 {
+///     fn name(&self) -> &str {
+///         "rot13"
+///     }
+///
+///     fn accepts(&self, path: &str, _start: &[u8]) -> bool {
+///         path.ends_with(".rot13")
+///     }
+///
+///     fn decode<'a>(&self, mut input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+///         let mut bytes = Vec::new();
+///         input.read_to_end(&mut bytes)?;
+///         for byte in &mut bytes {
+///             *byte = match *byte {
+///                 b'a'..=b'z' => (*byte - b'a' + 13) % 26 + b'a',
+///                 b'A'..=b'Z' => (*byte - b'A' + 13) % 26 + b'A',
+///                 other => other,
+///             };
+///         }
+///         Ok(Box::new(io::Cursor::new(bytes)))
+///     }
+/// }
+///
+/// let matcher = matcher::build(&[String::from("hello")], &MatcherOptions::default()).unwrap();
+/// let searcher = SearcherBuilder::new().decoder(Arc::new(Rot13)).build();
+/// let mut sink = CountSink::new(Vec::new(), false);
+/// let summary = searcher.search_slice(matcher.as_ref(), b"uryyb\n", "greeting.rot13", &mut sink).unwrap();
+/// assert_eq!(summary.matched_lines, 1);
+/// ```
+pub trait InputDecoder: Send + Sync {
+    /// Short name, for diagnostics
+    fn name(&self) -> &str;
+
+    /// Whether to decode the input called `path`. `start` is its beginning, as much as one read returned;
+    /// empty for an empty input.
+    fn accepts(&self, path: &str, start: &[u8]) -> bool;
+
+    /// Wraps the input, from its first byte, in a reader of the decoded bytes
+    fn decode<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>>;
+}
+
+impl fmt::Debug for dyn InputDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Decodes raw bytes as they arrive, wherever they come from
+pub(crate) struct Decoder {
+    /// `Auto` until the byte order mark was looked for
+    encoding: Encoding,
+    /// Whether the byte order mark was looked for
+    detected: bool,
+    /// Raw bytes not decoded yet, e.g. the first half of a UTF-16 unit
+    raw: Vec<u8>,
+}
+
+impl Decoder {
+    pub(crate) fn new(encoding: Encoding) -> Decoder {
+        Decoder { encoding, detected: false, raw: Vec::new() }
+    }
+
+    /// Encoding the input turned out to have, `Auto` until enough of it was seen
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Whether the bytes that follow would come out unchanged, so they do not need to go through `push`
+    pub(crate) fn is_passthrough(&self) -> bool {
+        self.detected && self.encoding == Encoding::Utf8 && self.raw.is_empty()
+    }
+
+    /// Decodes `input` and appends the result to `out`. Leftovers that need more input are kept for the next
+    /// call; `at_end` says there is no more input, and then leftovers become U+FFFD.
+    pub(crate) fn push(&mut self, input: &[u8], at_end: bool, out: &mut Vec<u8>) {
+        self.raw.extend_from_slice(input);
+        if !self.detected {
+            if self.raw.len() < UTF8_BOM.len() && !at_end {
+                return;
+            }
+            self.detect();
+        }
+        match self.encoding {
+            Encoding::Auto | Encoding::Utf8 => out.append(&mut self.raw),
+            Encoding::Latin1 => {
+                for byte in self.raw.drain(..) {
+                    let mut buffer = [0; 4];
+                    out.extend_from_slice(char::from(byte).encode_utf8(&mut buffer).as_bytes());
+                }
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let big_endian = self.encoding == Encoding::Utf16Be;
+                let mut units: Vec<u16> = self
+                    .raw
+                    .chunks_exact(2)
+                    .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+                    .collect();
+                let mut consumed = units.len() * 2;
+                // a high surrogate at the end of the input so far needs the unit after it
+                if !at_end && matches!(units.last(), Some(0xD800..=0xDBFF)) {
+                    units.pop();
+                    consumed -= 2;
+                }
+                for c in char::decode_utf16(units) {
+                    let mut buffer = [0; 4];
+                    let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                    out.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                self.raw.drain(..consumed);
+                if at_end && !self.raw.is_empty() {
+                    // an odd byte at the very end
+                    self.raw.clear();
+                    out.extend_from_slice("\u{FFFD}".as_bytes());
+                }
+            }
+        }
+    }
+
+    /// Looks at the start of the input to find out the encoding, and drops its byte order mark
+    fn detect(&mut self) {
+        let (encoding, bom) = match self.encoding {
+            Encoding::Auto if self.raw.starts_with(UTF8_BOM) => (Encoding::Utf8, UTF8_BOM.len()),
+            Encoding::Auto if self.raw.starts_with(UTF16LE_BOM) => (Encoding::Utf16Le, UTF16LE_BOM.len()),
+            Encoding::Auto if self.raw.starts_with(UTF16BE_BOM) => (Encoding::Utf16Be, UTF16BE_BOM.len()),
+            Encoding::Auto => (Encoding::Utf8, 0),
+            Encoding::Utf8 if self.raw.starts_with(UTF8_BOM) => (Encoding::Utf8, UTF8_BOM.len()),
+            Encoding::Utf16Le if self.raw.starts_with(UTF16LE_BOM) => (Encoding::Utf16Le, UTF16LE_BOM.len()),
+            Encoding::Utf16Be if self.raw.starts_with(UTF16BE_BOM) => (Encoding::Utf16Be, UTF16BE_BOM.len()),
+            encoding => (encoding, 0),
+        };
+        self.encoding = encoding;
+        self.detected = true;
+        self.raw.drain(..bom);
+    }
+}
+
+/// Reads an input and returns it as UTF-8. Invalid UTF-16 becomes U+FFFD.
+pub struct DecodeReader<R: Read> {
+    inner: R,
+    decoder: Decoder,
+    /// Raw bytes just read
+    chunk: Vec<u8>,
+    /// Decoded bytes not returned yet, starting at `position`
+    decoded: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecodeReader<R> {
+    pub fn new(inner: R, encoding: Encoding) -> DecodeReader<R> {
+        DecodeReader { inner, decoder: Decoder::new(encoding), chunk: Vec::new(), decoded: Vec::new(), position: 0 }
+    }
+
+    /// Encoding the input turned out to have, `Auto` only before the first read
+    pub fn encoding(&self) -> Encoding {
+        self.decoder.encoding()
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            // UTF-8 needs no decoding; once the start of the input was handled, read straight into `buf`
+            if self.decoder.is_passthrough() {
+                return self.inner.read(buf);
+            }
+            self.chunk.resize(CHUNK_SIZE, 0);
+            let read = match self.inner.read(&mut self.chunk) {
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            self.decoded.clear();
+            self.position = 0;
+            self.decoder.push(&self.chunk[..read], read == 0, &mut self.decoded);
+            if read == 0 && self.decoded.is_empty() {
+                return Ok(0);
+            }
+        }
+        let available = &self.decoded[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
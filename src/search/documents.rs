@@ -0,0 +1,327 @@
+//! Text of the document formats that are zip files of XML, for the `documents` feature: Office Open XML
+//! (.docx, .pptx, .xlsx), OpenDocument (.odt, .ods, .odp) and EPUB. Every paragraph becomes a line.
+
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Docx,
+    Pptx,
+    Xlsx,
+    OpenDocument,
+    Epub,
+}
+
+impl Format {
+    pub(crate) fn from_extension(extension: &str) -> Option<Format> {
+        Some(match extension {
+            "docx" => Format::Docx,
+            "pptx" => Format::Pptx,
+            "xlsx" => Format::Xlsx,
+            "odt" | "ods" | "odp" => Format::OpenDocument,
+            "epub" => Format::Epub,
+            _ => return None,
+        })
+    }
+
+    /// Whether the zip member `name` holds text of the document
+    fn has_text(self, name: &str) -> bool {
+        match self {
+            Format::Docx => name == "word/document.xml",
+            Format::Pptx => name.starts_with("ppt/slides/slide") && name.ends_with(".xml"),
+            Format::Xlsx => name == "xl/sharedStrings.xml",
+            Format::OpenDocument => name == "content.xml",
+            Format::Epub => [".xhtml", ".html", ".htm"].iter().any(|suffix| name.ends_with(suffix)),
+        }
+    }
+
+    /// The text of the document in `contents`, the bytes of the file
+    pub(crate) fn extract(self, contents: &[u8]) -> io::Result<Vec<u8>> {
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("not a document: {}", reason));
+        let members = zip_members(contents).ok_or_else(|| invalid("no zip directory"))?;
+        let mut members: Vec<Member> = members.into_iter().filter(|member| self.has_text(&member.name)).collect();
+        if self == Format::Pptx {
+            // slide10.xml comes after slide9.xml
+            members.sort_by(|a, b| (a.name.len(), &a.name).cmp(&(b.name.len(), &b.name)));
+        }
+        let mut text = Vec::new();
+        for member in members {
+            let xml = member.data(contents).ok_or_else(|| invalid(&format!("{} cannot be read", member.name)))?;
+            xml_text(&xml, &mut text);
+        }
+        Ok(text)
+    }
+}
+
+/// A file in a zip archive, as its central directory names it
+struct Member {
+    name: String,
+    /// Compression method, 0 for stored and 8 for deflate
+    method: u16,
+    compressed_size: usize,
+    /// Offset of its local header
+    offset: usize,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    let bytes = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The members of the zip archive `zip`, from its central directory
+fn zip_members(zip: &[u8]) -> Option<Vec<Member>> {
+    // the end of central directory record is last, followed by a comment of up to 64 KiB
+    let search_from = zip.len().saturating_sub(22 + 0xffff);
+    let end = (search_from..zip.len().saturating_sub(21)).rev().find(|&at| u32_at(zip, at) == Some(0x0605_4b50))?;
+    let count = u16_at(zip, end + 10)? as usize;
+    let mut at = u32_at(zip, end + 16)? as usize;
+    let mut members = Vec::with_capacity(count);
+    for _ in 0..count {
+        if u32_at(zip, at)? != 0x0201_4b50 {
+            return None;
+        }
+        let name_len = u16_at(zip, at + 28)? as usize;
+        let name = zip.get(at + 46..at + 46 + name_len)?;
+        members.push(Member {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(zip, at + 10)?,
+            compressed_size: u32_at(zip, at + 20)? as usize,
+            offset: u32_at(zip, at + 42)? as usize,
+        });
+        at += 46 + name_len + u16_at(zip, at + 30)? as usize + u16_at(zip, at + 32)? as usize;
+    }
+    Some(members)
+}
+
+impl Member {
+    /// The uncompressed contents of the member
+    fn data(&self, zip: &[u8]) -> Option<Vec<u8>> {
+        if u32_at(zip, self.offset)? != 0x0403_4b50 {
+            return None;
+        }
+        let name_len = u16_at(zip, self.offset + 26)? as usize;
+        let start = self.offset + 30 + name_len + u16_at(zip, self.offset + 28)? as usize;
+        let data = zip.get(start..start + self.compressed_size)?;
+        match self.method {
+            0 => Some(data.to_vec()),
+            8 => inflate(data),
+            _ => None,
+        }
+    }
+}
+
+/// Elements that end a line of text
+const LINE_ENDS: &[&str] =
+    &["p", "h", "h1", "h2", "h3", "h4", "h5", "h6", "br", "cr", "li", "tr", "si", "title", "div", "line-break"];
+
+/// Appends the text of the XML document `xml` to `text`, a line for every paragraph
+fn xml_text(xml: &[u8], text: &mut Vec<u8>) {
+    let mut at = 0;
+    while at < xml.len() {
+        match xml[at] {
+            b'<' => {
+                let end = xml[at..].iter().position(|&byte| byte == b'>').map_or(xml.len(), |end| at + end);
+                let tag = &xml[at + 1..end];
+                let closing = tag.starts_with(b"/") || tag.ends_with(b"/");
+                let name = tag.split(|&byte| byte == b' ' || byte == b'/').find(|name| !name.is_empty());
+                let name = name.unwrap_or_default();
+                // the name without its namespace prefix
+                let name = name.rsplit(|&byte| byte == b':').next().unwrap_or_default();
+                if closing && LINE_ENDS.iter().any(|end| end.as_bytes() == name) {
+                    text.push(b'\n');
+                } else if closing && name == b"tab" {
+                    text.push(b'\t');
+                }
+                at = end + 1;
+            }
+            b'&' => {
+                let end = xml[at..].iter().take(12).position(|&byte| byte == b';').map(|end| at + end);
+                let name = end.and_then(|end| std::str::from_utf8(&xml[at + 1..end]).ok());
+                let entity = name.and_then(|name| match name {
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "amp" => Some('&'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    _ => {
+                        let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                            None => name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+                        };
+                        code.and_then(char::from_u32)
+                    }
+                });
+                match (entity, end) {
+                    (Some(c), Some(end)) => {
+                        text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        at = end + 1;
+                    }
+                    _ => {
+                        text.push(b'&');
+                        at += 1;
+                    }
+                }
+            }
+            byte => {
+                text.push(byte);
+                at += 1;
+            }
+        }
+    }
+}
+
+/// Reads the bits of a deflate stream, least significant first
+struct Bits<'a> {
+    data: &'a [u8],
+    at: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        while self.count < count {
+            self.buffer |= (*self.data.get(self.at)? as u32) << self.count;
+            self.at += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << count) - 1) as u32;
+        self.buffer >>= count;
+        self.count -= count;
+        Some(value)
+    }
+
+    /// Reads a symbol coded with `huffman`
+    fn decode(&mut self, huffman: &Huffman) -> Option<u16> {
+        // canonical codes of every length are consecutive, so each length is a range of codes
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= self.bits(1)? as i32;
+            let count = huffman.counts[len] as i32;
+            if code - first < count {
+                return huffman.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of every length and the symbols ordered by code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for len in 1..16 {
+            symbols.extend((0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] == len));
+        }
+        Huffman { counts, symbols }
+    }
+}
+
+const LENGTH_BASES: [u16; 29] =
+    [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] =
+    [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order in which the lengths of the code length code are given
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses raw deflate data (RFC 1951), `None` if it is not valid
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut bits = Bits { data, at: 0, buffer: 0, count: 0 };
+    let mut out = Vec::with_capacity(data.len() * 4);
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                // stored: the length and its complement start at the next byte
+                bits.buffer = 0;
+                bits.count = 0;
+                let len = u16_at(data, bits.at)? as usize;
+                let start = bits.at + 4;
+                out.extend_from_slice(data.get(start..start + len)?);
+                bits.at = start + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                inflate_block(&mut bits, &mut out, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let literals = bits.bits(5)? as usize + 257;
+                let distances = bits.bits(5)? as usize + 1;
+                let code_lengths = bits.bits(4)? as usize + 4;
+                let mut lengths = [0u8; 19];
+                for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+                    lengths[symbol] = bits.bits(3)? as u8;
+                }
+                let code_length_code = Huffman::new(&lengths);
+                let mut lengths = Vec::with_capacity(literals + distances);
+                while lengths.len() < literals + distances {
+                    let (value, repeat) = match bits.decode(&code_length_code)? {
+                        len @ 0..=15 => (len as u8, 1),
+                        16 => (*lengths.last()?, 3 + bits.bits(2)?),
+                        17 => (0, 3 + bits.bits(3)?),
+                        _ => (0, 11 + bits.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() > literals + distances {
+                    return None;
+                }
+                let literal_code = Huffman::new(&lengths[..literals]);
+                let distance_code = Huffman::new(&lengths[literals..]);
+                inflate_block(&mut bits, &mut out, &literal_code, &distance_code)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+/// Decompresses a block coded with `literals` and `distances` up to its end
+fn inflate_block(bits: &mut Bits, out: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Option<()> {
+    loop {
+        match bits.decode(literals)? {
+            literal @ 0..=255 => out.push(literal as u8),
+            256 => return Some(()),
+            symbol => {
+                let index = symbol as usize - 257;
+                let len = *LENGTH_BASES.get(index)? as usize + bits.bits(*LENGTH_EXTRA.get(index)? as u32)? as usize;
+                let index = bits.decode(distances)? as usize;
+                let distance =
+                    *DISTANCE_BASES.get(index)? as usize + bits.bits(*DISTANCE_EXTRA.get(index)? as u32)? as usize;
+                let start = out.len().checked_sub(distance)?;
+                // the copy may overlap what it appends, e.g. one byte repeated
+                for at in start..start + len {
+                    out.push(out[at]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The line each match is in the block of, for [`SearcherBuilder::show_function`](super::SearcherBuilder):
+//! the signature of the function around it, the Markdown heading of its section, or the line starting the
+//! indented block it is in.
+//!
+//! Blocks are told apart by indentation alone, which is how most source files are laid out, so no language has
+//! to be parsed. The lines that start blocks are kept as the input is read, and a line ends the blocks indented
+//! as much or more than it is.
+
+use std::path::Path;
+
+/// Words that can come before the keyword of a definition, e.g. `pub` in `pub fn`
+const MODIFIERS: &[&str] = &[
+    "pub", "crate", "super", "async", "unsafe", "extern", "const", "static", "export", "default", "public",
+    "private", "protected", "internal", "abstract", "final", "override", "virtual", "inline", "local",
+];
+
+/// Keywords that start the definition of a function, a type or a module
+const DEFINITIONS: &[&str] = &[
+    "fn", "def", "function", "func", "fun", "class", "struct", "enum", "union", "impl", "trait", "mod", "module",
+    "interface", "namespace", "object", "sub", "proc", "macro_rules",
+];
+
+/// Keywords of statements that open blocks like functions do, but are not definitions
+const STATEMENTS: &[&str] = &[
+    "if", "else", "elif", "for", "foreach", "while", "do", "loop", "switch", "match", "case", "when", "try",
+    "catch", "except", "finally", "with", "return", "unless", "until",
+];
+
+/// A line that starts a block, or a Markdown heading
+#[derive(Debug, Clone)]
+pub(super) struct Block {
+    pub(super) number: usize,
+    pub(super) offset: u64,
+    pub(super) text: Vec<u8>,
+    indent: usize,
+    definition: bool,
+}
+
+/// Keeps track of the blocks the lines of an input are in, as they are read in order
+#[derive(Debug)]
+pub(super) struct Enclosing {
+    /// The input is Markdown, so sections start with headings instead of being indented
+    markdown: bool,
+    /// In a fenced code block of a Markdown input, whose `#` lines are not headings
+    in_fence: bool,
+    /// The blocks the last line is in, outermost first; only ever one heading for Markdown
+    blocks: Vec<Block>,
+}
+
+impl Enclosing {
+    pub(super) fn new(name: &str) -> Enclosing {
+        let extension = Path::new(name).extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+        let markdown = matches!(extension.as_deref(), Some("md" | "markdown" | "mdown" | "mkd"));
+        Enclosing { markdown, in_fence: false, blocks: Vec::new() }
+    }
+
+    /// Moves on to the line `text`: leaves the blocks it is not in. [`enclosing`](Enclosing::enclosing) is then
+    /// the block of this line, and [`push`](Enclosing::push) records it if it starts one.
+    pub(super) fn advance(&mut self, text: &[u8]) {
+        if self.markdown {
+            return;
+        }
+        if let Some(indent) = indentation(text) {
+            while self.blocks.last().is_some_and(|block| block.indent >= indent) {
+                self.blocks.pop();
+            }
+        }
+    }
+
+    /// The innermost definition around the current line, or the innermost block if it is in no definition
+    pub(super) fn enclosing(&self) -> Option<&Block> {
+        self.blocks.iter().rev().find(|block| block.definition).or_else(|| self.blocks.last())
+    }
+
+    /// Records the current line, after [`advance`](Enclosing::advance), if it may start a block
+    pub(super) fn push(&mut self, number: usize, offset: u64, text: &[u8]) {
+        if self.markdown {
+            let trimmed = text.trim_ascii_start();
+            if trimmed.starts_with(b"```") || trimmed.starts_with(b"~~~") {
+                self.in_fence = !self.in_fence;
+            } else if !self.in_fence && is_heading(text) {
+                self.blocks = vec![Block { number, offset, text: text.to_vec(), indent: 0, definition: true }];
+            }
+            return;
+        }
+        let indent = match indentation(text) {
+            Some(indent) => indent,
+            None => return,
+        };
+        // closing brackets and the like end blocks rather than start them
+        if !text.iter().any(|byte| byte.is_ascii_alphanumeric()) {
+            return;
+        }
+        let definition = is_definition(text, indent);
+        self.blocks.push(Block { number, offset, text: text.to_vec(), indent, definition });
+    }
+}
+
+/// Width of the whitespace `text` starts with, tabs going to the next multiple of 8, or `None` for a blank line
+fn indentation(text: &[u8]) -> Option<usize> {
+    let mut width = 0;
+    for &byte in text {
+        match byte {
+            b' ' => width += 1,
+            b'\t' => width += 8 - width % 8,
+            b'\r' => {}
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
+/// `# Title` up to `###### Title`
+fn is_heading(text: &[u8]) -> bool {
+    let hashes = text.iter().take_while(|&&byte| byte == b'#').count();
+    (1..=6).contains(&hashes) && matches!(text.get(hashes), None | Some(b' ' | b'\t'))
+}
+
+/// Whether the line looks like the start of a function, type or module definition in a common language
+fn is_definition(text: &[u8], indent: usize) -> bool {
+    let text = String::from_utf8_lossy(text);
+    let trimmed = text.trim();
+    let first = match words(trimmed).find(|word| !MODIFIERS.contains(word)) {
+        Some(first) => first,
+        None => return false,
+    };
+    if DEFINITIONS.contains(&first) {
+        return true;
+    }
+    if STATEMENTS.contains(&first) || trimmed.starts_with('}') || !trimmed.contains('(') {
+        return false;
+    }
+    // a call opening a block is likely a C-like function, e.g. `int main(void) {`, as is a declaration at the
+    // start of a line with its `{` on the next one, e.g. `int main(void)`
+    let declaration = || words(&trimmed[..trimmed.find('(').unwrap_or(0)]).count() >= 2;
+    trimmed.ends_with('{') || indent == 0 && trimmed.ends_with(')') && declaration()
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|word| !word.is_empty())
+}
@@ -0,0 +1,112 @@
+//! Text extraction from files that are not text, for `--pre` and the built-in document formats.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::args::CommandArgs;
+use crate::config::split_shell_words;
+
+/// A command whose output is searched instead of the files it is given, `--pre=[EXT,EXT=]COMMAND`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preprocessor {
+    /// File name extensions, without the dot, of the files the command is for; empty for all files
+    pub extensions: Vec<String>,
+    /// The command and its arguments. An argument `{}` is replaced by the path of the file, which is added as the
+    /// last argument otherwise.
+    pub command: Vec<String>,
+}
+
+impl Preprocessor {
+    /// Parses the value of `--pre`, e.g. `pdf=pdftotext -q {} -`
+    ///
+    /// ```
+    /// use rgrep::search::Preprocessor;
+    ///
+    /// let preprocessor = Preprocessor::parse("doc,docx=pandoc -t plain").unwrap();
+    /// assert_eq!(preprocessor.extensions, ["doc", "docx"]);
+    /// assert_eq!(preprocessor.command, ["pandoc", "-t", "plain"]);
+    /// ```
+    pub fn parse(value: &str) -> std::result::Result<Preprocessor, String> {
+        let is_extension = |ext: &str| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric());
+        let is_extensions = |text: &str| text.split(',').all(is_extension);
+        let (extensions, command) = match value.split_once('=') {
+            Some((extensions, command)) if is_extensions(extensions) => {
+                (extensions.split(',').map(|ext| ext.to_ascii_lowercase()).collect(), command)
+            }
+            _ => (Vec::new(), value),
+        };
+        let command = split_shell_words(command)?;
+        if command.is_empty() {
+            return Err(String::from("expected a command"));
+        }
+        Ok(Preprocessor { extensions, command })
+    }
+
+    fn applies_to(&self, extension: Option<&str>) -> bool {
+        let listed = |extension: &str| self.extensions.iter().any(|ext| ext == extension);
+        self.extensions.is_empty() || extension.is_some_and(listed)
+    }
+
+    /// Runs the command on `path`, returns what it printed
+    fn run(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut args: Vec<&str> = self.command[1..].iter().map(|arg| if arg == "{}" { path } else { arg }).collect();
+        if !self.command[1..].iter().any(|arg| arg == "{}") {
+            args.push(path);
+        }
+        debug!("running {} {}", self.command[0], args.join(" "));
+        let output = Command::new(&self.command[0])
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| io::Error::new(err.kind(), format!("could not run {}: {}", self.command[0], err)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().next().map_or_else(|| output.status.to_string(), String::from);
+            return Err(io::Error::other(format!("--pre command {} failed: {}", self.command[0], reason)));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// The text to search instead of the contents of `path`: what the first `--pre` command for it printed, or with
+/// the `documents` feature, the text of a document format rgrep knows. `None` to search the file as it is.
+pub(crate) fn extract(command_args: &CommandArgs, path: &str) -> Option<io::Result<Vec<u8>>> {
+    let extension = extension(path);
+    if let Some(preprocessor) = preprocessor(command_args, extension.as_deref()) {
+        return Some(preprocessor.run(path));
+    }
+    #[cfg(feature = "documents")]
+    {
+        if let Some(format) = extension.as_deref().and_then(super::documents::Format::from_extension) {
+            debug!("extracting the text of {} as {:?}", path, format);
+            return Some(std::fs::read(path).and_then(|contents| format.extract(&contents)));
+        }
+    }
+    None
+}
+
+/// Whether [`extract`] gives other text than the contents of `path` to search, without running anything
+pub(crate) fn extracts(command_args: &CommandArgs, path: &str) -> bool {
+    let extension = extension(path);
+    #[cfg(feature = "documents")]
+    {
+        if extension.as_deref().and_then(super::documents::Format::from_extension).is_some() {
+            return true;
+        }
+    }
+    preprocessor(command_args, extension.as_deref()).is_some()
+}
+
+/// The extension of `path` in lower case
+fn extension(path: &str) -> Option<String> {
+    Path::new(path).extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase())
+}
+
+/// The first `--pre` command for files with `extension`, commands for an extension before commands for all files
+fn preprocessor<'a>(command_args: &'a CommandArgs, extension: Option<&str>) -> Option<&'a Preprocessor> {
+    let pre = &command_args.pre;
+    pre.iter()
+        .find(|pre| !pre.extensions.is_empty() && pre.applies_to(extension))
+        .or_else(|| pre.iter().find(|pre| pre.applies_to(extension)))
+}
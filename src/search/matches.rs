@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::searcher::{InputSearch, Searcher};
+use crate::error::{Error, Result};
+use crate::matcher::{self, Match, Matcher};
+use crate::sink::{Sink, SinkLine};
+
+/// What [`Searcher::matches`] searches
+pub enum Input<'a> {
+    Path(&'a Path),
+    /// Any reader, and the name its matches are reported under
+    Reader(&'a str, Box<dyn Read + 'a>),
+}
+
+impl<'a> Input<'a> {
+    /// Opens the input, returns its name and a reader for it
+    pub(crate) fn open(self) -> Result<(Cow<'a, str>, Box<dyn Read + 'a>)> {
+        Ok(match self {
+            Input::Path(path) => {
+                let name = path.to_string_lossy();
+                let file = File::open(path).map_err(|err| Error::read(&name, err))?;
+                (name, Box::new(file))
+            }
+            Input::Reader(name, reader) => (Cow::Borrowed(name), reader),
+        })
+    }
+}
+
+impl<'a> From<&'a Path> for Input<'a> {
+    fn from(path: &'a Path) -> Input<'a> {
+        Input::Path(path)
+    }
+}
+
+/// A matching line, with everything needed to report it after the search moved on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// Name of the input, as for [`SinkLine::path`]
+    pub path: String,
+    pub line_number: usize,
+    /// Offset of the first byte of the line in the input, after decoding
+    pub offset: u64,
+    /// The line without its terminator
+    pub line: Vec<u8>,
+    /// Where the patterns match in the line, relative to its start. Empty for a line in the middle of a
+    /// match that spans several lines.
+    pub spans: Vec<Match>,
+}
+
+impl LineMatch {
+    /// The line as text, with invalid UTF-8 replaced
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.line)
+    }
+}
+
+/// Keeps the matching lines for the iterator to hand out
+struct Collector<'a> {
+    matcher: &'a dyn Matcher,
+    found: VecDeque<LineMatch>,
+}
+
+impl<'a> Sink for Collector<'a> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.found.push_back(LineMatch {
+            path: String::from(line.path),
+            line_number: line.line_number,
+            offset: line.offset,
+            line: line.line.to_vec(),
+            spans: matcher::find_iter(self.matcher, line.line).collect(),
+        });
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Iterator returned by [`Searcher::matches`]. Reads the input as it is advanced.
+/// An error ends the iteration after it was returned.
+pub struct Matches<'a> {
+    searcher: &'a Searcher,
+    /// The input, until the first call to `next` opens it
+    input: Option<Input<'a>>,
+    search: Option<InputSearch<'a, 'a>>,
+    collector: Collector<'a>,
+}
+
+impl<'a> Matches<'a> {
+    pub(crate) fn new(searcher: &'a Searcher, matcher: &'a dyn Matcher, input: Input<'a>) -> Matches<'a> {
+        Matches { searcher, input: Some(input), search: None, collector: Collector { matcher, found: VecDeque::new() } }
+    }
+
+    fn open(&mut self, input: Input<'a>) -> Result<()> {
+        let (name, reader) = input.open().map_err(|err| self.searcher.failed(err))?;
+        let search = self.searcher.start(self.collector.matcher, reader, &name);
+        self.search = Some(search.map_err(|err| self.searcher.failed(err))?);
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = Result<LineMatch>;
+
+    fn next(&mut self) -> Option<Result<LineMatch>> {
+        if let Some(input) = self.input.take() {
+            if let Err(err) = self.open(input) {
+                return Some(Err(err));
+            }
+        }
+        loop {
+            if let Some(found) = self.collector.found.pop_front() {
+                return Some(Ok(found));
+            }
+            let search = self.search.as_mut()?;
+            let matcher = self.collector.matcher;
+            match search.step(matcher, &mut self.collector) {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.searcher.searched(search.name(), search.summary());
+                    self.search = None;
+                }
+                Err(err) => {
+                    self.search = None;
+                    return Some(Err(self.searcher.failed(err)));
+                }
+            }
+        }
+    }
+}
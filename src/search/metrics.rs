@@ -0,0 +1,129 @@
+//! Counters about a search handed to the program embedding rgrep as it goes, e.g. to export them to a
+//! monitoring system, without parsing the output.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::Error;
+use crate::sink::FileSummary;
+
+/// Receives what every input of a search came to, once it was searched or failed. Registered with
+/// [`SearcherBuilder::metrics`](super::SearcherBuilder::metrics), it is called from whatever thread searches,
+/// so it has to count with atomics or a lock. [`Counters`] keeps the totals.
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// use rgrep::matcher::{self, MatcherOptions};
+/// use rgrep::search::{Metrics, SearcherBuilder};
+/// use rgrep::sink::{CountSink, FileSummary};
+///
+/// #[derive(Default)]
+/// struct LargestInput(AtomicU64);
+///
+/// impl Metrics for LargestInput {
+///     fn searched(&self, _path: &str, summary: &FileSummary) {
+///         self.0.fetch_max(summary.bytes_searched, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let largest = Arc::new(LargestInput::default());
+/// let matcher = matcher::build(&[String::from("b")], &MatcherOptions::default()).unwrap();
+/// let searcher = SearcherBuilder::new().metrics(largest.clone()).build();
+/// let mut sink = CountSink::new(Vec::new(), false);
+/// searcher.search_slice(matcher.as_ref(), b"a\nb\n", "small", &mut sink).unwrap();
+/// searcher.search_slice(matcher.as_ref(), b"abc\nbcd\ncde\n", "large", &mut sink).unwrap();
+/// assert_eq!(largest.0.load(Ordering::Relaxed), 12);
+/// ```
+pub trait Metrics: Send + Sync {
+    /// The input called `path` was searched, including one whose search was stopped early on purpose
+    fn searched(&self, _path: &str, _summary: &FileSummary) {}
+
+    /// The search of the input called `path` was stopped by Ctrl-C or a
+    /// [`CancellationToken`](super::CancellationToken) after it came to `summary`, so far
+    fn interrupted(&self, _path: &str, _summary: &FileSummary) {}
+
+    /// An input could not be searched: it could not be opened or read, or with
+    /// [`multi_line`](super::SearcherBuilder::multi_line) it was larger than
+    /// [`max_memory`](super::SearcherBuilder::max_memory). Neither an interrupted search nor failing to write
+    /// the output is an error of an input.
+    fn failed(&self, _error: &Error) {}
+}
+
+impl fmt::Debug for dyn Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+/// Totals of the inputs searched so far, which can be read while the search goes on
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use rgrep::matcher::{self, MatcherOptions};
+/// use rgrep::search::{Counters, SearcherBuilder};
+/// use rgrep::sink::CountSink;
+///
+/// let counters = Arc::new(Counters::new());
+/// let matcher = matcher::build(&[String::from("b")], &MatcherOptions::default()).unwrap();
+/// let searcher = SearcherBuilder::new().metrics(counters.clone()).build();
+/// let mut sink = CountSink::new(Vec::new(), false);
+/// searcher.search_slice(matcher.as_ref(), b"a\nb\nab\n", "buffer", &mut sink).unwrap();
+/// assert!(searcher.search_path(matcher.as_ref(), "missing.txt".as_ref(), &mut sink).is_err());
+/// assert_eq!(counters.files_searched(), 1);
+/// assert_eq!(counters.bytes_searched(), 7);
+/// assert_eq!(counters.matched_lines(), 2);
+/// assert_eq!(counters.errors(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct Counters {
+    files_searched: AtomicU64,
+    files_with_matches: AtomicU64,
+    bytes_searched: AtomicU64,
+    matched_lines: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    pub fn new() -> Counters {
+        Counters::default()
+    }
+
+    pub fn files_searched(&self) -> u64 {
+        self.files_searched.load(Ordering::Relaxed)
+    }
+
+    pub fn files_with_matches(&self) -> u64 {
+        self.files_with_matches.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_searched(&self) -> u64 {
+        self.bytes_searched.load(Ordering::Relaxed)
+    }
+
+    pub fn matched_lines(&self) -> u64 {
+        self.matched_lines.load(Ordering::Relaxed)
+    }
+
+    /// Inputs that could not be searched
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+impl Metrics for Counters {
+    fn searched(&self, _path: &str, summary: &FileSummary) {
+        self.files_searched.fetch_add(1, Ordering::Relaxed);
+        if summary.matched_lines > 0 {
+            self.files_with_matches.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_searched.fetch_add(summary.bytes_searched, Ordering::Relaxed);
+        self.matched_lines.fetch_add(summary.matched_lines, Ordering::Relaxed);
+    }
+
+    fn failed(&self, _error: &Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
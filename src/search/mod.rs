@@ -0,0 +1,714 @@
+//! Searching inputs line by line and printing what matched.
+//!
+//! [`run`] searches everything a [`CommandArgs`] asks for, the way the `rgrep` binary does.
+//! A [`Searcher`] searches a single input with settings given in code; it is all there is without the `cli` feature.
+
+#[cfg(feature = "cli")]
+mod all_match;
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "cli")]
+mod checkpoint;
+mod decode;
+#[cfg(feature = "documents")]
+mod documents;
+mod enclosing;
+#[cfg(feature = "cli")]
+mod extract;
+mod matches;
+mod metrics;
+#[cfg(feature = "cli")]
+mod progress;
+#[cfg(feature = "cli")]
+mod replace;
+mod searcher;
+#[cfg(feature = "cli")]
+mod separator;
+#[cfg(feature = "cli")]
+mod syntax;
+
+#[cfg(feature = "cli")]
+use std::collections::HashSet;
+#[cfg(feature = "cli")]
+use std::fs::{self, File};
+#[cfg(feature = "cli")]
+use std::path::{Path, PathBuf};
+use std::io::{self, Write};
+#[cfg(feature = "cli")]
+use std::io::IsTerminal;
+#[cfg(feature = "cli")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(feature = "cli")]
+use std::time::Instant;
+
+#[cfg(feature = "cli")]
+use crate::args::CommandArgs;
+#[cfg(feature = "cli")]
+use crate::error::{Error, Result};
+#[cfg(feature = "cli")]
+use crate::walk::{self, Walk};
+#[cfg(feature = "cli")]
+use crate::matcher::{self, Matcher, MultiMatcher};
+#[cfg(feature = "cli")]
+use crate::sink::{
+    ColorChoice, CountSink, DiffSink, ExcerptSink, FileSummary, JsonSink, OffsetsSink, OnlyMatchingSink,
+    PatternCountSink, ReplaceSink, Sink, TextSink, UniqueSink,
+};
+
+#[cfg(feature = "cli")]
+pub(crate) use self::all_match::AllMatchSink;
+#[cfg(feature = "async")]
+pub use self::asynchronous::AsyncRead;
+pub use self::decode::{Encoding, InputDecoder};
+#[cfg(feature = "cli")]
+pub(crate) use self::extract::extracts;
+#[cfg(feature = "cli")]
+pub use self::extract::Preprocessor;
+pub use self::matches::{Input, LineMatch, Matches};
+pub use self::metrics::{Counters, Metrics};
+pub use self::searcher::{BinaryDetection, CancellationToken, Searcher, SearcherBuilder, Stop};
+#[cfg(feature = "cli")]
+pub use self::syntax::Region;
+#[cfg(feature = "cli")]
+pub use crate::walk::DirectoryAction;
+
+/// Name printed for standard input
+#[cfg(feature = "cli")]
+const STDIN_NAME: &str = "(standard input)";
+
+/// In which order the inputs are searched and their results printed, `--order`
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// The order they are given in, with the results of each printed as soon as they are found
+    #[default]
+    AsCompleted,
+    /// Sorted by name, so the output is the same however they were given
+    Sorted,
+}
+
+#[cfg(feature = "cli")]
+impl Order {
+    /// Names accepted by `--order`
+    pub const NAMES: &'static [&'static str] = &["as-completed", "sorted"];
+
+    pub fn from_name(name: &str) -> Option<Order> {
+        Some(match name {
+            "as-completed" => Order::AsCompleted,
+            "sorted" => Order::Sorted,
+            _ => return None,
+        })
+    }
+}
+
+/// Outcome of a whole run
+#[cfg(feature = "cli")]
+pub struct Summary {
+    pub matched: bool,
+    pub had_errors: bool,
+    /// Inputs left out because they could not be read
+    pub skipped: usize,
+    /// The search was stopped early by Ctrl-C
+    pub interrupted: bool,
+}
+
+#[cfg(feature = "cli")]
+impl Summary {
+    /// The exit status of rgrep for the run: 0 if something matched, 1 if nothing did, 2 if an input could not
+    /// be searched, and 130 if it was interrupted
+    pub fn status(&self) -> i32 {
+        if self.interrupted {
+            crate::signal::INTERRUPTED_STATUS
+        } else if self.had_errors {
+            2
+        } else if self.matched {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+/// Counters about a whole run, printed by --stats
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub matched_lines: u64,
+    pub files_with_matches: u64,
+    pub files_searched: u64,
+    pub bytes_searched: u64,
+    /// Inputs that could not be searched, or rewritten by --write-replace
+    pub errors: u64,
+    pub elapsed: Duration,
+    /// Counters about every input searched, in order, if they were asked for with --stats-per-file
+    pub files: Vec<FileStats>,
+}
+
+/// Counters about one input, to find the ones that take long
+#[derive(Debug, Clone, Default)]
+pub struct FileStats {
+    pub path: String,
+    pub elapsed: Duration,
+    pub bytes_searched: u64,
+    /// How the input was read and matched, e.g. `file, line by line stream`
+    pub strategy: String,
+}
+
+impl Stats {
+    /// Writes the counters as text, one per line, after an empty line
+    pub(crate) fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let plural = |count: u64| if count == 1 { "" } else { "s" };
+        writeln!(out)?;
+        writeln!(out, "{} matched line{}", self.matched_lines, plural(self.matched_lines))?;
+        writeln!(out, "{} file{} contained matches", self.files_with_matches, plural(self.files_with_matches))?;
+        writeln!(out, "{} file{} searched", self.files_searched, plural(self.files_searched))?;
+        writeln!(out, "{} byte{} searched", self.bytes_searched, plural(self.bytes_searched))?;
+        writeln!(out, "{:.6} seconds spent searching", self.elapsed.as_secs_f64())?;
+        for file in &self.files {
+            let seconds = file.elapsed.as_secs_f64();
+            writeln!(out, "{:.6} seconds, {} bytes, {}: {}", seconds, file.bytes_searched, file.strategy, file.path)?;
+        }
+        Ok(())
+    }
+
+    /// The counters in a line, for --summary
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use rgrep::search::Stats;
+    ///
+    /// let stats = Stats {
+    ///     matched_lines: 42,
+    ///     files_with_matches: 7,
+    ///     files_searched: 1204,
+    ///     elapsed: Duration::from_millis(812),
+    ///     ..Stats::default()
+    /// };
+    /// assert_eq!(stats.summary(), "42 matching lines in 7 files (searched 1,204 files in 0.8s)");
+    /// ```
+    pub fn summary(&self) -> String {
+        let counted = |count: u64, what: &str| {
+            let digits = count.to_string();
+            let mut grouped = String::new();
+            for (index, digit) in digits.chars().enumerate() {
+                if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                    grouped.push(',');
+                }
+                grouped.push(digit);
+            }
+            format!("{} {}{}", grouped, what, if count == 1 { "" } else { "s" })
+        };
+        format!(
+            "{} in {} (searched {} in {:.1}s)",
+            counted(self.matched_lines, "matching line"),
+            counted(self.files_with_matches, "file"),
+            counted(self.files_searched, "file"),
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Searches every file, or standard input if there are none, and writes the results to `out`
+/// as text, JSON or counts. Errors reading individual files are reported to stderr and do not stop the search;
+/// errors writing the output are returned, and so are options that cannot be used together, which
+/// [`args::validate`](crate::args::validate) tells. A search of files that takes long shows its progress on stderr,
+/// if it is a terminal.
+#[cfg(feature = "cli")]
+pub fn run<W: Write>(command_args: &CommandArgs, matcher: &dyn Matcher, out: W) -> Result<Summary> {
+    crate::args::validate(command_args).map_err(Error::Usage)?;
+    let mut sink = output_sink(command_args, matcher, out);
+    // log messages would break up the line
+    let progress = !command_args.no_progress
+        && command_args.files.iter().any(|file| file != "-")
+        && command_args.log_level == crate::log::Level::Off
+        && io::stderr().is_terminal();
+    if progress {
+        run_with_sink(command_args, matcher, &mut progress::ProgressSink::new(sink))
+    } else {
+        run_with_sink(command_args, matcher, sink.as_mut())
+    }
+}
+
+/// The sink writing results to `out` the way the options say: as text, JSON, counts or diffs, with the
+/// replacements of --replace
+#[cfg(feature = "cli")]
+pub(crate) fn output_sink<'a, W: Write + 'a>(
+    command_args: &'a CommandArgs,
+    matcher: &'a dyn Matcher,
+    out: W,
+) -> Box<dyn Sink + 'a> {
+    let recursing = command_args.directories == DirectoryAction::Recurse
+        && (command_args.files.is_empty() || command_args.files.iter().any(|file| walk::is_dir(file)));
+    let with_filename = command_args.files.len() > 1
+        || recursing
+        || !command_args.git_rev.is_empty()
+        || !command_args.changed_since.is_empty();
+    let sink: Box<dyn Sink + 'a> = if command_args.dry_run {
+        Box::new(DiffSink::new(out))
+    } else if command_args.json && command_args.captures {
+        Box::new(JsonSink::with_submatches(out, matcher, command_args.utf16_columns).with_captures())
+    } else if command_args.json && command_args.utf16_columns {
+        Box::new(JsonSink::with_submatches(out, matcher, true))
+    } else if command_args.json {
+        Box::new(JsonSink::new(out).with_named_groups(matcher))
+    } else if command_args.count_per_pattern {
+        let options = command_args.matcher_options();
+        let each = matcher::build_each(&command_args.patterns, &options).expect("the patterns compiled together");
+        let patterns = command_args.patterns.iter().map(String::as_str).zip(each).collect();
+        let sink = PatternCountSink::new(out, patterns, with_filename);
+        if command_args.no_include_zero { Box::new(sink.without_zero()) } else { Box::new(sink) }
+    } else if command_args.unique {
+        Box::new(UniqueSink::new(out, matcher, command_args.count))
+    } else if command_args.count {
+        let sink = CountSink::new(out, with_filename);
+        if command_args.no_include_zero { Box::new(sink.without_zero()) } else { Box::new(sink) }
+    } else if command_args.byte_offsets {
+        let sink = OffsetsSink::new(out, matcher, with_filename, command_args.multiline);
+        if command_args.captures { Box::new(sink.with_captures()) } else { Box::new(sink) }
+    } else {
+        // -o prints no context, so there are no groups of lines to separate
+        let context_enabled = !command_args.only_matching
+            && (command_args.after_context > 0 || command_args.before_context > 0 || command_args.paragraph);
+        let mut sink = TextSink::new(out, with_filename, context_enabled);
+        if command_args.line_number || command_args.column {
+            sink = sink.with_line_numbers(command_args.line_number_width as usize);
+        }
+        if command_args.column {
+            sink = sink.with_columns(matcher);
+        }
+        if let Some(lines) = command_args.max_shown {
+            sink = sink.with_max_shown(lines as u64);
+        }
+        if command_args.heading.unwrap_or_else(|| io::stdout().is_terminal()) {
+            sink = sink.with_heading();
+            if command_args.heading_counts {
+                sink = sink.with_heading_counts();
+            }
+            if command_args.line_number_width == 0 {
+                sink = sink.with_input_line_number_width();
+            }
+        }
+        if colored(command_args.color) {
+            let mut colors = command_args.theme.colors();
+            if let Ok(grep_colors) = std::env::var("GREP_COLORS") {
+                colors = colors.with_grep_colors(&grep_colors);
+            }
+            for spec in &command_args.colors {
+                colors = colors.with_spec(spec);
+            }
+            // the matches of the pattern are not what is left of them after replacing
+            if command_args.replace.is_some() {
+                colors.matched.clear();
+                colors.context_matched.clear();
+            }
+            let sink = sink.with_colors(colors, matcher);
+            if command_args.patterns.len() > 1 {
+                let each = matcher::build_each(&command_args.patterns, &command_args.matcher_options())
+                    .expect("the patterns compiled together");
+                Box::new(sink.with_pattern_colors(MultiMatcher::new(each)))
+            } else {
+                Box::new(sink)
+            }
+        } else {
+            Box::new(sink)
+        }
+    };
+    let sink = match command_args.context_bytes {
+        Some(bytes) => Box::new(ExcerptSink::new(sink, matcher, bytes as usize)),
+        None => sink,
+    };
+    let sink = match &command_args.replace {
+        Some(replacement) => Box::new(ReplaceSink::new(sink, matcher, replacement.as_str())),
+        None => sink,
+    };
+    // outside of the replacements, which are made in the matches it passes on
+    let sink = if command_args.only_matching { Box::new(OnlyMatchingSink::new(sink, matcher)) } else { sink };
+    match command_args.path_separator {
+        Some(separator) => Box::new(separator::PathSeparatorSink::new(sink, separator)),
+        None => sink,
+    }
+}
+
+/// Whether output is colored with `--color=WHEN`
+#[cfg(feature = "cli")]
+fn colored(color: ColorChoice) -> bool {
+    match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => io::stdout().is_terminal() && std::env::var_os("TERM").is_none_or(|term| term != "dumb"),
+    }
+}
+
+/// A searcher with the settings of the options
+#[cfg(feature = "cli")]
+pub(crate) fn searcher(command_args: &CommandArgs) -> Searcher {
+    // counts and offsets do not need context lines, so they are not even collected
+    let lines_only = command_args.count
+        || command_args.count_per_pattern
+        || command_args.byte_offsets
+        || command_args.unique
+        || command_args.only_matching;
+    let context = |lines: u32| if lines_only { 0 } else { lines as usize };
+    let mut builder = SearcherBuilder::new();
+    builder
+        .after_context(context(command_args.after_context))
+        .before_context(context(command_args.before_context))
+        .binary_detection(command_args.binary)
+        .encoding(command_args.encoding)
+        .multi_line(command_args.multiline)
+        .paragraph(command_args.paragraph && !lines_only)
+        .show_function(command_args.show_function && !lines_only)
+        .stop_on_nonmatch(command_args.stop_on_nonmatch);
+    if let Some(bytes) = command_args.max_memory {
+        builder.max_memory(bytes);
+    }
+    if let Some(metrics) = &command_args.metrics {
+        builder.metrics(metrics.clone());
+    }
+    builder.build()
+}
+
+/// Passes everything on to the metrics of the command line, keeping what the input being searched when Ctrl-C
+/// was pressed came to so far, for --stats
+#[cfg(feature = "cli")]
+struct InterruptedInput {
+    metrics: Option<Arc<dyn Metrics>>,
+    summary: Mutex<Option<FileSummary>>,
+}
+
+#[cfg(feature = "cli")]
+impl Metrics for InterruptedInput {
+    fn searched(&self, path: &str, summary: &FileSummary) {
+        if let Some(metrics) = &self.metrics {
+            metrics.searched(path, summary);
+        }
+    }
+
+    fn interrupted(&self, path: &str, summary: &FileSummary) {
+        *self.summary.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(summary.clone());
+        if let Some(metrics) = &self.metrics {
+            metrics.interrupted(path, summary);
+        }
+    }
+
+    fn failed(&self, error: &Error) {
+        if let Some(metrics) = &self.metrics {
+            metrics.failed(error);
+        }
+    }
+}
+
+/// Device and inode of a file, which are the same for all names of it, or `None` where there are none
+#[cfg(all(feature = "cli", unix))]
+pub(crate) fn identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(all(feature = "cli", not(unix)))]
+pub(crate) fn identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Tells files apart however they are named, including through hard and symbolic links
+#[cfg(feature = "cli")]
+#[derive(PartialEq, Eq, Hash)]
+enum FileKey {
+    Identity(u64, u64),
+    /// Where files have no identity, their path with every link resolved
+    Path(PathBuf),
+}
+
+#[cfg(feature = "cli")]
+impl FileKey {
+    /// The key of the file at `path`, `None` if it cannot be looked at
+    fn of(path: &str) -> Option<FileKey> {
+        let metadata = fs::metadata(path).ok()?;
+        match identity(&metadata) {
+            Some((device, inode)) => Some(FileKey::Identity(device, inode)),
+            None => fs::canonicalize(path).ok().map(FileKey::Path),
+        }
+    }
+}
+
+/// Whether `file` was last modified after --newer-than and before --older-than. Files whose time cannot be read
+/// are searched, so the error is reported.
+#[cfg(feature = "cli")]
+fn modified_in_range(command_args: &CommandArgs, file: &str) -> bool {
+    if command_args.newer_than.is_none() && command_args.older_than.is_none() {
+        return true;
+    }
+    let modified = match fs::metadata(file).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return true,
+    };
+    command_args.newer_than.is_none_or(|newer_than| modified > newer_than)
+        && command_args.older_than.is_none_or(|older_than| modified < older_than)
+}
+
+/// Same as [`run`], with the results going to `sink` instead of being written out the way the options say
+#[cfg(feature = "cli")]
+pub fn run_with_sink(command_args: &CommandArgs, matcher: &dyn Matcher, sink: &mut dyn Sink) -> Result<Summary> {
+    run_inputs(command_args, matcher, sink, None)
+}
+
+/// Same as [`run_with_sink`], with the contents of files given by `contents` instead of read from them, for
+/// `--daemon` to search the files it keeps in memory
+#[cfg(feature = "cli")]
+pub(crate) fn run_with_contents(
+    command_args: &CommandArgs,
+    matcher: &dyn Matcher,
+    sink: &mut dyn Sink,
+    contents: Contents,
+) -> Result<Summary> {
+    run_inputs(command_args, matcher, sink, Some(contents))
+}
+
+/// The contents of the file at a path
+#[cfg(feature = "cli")]
+type Contents<'a> = &'a dyn Fn(&str) -> io::Result<Arc<[u8]>>;
+
+#[cfg(feature = "cli")]
+fn run_inputs(
+    command_args: &CommandArgs,
+    matcher: &dyn Matcher,
+    sink: &mut dyn Sink,
+    contents: Option<Contents>,
+) -> Result<Summary> {
+    let interrupted = Arc::new(InterruptedInput { metrics: command_args.metrics.clone(), summary: Mutex::new(None) });
+    let searcher = searcher(&CommandArgs { metrics: Some(interrupted.clone()), ..command_args.clone() });
+    let mut summary = Summary { matched: false, had_errors: false, skipped: 0, interrupted: false };
+    let mut stats = Stats::default();
+
+    debug!("patterns {:?}, ignore case: {}", command_args.patterns, command_args.ignore_case);
+    let mode = if command_args.multiline { "whole input" } else { "line by line stream" };
+    debug!("strategy: {}, matcher: {}", mode, matcher.strategy());
+    let started = Instant::now();
+    let mut searched = HashSet::new();
+    let mut checkpoint = match command_args.checkpoint.as_str() {
+        "" => None,
+        path => Some(checkpoint::Checkpoint::open(path, command_args)?),
+    };
+    // the results of earlier runs are part of this one, even if they are not printed again
+    summary.matched = checkpoint.as_ref().is_some_and(checkpoint::Checkpoint::matched_before);
+    let all_match_patterns = if command_args.all_match {
+        let options = command_args.matcher_options();
+        matcher::build_each(&command_args.patterns, &options).expect("the patterns compiled together")
+    } else {
+        Vec::new()
+    };
+
+    let mut inputs = 0;
+    let mut walk = Walk::of(command_args);
+    while let Some(file) = walk.next() {
+        let file = match file {
+            Ok(file) => file,
+            Err(err) => {
+                let err = searcher.failed(err);
+                if !command_args.no_messages {
+                    sink.error(&err).map_err(Error::write)?;
+                }
+                summary.had_errors = true;
+                summary.skipped += 1;
+                stats.errors += 1;
+                continue;
+            }
+        };
+        let file = &file;
+        inputs += 1;
+        if file != "-" && !modified_in_range(command_args, file) {
+            debug!("skipping {}, modified outside of --newer-than and --older-than", file);
+            continue;
+        }
+        if file != "-" && !command_args.no_dedupe {
+            if let Some(key) = FileKey::of(file) {
+                if !searched.insert(key) {
+                    debug!("skipping {}, the same file as one searched before", file);
+                    continue;
+                }
+            }
+        }
+        if checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.searched_before(file)) {
+            debug!("skipping {}, searched by an earlier run with the same --checkpoint", file);
+            continue;
+        }
+        let file_started = Instant::now();
+        let mut extracted = false;
+        // where the text searched came from, for --stats-per-file
+        let mut source = "file";
+        let name = if file != "-" {
+            file.as_str()
+        } else if command_args.label.is_empty() {
+            STDIN_NAME
+        } else {
+            command_args.label.as_str()
+        };
+        let mut all_match = None;
+        let file_sink: &mut dyn Sink = if command_args.all_match {
+            all_match.insert(AllMatchSink::new(&mut *sink, &all_match_patterns))
+        } else {
+            &mut *sink
+        };
+        // --write-replace only replaces the matches that were reported
+        let mut matched_lines = None;
+        let file_sink: &mut dyn Sink = if command_args.write_replace {
+            matched_lines.insert(replace::MatchedLines::new(file_sink))
+        } else {
+            file_sink
+        };
+        let result = if file == "-" {
+            source = "standard input";
+            searcher.search_reader(matcher, io::stdin().lock(), name, &mut *file_sink)
+        } else if let Some(text) = extract::extract(command_args, file) {
+            extracted = true;
+            source = "extracted text";
+            match text {
+                Ok(text) => searcher.search_slice(matcher, &text, file, &mut *file_sink),
+                Err(err) => Err(searcher.failed(Error::read(file, err))),
+            }
+        } else if let Some(result) =
+            command_args.only.and_then(|region| syntax::search(&searcher, matcher, region, file, &mut *file_sink))
+        {
+            source = "part of a source file";
+            result
+        } else if let Some(contents) = contents {
+            source = "kept in memory";
+            match contents(file) {
+                Ok(text) => searcher.search_slice(matcher, &text, file, &mut *file_sink),
+                Err(err) => Err(searcher.failed(Error::read(file, err))),
+            }
+        } else {
+            match File::open(file) {
+                Ok(handle) => searcher.search_reader(matcher, handle, file, &mut *file_sink),
+                Err(err) => Err(searcher.failed(Error::read(file, err))),
+            }
+        };
+        let shown = command_args.max_shown.map(|lines| lines as usize);
+        let last_line = matched_lines.and_then(|matched_lines| matched_lines.last(shown));
+        // with --all-match, an input without every pattern counts as one without matches
+        let missed_patterns = all_match.is_some_and(|all_match| !all_match.all_matched());
+        match result {
+            Ok(file_summary) if missed_patterns => {
+                stats.files_searched += 1;
+                stats.bytes_searched += file_summary.bytes_searched;
+                if let Some(checkpoint) = checkpoint.as_mut().filter(|_| file != "-") {
+                    checkpoint.record(file, false)?;
+                }
+            }
+            Ok(file_summary) => {
+                stats.files_searched += 1;
+                stats.bytes_searched += file_summary.bytes_searched;
+                stats.matched_lines += file_summary.matched_lines;
+                if file_summary.matched_lines > 0 {
+                    stats.files_with_matches += 1;
+                    summary.matched = true;
+                }
+                if command_args.stats_per_file {
+                    stats.files.push(FileStats {
+                        path: String::from(name),
+                        elapsed: file_started.elapsed(),
+                        bytes_searched: file_summary.bytes_searched,
+                        strategy: format!("{}, {}", source, mode),
+                    });
+                }
+                let searched_as_text = !file_summary.binary || command_args.binary == BinaryDetection::Text;
+                // the text of an extracted file is not what is in it
+                let rewritable = file != "-" && !extracted && searched_as_text;
+                if command_args.write_replace && file_summary.matched_lines > 0 && rewritable {
+                    match write_replacements(command_args, matcher, file, last_line, &mut *sink) {
+                        Ok(()) => {}
+                        Err(err @ Error::Io { path: Some(_), .. }) => {
+                            if !command_args.no_messages {
+                                sink.error(&err).map_err(Error::write)?;
+                            }
+                            summary.had_errors = true;
+                            stats.errors += 1;
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                if let Some(checkpoint) = checkpoint.as_mut().filter(|_| file != "-") {
+                    checkpoint.record(file, file_summary.matched_lines > 0)?;
+                }
+            }
+            // files come and go in busy directories, between when they were listed or found and when they are read
+            Err(Error::Io { path: Some(_), source })
+                if (command_args.files_listed || walk.found()) && source.kind() == io::ErrorKind::NotFound =>
+            {
+                debug!("skipping {}, it was removed after it was listed", file);
+            }
+            // the other inputs are still searched, the error is reported and counted
+            Err(err @ Error::Io { path: Some(_), .. }) => {
+                if !command_args.no_messages {
+                    sink.error(&err).map_err(Error::write)?;
+                }
+                summary.had_errors = true;
+                summary.skipped += 1;
+                stats.errors += 1;
+            }
+            Err(Error::Interrupted) => {
+                debug!("interrupted while searching {}", file);
+                // what was searched of it before is counted too
+                let partial = interrupted.summary.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+                if let Some(partial) = partial {
+                    stats.files_searched += 1;
+                    stats.bytes_searched += partial.bytes_searched;
+                    stats.matched_lines += partial.matched_lines;
+                    if partial.matched_lines > 0 {
+                        stats.files_with_matches += 1;
+                    }
+                }
+                summary.interrupted = true;
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+        debug!("searched {} in {:.3}ms", file, file_started.elapsed().as_secs_f64() * 1000.0);
+    }
+    debug!("searched {} inputs in {:.3}ms", inputs, started.elapsed().as_secs_f64() * 1000.0);
+    // a run that is stopped, or could not read everything, leaves what remains to be searched to the next one
+    if let Some(checkpoint) = checkpoint.filter(|_| !summary.interrupted && !summary.had_errors) {
+        checkpoint.finish()?;
+    }
+    stats.elapsed = started.elapsed();
+    if command_args.stats {
+        sink.stats(&stats).map_err(Error::write)?;
+    }
+    sink.finish(&stats, summary.status()).map_err(Error::write)?;
+    sink.flush().map_err(Error::write)?;
+    // on stderr, so the results can still be piped somewhere
+    if command_args.summary {
+        eprintln!("{}", stats.summary());
+    }
+    Ok(summary)
+}
+
+/// Writes the replacements of --replace back to `file` for --write-replace, up to the end of line `last_line`, the
+/// last matching line reported, or with --dry-run, hands them to the sink instead
+#[cfg(feature = "cli")]
+fn write_replacements(
+    command_args: &CommandArgs,
+    matcher: &dyn Matcher,
+    file: &str,
+    last_line: Option<usize>,
+    sink: &mut dyn Sink,
+) -> Result<()> {
+    let replacement = command_args.replace.as_deref().unwrap_or_default();
+    let rewrite = replace::read_rewrite(Path::new(file), matcher, replacement, command_args.multiline, last_line)
+        .map_err(|err| Error::read(file, err))?;
+    match rewrite {
+        Some(rewrite) if command_args.dry_run => {
+            sink.replaced(file, &rewrite.original, &rewrite.replaced).map_err(Error::write)
+        }
+        Some(rewrite) => {
+            rewrite.write(command_args.backup).map_err(|err| Error::read(file, err))?;
+            debug!("rewrote {}", file);
+            Ok(())
+        }
+        None => {
+            debug!("replacing matches leaves {} as it is", file);
+            Ok(())
+        }
+    }
+}
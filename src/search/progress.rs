@@ -0,0 +1,160 @@
+//! The progress line shown on stderr while a search that takes long runs.
+//!
+//! It is redrawn when an input starts or ends, so it needs no thread of its own. When stdout is a terminal too,
+//! the line is erased before results are printed, and results are flushed before it is drawn again, so the two
+//! do not mix.
+
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::Stats;
+use crate::error::Error;
+use crate::sink::{FileSummary, Sink, SinkLine};
+use crate::terminal;
+
+/// How long a search runs before the progress line is shown
+const SHOW_AFTER: Duration = Duration::from_secs(1);
+
+/// How often the progress line is redrawn at most
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+
+/// Width of the line when the terminal does not say
+const DEFAULT_WIDTH: usize = 80;
+
+/// Passes results on to another sink and shows how far the search got on stderr, which has to be a terminal
+pub(crate) struct ProgressSink<S: Sink> {
+    inner: S,
+    started: Instant,
+    last_drawn: Option<Instant>,
+    /// The line is on the screen
+    shown: bool,
+    /// Results go to the same screen as the line
+    stdout_is_terminal: bool,
+    files_searched: u64,
+    matched_lines: u64,
+    /// Directory of the input being searched
+    directory: String,
+    width: usize,
+}
+
+impl<S: Sink> ProgressSink<S> {
+    pub(crate) fn new(inner: S) -> ProgressSink<S> {
+        ProgressSink {
+            inner,
+            started: Instant::now(),
+            last_drawn: None,
+            shown: false,
+            stdout_is_terminal: io::stdout().is_terminal(),
+            files_searched: 0,
+            matched_lines: 0,
+            directory: String::new(),
+            width: terminal::size().map_or(DEFAULT_WIDTH, |size| size.columns),
+        }
+    }
+
+    /// Redraws the line if it is time to
+    fn tick(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        if now.duration_since(self.started) < SHOW_AFTER
+            || self.last_drawn.is_some_and(|drawn| now.duration_since(drawn) < REDRAW_EVERY)
+        {
+            return Ok(());
+        }
+        self.erase()?;
+        self.inner.flush()?;
+        let line = format!(
+            "{} files searched, {} matching lines, in {}",
+            self.files_searched, self.matched_lines, self.directory
+        );
+        // the cursor stays on the line, so it must not wrap
+        let line: String = line.chars().take(self.width.saturating_sub(1)).collect();
+        let mut stderr = io::stderr().lock();
+        write!(stderr, "\r\x1b[K{}", line)?;
+        stderr.flush()?;
+        self.last_drawn = Some(now);
+        self.shown = true;
+        Ok(())
+    }
+
+    /// Erases the line before results are printed, if they go to the screen too
+    fn erase_for_output(&mut self) -> io::Result<()> {
+        if self.stdout_is_terminal {
+            self.erase()?;
+        }
+        Ok(())
+    }
+
+    /// Erases the line if it is shown
+    fn erase(&mut self) -> io::Result<()> {
+        if self.shown {
+            let mut stderr = io::stderr().lock();
+            stderr.write_all(b"\r\x1b[K")?;
+            stderr.flush()?;
+            self.shown = false;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Sink> Sink for ProgressSink<S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        let directory = Path::new(path).parent().map(Path::to_string_lossy).unwrap_or_default();
+        self.directory = if directory.is_empty() { String::from(".") } else { directory.into_owned() };
+        self.tick()?;
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.matched(line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.files_searched += 1;
+        self.matched_lines += summary.matched_lines;
+        // what the sink prints for the end of an input, like a count, goes out when the line is drawn again
+        self.inner.end(path, summary)?;
+        self.tick()
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.erase()?;
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.erase_for_output()?;
+        self.inner.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.erase()?;
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,182 @@
+//! Writing the replacements of `--replace` back to the files, for `--write-replace`.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::error::Error;
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Replaces the matches in every line of `contents`, or anywhere in it with `multi_line`, the way they are
+/// printed, up to the end of line `last_line` if there is one. Line terminators and a UTF-8 byte order mark stay
+/// as they are. Returns `None` if nothing matched.
+pub(crate) fn replace_contents(
+    contents: &[u8],
+    matcher: &dyn Matcher,
+    replacement: &str,
+    multi_line: bool,
+    last_line: Option<usize>,
+) -> Option<Vec<u8>> {
+    let (bom, text) = match contents.strip_prefix(UTF8_BOM) {
+        Some(text) => (UTF8_BOM, text),
+        None => (&b""[..], contents),
+    };
+    // what comes after the last line is left as it is
+    let end = match last_line {
+        Some(lines) => text.split_inclusive(|&byte| byte == b'\n').take(lines).map(<[u8]>::len).sum(),
+        None => text.len(),
+    };
+    let (text, rest) = text.split_at(end);
+    let mut replaced = Vec::with_capacity(contents.len());
+    replaced.extend_from_slice(bom);
+    let mut count = 0;
+    if multi_line {
+        count += matcher::replace_all(matcher, text, replacement, &mut replaced);
+    } else {
+        for line in text.split_inclusive(|&byte| byte == b'\n') {
+            let content = line.strip_suffix(b"\n").unwrap_or(line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+            count += matcher::replace_all(matcher, content, replacement, &mut replaced);
+            replaced.extend_from_slice(&line[content.len()..]);
+        }
+    }
+    replaced.extend_from_slice(rest);
+    if count == 0 { None } else { Some(replaced) }
+}
+
+/// Passes everything on, keeping the numbers of the matching lines of the input being searched, so that only
+/// the matches reported are replaced
+pub(crate) struct MatchedLines<'s> {
+    inner: &'s mut dyn Sink,
+    lines: Vec<usize>,
+}
+
+impl<'s> MatchedLines<'s> {
+    pub(crate) fn new(inner: &'s mut dyn Sink) -> MatchedLines<'s> {
+        MatchedLines { inner, lines: Vec::new() }
+    }
+
+    /// The last of the first `shown` matching lines, or of all of them, which --write-replace rewrites up to
+    pub(crate) fn last(&self, shown: Option<usize>) -> Option<usize> {
+        let lines = match shown {
+            Some(shown) => &self.lines[..shown.min(self.lines.len())],
+            None => &self.lines,
+        };
+        lines.last().copied()
+    }
+}
+
+impl Sink for MatchedLines<'_> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.lines.clear();
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.lines.push(line.line_number);
+        self.inner.matched(line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.inner.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.inner.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A file with its matches replaced, not written yet
+pub(crate) struct Rewrite {
+    /// Where the file really is, with symbolic links resolved
+    pub path: PathBuf,
+    pub original: Vec<u8>,
+    pub replaced: Vec<u8>,
+}
+
+/// Reads the file at `path` and replaces the matches in it, up to the end of line `last_line` if there is one.
+/// Returns `None` if that changes nothing. A symbolic link is followed, so the file it points to is the one that
+/// changes and the link stays.
+pub(crate) fn read_rewrite(
+    path: &Path,
+    matcher: &dyn Matcher,
+    replacement: &str,
+    multi_line: bool,
+    last_line: Option<usize>,
+) -> io::Result<Option<Rewrite>> {
+    let path = fs::canonicalize(path)?;
+    let original = fs::read(&path)?;
+    if original.starts_with(b"\xff\xfe") || original.starts_with(b"\xfe\xff") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "UTF-16 files are not rewritten"));
+    }
+    Ok(match replace_contents(&original, matcher, replacement, multi_line, last_line) {
+        Some(replaced) if replaced != original => Some(Rewrite { path, original, replaced }),
+        _ => None,
+    })
+}
+
+impl Rewrite {
+    /// Writes the replaced contents. They go to a temporary file next to the file first, which then takes its
+    /// place, so the file is never seen half written. The permissions of the file are kept and with `backup`,
+    /// the original is kept as `FILE.bak`.
+    pub(crate) fn write(&self, backup: bool) -> io::Result<()> {
+        let permissions = fs::metadata(&self.path)?.permissions();
+        let temp = sibling(&self.path, &format!(".rgrep-{}.tmp", process::id()), true);
+        let result = OpenOptions::new().write(true).create_new(true).open(&temp).and_then(|mut file| {
+            file.write_all(&self.replaced)?;
+            file.sync_all()?;
+            fs::set_permissions(&temp, permissions)?;
+            if backup {
+                fs::copy(&self.path, sibling(&self.path, ".bak", false))?;
+            }
+            fs::rename(&temp, &self.path)
+        });
+        if result.is_err() {
+            // the temporary file is not worth reporting a second error for
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+}
+
+/// `path` with `suffix` added to its file name, and with a dot in front of it for `hidden`
+fn sibling(path: &Path, suffix: &str, hidden: bool) -> PathBuf {
+    let mut name = OsString::from(if hidden { "." } else { "" });
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
@@ -0,0 +1,786 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::decode::{DecodeReader, Encoding, InputDecoder};
+use super::enclosing::Enclosing;
+use super::matches::{Input, Matches};
+use super::metrics::Metrics;
+use crate::error::{Error, Result};
+use crate::matcher::Matcher;
+use crate::signal;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+/// What to do with inputs that contain binary data, i.e. a NUL byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryDetection {
+    /// Print "Binary file NAME matches" instead of matching lines once a NUL byte was seen,
+    /// and stop searching the input at that match
+    #[default]
+    Report,
+    /// Search the input like text
+    Text,
+    /// Stop searching the input at the first NUL byte, as if the rest did not match
+    Skip,
+}
+
+impl BinaryDetection {
+    /// Names accepted by `--binary-files`, like grep's
+    pub const NAMES: &'static [&'static str] = &["binary", "text", "without-match"];
+
+    pub fn from_name(name: &str) -> Option<BinaryDetection> {
+        Some(match name {
+            "binary" => BinaryDetection::Report,
+            "text" => BinaryDetection::Text,
+            "without-match" => BinaryDetection::Skip,
+            _ => return None,
+        })
+    }
+}
+
+/// Where a callback passed to [`Searcher::for_each_match`] stops the search, when it breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Skip the rest of the current input and go on with the next one
+    Input,
+    /// Stop the whole search
+    Search,
+}
+
+/// Stops searches from another thread. Clones share the same state, so one clone can be handed to a
+/// [`SearcherBuilder`] and another kept to cancel with. Searches check it between lines, like Ctrl-C,
+/// and return [`Error::Interrupted`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Makes every search using this token stop. Cannot be undone.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Searches inputs for lines that a [`Matcher`] matches. Built with a [`SearcherBuilder`].
+#[derive(Debug, Clone, Default)]
+pub struct Searcher {
+    after_context: usize,
+    before_context: usize,
+    binary: BinaryDetection,
+    encoding: Encoding,
+    multi_line: bool,
+    paragraph: bool,
+    show_function: bool,
+    stop_on_nonmatch: bool,
+    max_memory: Option<u64>,
+    cancellation: Option<CancellationToken>,
+    decoders: Vec<Arc<dyn InputDecoder>>,
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+/// Configures a [`Searcher`]. Every setting starts out like rgrep without options.
+///
+/// ```
+/// use rgrep::search::{BinaryDetection, SearcherBuilder};
+///
+/// let searcher = SearcherBuilder::new().context(2).binary_detection(BinaryDetection::Text).build();
+/// assert_eq!(searcher.after_context(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearcherBuilder {
+    config: Searcher,
+}
+
+impl SearcherBuilder {
+    pub fn new() -> SearcherBuilder {
+        SearcherBuilder::default()
+    }
+
+    pub fn build(&self) -> Searcher {
+        self.config.clone()
+    }
+
+    /// Number of lines printed after each matching line
+    pub fn after_context(&mut self, lines: usize) -> &mut SearcherBuilder {
+        self.config.after_context = lines;
+        self
+    }
+
+    /// Number of lines printed before each matching line
+    pub fn before_context(&mut self, lines: usize) -> &mut SearcherBuilder {
+        self.config.before_context = lines;
+        self
+    }
+
+    /// Sets both the after and before context
+    pub fn context(&mut self, lines: usize) -> &mut SearcherBuilder {
+        self.after_context(lines).before_context(lines)
+    }
+
+    pub fn binary_detection(&mut self, binary: BinaryDetection) -> &mut SearcherBuilder {
+        self.config.binary = binary;
+        self
+    }
+
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut SearcherBuilder {
+        self.config.encoding = encoding;
+        self
+    }
+
+    /// Matches the patterns against whole inputs instead of line by line, so a match can span lines.
+    /// Every line that a match touches is printed. Inputs are read into memory completely.
+    pub fn multi_line(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.multi_line = yes;
+        self
+    }
+
+    /// Reports the whole paragraph every match is in as its context instead of a number of lines before and
+    /// after it: the lines up to the blank lines around it, or the start and end of the input. A paragraph is
+    /// held in memory until its end or its first match.
+    pub fn paragraph(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.paragraph = yes;
+        self
+    }
+
+    /// Reports the line of the function, Markdown section or indented block each match is in to
+    /// [`Sink::enclosing`] before the match, unless it is printed anyway. Blocks are found by indentation.
+    pub fn show_function(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.show_function = yes;
+        self
+    }
+
+    /// Stops reading an input at the first line that does not match after one that did, e.g. to find a block of
+    /// sorted lines without reading on to the end
+    pub fn stop_on_nonmatch(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.stop_on_nonmatch = yes;
+        self
+    }
+
+    /// Holds at most `bytes` of an input in memory. Only the first `bytes` of a longer line are searched, with a
+    /// warning given to [`Sink::error`], and the search goes on with the next line. With
+    /// [`multi_line`](Self::multi_line) a larger input is not searched and is an error of that input, like one that
+    /// cannot be read.
+    pub fn max_memory(&mut self, bytes: u64) -> &mut SearcherBuilder {
+        self.config.max_memory = Some(bytes);
+        self
+    }
+
+    /// Stops searches once `token` is cancelled, in addition to Ctrl-C
+    pub fn cancellation(&mut self, token: CancellationToken) -> &mut SearcherBuilder {
+        self.config.cancellation = Some(token);
+        self
+    }
+
+    /// Adds a decoder for inputs of a format rgrep does not know. Decoders are asked in the order they were
+    /// added, and the first one that accepts an input decodes it.
+    pub fn decoder(&mut self, decoder: Arc<dyn InputDecoder>) -> &mut SearcherBuilder {
+        self.config.decoders.push(decoder);
+        self
+    }
+
+    /// Reports every input searched, and every one that could not be, to `metrics`
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut SearcherBuilder {
+        self.config.metrics = Some(metrics);
+        self
+    }
+}
+
+/// Whether to go on with the lines of an input
+#[derive(PartialEq, Eq)]
+pub(super) enum Flow {
+    Continue,
+    Stop,
+}
+
+/// A line of an input and whether it matched
+pub(super) struct Line<'a> {
+    pub(super) number: usize,
+    /// Offset of the first byte of the line in the decoded input
+    pub(super) offset: u64,
+    /// The line without its terminator
+    pub(super) text: &'a [u8],
+    /// Length of the line including its terminator
+    pub(super) length: usize,
+    pub(super) is_match: bool,
+    /// Only the start of the line is in `text`, the rest of it was over the memory limit and skipped
+    pub(super) truncated: bool,
+}
+
+/// The lines of one input, each with whether it matched
+enum Lines<R: BufRead> {
+    /// Lines are matched one at a time as they are read
+    Stream { reader: R, buffer: Vec<u8>, max_memory: Option<u64> },
+    /// The whole input was matched at once; `matched` marks every line that a match touches
+    Whole { buffer: Vec<u8>, starts: Vec<usize>, matched: Vec<bool> },
+}
+
+pub(super) struct LineReader<R: BufRead> {
+    lines: Lines<R>,
+    /// Number of lines returned so far
+    count: usize,
+    /// Offset of the next line
+    offset: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn stream(reader: R, max_memory: Option<u64>) -> LineReader<R> {
+        LineReader { lines: Lines::Stream { reader, buffer: Vec::new(), max_memory }, count: 0, offset: 0 }
+    }
+
+    /// Reads the whole input and matches it at once, so matches can span lines
+    pub(super) fn whole(mut reader: R, searcher: &Searcher, matcher: &dyn Matcher, name: &str) -> Result<LineReader<R>> {
+        let mut buffer = Vec::new();
+        let limit = searcher.max_memory.unwrap_or(u64::MAX);
+        reader.by_ref().take(limit).read_to_end(&mut buffer).map_err(|err| Error::read(name, err))?;
+        if buffer.len() as u64 == limit && !reader.fill_buf().map_err(|err| Error::read(name, err))?.is_empty() {
+            return Err(over_memory(name, "the input is", limit));
+        }
+
+        // offset where every line starts
+        let mut starts = vec![0];
+        starts.extend(buffer.iter().enumerate().filter(|(_, byte)| **byte == b'\n').map(|(index, _)| index + 1));
+        if starts.last() == Some(&buffer.len()) {
+            // the input ends with a line terminator, not with an empty line
+            starts.pop();
+        }
+        let line_of = |offset: usize| starts.partition_point(|start| *start <= offset).saturating_sub(1);
+
+        let mut matched = vec![false; starts.len()];
+        let mut at = 0;
+        while let Some(found) = matcher.find_at(&buffer, at) {
+            if searcher.cancelled() {
+                return Err(Error::Interrupted);
+            }
+            if starts.is_empty() || found.start == buffer.len() && buffer.ends_with(b"\n") {
+                // an empty match after the last line terminator is not on any line
+                break;
+            }
+            let last = if found.is_empty() { found.start } else { found.end - 1 };
+            for flag in &mut matched[line_of(found.start)..=line_of(last)] {
+                *flag = true;
+            }
+            if matcher.gave_up() {
+                return Err(gave_up(name, &format!("line {}", line_of(found.start) + 1)));
+            }
+            at = if found.is_empty() { found.end + 1 } else { found.end };
+            if at > buffer.len() {
+                break;
+            }
+        }
+        if matcher.gave_up() {
+            return Err(gave_up(name, "the input"));
+        }
+        Ok(LineReader { lines: Lines::Whole { buffer, starts, matched }, count: 0, offset: 0 })
+    }
+
+    pub(super) fn next(&mut self, matcher: &dyn Matcher, name: &str) -> Result<Option<Line<'_>>> {
+        let (text, length, is_match, truncated) = match &mut self.lines {
+            Lines::Stream { reader, buffer, max_memory } => {
+                buffer.clear();
+                let limit = max_memory.unwrap_or(u64::MAX);
+                let read = reader.by_ref().take(limit).read_until(b'\n', buffer);
+                let length = read.map_err(|err| Error::read(name, err))?;
+                if length == 0 {
+                    return Ok(None);
+                }
+                let cut = length as u64 == limit && !buffer.ends_with(b"\n");
+                let truncated = cut && !reader.fill_buf().map_err(|err| Error::read(name, err))?.is_empty();
+                // the rest of the line is not held, only gone through to find where the next one starts
+                let mut skipped = 0;
+                if truncated {
+                    skipped = reader.skip_until(b'\n').map_err(|err| Error::read(name, err))?;
+                }
+                let text = if truncated { &buffer[..] } else { trim_line_end(buffer) };
+                (text, length + skipped, matcher.is_match(text), truncated)
+            }
+            Lines::Whole { buffer, starts, matched } => {
+                let start = match starts.get(self.count) {
+                    Some(start) => *start,
+                    None => return Ok(None),
+                };
+                let end = starts.get(self.count + 1).copied().unwrap_or(buffer.len());
+                (trim_line_end(&buffer[start..end]), end - start, matched[self.count], false)
+            }
+        };
+        self.count += 1;
+        let line = Line { number: self.count, offset: self.offset, text, length, is_match, truncated };
+        self.offset += length as u64;
+        Ok(Some(line))
+    }
+}
+
+/// The error of an input of which `what` needs more memory than the `limit` of [`SearcherBuilder::max_memory`]
+pub(super) fn over_memory(name: &str, what: &str, limit: u64) -> Error {
+    let message = format!("{} larger than the memory limit of {} bytes", what, limit);
+    Error::read(name, io::Error::new(io::ErrorKind::OutOfMemory, message))
+}
+
+/// The warning about line `number` of an input, of which only the first `limit` bytes of
+/// [`SearcherBuilder::max_memory`] were searched
+pub(super) fn truncated(name: &str, number: usize, limit: u64) -> Error {
+    let message =
+        format!("line {} is larger than the memory limit of {} bytes, only its start was searched", number, limit);
+    Error::read(name, io::Error::new(io::ErrorKind::OutOfMemory, message))
+}
+
+/// The error of an input the matcher [gave up](Matcher::gave_up) on at `what`
+pub(super) fn gave_up(name: &str, what: &str) -> Error {
+    let message = format!("backreference search too expensive on {}", what);
+    Error::read(name, io::Error::other(message))
+}
+
+/// Removes the line terminator, `\n` or `\r\n`
+pub(super) fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Whether a line has nothing but white space, so it is between paragraphs
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Passes matching lines to a callback and remembers what it answered
+struct Callback<F> {
+    callback: F,
+    control: ControlFlow<Stop>,
+}
+
+impl<F: FnMut(&SinkLine) -> ControlFlow<Stop>> Sink for Callback<F> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.control = (self.callback)(line);
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Decides which lines of an input go to the sink, as they are fed to it in order
+pub(super) struct LineState<'s> {
+    searcher: &'s Searcher,
+    pub(super) name: String,
+    /// Lines that may become context of a later match: number, offset and text
+    before: VecDeque<(usize, u64, Vec<u8>)>,
+    after_remaining: usize,
+    /// The blocks lines are in, when enclosing lines are reported
+    enclosing: Option<Enclosing>,
+    /// Number of the last line that went to the sink
+    last_reported: usize,
+    pub(super) summary: FileSummary,
+}
+
+impl<'s> LineState<'s> {
+    pub(super) fn new(searcher: &'s Searcher, name: &str) -> LineState<'s> {
+        LineState {
+            searcher,
+            name: String::from(name),
+            before: VecDeque::with_capacity(searcher.before_context),
+            after_remaining: 0,
+            enclosing: if searcher.show_function { Some(Enclosing::new(name)) } else { None },
+            last_reported: 0,
+            summary: FileSummary::default(),
+        }
+    }
+
+    pub(super) fn line(&mut self, sink: &mut dyn Sink, line: &Line) -> Result<Flow> {
+        let name = self.name.as_str();
+        if self.searcher.binary != BinaryDetection::Text && !self.summary.binary && line.text.contains(&0) {
+            debug!("{} contains binary data at line {}", name, line.number);
+            self.summary.binary = true;
+            if self.searcher.binary == BinaryDetection::Skip {
+                return Ok(Flow::Stop);
+            }
+        }
+        if self.searcher.stop_on_nonmatch && !line.is_match && self.summary.matched_lines > 0 {
+            trace!("{}:{} does not match, so the search of it stops", name, line.number);
+            return Ok(Flow::Stop);
+        }
+        if let Some(enclosing) = &mut self.enclosing {
+            enclosing.advance(line.text);
+        }
+        if line.is_match {
+            self.summary.matched_lines += 1;
+            trace!("{}:{} matched", name, line.number);
+            if self.summary.binary {
+                sink.binary_match(name).map_err(Error::write)?;
+                return Ok(Flow::Stop);
+            }
+            // the enclosing line is only reported if it is not printed as a line of its own anyway
+            let first = self.before.front().map_or(line.number, |(number, _, _)| *number);
+            let block = self.enclosing.as_ref().and_then(Enclosing::enclosing);
+            if let Some(block) = block.filter(|block| block.number > self.last_reported && block.number < first) {
+                let (line_number, offset) = (block.number, block.offset);
+                let enclosing = SinkLine { path: name, line_number, offset, line: &block.text };
+                sink.enclosing(&enclosing).map_err(Error::write)?;
+            }
+            for (number, offset, text) in self.before.drain(..) {
+                let context = SinkLine { path: name, line_number: number, offset, line: &text };
+                sink.context(&context).map_err(Error::write)?;
+            }
+            let matched = SinkLine { path: name, line_number: line.number, offset: line.offset, line: line.text };
+            sink.matched(&matched).map_err(Error::write)?;
+            // with paragraphs, up to the next blank line
+            self.after_remaining = if self.searcher.paragraph { usize::MAX } else { self.searcher.after_context };
+            self.last_reported = line.number;
+        } else if self.searcher.paragraph && is_blank(line.text) {
+            self.after_remaining = 0;
+            self.before.clear();
+        } else if self.after_remaining > 0 && !self.summary.binary {
+            let context = SinkLine { path: name, line_number: line.number, offset: line.offset, line: line.text };
+            sink.context(&context).map_err(Error::write)?;
+            self.after_remaining -= 1;
+            self.last_reported = line.number;
+        } else if self.searcher.paragraph {
+            self.before.push_back((line.number, line.offset, line.text.to_vec()));
+        } else if self.searcher.before_context > 0 {
+            if self.before.len() == self.searcher.before_context {
+                self.before.pop_front();
+            }
+            self.before.push_back((line.number, line.offset, line.text.to_vec()));
+        }
+        if let Some(enclosing) = &mut self.enclosing {
+            enclosing.push(line.number, line.offset, line.text);
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+/// The search of one input, advanced one line at a time
+pub(crate) struct InputSearch<'s, 'r> {
+    state: LineState<'s>,
+    lines: LineReader<BufReader<DecodeReader<Box<dyn Read + 'r>>>>,
+    done: bool,
+}
+
+impl<'s, 'r> InputSearch<'s, 'r> {
+    /// Starts searching `reader`. Like grep, only its first block is checked for binary data up front.
+    fn start<R: Read + 'r>(
+        searcher: &'s Searcher,
+        matcher: &dyn Matcher,
+        reader: R,
+        name: &str,
+    ) -> Result<InputSearch<'s, 'r>> {
+        let reader = searcher.plugin_decode(reader, name).map_err(|err| Error::read(name, err))?;
+        let mut reader = BufReader::new(DecodeReader::new(reader, searcher.encoding));
+        let mut state = LineState::new(searcher, name);
+        let mut done = false;
+        if searcher.binary != BinaryDetection::Text {
+            let first = reader.fill_buf().map_err(|err| Error::read(name, err))?;
+            if first.contains(&0) {
+                debug!("{} contains binary data", name);
+                state.summary.binary = true;
+                done = searcher.binary == BinaryDetection::Skip;
+            }
+        }
+        if reader.get_ref().encoding() != Encoding::Utf8 {
+            debug!("{} decoded as {}", name, reader.get_ref().encoding().name());
+        }
+        let lines = if searcher.multi_line && !done {
+            LineReader::whole(reader, searcher, matcher, name)?
+        } else {
+            LineReader::stream(reader, searcher.max_memory)
+        };
+        Ok(InputSearch { state, lines, done })
+    }
+
+    /// Handles the next line, returns false once the input is done
+    pub(crate) fn step(&mut self, matcher: &dyn Matcher, sink: &mut dyn Sink) -> Result<bool> {
+        if self.done {
+            return Ok(false);
+        }
+        if self.state.searcher.cancelled() {
+            self.state.searcher.interrupted(&self.state.name, &self.state.summary);
+            return Err(Error::Interrupted);
+        }
+        let line = match self.lines.next(matcher, &self.state.name)? {
+            Some(line) => line,
+            None => {
+                debug!("read {} lines from {}, matched lines: {}", self.lines.count, self.state.name, self.state.summary.matched_lines);
+                self.done = true;
+                return Ok(false);
+            }
+        };
+        self.state.summary.bytes_searched += line.length as u64;
+        self.state.summary.lines_searched += 1;
+        let number = line.number;
+        if line.truncated {
+            let limit = self.state.searcher.max_memory.unwrap_or(u64::MAX);
+            sink.error(&truncated(&self.state.name, number, limit)).map_err(Error::write)?;
+        }
+        if self.state.line(sink, &line)? == Flow::Stop {
+            self.done = true;
+        }
+        // the line is matched again to be shown, which may give up where deciding that it matches did not
+        if matcher.gave_up() {
+            return Err(gave_up(&self.state.name, &format!("line {}", number)));
+        }
+        Ok(!self.done)
+    }
+
+    pub(crate) fn summary(&self) -> &FileSummary {
+        &self.state.summary
+    }
+
+    /// Name of the input, as it was given
+    pub(crate) fn name(&self) -> &str {
+        &self.state.name
+    }
+}
+
+impl Searcher {
+    pub fn after_context(&self) -> usize {
+        self.after_context
+    }
+
+    pub fn before_context(&self) -> usize {
+        self.before_context
+    }
+
+    pub fn binary_detection(&self) -> BinaryDetection {
+        self.binary
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn multi_line(&self) -> bool {
+        self.multi_line
+    }
+
+    pub fn paragraph(&self) -> bool {
+        self.paragraph
+    }
+
+    pub fn show_function(&self) -> bool {
+        self.show_function
+    }
+
+    pub fn stop_on_nonmatch(&self) -> bool {
+        self.stop_on_nonmatch
+    }
+
+    pub fn max_memory(&self) -> Option<u64> {
+        self.max_memory
+    }
+
+    /// Whether the search should stop, because of Ctrl-C or the cancellation token
+    pub(super) fn cancelled(&self) -> bool {
+        signal::interrupted() || self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Reports how the search of the input called `name` ended to the metrics, if there are any
+    pub(crate) fn report(&self, name: &str, result: Result<FileSummary>) -> Result<FileSummary> {
+        match result {
+            Ok(summary) => {
+                self.searched(name, &summary);
+                Ok(summary)
+            }
+            Err(err) => Err(self.failed(err)),
+        }
+    }
+
+    /// Reports the input called `name`, searched, to the metrics
+    pub(crate) fn searched(&self, name: &str, summary: &FileSummary) {
+        if let Some(metrics) = &self.metrics {
+            metrics.searched(name, summary);
+        }
+    }
+
+    /// Reports the input called `name`, stopped after it came to `summary`, to the metrics
+    pub(crate) fn interrupted(&self, name: &str, summary: &FileSummary) {
+        if let Some(metrics) = &self.metrics {
+            metrics.interrupted(name, summary);
+        }
+    }
+
+    /// Reports `error` to the metrics if it is one of an input, and returns it
+    pub(crate) fn failed(&self, error: Error) -> Error {
+        if let (Some(metrics), Error::Io { path: Some(_), .. }) = (&self.metrics, &error) {
+            metrics.failed(&error);
+        }
+        error
+    }
+
+    /// Searches the file at `path` and reports its matching lines, and their context, to `sink`.
+    /// Returns what was found. Errors reading the file are returned, not reported to the sink.
+    pub fn search_path(&self, matcher: &dyn Matcher, path: &Path, sink: &mut dyn Sink) -> Result<FileSummary> {
+        let name = path.to_string_lossy();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => return Err(self.failed(Error::read(&name, err))),
+        };
+        let summary = self.search_reader(matcher, file, &name, sink);
+        sink.flush().map_err(Error::write)?;
+        summary
+    }
+
+    /// Iterates over the matching lines of a file or reader, for callers that prefer pulling results
+    /// to implementing a [`Sink`]. Context lines are not returned; neither are matches in binary data,
+    /// unless binary detection is [`BinaryDetection::Text`].
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::search::SearcherBuilder;
+    ///
+    /// let matcher = matcher::build(&[String::from("fn \\w+")], &MatcherOptions::default()).unwrap();
+    /// let searcher = SearcherBuilder::new().build();
+    /// for found in searcher.matches(matcher.as_ref(), Path::new("src/lib.rs")) {
+    ///     let found = found.unwrap();
+    ///     println!("{}:{}", found.line_number, found.text());
+    /// }
+    /// ```
+    pub fn matches<'a>(&'a self, matcher: &'a dyn Matcher, input: impl Into<Input<'a>>) -> Matches<'a> {
+        Matches::new(self, matcher, input.into())
+    }
+
+    /// Searches the inputs in order and calls `callback` for every matching line. The callback decides
+    /// whether to go on, skip the rest of the input or stop altogether, e.g. to find the first match anywhere:
+    ///
+    /// ```no_run
+    /// use std::ops::ControlFlow;
+    /// use std::path::Path;
+    ///
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::search::{Input, SearcherBuilder, Stop};
+    ///
+    /// let matcher = matcher::build(&[String::from("unsafe")], &MatcherOptions::default()).unwrap();
+    /// let inputs = ["src/lib.rs", "src/main.rs"].iter().map(|path| Input::from(Path::new(path)));
+    /// let mut first = None;
+    /// SearcherBuilder::new().build().for_each_match(matcher.as_ref(), inputs, |line| {
+    ///     first = Some((line.path.to_string(), line.line_number));
+    ///     ControlFlow::Break(Stop::Search)
+    /// }).unwrap();
+    /// ```
+    ///
+    /// Context lines and matches in binary data are not passed to the callback. The first input that cannot
+    /// be read stops the search with its error.
+    pub fn for_each_match<'a, I, F>(&self, matcher: &dyn Matcher, inputs: I, callback: F) -> Result<()>
+    where
+        I: IntoIterator<Item = Input<'a>>,
+        F: FnMut(&SinkLine) -> ControlFlow<Stop>,
+    {
+        let mut sink = Callback { callback, control: ControlFlow::Continue(()) };
+        for input in inputs {
+            let (name, reader) = input.open().map_err(|err| self.failed(err))?;
+            let mut search = self.start(matcher, reader, &name).map_err(|err| self.failed(err))?;
+            loop {
+                let more = search.step(matcher, &mut sink).map_err(|err| self.failed(err))?;
+                match std::mem::replace(&mut sink.control, ControlFlow::Continue(())) {
+                    ControlFlow::Break(Stop::Search) => {
+                        debug!("callback stopped the search at {}", name);
+                        self.searched(&name, search.summary());
+                        return Ok(());
+                    }
+                    ControlFlow::Break(Stop::Input) => break,
+                    ControlFlow::Continue(()) if more => {}
+                    ControlFlow::Continue(()) => break,
+                }
+            }
+            self.searched(&name, search.summary());
+        }
+        Ok(())
+    }
+
+    /// Searches a buffer in memory like a file, with the same line numbers, context and multi-line matching,
+    /// and reports it to `sink` under `name`. The sink is not flushed.
+    ///
+    /// ```
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::search::SearcherBuilder;
+    /// use rgrep::sink::TextSink;
+    ///
+    /// let matcher = matcher::build(&[String::from("b")], &MatcherOptions::default()).unwrap();
+    /// let searcher = SearcherBuilder::new().context(1).build();
+    /// let mut sink = TextSink::new(Vec::new(), false, true);
+    /// let summary = searcher.search_slice(matcher.as_ref(), b"a\nb\nc\nd\n", "buffer", &mut sink).unwrap();
+    /// assert_eq!(summary.matched_lines, 1);
+    /// assert_eq!(sink.into_inner(), b"a\nb\nc\n");
+    /// ```
+    pub fn search_slice(
+        &self,
+        matcher: &dyn Matcher,
+        slice: &[u8],
+        name: &str,
+        sink: &mut dyn Sink,
+    ) -> Result<FileSummary> {
+        self.search_reader(matcher, slice, name, sink)
+    }
+
+    /// Starts the search of one input, which is then advanced with [`InputSearch::step`]
+    pub(crate) fn start<'r, R: Read + 'r>(
+        &self,
+        matcher: &dyn Matcher,
+        reader: R,
+        name: &str,
+    ) -> Result<InputSearch<'_, 'r>> {
+        InputSearch::start(self, matcher, reader, name)
+    }
+
+    /// Hands `reader` to the first registered decoder that accepts it, if any
+    fn plugin_decode<'r, R: Read + 'r>(&self, reader: R, name: &str) -> io::Result<Box<dyn Read + 'r>> {
+        if self.decoders.is_empty() {
+            return Ok(Box::new(reader));
+        }
+        let mut reader = BufReader::new(reader);
+        let start = reader.fill_buf()?;
+        match self.decoders.iter().find(|decoder| decoder.accepts(name, start)) {
+            Some(decoder) => {
+                debug!("{} decoded by {}", name, decoder.name());
+                decoder.decode(Box::new(reader))
+            }
+            None => Ok(Box::new(reader)),
+        }
+    }
+
+    /// Searches anything that can be read, e.g. a socket or a decompressing reader, with the same semantics as
+    /// a file, and reports it to `sink` under `name`, from `begin` to `end`. The reader is read until its end,
+    /// unless the search stops before. The sink is not flushed.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::search::SearcherBuilder;
+    /// use rgrep::sink::CountSink;
+    ///
+    /// let matcher = matcher::build(&[String::from("b")], &MatcherOptions::default()).unwrap();
+    /// let reader = Cursor::new(b"a\nb\nab\n".to_vec());
+    /// let mut sink = CountSink::new(Vec::new(), true);
+    /// SearcherBuilder::new().build().search_reader(matcher.as_ref(), reader, "stream", &mut sink).unwrap();
+    /// assert_eq!(sink.into_inner(), b"stream:2\n");
+    /// ```
+    pub fn search_reader<R: Read>(
+        &self,
+        matcher: &dyn Matcher,
+        reader: R,
+        name: &str,
+        sink: &mut dyn Sink,
+    ) -> Result<FileSummary> {
+        sink.begin(name).map_err(Error::write)?;
+        let summary = self.start(matcher, reader, name).and_then(|mut search| {
+            while search.step(matcher, sink)? {}
+            Ok(search.summary().clone())
+        });
+        let summary = self.report(name, summary)?;
+        sink.end(name, &summary).map_err(Error::write)?;
+        Ok(summary)
+    }
+}
@@ -0,0 +1,101 @@
+//! `--path-separator SEPARATOR`: the paths in the results with another separator between their components, like
+//! `/` on Windows for tools that expect it, or `\` for those of Windows reading the results of a Unix shell.
+//!
+//! Only printed paths change; inputs are still opened, and errors still name them, as they were given.
+
+use std::io;
+
+use super::Stats;
+use crate::error::Error;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+/// Passes results on to another sink with `separator` between the components of their paths
+pub(crate) struct PathSeparatorSink<S: Sink> {
+    inner: S,
+    separator: char,
+    /// The current path, and what it is with the separator
+    renamed: (String, String),
+}
+
+impl<S: Sink> PathSeparatorSink<S> {
+    pub(crate) fn new(inner: S, separator: char) -> PathSeparatorSink<S> {
+        PathSeparatorSink { inner, separator, renamed: (String::new(), String::new()) }
+    }
+
+    /// `path` with the separator, which is the same for all the lines of an input, so it is kept
+    fn rename(&mut self, path: &str) -> &str {
+        if self.renamed.0 != path {
+            let separator = self.separator;
+            // Windows takes both
+            let renamed = path.chars().map(|c| if c == '/' || cfg!(windows) && c == '\\' { separator } else { c });
+            self.renamed = (String::from(path), renamed.collect());
+        }
+        &self.renamed.1
+    }
+
+    fn renamed_line<'a>(&'a mut self, line: &SinkLine<'a>) -> (&'a mut S, SinkLine<'a>) {
+        self.rename(line.path);
+        (&mut self.inner, SinkLine { path: &self.renamed.1, ..*line })
+    }
+}
+
+impl<S: Sink> Sink for PathSeparatorSink<S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.rename(path);
+        self.inner.begin(&self.renamed.1)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        let (inner, line) = self.renamed_line(line);
+        inner.matched(&line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        let (inner, line) = self.renamed_line(line);
+        inner.context(&line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        let (inner, line) = self.renamed_line(line);
+        inner.enclosing(&line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.rename(path);
+        self.inner.binary_match(&self.renamed.1)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.rename(path);
+        self.inner.end(&self.renamed.1, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.rename(path);
+        self.inner.replaced(&self.renamed.1, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        let mut stats = stats.clone();
+        for file in &mut stats.files {
+            file.path = String::from(self.rename(&file.path));
+        }
+        self.inner.stats(&stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        let mut stats = stats.clone();
+        for file in &mut stats.files {
+            file.path = String::from(self.rename(&file.path));
+        }
+        self.inner.finish(&stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,257 @@
+//! `--only`: restricts matches to the comments, the string literals or the rest of source files.
+//!
+//! Every language is described by its comment and string delimiters, which is enough to tell the parts apart
+//! without parsing. The parts that are not searched are blanked out, keeping line ends and the offset of every
+//! byte, and the lines are printed as they are in the file.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{Encoding, Searcher};
+use crate::error::{Error, Result};
+use crate::matcher::Matcher;
+use crate::sink::{FileSummary, Sink, SinkLine};
+
+/// The part of source files `--only` searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Comments,
+    Strings,
+    /// Everything but comments and strings
+    Code,
+}
+
+impl Region {
+    /// Names accepted by `--only`
+    pub const NAMES: &'static [&'static str] = &["comments", "strings", "code"];
+
+    pub fn from_name(name: &str) -> Option<Region> {
+        Some(match name {
+            "comments" => Region::Comments,
+            "strings" => Region::Strings,
+            "code" => Region::Code,
+            _ => return None,
+        })
+    }
+}
+
+/// Comment and string delimiters of a language
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+    /// Opening and closing quotes; a backslash escapes the next character in all of them
+    strings: &'static [(&'static str, &'static str)],
+    /// `'x'` is a character literal, but a `'` without a closing one close by is not a string, e.g. a lifetime
+    char_literals: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &[("\"", "\"")],
+    char_literals: true,
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &[("\"", "\""), ("'", "'"), ("`", "`")],
+    char_literals: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    strings: &[("\"\"\"", "\"\"\""), ("'''", "'''"), ("\"", "\""), ("'", "'")],
+    char_literals: false,
+};
+
+const HASH_COMMENTS: Syntax =
+    Syntax { line_comments: &["#"], block_comments: &[], strings: &[("\"", "\""), ("'", "'")], char_literals: false };
+
+const DASH_COMMENTS: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("/*", "*/")],
+    strings: &[("'", "'"), ("\"", "\"")],
+    char_literals: false,
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    strings: &[("[[", "]]"), ("\"", "\""), ("'", "'")],
+    char_literals: false,
+};
+
+/// Quotes in text are not strings, so only comments are told apart
+const MARKUP: Syntax =
+    Syntax { line_comments: &[], block_comments: &[("<!--", "-->")], strings: &[], char_literals: false };
+
+const CSS: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("/*", "*/")],
+    strings: &[("\"", "\""), ("'", "'")],
+    char_literals: false,
+};
+
+impl Syntax {
+    /// The syntax of the language of files ending in `.extension`
+    fn of(extension: &str) -> Option<&'static Syntax> {
+        Some(match extension {
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "cs" | "java" | "go" | "rs" | "swift" | "kt" | "kts"
+            | "scala" | "dart" | "zig" | "proto" => &C_LIKE,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "php" => &JAVASCRIPT,
+            "py" | "pyi" => &PYTHON,
+            "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "pm" | "r" | "yml" | "yaml" | "toml" | "cmake" | "nix"
+            | "tf" | "ps1" => &HASH_COMMENTS,
+            "sql" | "hs" | "elm" | "ada" => &DASH_COMMENTS,
+            "lua" => &LUA,
+            "html" | "htm" | "xml" | "svg" | "vue" => &MARKUP,
+            "css" | "scss" | "less" => &CSS,
+            _ => return None,
+        })
+    }
+
+    /// Which region every byte of `text` is in
+    fn regions(&self, text: &[u8]) -> Vec<Region> {
+        let mut regions = vec![Region::Code; text.len()];
+        let mut at = 0;
+        while at < text.len() {
+            let rest = &text[at..];
+            // block comments first, so `--[[` is not taken for a line comment
+            let (region, len) = if let Some((open, close)) =
+                self.block_comments.iter().find(|(open, _)| rest.starts_with(open.as_bytes()))
+            {
+                (Region::Comments, closed_at(rest, open.len(), close, false))
+            } else if self.line_comments.iter().any(|open| rest.starts_with(open.as_bytes())) {
+                (Region::Comments, rest.iter().position(|&byte| byte == b'\n').unwrap_or(rest.len()))
+            } else if let Some((open, close)) = self.strings.iter().find(|(open, _)| rest.starts_with(open.as_bytes()))
+            {
+                (Region::Strings, closed_at(rest, open.len(), close, true))
+            } else if self.char_literals && rest[0] == b'\'' {
+                let literal = match rest.get(1) {
+                    Some(b'\\') => rest.iter().skip(2).take(10).position(|&byte| byte == b'\'').map(|end| end + 3),
+                    // one character, which takes up to 4 bytes
+                    Some(&first) => {
+                        let len = match first {
+                            0xf0..=0xff => 4,
+                            0xe0..=0xef => 3,
+                            0xc0..=0xdf => 2,
+                            _ => 1,
+                        };
+                        Some(len + 2).filter(|_| rest.get(1 + len) == Some(&b'\''))
+                    }
+                    None => None,
+                };
+                match literal {
+                    Some(len) => (Region::Strings, len),
+                    None => (Region::Code, 1),
+                }
+            } else {
+                (Region::Code, 1)
+            };
+            regions[at..at + len].fill(region);
+            at += len;
+        }
+        regions
+    }
+}
+
+/// Length of the comment or string at the start of `text`, whose opening delimiter is `open_len` bytes long,
+/// up to and with `close`, or up to the end if it is not closed
+fn closed_at(text: &[u8], open_len: usize, close: &str, escapes: bool) -> usize {
+    let mut at = open_len;
+    while at < text.len() {
+        if escapes && text[at] == b'\\' {
+            at += 2;
+        } else if text[at..].starts_with(close.as_bytes()) {
+            return at + close.len();
+        } else {
+            at += 1;
+        }
+    }
+    text.len()
+}
+
+/// `text` with everything outside of `region` replaced by spaces, except line ends
+fn mask(text: &[u8], syntax: &Syntax, region: Region) -> Vec<u8> {
+    let regions = syntax.regions(text);
+    text.iter()
+        .zip(regions)
+        .map(|(&byte, in_region)| if in_region == region || byte == b'\n' || byte == b'\r' { byte } else { b' ' })
+        .collect()
+}
+
+/// Passes lines on as they are in the file rather than as they were searched
+struct OriginalLines<'s> {
+    inner: &'s mut dyn Sink,
+    original: &'s [u8],
+}
+
+/// `line` of the masked text as it is in `original`
+fn original<'a>(original: &'a [u8], line: &SinkLine<'a>) -> SinkLine<'a> {
+    let start = line.offset as usize;
+    SinkLine { line: &original[start..start + line.line.len()], ..*line }
+}
+
+impl Sink for OriginalLines<'_> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = original(self.original, line);
+        self.inner.matched(&line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = original(self.original, line);
+        self.inner.context(&line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = original(self.original, line);
+        self.inner.enclosing(&line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+}
+
+/// Searches only `region` of the source file `path`, or returns `None` if rgrep does not know its language or
+/// it is not UTF-8, so it is searched as a whole
+pub(crate) fn search(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    region: Region,
+    path: &str,
+    sink: &mut dyn Sink,
+) -> Option<Result<FileSummary>> {
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let syntax = Syntax::of(&extension)?;
+    // decoding would move the lines away from where they are in the file
+    if !matches!(searcher.encoding(), Encoding::Auto | Encoding::Utf8) {
+        return None;
+    }
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) => return Some(Err(searcher.failed(Error::read(path, err)))),
+    };
+    if contents.starts_with(&[0xff, 0xfe]) || contents.starts_with(&[0xfe, 0xff]) {
+        return None;
+    }
+    let contents = contents.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(&contents);
+    let masked = mask(contents, syntax, region);
+    let mut sink = OriginalLines { inner: sink, original: contents };
+    Some(searcher.search_slice(matcher, &masked, path, &mut sink))
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit status after an interrupt, following the shell convention of 128 + SIGINT
+pub const INTERRUPTED_STATUS: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C was pressed. Searches check it between lines and stop early.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Called from the signal handler. The first interrupt asks the search to stop cleanly;
+/// a second one exits right away, in case the search is stuck in a blocking read.
+fn on_interrupt(exit_now: fn()) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        exit_now();
+    }
+}
+
+#[cfg(unix)]
+pub fn install() {
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    extern "C" fn handler(_: c_int) {
+        // only async-signal-safe work here: an atomic swap and _exit
+        on_interrupt(|| unsafe { _exit(INTERRUPTED_STATUS) });
+    }
+
+    // SAFETY: the handler only touches an atomic and calls _exit, both async-signal-safe
+    unsafe {
+        signal(SIGINT, handler);
+    }
+}
+
+#[cfg(windows)]
+pub fn install() {
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+    }
+
+    extern "system" fn handler(_: u32) -> i32 {
+        on_interrupt(|| std::process::exit(INTERRUPTED_STATUS));
+        // handled, do not run the default handler that terminates the process
+        1
+    }
+
+    // SAFETY: registering a handler function that lives for the whole program
+    unsafe {
+        SetConsoleCtrlHandler(handler, 1);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn install() {}
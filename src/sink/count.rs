@@ -0,0 +1,163 @@
+use std::io::{self, Write};
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+
+/// Prints the number of matching lines of every input, like `grep -c`
+pub struct CountSink<W: Write> {
+    out: W,
+    /// Print `name:count` instead of just the count
+    with_filename: bool,
+    /// Print counts of 0 as well
+    include_zero: bool,
+}
+
+impl<W: Write> CountSink<W> {
+    pub fn new(out: W, with_filename: bool) -> CountSink<W> {
+        CountSink { out, with_filename, include_zero: true }
+    }
+
+    /// Leaves out the inputs without matching lines, so only the ones with matches are listed
+    pub fn without_zero(mut self) -> CountSink<W> {
+        self.include_zero = false;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Sink for CountSink<W> {
+    fn matched(&mut self, _line: &SinkLine) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        if summary.matched_lines == 0 && !self.include_zero {
+            return Ok(());
+        }
+        if self.with_filename {
+            write!(self.out, "{}:", path)?;
+        }
+        writeln!(self.out, "{}", summary.matched_lines)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Lines and matches found by one pattern
+#[derive(Debug, Clone, Copy, Default)]
+struct PatternCount {
+    lines: u64,
+    matches: u64,
+}
+
+/// Prints how many lines every pattern matched in every input, and how many matches it had in them, for
+/// `--count-per-pattern`. Each pattern is counted on its own, so matches of different patterns can overlap.
+///
+/// Every pattern gets a line `[PATH:]LINES:MATCHES:PATTERN`, the pattern last since it can contain `:`. With
+/// several inputs, lines starting with `(total):` follow with the counts of all of them.
+pub struct PatternCountSink<'m, W: Write> {
+    out: W,
+    /// Every pattern as it was given, with a matcher for it alone
+    patterns: Vec<(&'m str, Box<dyn Matcher>)>,
+    with_filename: bool,
+    /// Print the counts of inputs no pattern matched in as well
+    include_zero: bool,
+    /// Counts of the current input
+    counts: Vec<PatternCount>,
+    /// Counts of the inputs since the totals were printed last
+    totals: Vec<PatternCount>,
+    /// Inputs ended since the totals were printed last
+    inputs: u64,
+}
+
+impl<'m, W: Write> PatternCountSink<'m, W> {
+    pub fn new(out: W, patterns: Vec<(&'m str, Box<dyn Matcher>)>, with_filename: bool) -> PatternCountSink<'m, W> {
+        let counts = vec![PatternCount::default(); patterns.len()];
+        PatternCountSink { out, patterns, with_filename, include_zero: true, totals: counts.clone(), counts, inputs: 0 }
+    }
+
+    /// Leaves out the inputs no pattern matched in; they still count towards the totals
+    pub fn without_zero(mut self) -> PatternCountSink<'m, W> {
+        self.include_zero = false;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write_counts(&mut self, prefix: Option<&str>, counts: &[PatternCount]) -> io::Result<()> {
+        for ((pattern, _), count) in self.patterns.iter().zip(counts) {
+            if let Some(prefix) = prefix {
+                write!(self.out, "{}:", prefix)?;
+            }
+            writeln!(self.out, "{}:{}:{}", count.lines, count.matches, pattern)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the totals of the inputs ended since they were printed last, if there are several
+    fn write_totals(&mut self) -> io::Result<()> {
+        let totals = std::mem::replace(&mut self.totals, vec![PatternCount::default(); self.patterns.len()]);
+        if self.with_filename && self.inputs > 0 {
+            self.write_counts(Some("(total)"), &totals)?;
+        }
+        self.inputs = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for PatternCountSink<'_, W> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        for ((_, matcher), count) in self.patterns.iter().zip(&mut self.counts) {
+            let matches = matcher::find_iter(matcher.as_ref(), line.line).count() as u64;
+            if matches > 0 {
+                count.lines += 1;
+                count.matches += matches;
+            }
+        }
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self, path: &str, _summary: &FileSummary) -> io::Result<()> {
+        let counts = std::mem::replace(&mut self.counts, vec![PatternCount::default(); self.patterns.len()]);
+        for (total, count) in self.totals.iter_mut().zip(&counts) {
+            total.lines += count.lines;
+            total.matches += count.matches;
+        }
+        self.inputs += 1;
+        if !self.include_zero && counts.iter().all(|count| count.lines == 0) {
+            return Ok(());
+        }
+        let prefix = if self.with_filename { Some(path) } else { None };
+        self.write_counts(prefix, &counts)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.write_totals()?;
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_totals()?;
+        self.out.flush()
+    }
+}
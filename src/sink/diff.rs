@@ -0,0 +1,235 @@
+use std::io::{self, Write};
+
+use super::{Sink, SinkLine};
+use crate::search::Stats;
+
+/// Unchanged lines printed around every change
+const CONTEXT: usize = 3;
+
+/// Prints the replacements `--write-replace --dry-run` would write as a unified diff, one per file, that
+/// `patch -p0` applies. Matching lines are not printed.
+pub struct DiffSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> DiffSink<W> {
+    pub fn new(out: W) -> DiffSink<W> {
+        DiffSink { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Sink for DiffSink<W> {
+    fn matched(&mut self, _line: &SinkLine) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        write_diff(&mut self.out, path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Writes the changes from `original` to `replaced` as a unified diff of the file at `path`
+fn write_diff<W: Write>(out: &mut W, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+    let old = lines(original);
+    let new = lines(replaced);
+    let edits = edits(&old, &new);
+    if edits.iter().all(|&edit| edit == Edit::Keep) {
+        return Ok(());
+    }
+    writeln!(out, "--- {}", path)?;
+    writeln!(out, "+++ {}", path)?;
+
+    // line in `old` and `new` every edit starts at
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut at_old, mut at_new) = (0, 0);
+    for edit in &edits {
+        positions.push((at_old, at_new));
+        match edit {
+            Edit::Keep => {
+                at_old += 1;
+                at_new += 1;
+            }
+            Edit::Remove => at_old += 1,
+            Edit::Add => at_new += 1,
+        }
+    }
+    positions.push((at_old, at_new));
+
+    let mut start = 0;
+    while let Some(first) = edits[start..]
+        .iter()
+        .position(|&edit| edit != Edit::Keep)
+        .map(|first| start + first)
+    {
+        // a hunk goes on while the next change is close enough for their context to touch
+        let mut last = first;
+        let mut next = first;
+        while next < edits.len() && next <= last + 2 * CONTEXT + 1 {
+            if edits[next] != Edit::Keep {
+                last = next;
+            }
+            next += 1;
+        }
+        let from = first.saturating_sub(CONTEXT);
+        let to = (last + 1 + CONTEXT).min(edits.len());
+        let (old_from, new_from) = positions[from];
+        let (old_to, new_to) = positions[to];
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(old_from, old_to - old_from),
+            range(new_from, new_to - new_from)
+        )?;
+        for index in from..to {
+            let (at_old, at_new) = positions[index];
+            let (prefix, line) = match edits[index] {
+                Edit::Keep => (b' ', old[at_old]),
+                Edit::Remove => (b'-', old[at_old]),
+                Edit::Add => (b'+', new[at_new]),
+            };
+            out.write_all(&[prefix])?;
+            out.write_all(line)?;
+            if !line.ends_with(b"\n") {
+                out.write_all(b"\n\\ No newline at end of file\n")?;
+            }
+        }
+        start = to;
+    }
+    Ok(())
+}
+
+/// Lines with their terminators
+fn lines(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|&byte| byte == b'\n').collect()
+}
+
+/// Start and length of a hunk side as the header says them: lines count from 1, and an empty side names the line
+/// before it
+fn range(from: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", from),
+        1 => format!("{}", from + 1),
+        _ => format!("{},{}", from + 1, len),
+    }
+}
+
+/// The shortest edits that turn `old` into `new`. Replacements keep the number of lines unless they match across
+/// lines, so when both sides have as many lines the changed ones are paired up; otherwise, Myers' algorithm finds
+/// them.
+fn edits(old: &[&[u8]], new: &[&[u8]]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut edits = vec![Edit::Keep; prefix];
+    if old_middle.len() == new_middle.len() {
+        // lines changed in a row are removed together, then added together
+        let mut changed = 0;
+        for (a, b) in old_middle.iter().zip(new_middle) {
+            if a == b {
+                edits.extend(changes(changed));
+                edits.push(Edit::Keep);
+                changed = 0;
+            } else {
+                changed += 1;
+            }
+        }
+        edits.extend(changes(changed));
+    } else {
+        edits.extend(myers(old_middle, new_middle));
+    }
+    edits.extend(std::iter::repeat_n(Edit::Keep, suffix));
+    edits
+}
+
+/// `lines` lines removed, then as many added
+fn changes(lines: usize) -> impl Iterator<Item = Edit> {
+    std::iter::repeat_n(Edit::Remove, lines).chain(std::iter::repeat_n(Edit::Add, lines))
+}
+
+/// Myers' O(ND) difference algorithm, keeping the furthest reaching paths of every step to walk back along
+fn myers(old: &[&[u8]], new: &[&[u8]]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let index = |k: isize| (k + offset) as usize;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(furthest.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+                furthest[index(k + 1)]
+            } else {
+                furthest[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest[index(previous_k)];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Add);
+                y -= 1;
+            } else {
+                edits.push(Edit::Remove);
+                x -= 1;
+            }
+        }
+    }
+    edits.reverse();
+    edits
+}
@@ -0,0 +1,101 @@
+use std::io;
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::error::Error;
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+
+/// Passes on the bytes around every match in matching lines instead of the whole lines, for `--context-bytes`.
+/// Every excerpt goes on as a matching line of its own, with the number of its line and its own offset. Excerpts
+/// that overlap are passed on as one, and they never cut a UTF-8 character in two. Context lines are passed on
+/// as they are.
+pub struct ExcerptSink<'m, S: Sink> {
+    inner: S,
+    matcher: &'m dyn Matcher,
+    /// Bytes kept before and after every match
+    bytes: usize,
+}
+
+impl<'m, S: Sink> ExcerptSink<'m, S> {
+    pub fn new(inner: S, matcher: &'m dyn Matcher, bytes: usize) -> ExcerptSink<'m, S> {
+        ExcerptSink { inner, matcher, bytes }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Whether `at` is the start of a UTF-8 character in `line`, or its end
+fn is_boundary(line: &[u8], at: usize) -> bool {
+    line.get(at).is_none_or(|byte| byte & 0xC0 != 0x80)
+}
+
+impl<S: Sink> Sink for ExcerptSink<'_, S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        // every excerpt, with where its first match starts
+        let mut excerpts: Vec<(usize, usize, usize)> = Vec::new();
+        for found in matcher::find_iter(self.matcher, line.line) {
+            let mut start = found.start.saturating_sub(self.bytes);
+            while !is_boundary(line.line, start) {
+                start += 1;
+            }
+            let mut end = found.end.saturating_add(self.bytes).min(line.line.len());
+            while !is_boundary(line.line, end) {
+                end -= 1;
+            }
+            match excerpts.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => excerpts.push((start, end, found.start)),
+            }
+        }
+        if excerpts.is_empty() {
+            return self.inner.matched(line);
+        }
+        for (start, end, first) in excerpts {
+            let offset = line.offset + start as u64;
+            self.inner.matched_part(&SinkLine { line: &line.line[start..end], offset, ..*line }, first + 1)?;
+        }
+        Ok(())
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.inner.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.inner.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
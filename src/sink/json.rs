@@ -0,0 +1,377 @@
+use std::io::{self, Write};
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::error::Error;
+use crate::matcher::{self, Captures, Match, Matcher};
+use crate::search::{FileStats, LineMatch, Stats};
+
+/// Version of the `--json` output, given as `"version"` in its summary event. Later versions only add to it:
+/// new event types, and new members in the data of events, which readers have to leave alone. The events and
+/// members that are there keep their names and meaning, and a change that would break that gets a new version.
+pub const JSON_VERSION: u32 = 1;
+
+/// The JSON form of a result type, the same one [`JsonSink`] prints, so programs that store or send results
+/// themselves stay compatible with `--json` output
+pub trait ToJson {
+    /// Writes the members of the object, without the braces around them
+    fn write_members(&self, out: &mut String);
+
+    fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        self.write_members(&mut json);
+        json.push('}');
+        json
+    }
+}
+
+/// `{"path":...,"lines":...,"line_number":N}`, the data of match and context events
+impl<'a> ToJson for SinkLine<'a> {
+    fn write_members(&self, out: &mut String) {
+        out.push_str(&format!(
+            "\"path\":{},\"lines\":{},\"line_number\":{}",
+            data(self.path.as_bytes()),
+            data(self.line),
+            self.line_number
+        ));
+    }
+}
+
+/// Like a [`SinkLine`], with `"submatches":[{"match":...,"start":N,"end":N}]` for the spans
+impl ToJson for LineMatch {
+    fn write_members(&self, out: &mut String) {
+        let line = SinkLine { path: &self.path, line_number: self.line_number, offset: self.offset, line: &self.line };
+        line.write_members(out);
+        write_submatches(&self.line, &self.spans, &[], false, false, out);
+    }
+}
+
+/// `,"submatches":[...]` for the spans in `line`, with `"start_utf16"` and `"end_utf16"` too if `utf16_columns`.
+/// `groups` has the groups of every span, or is empty to leave them out. Named groups are given by name, as
+/// `"captures":{"NAME":{"text":...}}`, with `null` for those that did not take part, and with `numbered` all the
+/// groups that took part are given as `"groups":[{"group":N,"name":...,"match":...}]` too.
+fn write_submatches(
+    line: &[u8],
+    spans: &[Match],
+    groups: &[Captures],
+    numbered: bool,
+    utf16_columns: bool,
+    out: &mut String,
+) {
+    let submatches: Vec<String> = spans
+        .iter()
+        .enumerate()
+        .map(|(index, span)| {
+            let mut submatch = format!("{{{}", span_members(line, *span, utf16_columns));
+            if let Some(groups) = groups.get(index) {
+                let named: Vec<String> = (1..groups.len())
+                    .filter_map(|group| Some((groups.names()[group].as_deref()?, groups.get(group))))
+                    .map(|(name, span)| {
+                        let text = span.map_or_else(|| String::from("null"), |span| data(&line[span.start..span.end]));
+                        format!("{}:{}", string(name), text)
+                    })
+                    .collect();
+                if !named.is_empty() {
+                    submatch.push_str(&format!(",\"captures\":{{{}}}", named.join(",")));
+                }
+                if numbered {
+                    let numbered: Vec<String> = (1..groups.len())
+                        .filter_map(|group| Some((group, groups.get(group)?)))
+                        .map(|(group, span)| {
+                            let name = groups.names()[group].as_deref().map_or_else(|| String::from("null"), string);
+                            let members = span_members(line, span, utf16_columns);
+                            format!("{{\"group\":{},\"name\":{},{}}}", group, name, members)
+                        })
+                        .collect();
+                    submatch.push_str(&format!(",\"groups\":[{}]", numbered.join(",")));
+                }
+            }
+            submatch.push('}');
+            submatch
+        })
+        .collect();
+    out.push_str(&format!(",\"submatches\":[{}]", submatches.join(",")));
+}
+
+/// `"match":...,"start":N,"end":N` for `span` of `line`, with its UTF-16 columns too if `utf16_columns`
+fn span_members(line: &[u8], span: Match, utf16_columns: bool) -> String {
+    let text = data(&line[span.start..span.end]);
+    let mut members = format!("\"match\":{},\"start\":{},\"end\":{}", text, span.start, span.end);
+    if utf16_columns {
+        let start = utf16_column(line, span.start);
+        let end = start + utf16_column(&line[span.start..], span.len());
+        members.push_str(&format!(",\"start_utf16\":{},\"end_utf16\":{}", start, end));
+    }
+    members
+}
+
+/// Number of UTF-16 code units the first `bytes` bytes of `line` take, where the Language Server Protocol puts
+/// column `bytes`. Invalid UTF-8 counts as one replacement character per byte sequence, like it is printed.
+fn utf16_column(line: &[u8], bytes: usize) -> usize {
+    String::from_utf8_lossy(&line[..bytes]).encode_utf16().count()
+}
+
+/// `{"binary":B,"stats":{"matched_lines":N,"bytes_searched":N}}`, the data of end events without the path
+impl ToJson for FileSummary {
+    fn write_members(&self, out: &mut String) {
+        out.push_str(&format!(
+            "\"binary\":{},\"stats\":{{\"matched_lines\":{},\"bytes_searched\":{}}}",
+            self.binary, self.matched_lines, self.bytes_searched
+        ));
+    }
+}
+
+/// `{"matched_lines":N,"files_with_matches":N,"files_searched":N,"bytes_searched":N,"elapsed_seconds":F}`, with
+/// `"files":[...]` too when there are [`FileStats`]
+impl ToJson for Stats {
+    fn write_members(&self, out: &mut String) {
+        out.push_str(&format!(
+            "\"matched_lines\":{},\"files_with_matches\":{},\"files_searched\":{},\"bytes_searched\":{},\
+             \"elapsed_seconds\":{:.6}",
+            self.matched_lines,
+            self.files_with_matches,
+            self.files_searched,
+            self.bytes_searched,
+            self.elapsed.as_secs_f64()
+        ));
+        if !self.files.is_empty() {
+            let files: Vec<String> = self.files.iter().map(ToJson::to_json).collect();
+            out.push_str(&format!(",\"files\":[{}]", files.join(",")));
+        }
+    }
+}
+
+/// `{"path":...,"elapsed_seconds":F,"bytes_searched":N,"strategy":"..."}`
+impl ToJson for FileStats {
+    fn write_members(&self, out: &mut String) {
+        out.push_str(&format!(
+            "\"path\":{},\"elapsed_seconds\":{:.6},\"bytes_searched\":{},\"strategy\":{}",
+            data(self.path.as_bytes()),
+            self.elapsed.as_secs_f64(),
+            self.bytes_searched,
+            string(&self.strategy)
+        ));
+    }
+}
+
+/// `{"message":...}`, with `"path":...` too for an input that could not be read
+impl ToJson for Error {
+    fn write_members(&self, out: &mut String) {
+        out.push_str(&format!("\"message\":{}", string(&self.to_string())));
+        if let Error::Io { path: Some(path), .. } = self {
+            out.push_str(&format!(",\"path\":{}", data(path.to_string_lossy().as_bytes())));
+        }
+    }
+}
+
+/// Prints one JSON object per line for every event, e.g.
+/// `{"type":"match","data":{"path":{"text":"a.txt"},"lines":{"text":"foo"},"line_number":3}}`.
+///
+/// Paths and lines are `{"text":...}` when they are valid UTF-8 and `{"bytes":...}`, in base64, otherwise.
+/// The events are, with the data they have:
+///
+/// - `begin`, before the lines of an input: `path`
+/// - `match` and `context`, for every line printed: `path`, `lines` and `line_number`, and for matches the
+///   `submatches` of `--json-columns`, `--captures` and patterns with named groups
+/// - `binary_match`, instead of the lines of a binary input that matches: `path`
+/// - `end`, after them: `path`, `binary`, and `stats` with `matched_lines` and `bytes_searched`
+/// - `error`, for an input that cannot be searched: `message`, and its `path`
+/// - `summary`, last: the [`JSON_VERSION`] as `version`, the exit status as `status`, `totals` with
+///   `matched_lines`, `files_with_matches`, `files_searched`, `bytes_searched` and `errors`, and with --stats the
+///   counters of [`Stats`] as `stats`
+///
+/// The output only changes the way [`JSON_VERSION`] says.
+///
+/// ```
+/// use rgrep::search::Stats;
+/// use rgrep::sink::{JsonSink, Sink};
+///
+/// let mut sink = JsonSink::new(Vec::new());
+/// let stats = Stats { files_searched: 3, errors: 1, ..Stats::default() };
+/// sink.finish(&stats, 2).unwrap();
+/// let summary = String::from_utf8(sink.into_inner()).unwrap();
+/// assert!(summary.starts_with(r#"{"type":"summary","data":{"version":1,"status":2,"totals":{"matched_lines":0,"#));
+/// assert!(summary.trim_end().ends_with(r#""files_searched":3,"bytes_searched":0,"errors":1}}}"#));
+/// ```
+pub struct JsonSink<'m, W: Write> {
+    out: W,
+    /// Finds the matches in matching lines, to give them as `submatches`; `None` to only give the lines
+    matcher: Option<&'m dyn Matcher>,
+    /// Give the columns of submatches in UTF-16 code units too
+    utf16_columns: bool,
+    /// Give all the groups of every submatch by number too
+    captures: bool,
+    /// Whether the pattern has named groups, found out at the first match
+    named: Option<bool>,
+    /// Only give submatches if the pattern has named groups
+    only_named: bool,
+    /// The counters of --stats, given with the summary once the search is finished
+    stats: Option<Stats>,
+}
+
+impl<W: Write> JsonSink<'static, W> {
+    pub fn new(out: W) -> JsonSink<'static, W> {
+        JsonSink {
+            out,
+            matcher: None,
+            utf16_columns: false,
+            captures: false,
+            named: None,
+            only_named: false,
+            stats: None,
+        }
+    }
+}
+
+impl<'m, W: Write> JsonSink<'m, W> {
+    /// Also gives where `matcher` matches in every matching line, as `"submatches"` the way [`LineMatch`] does.
+    /// With `utf16_columns`, every submatch has its columns in UTF-16 code units as `"start_utf16"` and
+    /// `"end_utf16"` too, the columns the Language Server Protocol uses.
+    pub fn with_submatches(out: W, matcher: &'m dyn Matcher, utf16_columns: bool) -> JsonSink<'m, W> {
+        JsonSink { matcher: Some(matcher), utf16_columns, ..JsonSink::new(out) }
+    }
+
+    /// Gives the spans and text of all the groups of every submatch too, as `"groups"`. Named groups are given as
+    /// `"captures"` without it.
+    pub fn with_captures(mut self) -> JsonSink<'m, W> {
+        self.captures = true;
+        self
+    }
+
+    /// Gives the submatches of `matcher` the way [`with_submatches`](JsonSink::with_submatches) does, but only if it
+    /// has named groups, so that they can be given as `"captures"`
+    pub fn with_named_groups(mut self, matcher: &'m dyn Matcher) -> JsonSink<'m, W> {
+        self.matcher = Some(matcher);
+        self.only_named = true;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes an event of type `kind`, with `data` as it is, which has to be JSON
+    pub(crate) fn event(&mut self, kind: &str, data: &str) -> io::Result<()> {
+        writeln!(self.out, "{{\"type\":\"{}\",\"data\":{}}}", kind, data)
+    }
+
+    /// Writes an `error` event with only a message, for failures that are not an [`Error`]
+    #[cfg(feature = "cli")]
+    pub(crate) fn error_message(&mut self, message: &str) -> io::Result<()> {
+        self.event("error", &format!("{{\"message\":{}}}", string(message)))
+    }
+}
+
+/// `bytes` as `{"text":...}` or `{"bytes":...}`
+fn data(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => format!("{{\"text\":{}}}", string(text)),
+        Err(_) => format!("{{\"bytes\":\"{}\"}}", base64(bytes)),
+    }
+}
+
+/// Quotes and escapes a JSON string
+pub(crate) fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (index, byte)| value | (*byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+impl<W: Write> Sink for JsonSink<'_, W> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.event("begin", &format!("{{\"path\":{}}}", data(path.as_bytes())))
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        let mut members = String::new();
+        line.write_members(&mut members);
+        if let Some(matcher) = self.matcher {
+            if self.named.is_none() {
+                self.named = matcher.captures(line.line).map(|groups| groups.names().iter().any(Option::is_some));
+            }
+            if self.captures || self.named == Some(true) {
+                let groups: Vec<Captures> = matcher::captures_iter(matcher, line.line).collect();
+                let spans: Vec<Match> = groups.iter().filter_map(|groups| groups.get(0)).collect();
+                write_submatches(line.line, &spans, &groups, self.captures, self.utf16_columns, &mut members);
+            } else if !self.only_named {
+                let spans: Vec<Match> = matcher::find_iter(matcher, line.line).collect();
+                write_submatches(line.line, &spans, &[], false, self.utf16_columns, &mut members);
+            }
+        }
+        self.event("match", &format!("{{{}}}", members))
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.event("context", &line.to_json())
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.event("binary_match", &format!("{{\"path\":{}}}", data(path.as_bytes())))
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        let mut members = format!("\"path\":{},", data(path.as_bytes()));
+        summary.write_members(&mut members);
+        self.event("end", &format!("{{{}}}", members))
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.stats = Some(stats.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        let mut data = format!(
+            "{{\"version\":{},\"status\":{},\"totals\":{{\"matched_lines\":{},\"files_with_matches\":{},\
+             \"files_searched\":{},\"bytes_searched\":{},\"errors\":{}}}",
+            JSON_VERSION,
+            status,
+            stats.matched_lines,
+            stats.files_with_matches,
+            stats.files_searched,
+            stats.bytes_searched,
+            stats.errors
+        );
+        if let Some(stats) = self.stats.take() {
+            data.push_str(&format!(",\"stats\":{}", stats.to_json()));
+        }
+        data.push('}');
+        self.event("summary", &data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // the counters of a search that did not finish the way run does
+        if let Some(stats) = self.stats.take() {
+            self.event("summary", &format!("{{\"stats\":{}}}", stats.to_json()))?;
+        }
+        self.out.flush()
+    }
+}
@@ -0,0 +1,264 @@
+//! Where search results go.
+//!
+//! A [`Searcher`](crate::search::Searcher) reports every input it searches, and the matching and context lines
+//! in it, to a [`Sink`]. [`TextSink`] prints them like grep, [`JsonSink`] prints one JSON object per event,
+//! [`CountSink`] only prints how many lines matched and [`PatternCountSink`] how many every pattern matched.
+//! [`OffsetsSink`] prints where in the input every match is, in bytes, and [`UniqueSink`] every distinct match.
+//! [`DiffSink`] prints the changes `--write-replace --dry-run` would make. [`ReplaceSink`] replaces the matches
+//! in the lines before passing them on to one of them, [`ExcerptSink`] cuts them down to the bytes around
+//! the matches and [`OnlyMatchingSink`] to the matches.
+//! Programs embedding rgrep can implement [`Sink`] to collect results into their own data structures instead:
+//!
+//! ```no_run
+//! use std::io;
+//! use std::path::Path;
+//!
+//! use rgrep::matcher::{self, MatcherOptions};
+//! use rgrep::search::SearcherBuilder;
+//! use rgrep::sink::{Sink, SinkLine};
+//!
+//! /// Collects the numbers of the matching lines
+//! struct LineNumbers(Vec<usize>);
+//!
+//! impl Sink for LineNumbers {
+//!     fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+//!         self.0.push(line.line_number);
+//!         Ok(())
+//!     }
+//!
+//!     fn binary_match(&mut self, _path: &str) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let matcher = matcher::build(&[String::from("TODO")], &MatcherOptions::default()).unwrap();
+//! let mut sink = LineNumbers(Vec::new());
+//! SearcherBuilder::new().build().search_path(matcher.as_ref(), Path::new("src/main.rs"), &mut sink).unwrap();
+//! println!("{:?}", sink.0);
+//! ```
+//!
+//! Results kept that way can still be written out as `--json` prints them, with [`ToJson`].
+
+mod count;
+mod diff;
+mod excerpt;
+mod json;
+mod offsets;
+mod only_matching;
+mod replace;
+mod text;
+mod unique;
+
+use std::io;
+
+use crate::error::Error;
+use crate::search::Stats;
+
+pub use self::count::{CountSink, PatternCountSink};
+pub use self::diff::DiffSink;
+pub use self::excerpt::ExcerptSink;
+pub use self::json::{JsonSink, ToJson, JSON_VERSION};
+#[cfg(feature = "cli")]
+pub(crate) use self::json::string as json_string;
+pub use self::offsets::OffsetsSink;
+pub use self::only_matching::OnlyMatchingSink;
+pub use self::replace::ReplaceSink;
+pub use self::text::{ColorChoice, ColorPart, ColorSpec, Colors, TextSink, Theme};
+pub use self::unique::UniqueSink;
+
+/// A matching line or a context line
+#[derive(Debug, Clone, Copy)]
+pub struct SinkLine<'a> {
+    /// Name of the input, the file name as given or the name of standard input
+    pub path: &'a str,
+    /// Number of the line in the input, starting at 1
+    pub line_number: usize,
+    /// Offset of the first byte of the line in the input, after decoding
+    pub offset: u64,
+    /// The line without its terminator
+    pub line: &'a [u8],
+}
+
+/// Counters about one input, reported when it has been searched
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSummary {
+    pub matched_lines: u64,
+    pub bytes_searched: u64,
+    /// Lines searched, which may be fewer than the input has when the search stopped early
+    pub lines_searched: u64,
+    /// The input contains binary data
+    pub binary: bool,
+}
+
+/// Receives the results of a search as they are found.
+///
+/// For every input, [`begin`](Sink::begin) is called first, then [`matched`](Sink::matched) and
+/// [`context`](Sink::context) for its lines in order, and [`end`](Sink::end) once it has been searched.
+/// An input that cannot be read gets [`error`](Sink::error) instead of `end`.
+/// Returning an error stops the whole search; it is meant for failures writing the output.
+pub trait Sink {
+    fn begin(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()>;
+
+    /// A part of a matching line, passed on instead of the whole line by sinks that cut lines down, like
+    /// [`OnlyMatchingSink`]. `line` holds the part, and `column` is where the first match in it starts in the
+    /// whole line, in bytes from 1. Sinks that print no columns take it as a matching line.
+    fn matched_part(&mut self, line: &SinkLine, _column: usize) -> io::Result<()> {
+        self.matched(line)
+    }
+
+    fn context(&mut self, _line: &SinkLine) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The line of the function or section a match is in, reported before the match and its context when it
+    /// was asked for with [`SearcherBuilder::show_function`](crate::search::SearcherBuilder::show_function).
+    /// Sinks that do not tell it apart get it as context.
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.context(line)
+    }
+
+    /// A line matched in an input containing binary data. Its lines are not reported and the search of
+    /// the input stops here.
+    fn binary_match(&mut self, path: &str) -> io::Result<()>;
+
+    fn end(&mut self, _path: &str, _summary: &FileSummary) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// An input could not be opened or read; the search goes on with the next one. Also a warning about a line of
+    /// which only the start was searched, as it was over the memory limit, and the search of the input goes on.
+    /// The error is an [`Error::Io`] naming the input.
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        eprintln!("rgrep: {}", error);
+        Ok(())
+    }
+
+    /// `--write-replace --dry-run` would change the file at `path` from `original` to `replaced`. Called after
+    /// [`end`](Sink::end) for the file; only [`DiffSink`] prints it.
+    fn replaced(&mut self, _path: &str, _original: &[u8], _replaced: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Counters about the whole search, only called when they were asked for with --stats
+    fn stats(&mut self, _stats: &Stats) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The search is over, with counters about it and `status`, the exit status rgrep has for it. Called once
+    /// by [`run`](crate::search::run), and at the end of --git-rev and --watch, before [`flush`](Sink::flush),
+    /// whether or not --stats was given.
+    fn finish(&mut self, _stats: &Stats, _status: i32) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called last, so buffered output is written before the search returns
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Lets sinks chosen at run time, like the ones [`run`](crate::search::run) picks, be wrapped by other sinks
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        (**self).begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).matched(line)
+    }
+
+    fn matched_part(&mut self, line: &SinkLine, column: usize) -> io::Result<()> {
+        (**self).matched_part(line, column)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        (**self).binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        (**self).end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        (**self).error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        (**self).replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        (**self).stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        (**self).finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// Lets a sink be wrapped by another one for a while and used on its own again afterwards
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        (**self).begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).matched(line)
+    }
+
+    fn matched_part(&mut self, line: &SinkLine, column: usize) -> io::Result<()> {
+        (**self).matched_part(line, column)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        (**self).enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        (**self).binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        (**self).end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        (**self).error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        (**self).replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        (**self).stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        (**self).finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
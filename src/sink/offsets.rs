@@ -0,0 +1,158 @@
+use std::io::{self, Write};
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::matcher::{self, Match, Matcher};
+use crate::search::Stats;
+
+/// Prints every match as `START:END:TEXT`, the offsets of its first byte and of the byte after it in the input,
+/// for `--byte-offsets`. Editors and refactoring tools can apply edits at those offsets without working them out
+/// from lines and columns.
+///
+/// Offsets are counted in the input after decoding, which is the file as it is for UTF-8 input without a byte
+/// order mark. Line ends, `\` and other control characters in the text are escaped like in Rust strings.
+///
+/// With captures, every line says which group it is for, `[PATH:]START:END:$GROUP:TEXT`: `$0` for the whole
+/// match, followed by a line for every group that took part in it, by its name if it has one.
+pub struct OffsetsSink<'m, W: Write> {
+    out: W,
+    matcher: &'m dyn Matcher,
+    /// Start every match with the name of its input
+    with_filename: bool,
+    /// Matches can span lines, so adjacent matching lines are matched again as a whole
+    multi_line: bool,
+    /// Print the groups of every match too
+    captures: bool,
+    /// Adjacent matching lines of a multi-line search, joined with their line ends
+    pending: Vec<u8>,
+    /// Offset of the first of the pending lines
+    pending_start: u64,
+    /// Number of the last of the pending lines, `None` if there are none
+    pending_last: Option<usize>,
+    pending_path: String,
+}
+
+impl<'m, W: Write> OffsetsSink<'m, W> {
+    pub fn new(out: W, matcher: &'m dyn Matcher, with_filename: bool, multi_line: bool) -> OffsetsSink<'m, W> {
+        OffsetsSink {
+            out,
+            matcher,
+            with_filename,
+            multi_line,
+            captures: false,
+            pending: Vec::new(),
+            pending_start: 0,
+            pending_last: None,
+            pending_path: String::new(),
+        }
+    }
+
+    /// Prints the offsets and text of the groups of every match too
+    pub fn with_captures(mut self) -> OffsetsSink<'m, W> {
+        self.captures = true;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Prints the matches in `text`, which starts at offset `start` of input `path`
+    fn write_matches(&mut self, path: &str, start: u64, text: &[u8]) -> io::Result<()> {
+        if !self.captures {
+            for found in matcher::find_iter(self.matcher, text) {
+                self.write_span(path, start, text, found, None)?;
+            }
+            return Ok(());
+        }
+        for groups in matcher::captures_iter(self.matcher, text) {
+            for group in 0..groups.len() {
+                if let Some(found) = groups.get(group) {
+                    let name = groups.names()[group].clone().unwrap_or_else(|| group.to_string());
+                    self.write_span(path, start, text, found, Some(&name))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints `found` in `text`, which starts at offset `start`, for group `group` if captures are printed
+    fn write_span(&mut self, path: &str, start: u64, text: &[u8], found: Match, group: Option<&str>) -> io::Result<()> {
+        if self.with_filename {
+            write!(self.out, "{}:", path)?;
+        }
+        write!(self.out, "{}:{}:", start + found.start as u64, start + found.end as u64)?;
+        if let Some(group) = group {
+            write!(self.out, "${}:", group)?;
+        }
+        self.out.write_all(&escape(&text[found.start..found.end]))?;
+        self.out.write_all(b"\n")
+    }
+
+    /// Prints the matches in the adjacent matching lines seen last
+    fn write_pending(&mut self) -> io::Result<()> {
+        if self.pending_last.take().is_some() {
+            let (path, text) = (std::mem::take(&mut self.pending_path), std::mem::take(&mut self.pending));
+            self.write_matches(&path, self.pending_start, &text)?;
+        }
+        Ok(())
+    }
+}
+
+/// `text` with `\n`, `\r`, `\t`, `\` and other control characters escaped
+pub(super) fn escape(text: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(text.len());
+    for &byte in text {
+        match byte {
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            b'\r' => escaped.extend_from_slice(b"\\r"),
+            b'\t' => escaped.extend_from_slice(b"\\t"),
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            byte if byte < 0x20 || byte == 0x7f => escaped.extend_from_slice(format!("\\x{:02x}", byte).as_bytes()),
+            byte => escaped.push(byte),
+        }
+    }
+    escaped
+}
+
+impl<W: Write> Sink for OffsetsSink<'_, W> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        if !self.multi_line {
+            return self.write_matches(line.path, line.offset, line.line);
+        }
+        if self.pending_last.is_some_and(|last| last + 1 == line.line_number) {
+            // the line end between the lines, which was cut off them
+            let end = self.pending_start + self.pending.len() as u64;
+            let terminator: &[u8] = if line.offset - end == 2 { b"\r\n" } else { b"\n" };
+            self.pending.extend_from_slice(terminator);
+        } else {
+            self.write_pending()?;
+            self.pending_start = line.offset;
+            self.pending_path = String::from(line.path);
+        }
+        self.pending.extend_from_slice(line.line);
+        self.pending_last = Some(line.line_number);
+        Ok(())
+    }
+
+    fn context(&mut self, _line: &SinkLine) -> io::Result<()> {
+        self.write_pending()
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.write_pending()?;
+        writeln!(self.out, "Binary file {} matches", path)
+    }
+
+    fn end(&mut self, _path: &str, _summary: &FileSummary) -> io::Result<()> {
+        self.write_pending()
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.out.flush()
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::error::Error;
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+
+/// Passes on every match in matching lines instead of the whole lines, for `-o`. Every match goes on as a matching
+/// line of its own, with the number of its line and its own offset, and empty matches are left out. Context lines
+/// are not passed on, like grep does not print them with `-o`.
+pub struct OnlyMatchingSink<'m, S: Sink> {
+    inner: S,
+    matcher: &'m dyn Matcher,
+}
+
+impl<'m, S: Sink> OnlyMatchingSink<'m, S> {
+    pub fn new(inner: S, matcher: &'m dyn Matcher) -> OnlyMatchingSink<'m, S> {
+        OnlyMatchingSink { inner, matcher }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for OnlyMatchingSink<'_, S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        for found in matcher::find_iter(self.matcher, line.line).filter(|found| !found.is_empty()) {
+            let offset = line.offset + found.start as u64;
+            let part = SinkLine { line: &line.line[found.start..found.end], offset, ..*line };
+            self.inner.matched_part(&part, found.start + 1)?;
+        }
+        Ok(())
+    }
+
+    fn context(&mut self, _line: &SinkLine) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn enclosing(&mut self, _line: &SinkLine) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.inner.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.inner.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
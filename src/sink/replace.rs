@@ -0,0 +1,89 @@
+use std::io;
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::error::Error;
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+
+/// Replaces the matches in every matching line before passing it on to another sink, for `--replace`.
+/// Context lines are passed on as they are.
+pub struct ReplaceSink<'m, S: Sink> {
+    inner: S,
+    matcher: &'m dyn Matcher,
+    /// Expanded with [`Captures::expand`](crate::matcher::Captures::expand) for every match
+    replacement: String,
+    /// The line with its matches replaced, kept to reuse its allocation
+    buffer: Vec<u8>,
+}
+
+impl<'m, S: Sink> ReplaceSink<'m, S> {
+    pub fn new(inner: S, matcher: &'m dyn Matcher, replacement: impl Into<String>) -> ReplaceSink<'m, S> {
+        ReplaceSink {
+            inner,
+            matcher,
+            replacement: replacement.into(),
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for ReplaceSink<'_, S> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.buffer.clear();
+        matcher::replace_all(self.matcher, line.line, &self.replacement, &mut self.buffer);
+        self.inner.matched(&SinkLine {
+            line: &self.buffer,
+            ..*line
+        })
+    }
+
+    fn matched_part(&mut self, line: &SinkLine, column: usize) -> io::Result<()> {
+        self.buffer.clear();
+        matcher::replace_all(self.matcher, line.line, &self.replacement, &mut self.buffer);
+        self.inner.matched_part(&SinkLine { line: &self.buffer, ..*line }, column)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.context(line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        self.inner.enclosing(line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        self.inner.stats(stats)
+    }
+
+    fn finish(&mut self, stats: &Stats, status: i32) -> io::Result<()> {
+        self.inner.finish(stats, status)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,712 @@
+use std::io::{self, Write};
+
+use super::{FileSummary, Sink, SinkLine};
+use crate::error::Error;
+use crate::matcher::{self, Matcher, MultiMatcher};
+use crate::search::Stats;
+
+/// When output is colored, `--color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// When standard output is a terminal that is not `TERM=dumb`
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Names accepted by `--color`
+    pub const NAMES: &'static [&'static str] = &["auto", "always", "never"];
+
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        Some(match name {
+            "auto" => ColorChoice::Auto,
+            "always" => ColorChoice::Always,
+            "never" => ColorChoice::Never,
+            _ => return None,
+        })
+    }
+}
+
+/// SGR parameters of the parts of colored output, e.g. `01;31` for bold red, the way `GREP_COLORS` gives them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Colors {
+    /// The text of matches in matching lines
+    pub matched: String,
+    /// The text of matches in context lines
+    pub context_matched: String,
+    pub path: String,
+    pub line_number: String,
+    /// `:`, `-` and `=` after paths and line numbers, and `--` between groups of lines
+    pub separator: String,
+}
+
+/// The colors of grep
+impl Default for Colors {
+    fn default() -> Colors {
+        Colors {
+            matched: String::from("01;31"),
+            context_matched: String::from("01;31"),
+            path: String::from("35"),
+            line_number: String::from("32"),
+            separator: String::from("36"),
+        }
+    }
+}
+
+impl Colors {
+    /// Takes the colors `GREP_COLORS` gives, like `ms=01;32:fn=34`: `ms` and `mc` for matches in matching and
+    /// context lines, `mt` for both, `fn` for paths, `ln` for line numbers and `se` for separators. Like grep,
+    /// other capabilities are ignored, and so are entries whose value is not SGR parameters.
+    ///
+    /// ```
+    /// use rgrep::sink::Colors;
+    ///
+    /// let colors = Colors::default().with_grep_colors("mt=01;32:fn=:sl=1");
+    /// assert_eq!(colors.matched, "01;32");
+    /// assert_eq!(colors.context_matched, "01;32");
+    /// assert_eq!(colors.path, "");
+    /// ```
+    pub fn with_grep_colors(mut self, grep_colors: &str) -> Colors {
+        for entry in grep_colors.split(':').filter(|entry| !entry.is_empty()) {
+            let (name, sgr) = match entry.split_once('=') {
+                Some((name, sgr)) if sgr.chars().all(|c| c.is_ascii_digit() || c == ';') => (name, String::from(sgr)),
+                _ => {
+                    debug!("ignoring {:?} in GREP_COLORS", entry);
+                    continue;
+                }
+            };
+            match name {
+                "ms" => self.matched = sgr,
+                "mc" => self.context_matched = sgr,
+                "mt" => {
+                    self.matched = sgr.clone();
+                    self.context_matched = sgr;
+                }
+                "fn" => self.path = sgr,
+                "ln" => self.line_number = sgr,
+                "se" => self.separator = sgr,
+                _ => debug!("ignoring {:?} in GREP_COLORS, rgrep colors nothing like it", entry),
+            }
+        }
+        self
+    }
+
+    /// Takes a color setting of `--colors`, after those that came before: a color replaces the one of the part
+    /// before, and styles add up
+    ///
+    /// ```
+    /// use rgrep::sink::{ColorSpec, Colors};
+    ///
+    /// let colors = Colors::default().with_spec(&ColorSpec::parse("match:style:underline").unwrap());
+    /// assert_eq!(colors.matched, "01;31;4");
+    /// let colors = colors.with_spec(&ColorSpec::parse("match:fg:blue").unwrap());
+    /// assert_eq!(colors.matched, "01;4;34");
+    /// let colors = colors.with_spec(&ColorSpec::parse("match:none").unwrap());
+    /// assert_eq!(colors.matched, "");
+    /// ```
+    pub fn with_spec(mut self, spec: &ColorSpec) -> Colors {
+        let parts = match spec.part {
+            ColorPart::Match => vec![&mut self.matched, &mut self.context_matched],
+            ColorPart::Path => vec![&mut self.path],
+            ColorPart::Line => vec![&mut self.line_number],
+            ColorPart::Separator => vec![&mut self.separator],
+        };
+        // the color the setting replaces, if it is one
+        let replaced = ground(&spec.sgr);
+        for part in parts {
+            if spec.sgr.is_empty() {
+                part.clear();
+                continue;
+            }
+            let mut kept = sgr_parameters(part);
+            if replaced.is_some() {
+                kept.retain(|parameter| ground(parameter) != replaced);
+            }
+            kept.push(spec.sgr.clone());
+            *part = kept.join(";");
+        }
+        self
+    }
+}
+
+/// What a setting of `--colors` is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorPart {
+    /// Matches, in matching and context lines
+    Match,
+    Path,
+    Line,
+    /// `:`, `-` and `=` after paths and line numbers, and `--` between groups of lines
+    Separator,
+}
+
+impl ColorPart {
+    /// Names accepted by `--colors`
+    pub const NAMES: &'static [&'static str] = &["match", "path", "line", "separator"];
+
+    pub fn from_name(name: &str) -> Option<ColorPart> {
+        Some(match name {
+            "match" => ColorPart::Match,
+            "path" => ColorPart::Path,
+            "line" => ColorPart::Line,
+            "separator" => ColorPart::Separator,
+            _ => return None,
+        })
+    }
+}
+
+/// A set of colors to start from, `--theme`, which `GREP_COLORS` and `--colors` change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// The colors of grep
+    #[default]
+    Default,
+    /// Darker colors that are easier to read on a light background
+    LightTerminal,
+    /// Reversed matches and bold paths and line numbers, for monochrome terminals and poor eyesight
+    HighContrast,
+    /// Blue and orange, which are told apart with every common kind of color blindness, instead of red and green
+    ColorblindSafe,
+}
+
+impl Theme {
+    /// Names accepted by `--theme`
+    pub const NAMES: &'static [&'static str] = &["default", "light-terminal", "high-contrast", "colorblind-safe"];
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Some(match name {
+            "default" => Theme::Default,
+            "light-terminal" => Theme::LightTerminal,
+            "high-contrast" => Theme::HighContrast,
+            "colorblind-safe" => Theme::ColorblindSafe,
+            _ => return None,
+        })
+    }
+
+    /// The settings of `--colors` the theme is made of, on top of no colors at all
+    pub fn specs(self) -> &'static [&'static str] {
+        match self {
+            Theme::Default => &[
+                "match:style:bold", "match:fg:red", "path:fg:magenta", "line:fg:green", "separator:fg:cyan",
+            ],
+            Theme::LightTerminal => &[
+                "match:style:bold", "match:fg:blue", "path:fg:magenta", "line:fg:green", "separator:fg:black",
+            ],
+            Theme::HighContrast => &[
+                "match:style:bold", "match:style:reverse", "path:style:bold", "path:style:underline",
+                "line:style:bold",
+            ],
+            Theme::ColorblindSafe => &[
+                "match:style:bold", "match:fg:208", "path:fg:blue", "line:fg:cyan",
+            ],
+        }
+    }
+
+    /// The colors of the theme
+    ///
+    /// ```
+    /// use rgrep::sink::{Colors, Theme};
+    ///
+    /// assert_eq!(Theme::Default.colors(), Colors::default());
+    /// assert_eq!(Theme::HighContrast.colors().matched, "1;7");
+    /// ```
+    pub fn colors(self) -> Colors {
+        // grep writes the parameters of its colors a little differently
+        if self == Theme::Default {
+            return Colors::default();
+        }
+        let none = Colors {
+            matched: String::new(),
+            context_matched: String::new(),
+            path: String::new(),
+            line_number: String::new(),
+            separator: String::new(),
+        };
+        self.specs().iter().fold(none, |colors, spec| {
+            colors.with_spec(&ColorSpec::parse(spec).expect("themes are made of valid settings"))
+        })
+    }
+}
+
+/// Color names of `--colors`, in the order of their SGR parameters
+const COLOR_NAMES: &[&str] = &["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Text attributes of `--colors` and their SGR parameters
+const STYLES: &[(&str, &str)] = &[
+    ("bold", "1"),
+    ("nobold", "22"),
+    ("italic", "3"),
+    ("noitalic", "23"),
+    ("underline", "4"),
+    ("nounderline", "24"),
+    ("reverse", "7"),
+    ("noreverse", "27"),
+];
+
+/// A color setting, `--colors PART:fg:COLOR`, `PART:bg:COLOR`, `PART:style:STYLE` or `PART:none`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorSpec {
+    pub part: ColorPart,
+    /// SGR parameters added to those of the part, or nothing to take them all away
+    pub sgr: String,
+}
+
+impl ColorSpec {
+    /// Parses the value of `--colors`. Colors are named, like `blue`, or numbered from 0 to 255; styles are
+    /// `bold`, `italic`, `underline` and `reverse`, and the same with `no` in front to turn them off.
+    ///
+    /// ```
+    /// use rgrep::sink::{ColorPart, ColorSpec};
+    ///
+    /// let spec = ColorSpec::parse("path:fg:blue").unwrap();
+    /// assert_eq!(spec, ColorSpec { part: ColorPart::Path, sgr: String::from("34") });
+    /// assert_eq!(ColorSpec::parse("match:bg:208").unwrap().sgr, "48;5;208");
+    /// assert!(ColorSpec::parse("match:style:blink").is_err());
+    /// ```
+    pub fn parse(value: &str) -> Result<ColorSpec, String> {
+        let mut fields = value.splitn(3, ':');
+        let part = fields.next().unwrap_or_default();
+        let part = ColorPart::from_name(part)
+            .ok_or_else(|| format!("unknown part {:?}, expected one of {}", part, ColorPart::NAMES.join(", ")))?;
+        let sgr = match (fields.next(), fields.next()) {
+            (Some("none"), None) => String::new(),
+            (Some(ground @ ("fg" | "bg")), Some(color)) => {
+                let base = if ground == "fg" { 30 } else { 40 };
+                match (COLOR_NAMES.iter().position(|name| *name == color), color.parse::<u8>()) {
+                    (Some(index), _) => (base + index).to_string(),
+                    (None, Ok(number)) => format!("{};5;{}", base + 8, number),
+                    _ => {
+                        let names = COLOR_NAMES.join(", ");
+                        return Err(format!("unknown color {:?}, expected one of {} or 0 to 255", color, names));
+                    }
+                }
+            }
+            (Some("style"), Some(style)) => match STYLES.iter().find(|(name, _)| *name == style) {
+                Some((_, sgr)) => String::from(*sgr),
+                None => {
+                    let names: Vec<&str> = STYLES.iter().map(|(name, _)| *name).collect();
+                    return Err(format!("unknown style {:?}, expected one of {}", style, names.join(", ")));
+                }
+            },
+            _ => return Err(String::from("expected PART:fg:COLOR, PART:bg:COLOR, PART:style:STYLE or PART:none")),
+        };
+        Ok(ColorSpec { part, sgr })
+    }
+}
+
+/// Colors of the matches of the second pattern, the third and so on, starting over after the last one; those of
+/// the first have the color of matches
+const PATTERN_COLORS: &[&str] = &["01;32", "01;33", "01;34", "01;36", "01;31"];
+
+/// The parameters of `sgr` one by one, with a color of 256 or more like `38;5;208` as one
+fn sgr_parameters(sgr: &str) -> Vec<String> {
+    let mut parameters = Vec::new();
+    let mut numbers = sgr.split(';').filter(|number| !number.is_empty());
+    while let Some(number) = numbers.next() {
+        let mut parameter = String::from(number);
+        let arguments = match number.trim_start_matches('0') {
+            "38" | "48" => match numbers.next() {
+                Some(mode) => {
+                    parameter.push(';');
+                    parameter.push_str(mode);
+                    if mode == "2" { 3 } else { 1 }
+                }
+                None => 0,
+            },
+            _ => 0,
+        };
+        for argument in numbers.by_ref().take(arguments) {
+            parameter.push(';');
+            parameter.push_str(argument);
+        }
+        parameters.push(parameter);
+    }
+    parameters
+}
+
+/// Whether the SGR parameter `parameter` sets the foreground color, `Some(true)`, the background color,
+/// `Some(false)`, or neither
+fn ground(parameter: &str) -> Option<bool> {
+    match parameter.split(';').next()?.parse::<u8>().ok()? {
+        30..=39 | 90..=97 => Some(true),
+        40..=49 | 100..=107 => Some(false),
+        _ => None,
+    }
+}
+
+/// Output of `text` in the style `sgr`, or as it is if `sgr` is empty
+fn paint(sgr: &str, text: &str) -> String {
+    if sgr.is_empty() {
+        String::from(text)
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", sgr, text)
+    }
+}
+
+/// Prints matching lines and their context like grep, keeping track of what was printed last
+pub struct TextSink<'m, W: Write> {
+    out: W,
+    /// Start every line with the name of its input
+    with_filename: bool,
+    /// Separate groups of lines that are not adjacent with `--`
+    context_separator: bool,
+    /// Print the name of every input once above its lines, instead of before every line
+    heading: bool,
+    /// Add the number of matching lines to every heading
+    heading_counts: bool,
+    /// With heading counts, the lines of the current input, held back until their count is known
+    held: Option<Held>,
+    /// Colors, with the matcher finding the matches to color in matching lines; `None` for plain text
+    colors: Option<(Colors, &'m dyn Matcher)>,
+    /// The patterns apart, to color the matches of each in a color of its own
+    patterns: Option<MultiMatcher>,
+    /// Whether any line was printed, in any file
+    printed_any: bool,
+    /// Number of the last printed line in the current file
+    last_printed: Option<usize>,
+    /// The last printed line was the enclosing line of the next one, which belongs with it
+    enclosing_printed: bool,
+    /// Columns line numbers are right-aligned in, `None` to print no line numbers
+    line_number_width: Option<usize>,
+    /// Under headings, line numbers are right-aligned in as many columns as the line count of their input has digits
+    input_line_number_width: bool,
+    /// Finds the first match in matching lines, to print its column after the line number; `None` for no columns
+    columns: Option<&'m dyn Matcher>,
+    /// Matching lines printed per input at most, `None` for all of them
+    max_shown: Option<u64>,
+    /// Matching lines seen in the current input, printed or not
+    shown: u64,
+}
+
+impl<W: Write> TextSink<'static, W> {
+    pub fn new(out: W, with_filename: bool, context_separator: bool) -> TextSink<'static, W> {
+        TextSink {
+            out,
+            with_filename,
+            context_separator,
+            heading: false,
+            heading_counts: false,
+            held: None,
+            colors: None,
+            patterns: None,
+            printed_any: false,
+            last_printed: None,
+            enclosing_printed: false,
+            line_number_width: None,
+            input_line_number_width: false,
+            columns: None,
+            max_shown: None,
+            shown: 0,
+        }
+    }
+}
+
+impl<'m, W: Write> TextSink<'m, W> {
+    /// Colors paths, line numbers and separators, and the matches `matcher` finds in matching lines, with the
+    /// escape sequences of terminals
+    pub fn with_colors<'n>(self, colors: Colors, matcher: &'n dyn Matcher) -> TextSink<'n, W>
+    where
+        'm: 'n,
+    {
+        TextSink {
+            out: self.out,
+            with_filename: self.with_filename,
+            context_separator: self.context_separator,
+            heading: self.heading,
+            heading_counts: self.heading_counts,
+            held: self.held,
+            colors: Some((colors, matcher)),
+            patterns: self.patterns,
+            printed_any: self.printed_any,
+            last_printed: self.last_printed,
+            enclosing_printed: self.enclosing_printed,
+            line_number_width: self.line_number_width,
+            input_line_number_width: self.input_line_number_width,
+            columns: self.columns,
+            max_shown: self.max_shown,
+            shown: self.shown,
+        }
+    }
+
+    /// With colors, colors the matches of every pattern in `patterns`, which were built on their own with
+    /// [`matcher::build_each`], in a color of its own: the first in the color of matches, and the others in
+    /// green, yellow, blue, cyan and red, starting over after the last one
+    pub fn with_pattern_colors(mut self, patterns: MultiMatcher) -> TextSink<'m, W> {
+        self.patterns = Some(patterns);
+        self
+    }
+
+    /// Prints the name of every input on a line of its own above its lines, with an empty line between inputs,
+    /// if names are printed at all
+    pub fn with_heading(mut self) -> TextSink<'m, W> {
+        self.heading = true;
+        self
+    }
+
+    /// With headings, adds the number of matching lines of every input to its heading, like
+    /// `src/main.rs (17 matching lines)`. The lines of every input are held back until it has been searched.
+    pub fn with_heading_counts(mut self) -> TextSink<'m, W> {
+        self.heading_counts = true;
+        self
+    }
+
+    /// Starts every line with its number, right-aligned in `width` columns; 0 prints numbers as they are
+    pub fn with_line_numbers(mut self, width: usize) -> TextSink<'m, W> {
+        self.line_number_width = Some(width);
+        self
+    }
+
+    /// With headings and line numbers, right-aligns the line numbers of every input in as many columns as the
+    /// number of its lines has digits, at most 20, so they line up under its heading. The lines of every input are
+    /// held back until it has been searched.
+    pub fn with_input_line_number_width(mut self) -> TextSink<'m, W> {
+        self.input_line_number_width = true;
+        self
+    }
+
+    /// Prints the column of the first match `matcher` finds in every matching line after its number, in bytes from
+    /// 1, or 1 if it finds none. Parts of lines passed on with
+    /// [`Sink::matched_part`] get the column they come with.
+    ///
+    /// ```
+    /// use rgrep::matcher::{self, MatcherOptions};
+    /// use rgrep::sink::{Sink, SinkLine, TextSink};
+    ///
+    /// let matcher = matcher::build(&[String::from("fox")], &MatcherOptions::default()).unwrap();
+    /// let mut sink = TextSink::new(Vec::new(), false, false).with_line_numbers(0).with_columns(matcher.as_ref());
+    /// sink.matched(&SinkLine { path: "poem.txt", line_number: 1, offset: 0, line: b"The quick brown fox" }).unwrap();
+    /// assert_eq!(sink.into_inner(), b"1:17:The quick brown fox\n");
+    /// ```
+    pub fn with_columns<'n>(self, matcher: &'n dyn Matcher) -> TextSink<'n, W>
+    where
+        'm: 'n,
+    {
+        let mut sink: TextSink<'n, W> = self;
+        sink.columns = Some(matcher);
+        sink
+    }
+
+    /// Prints only the first `lines` matching lines of every input, and then how many more there are instead of
+    /// the rest of them and their context
+    pub fn with_max_shown(mut self, lines: u64) -> TextSink<'m, W> {
+        self.max_shown = Some(lines);
+        self
+    }
+
+    /// The matching lines shown in the current input reached the limit
+    fn limit_reached(&self) -> bool {
+        self.max_shown.is_some_and(|max| self.shown >= max)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// `text` in the color `pick` chooses, if there are colors
+    fn styled(&self, pick: fn(&Colors) -> &str, text: &str) -> String {
+        match &self.colors {
+            Some((colors, _)) => paint(pick(colors), text),
+            None => String::from(text),
+        }
+    }
+
+    /// Prints a line. `separator` is `:` for matching lines, `-` for context lines and `=` for enclosing lines,
+    /// like `git grep --show-function`. Matching lines have the `column` of their first match with columns.
+    fn line(&mut self, line: &SinkLine, separator: char, column: Option<usize>) -> io::Result<()> {
+        let adjacent = matches!(self.last_printed, Some(last) if last + 1 == line.line_number);
+        let contiguous = adjacent || self.enclosing_printed;
+        let headed = self.heading && self.with_filename;
+        let mut out = Vec::new();
+        if headed && self.last_printed.is_none() {
+            // with counts, the heading is printed once the input has been searched
+            if self.held.is_none() {
+                self.heading_line(line.path, None, self.printed_any)?;
+            }
+        } else if self.context_separator && self.printed_any && !contiguous {
+            writeln!(out, "{}", self.styled(|colors| &colors.separator, "--"))?;
+        }
+        let styled_separator = self.styled(|colors| &colors.separator, separator.encode_utf8(&mut [0; 4]));
+        if self.with_filename && !headed {
+            write!(out, "{}{}", self.styled(|colors| &colors.path, line.path), styled_separator)?;
+        }
+        if let Some(width) = self.line_number_width {
+            // padded by hand, `format!` panics on widths above `u16::MAX`
+            let mut number = line.line_number.to_string();
+            match &mut self.held {
+                // padded once the width is known
+                Some(held) if self.input_line_number_width => {
+                    held.numbers.push((held.lines.len() + out.len(), number.len()));
+                }
+                _ => number.insert_str(0, &" ".repeat(width.saturating_sub(number.len()))),
+            }
+            write!(out, "{}{}", self.styled(|colors| &colors.line_number, &number), styled_separator)?;
+        }
+        if let Some(column) = column.filter(|_| self.columns.is_some()) {
+            write!(out, "{}{}", self.styled(|colors| &colors.line_number, &column.to_string()), styled_separator)?;
+        }
+        let match_sgr = match &self.colors {
+            Some((colors, _)) if separator == ':' => colors.matched.as_str(),
+            Some((colors, _)) if separator == '-' => colors.context_matched.as_str(),
+            _ => "",
+        };
+        match &self.colors {
+            Some((_, matcher)) if !match_sgr.is_empty() => {
+                let mut written = 0;
+                for found in matcher::find_iter(*matcher, line.line).filter(|found| !found.is_empty()) {
+                    out.write_all(&line.line[written..found.start])?;
+                    // the pattern that matched there, if it can be told
+                    let pattern = self.patterns.as_ref().and_then(|each| each.find_pattern_at(line.line, found.start));
+                    let sgr = match pattern {
+                        Some((index, at)) if index > 0 && at.start == found.start => {
+                            PATTERN_COLORS[(index - 1) % PATTERN_COLORS.len()]
+                        }
+                        _ => match_sgr,
+                    };
+                    write!(out, "\x1b[{}m", sgr)?;
+                    out.write_all(&line.line[found.start..found.end])?;
+                    out.write_all(b"\x1b[0m")?;
+                    written = found.end;
+                }
+                out.write_all(&line.line[written..])?;
+            }
+            _ => out.write_all(line.line)?,
+        }
+        out.write_all(b"\n")?;
+        match &mut self.held {
+            Some(held) => held.lines.extend_from_slice(&out),
+            None => self.out.write_all(&out)?,
+        }
+        self.printed_any = true;
+        self.last_printed = Some(line.line_number);
+        self.enclosing_printed = separator == '=';
+        Ok(())
+    }
+
+    /// Prints the heading of the input at `path`, with the number of its matching lines if there is one, after an
+    /// empty line if the output of another input is above it
+    fn heading_line(&mut self, path: &str, count: Option<u64>, below_other: bool) -> io::Result<()> {
+        if below_other {
+            self.out.write_all(b"\n")?;
+        }
+        let path = self.styled(|colors| &colors.path, path);
+        match count {
+            Some(count) => {
+                let lines = if count == 1 { "line" } else { "lines" };
+                writeln!(self.out, "{} ({} matching {})", path, count, lines)
+            }
+            None => writeln!(self.out, "{}", path),
+        }
+    }
+
+    /// Prints the lines held back for the current input under its heading, with its number of matching lines with
+    /// heading counts, and line numbers aligned to the digits of its number of `lines`, if it is known
+    fn print_held(&mut self, count: Option<u64>, lines: Option<u64>) -> io::Result<()> {
+        let held = match self.held.take() {
+            Some(held) if !held.lines.is_empty() => held,
+            _ => return Ok(()),
+        };
+        let count = count.filter(|_| self.heading_counts);
+        self.heading_line(&held.path, count, held.printed_before)?;
+        // wide enough for every line number, if the number of lines is not known
+        let widest = held.numbers.iter().map(|&(_, digits)| digits).max().unwrap_or(0);
+        let width = lines.map_or(widest, |lines| lines.to_string().len().max(widest));
+        let mut written = 0;
+        for (at, digits) in held.numbers {
+            self.out.write_all(&held.lines[written..at])?;
+            self.out.write_all(" ".repeat(width - digits).as_bytes())?;
+            written = at;
+        }
+        self.out.write_all(&held.lines[written..])
+    }
+}
+
+/// The lines of an input while they are held back
+struct Held {
+    path: String,
+    /// Whether lines of other inputs were printed before
+    printed_before: bool,
+    lines: Vec<u8>,
+    /// Where in `lines` line numbers that are still to be padded start, and their digits
+    numbers: Vec<(usize, usize)>,
+}
+
+impl<W: Write> Sink for TextSink<'_, W> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.last_printed = None;
+        self.shown = 0;
+        let aligned = self.input_line_number_width && self.line_number_width.is_some();
+        if (self.heading_counts || aligned) && self.heading && self.with_filename {
+            let (printed_before, lines, numbers) = (self.printed_any, Vec::new(), Vec::new());
+            self.held = Some(Held { path: String::from(path), printed_before, lines, numbers });
+        }
+        Ok(())
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        let column = self.columns.map(|matcher| {
+            let first = matcher::find_iter(matcher, line.line).find(|found| !found.is_empty());
+            first.map_or(1, |found| found.start + 1)
+        });
+        self.matched_part(line, column.unwrap_or(1))
+    }
+
+    fn matched_part(&mut self, line: &SinkLine, column: usize) -> io::Result<()> {
+        let hidden = self.limit_reached();
+        self.shown += 1;
+        if hidden {
+            return Ok(());
+        }
+        self.line(line, ':', Some(column))
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        // once a match is hidden, nothing else of the input is printed, not even the context of earlier ones
+        if self.max_shown.is_some_and(|max| self.shown > max) {
+            return Ok(());
+        }
+        self.line(line, '-', None)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        if self.limit_reached() {
+            return Ok(());
+        }
+        self.line(line, '=', None)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.print_held(Some(summary.matched_lines), Some(summary.lines_searched))?;
+        let hidden = match self.max_shown {
+            Some(max) if summary.matched_lines > max => summary.matched_lines - max,
+            _ => return Ok(()),
+        };
+        if self.with_filename && !self.heading {
+            write!(self.out, "{}: ", self.styled(|colors| &colors.path, path))?;
+        }
+        let lines = if hidden == 1 { "line" } else { "lines" };
+        writeln!(self.out, "and {} more matching {} not shown", hidden, lines)?;
+        self.last_printed = None;
+        Ok(())
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        writeln!(self.out, "Binary file {} matches", path)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        // what was read before the error, but not how many lines matched in all of it
+        self.print_held(None, None)?;
+        eprintln!("rgrep: {}", error);
+        Ok(())
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use super::offsets::escape;
+use super::{Sink, SinkLine};
+use crate::matcher::{self, Matcher};
+use crate::search::Stats;
+
+/// Prints the text of every distinct match once, for `--unique`, in the order they are first found in. Matches
+/// of all inputs are told apart by their bytes, and escaped like [`OffsetsSink`](super::OffsetsSink) does.
+///
+/// With counts, every text is printed as `COUNT:TEXT` once the search is done, counting how often it was
+/// matched; without, it is printed as soon as it is found.
+pub struct UniqueSink<'m, W: Write> {
+    out: W,
+    matcher: &'m dyn Matcher,
+    with_counts: bool,
+    /// Index into `texts` of every text seen so far
+    seen: HashMap<Vec<u8>, usize>,
+    /// Every text in the order it was first found in, with how often it was matched
+    texts: Vec<(Vec<u8>, u64)>,
+}
+
+impl<'m, W: Write> UniqueSink<'m, W> {
+    pub fn new(out: W, matcher: &'m dyn Matcher, with_counts: bool) -> UniqueSink<'m, W> {
+        UniqueSink { out, matcher, with_counts, seen: HashMap::new(), texts: Vec::new() }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Prints the counts of the texts found since they were printed last
+    fn write_counts(&mut self) -> io::Result<()> {
+        self.seen.clear();
+        for (text, count) in std::mem::take(&mut self.texts) {
+            write!(self.out, "{}:", count)?;
+            self.out.write_all(&escape(&text))?;
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for UniqueSink<'_, W> {
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        for found in matcher::find_iter(self.matcher, line.line) {
+            let text = &line.line[found.start..found.end];
+            if let Some(&index) = self.seen.get(text) {
+                self.texts[index].1 += 1;
+                continue;
+            }
+            self.seen.insert(text.to_vec(), self.texts.len());
+            self.texts.push((text.to_vec(), 1));
+            if !self.with_counts {
+                self.out.write_all(&escape(text))?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        writeln!(self.out, "Binary file {} matches", path)
+    }
+
+    fn stats(&mut self, stats: &Stats) -> io::Result<()> {
+        if self.with_counts {
+            self.write_counts()?;
+        }
+        stats.write(&mut self.out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.with_counts {
+            self.write_counts()?;
+        }
+        self.out.flush()
+    }
+}
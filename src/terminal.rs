@@ -0,0 +1,144 @@
+use std::env;
+
+/// Size of the terminal attached to stdout, in columns and rows
+pub struct Size {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// Returns the size of the terminal attached to stdout. `COLUMNS` and `LINES` take precedence over
+/// what the terminal reports, so users and scripts can override it.
+pub fn size() -> Option<Size> {
+    let from_env = |name: &str| env::var(name).ok().and_then(|value| value.parse::<usize>().ok()).filter(|value| *value > 0);
+    let reported = query_size();
+    let columns = from_env("COLUMNS").or_else(|| reported.as_ref().map(|size| size.columns))?;
+    let rows = from_env("LINES").or_else(|| reported.as_ref().map(|size| size.rows)).unwrap_or(24);
+    Some(Size { columns, rows })
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn query_size() -> Option<Size> {
+    use std::os::raw::{c_int, c_ulong, c_ushort};
+
+    #[repr(C)]
+    struct WinSize {
+        ws_row: c_ushort,
+        ws_col: c_ushort,
+        ws_xpixel: c_ushort,
+        ws_ypixel: c_ushort,
+    }
+
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    #[cfg(target_os = "linux")]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(target_os = "macos")]
+    const TIOCGWINSZ: c_ulong = 0x4008_7468;
+    const STDOUT_FILENO: c_int = 1;
+
+    let mut size = WinSize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: TIOCGWINSZ only writes a winsize struct through the pointer, which points to one
+    let result = unsafe { ioctl(STDOUT_FILENO, TIOCGWINSZ, &mut size as *mut WinSize) };
+    if result == 0 && size.ws_col > 0 {
+        Some(Size { columns: size.ws_col as usize, rows: size.ws_row as usize })
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn query_size() -> Option<Size> {
+    None
+}
+
+/// The settings of a terminal, as `tcgetattr` returns them. Its layout differs between platforms and only the C
+/// library looks inside, so it is kept as enough suitably aligned bytes for any of them.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[repr(C, align(8))]
+#[derive(Clone, Copy)]
+struct Termios([u8; 256]);
+
+/// A terminal in raw mode: keys arrive one at a time, without echo, line editing or Ctrl-C sending a signal.
+/// The terminal gets its previous settings back when this is dropped.
+pub struct RawMode {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fd: std::os::raw::c_int,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    original: Termios,
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod termios {
+    use std::os::raw::c_int;
+
+    use super::Termios;
+
+    pub const TCSANOW: c_int = 0;
+
+    extern "C" {
+        pub fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        pub fn tcsetattr(fd: c_int, actions: c_int, termios: *const Termios) -> c_int;
+        pub fn cfmakeraw(termios: *mut Termios);
+    }
+}
+
+impl RawMode {
+    /// Puts the terminal `tty` is open on into raw mode
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn enable(tty: &std::fs::File) -> std::io::Result<RawMode> {
+        use std::io;
+        use std::os::unix::io::AsRawFd;
+
+        let fd = tty.as_raw_fd();
+        let mut original = Termios([0; 256]);
+        // SAFETY: both calls only read and write the settings through the pointers, which point to a buffer
+        // larger than the settings are on any supported platform
+        unsafe {
+            if termios::tcgetattr(fd, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            termios::cfmakeraw(&mut raw);
+            if termios::tcsetattr(fd, termios::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(RawMode { fd, original })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub fn enable(_tty: &std::fs::File) -> std::io::Result<RawMode> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "raw terminal mode is not supported here"))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: the settings were read from the same terminal, so they are valid to set again
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        unsafe {
+            termios::tcsetattr(self.fd, termios::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Wraps text at word boundaries so no line is longer than `width`, unless a single word is.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
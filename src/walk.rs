@@ -0,0 +1,432 @@
+//! Goes from the inputs on the command line to the files that are searched, with `-d ACTION` for the ones
+//! that are directories.
+//!
+//! With `-d recurse`, directories are walked depth first, their entries in the order of their names, so the
+//! output is the same from one run to the next. Like `grep -r`, symbolic links are followed when they are
+//! given, and left out when they are found in a directory, so links cannot make the walk go around in circles.
+//! On Windows, junctions and the other reparse points that name another place, like mount points, count as
+//! symbolic links too; reparse points that stand for their own contents, like cloud files, are walked.
+//! Found files that are not regular files, like FIFOs and devices, are left out as well. So are the directories
+//! found that virtual file systems like procfs, sysfs, devtmpfs and FUSE are mounted on, on Linux, unless
+//! `--no-skip-virtual`: walking from `/` would read `/proc/kcore` or hang on a FUSE mount that does not answer.
+//!
+//! With `--skip-minified`, found files that look minified or generated are left out too: `.min.js` and `.min.css`
+//! files, source maps, and files whose start has lines far longer than people write, or a source map comment.
+//! Their matches would be a line of many kilobytes, and searching them takes most of the time of a walk.
+//!
+//! Like grep, the walk goes through everything by default, `.git` directories too. With `--gitignore` it leaves
+//! those out, and what the `.gitignore` and `.ignore` files of the directories it walks ignore, by the rules of
+//! [`ignore`](crate::ignore); the files of `.ignore` win. Files above the walked directories are not read.
+//!
+//! [`Walk`] is what [`search::run`](crate::search::run) goes through, so other tools can list the files rgrep
+//! would search without searching them:
+//!
+//! ```
+//! use rgrep::glob::Glob;
+//! use rgrep::walk::{DirectoryAction, Walk};
+//!
+//! let walk = Walk::new(&[String::from("src")], DirectoryAction::Recurse).include(Glob::new("*.rs", false));
+//! let files: Vec<String> = walk.collect::<Result<_, _>>().unwrap();
+//! assert!(files.contains(&String::from("src/walk.rs")));
+//! assert!(files.iter().all(|file| file.ends_with(".rs")));
+//! ```
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::sync::OnceLock;
+
+use crate::args::CommandArgs;
+use crate::error::{Error, Result};
+use crate::glob::Glob;
+use crate::ignore::Ignore;
+use crate::search::{self, Order};
+
+/// What to do with inputs that are directories, `-d ACTION`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirectoryAction {
+    /// Read them like files, which fails
+    #[default]
+    Read,
+    /// Leave them out without a word
+    Skip,
+    /// Search the files in them and in the directories in them
+    Recurse,
+}
+
+impl DirectoryAction {
+    /// Names accepted by `-d`
+    pub const NAMES: &'static [&'static str] = &["read", "skip", "recurse"];
+
+    pub fn from_name(name: &str) -> Option<DirectoryAction> {
+        Some(match name {
+            "read" => DirectoryAction::Read,
+            "skip" => DirectoryAction::Skip,
+            "recurse" => DirectoryAction::Recurse,
+            _ => return None,
+        })
+    }
+}
+
+/// The input that stands for the current directory when there are no others, with `-d recurse`. Its
+/// files are named like `src/main.rs` instead of `./src/main.rs`.
+pub const CURRENT_DIRECTORY: &str = "";
+
+/// Bytes read from the start of a file to tell whether it is minified
+const MINIFIED_SAMPLE: u64 = 64 * 1024;
+
+/// Average length of lines, in bytes, above which the start of a file is taken to be minified
+const MINIFIED_LINE_LENGTH: usize = 500;
+
+/// Whether the file at `path` looks minified or generated, by its name or the start of its contents
+fn is_minified(path: &str) -> bool {
+    let name = path.to_ascii_lowercase();
+    if [".min.js", ".min.css", ".js.map", ".css.map"].iter().any(|suffix| name.ends_with(suffix)) {
+        return true;
+    }
+    let mut sample = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(MINIFIED_SAMPLE).read_to_end(&mut sample));
+    // binary files are told apart by the search, and files that cannot be read are reported there
+    if read.is_err() || sample.contains(&0) {
+        return false;
+    }
+    let marker = b"//# sourceMappingURL=";
+    if sample.windows(marker.len()).any(|window| window == marker) {
+        return true;
+    }
+    let lines = sample.iter().filter(|byte| **byte == b'\n').count() + 1;
+    sample.len() / lines > MINIFIED_LINE_LENGTH
+}
+
+/// The path of the entry `name` of `directory`, which is just `name` in [`CURRENT_DIRECTORY`]. Joined by the
+/// standard library, so paths with the `\\?\` prefix of Windows get a `\`.
+pub(crate) fn join(directory: &str, name: &str) -> String {
+    Path::new(directory).join(name).to_string_lossy().into_owned()
+}
+
+/// Types of the file systems, in `/proc/self/mounts`, that are made up by the kernel or a program rather than
+/// stored, with `fuse.` in front of the name of the program for FUSE
+#[cfg(target_os = "linux")]
+const VIRTUAL_FILE_SYSTEMS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts", "devtmpfs", "efivarfs",
+    "fuse", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore", "rpc_pipefs", "securityfs", "selinuxfs",
+    "sysfs", "tracefs",
+];
+
+/// The mount points of the virtual file systems, and their types, read once
+#[cfg(target_os = "linux")]
+fn virtual_mounts() -> &'static [(PathBuf, String)] {
+    static MOUNTS: OnceLock<Vec<(PathBuf, String)>> = OnceLock::new();
+    MOUNTS.get_or_init(|| {
+        let table = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        let mut mounts = Vec::new();
+        for line in table.lines() {
+            let mut fields = line.split(' ').skip(1);
+            let (point, kind) = match (fields.next(), fields.next()) {
+                (Some(point), Some(kind)) => (point, kind),
+                _ => continue,
+            };
+            if VIRTUAL_FILE_SYSTEMS.contains(&kind) || kind.starts_with("fuse.") {
+                mounts.push((PathBuf::from(unescape_mount_point(point)), String::from(kind)));
+            }
+        }
+        mounts
+    })
+}
+
+/// A mount point as it is written in `/proc/self/mounts`, with spaces, tabs, newlines and backslashes as octal
+/// escapes like `\040`
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(point: &str) -> String {
+    let mut unescaped = String::with_capacity(point.len());
+    let mut rest = point;
+    while let Some(start) = rest.find('\\') {
+        unescaped.push_str(&rest[..start]);
+        let escape = rest.get(start + 1..start + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match escape {
+            Some(byte) => {
+                unescaped.push(char::from(byte));
+                rest = &rest[start + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The type of the virtual file system mounted on the directory at `path`, like `proc` or `fuse.sshfs`, or
+/// `None` if there is none, or it is not known, as it is not on other systems than Linux
+///
+/// ```
+/// use rgrep::walk;
+///
+/// if cfg!(target_os = "linux") {
+///     assert_eq!(walk::virtual_file_system("/proc").as_deref(), Some("proc"));
+/// }
+/// assert_eq!(walk::virtual_file_system("src"), None);
+/// ```
+#[cfg(target_os = "linux")]
+pub fn virtual_file_system(path: &str) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    // the last mount on a directory hides those before it
+    virtual_mounts().iter().rev().find(|(point, _)| *point == path).map(|(_, kind)| kind.clone())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn virtual_file_system(_path: &str) -> Option<String> {
+    None
+}
+
+/// The device of a file, `None` where files have no identity
+fn device(metadata: std::io::Result<fs::Metadata>) -> Option<u64> {
+    metadata.ok().as_ref().and_then(search::identity).map(|(device, _)| device)
+}
+
+/// Whether `path` is a directory, following links
+pub(crate) fn is_dir(path: &str) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// The files to search for `inputs`, in order, with `-` for standard input. Directories that cannot be read are
+/// errors, the walk goes on after them.
+pub struct Walk {
+    /// Paths still to go through, the next one last, and whether they were given rather than found
+    pending: Vec<(String, bool)>,
+    action: DirectoryAction,
+    /// Leave out found files that look minified
+    skip_minified: bool,
+    /// Leave out found directories that virtual file systems are mounted on
+    skip_virtual: bool,
+    /// Only files whose base names match one of these, if there are any
+    include: Vec<Glob>,
+    /// No files whose base names match one of these
+    exclude: Vec<Glob>,
+    /// The rules of the ignore files read so far, if found paths they ignore are left out
+    ignore: Option<Ignore>,
+    /// The last file returned was found in a directory, not given
+    found: bool,
+    /// Directories gone into since [`take_entered`](Walk::take_entered) was last called
+    entered: Vec<String>,
+}
+
+impl Walk {
+    pub fn new(inputs: &[String], action: DirectoryAction) -> Walk {
+        let pending = inputs.iter().rev().map(|input| (input.clone(), true)).collect();
+        let (include, exclude) = (Vec::new(), Vec::new());
+        let (skip_minified, skip_virtual) = (false, true);
+        let (ignore, found, entered) = (None, false, Vec::new());
+        Walk { pending, action, skip_minified, skip_virtual, include, exclude, ignore, found, entered }
+    }
+
+    /// The walk [`search::run`](crate::search::run) goes through for `command_args`: their files, or standard
+    /// input or the current directory if there are none, with `-d`, `--include`, `--exclude`, `--skip-minified`,
+    /// `--skip-virtual`, `--gitignore` and `--order`. The files are still filtered by time, and by --dedupe, as they
+    /// are searched.
+    pub fn of(command_args: &CommandArgs) -> Walk {
+        let mut files = if command_args.files.is_empty() && command_args.directories == DirectoryAction::Recurse {
+            vec![String::from(CURRENT_DIRECTORY)]
+        } else if command_args.files.is_empty() {
+            vec![String::from("-")]
+        } else {
+            command_args.files.clone()
+        };
+        if command_args.order == Order::Sorted {
+            files.sort();
+        }
+        let mut walk = Walk::new(&files, command_args.directories)
+            .skip_minified(command_args.skip_minified)
+            .skip_virtual(!command_args.no_skip_virtual)
+            .gitignore(command_args.gitignore);
+        walk.include = command_args.include.iter().map(|glob| Glob::new(glob, false)).collect();
+        walk.exclude = command_args.exclude.iter().map(|glob| Glob::new(glob, false)).collect();
+        walk
+    }
+
+    /// Leaves out the files found in directories that look minified or generated, but not those that were given
+    pub fn skip_minified(mut self, yes: bool) -> Walk {
+        self.skip_minified = yes;
+        self
+    }
+
+    /// Leaves out the directories found that a [virtual file system](virtual_file_system) is mounted on, but not
+    /// those that were given; on by default
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use rgrep::walk::{self, DirectoryAction, Walk};
+    ///
+    /// // the sysfs of Linux is walked as it is given, but not what is mounted in it, like cgroup file systems
+    /// let walk = Walk::new(&[String::from("/sys/fs")], DirectoryAction::Recurse);
+    /// let found: Vec<String> = walk.filter_map(Result::ok).collect();
+    /// let mounted = |file: &String| {
+    ///     let mut directories = Path::new(file).ancestors().skip(1).take_while(|dir| *dir != Path::new("/sys/fs"));
+    ///     directories.any(|dir| walk::virtual_file_system(dir.to_str().unwrap()).is_some())
+    /// };
+    /// assert!(!found.iter().any(mounted));
+    /// ```
+    pub fn skip_virtual(mut self, yes: bool) -> Walk {
+        self.skip_virtual = yes;
+        self
+    }
+
+    /// Leaves out the `.git` directories found, and the files and directories found that the `.gitignore` and
+    /// `.ignore` files of the directories walked ignore; what was given is gone through all the same
+    ///
+    /// ```
+    /// use rgrep::walk::{DirectoryAction, Walk};
+    ///
+    /// let walk = Walk::new(&[String::from(".")], DirectoryAction::Recurse).gitignore(true);
+    /// let files: Vec<String> = walk.collect::<Result<_, _>>().unwrap();
+    /// assert!(files.contains(&String::from("./src/walk.rs")));
+    /// assert!(!files.iter().any(|file| file.starts_with("./.git/") || file.starts_with("./target/")));
+    /// ```
+    pub fn gitignore(mut self, yes: bool) -> Walk {
+        self.ignore = if yes { Some(Ignore::new()) } else { None };
+        self
+    }
+
+    /// Only goes through files whose base name matches `glob`, or another one given like it, like `--include`
+    pub fn include(mut self, glob: Glob) -> Walk {
+        self.include.push(glob);
+        self
+    }
+
+    /// Leaves out files whose base name matches `glob`, even if it is included, like `--exclude`
+    pub fn exclude(mut self, glob: Glob) -> Walk {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// Whether the base name of `file` is included and not excluded, like GNU grep matches it
+    pub(crate) fn selects(&self, file: &str) -> bool {
+        let name = Path::new(file).file_name().and_then(|name| name.to_str()).unwrap_or(file);
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(name)))
+            && !self.exclude.iter().any(|glob| glob.is_match(name))
+    }
+
+    /// Whether the last file returned was found in a directory, rather than given, so it may be gone by the time it
+    /// is read without the user having named it
+    ///
+    /// ```
+    /// use rgrep::walk::{DirectoryAction, Walk};
+    ///
+    /// let mut walk = Walk::new(&[String::from("Cargo.toml"), String::from("src")], DirectoryAction::Recurse);
+    /// assert_eq!(walk.next().unwrap().unwrap(), "Cargo.toml");
+    /// assert!(!walk.found());
+    /// assert!(walk.next().unwrap().unwrap().starts_with("src/"));
+    /// assert!(walk.found());
+    /// ```
+    pub fn found(&self) -> bool {
+        self.found
+    }
+
+    /// The directories gone into since the last call, for `--watch` to watch them
+    pub(crate) fn take_entered(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.entered)
+    }
+
+    /// Goes through `path` next, as if it had been found where it is, unless the ignore files read so far
+    /// ignore it; for `--watch` to walk what is created in the directories walked
+    pub(crate) fn add_found(&mut self, path: &str) {
+        let name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path);
+        let directory = is_dir(path);
+        let ignored = match &self.ignore {
+            Some(ignore) => directory && name == ".git" || ignore.is_ignored(path, directory),
+            None => false,
+        };
+        if ignored {
+            debug!("skipping {}, left out by --gitignore", path);
+        } else {
+            self.pending.push((String::from(path), false));
+        }
+    }
+
+    /// Adds the entries of `directory` to go through next
+    fn enter(&mut self, directory: &str) -> Result<()> {
+        let shown = if directory == CURRENT_DIRECTORY { "." } else { directory };
+        let entries = fs::read_dir(shown).map_err(|err| Error::read(shown, err))?;
+        self.entered.push(String::from(directory));
+        if let Some(ignore) = &mut self.ignore {
+            for name in [".gitignore", ".ignore"] {
+                let file = join(directory, name);
+                if Path::new(&file).is_file() {
+                    ignore.add_file(&file)?;
+                }
+            }
+        }
+        // a directory on another device than the one it is in is a mount point
+        let here = device(fs::metadata(shown));
+        let mut found = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| Error::read(shown, err))?;
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(name) => {
+                    debug!("skipping {:?} in {}, its name is not UTF-8", name, shown);
+                    continue;
+                }
+            };
+            let path = join(directory, &name);
+            let ignored = |is_dir: bool| match &self.ignore {
+                Some(ignore) => is_dir && name == ".git" || ignore.is_ignored(&path, is_dir),
+                None => false,
+            };
+            match entry.file_type() {
+                Ok(file_type) if ignored(file_type.is_dir()) => debug!("skipping {}, left out by --gitignore", path),
+                Ok(file_type) if file_type.is_dir() && self.skip_virtual && device(entry.metadata()) != here => {
+                    match virtual_file_system(&path) {
+                        Some(kind) => debug!("skipping {}, a {} file system", path, kind),
+                        None => found.push(path),
+                    }
+                }
+                Ok(file_type) if file_type.is_dir() || file_type.is_file() => found.push(path),
+                // the standard library takes junctions of Windows for links as well
+                Ok(file_type) if file_type.is_symlink() => debug!("skipping {}, a symbolic link or junction", path),
+                Ok(_) => debug!("skipping {}, not a regular file", path),
+                Err(err) => return Err(Error::read(&path, err)),
+            }
+        }
+        found.sort();
+        self.pending.extend(found.into_iter().rev().map(|path| (path, false)));
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        loop {
+            let (path, given) = self.pending.pop()?;
+            // standard input, and files, are searched as they are
+            let directory = path == CURRENT_DIRECTORY || is_dir(&path);
+            if !given && !directory && self.skip_minified && is_minified(&path) {
+                debug!("skipping {}, it looks minified", path);
+                continue;
+            }
+            if path == "-" || self.action == DirectoryAction::Read && given || !directory {
+                if path != "-" && !self.selects(&path) {
+                    debug!("skipping {}, left out by --include or --exclude", path);
+                    continue;
+                }
+                self.found = !given;
+                return Some(Ok(path));
+            }
+            if self.action == DirectoryAction::Skip {
+                debug!("skipping {}, a directory", path);
+                continue;
+            }
+            if let Err(err) = self.enter(&path) {
+                return Some(Err(err));
+            }
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! `--watch`: after searching, keep running and search what is added to the files, like `tail -f`.
+//!
+//! Every file searched is watched from where its search ended, so nothing written in the meantime is missed, and
+//! files the search left out, with --include, --newer-than or --dedupe, are not watched at all. With
+//! `-d recurse`, the directories walked are watched too, and what is created in them is walked and searched the
+//! way it would have been from the start. On Linux, the directories are watched with inotify, so a file that is
+//! created, replaced or appended to is searched as soon as that happens. Elsewhere, they are looked at a few
+//! times a second.
+//!
+//! Only complete lines are searched; a line still being written is searched once its end arrives. The lines added
+//! to a file are searched on their own, except where that would find other lines than searching all of it: when
+//! its text is extracted by --pre, decoded or masked by --only, or with --all-match or --multiline. Such files are
+//! searched again from the start, and only the lines after those found before are passed on.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use crate::args::CommandArgs;
+use crate::error::{Error, Result};
+use crate::matcher::Matcher;
+use crate::search::{self, Encoding, Metrics, Searcher, Stats, Summary};
+use crate::signal;
+use crate::sink::{FileSummary, Sink, SinkLine};
+use crate::walk::{self, DirectoryAction, Walk};
+
+/// What is known about a watched file
+struct Watched {
+    path: String,
+    /// Where the next line starts, in bytes of the text searched
+    offset: u64,
+    /// Lines before `offset`
+    lines: u64,
+    /// Device and inode of the file, to notice when it was replaced by another one
+    identity: Option<(u64, u64)>,
+    /// Size and modification time of the file when it was last searched, `None` if it was not there
+    stamp: Option<(u64, SystemTime)>,
+}
+
+impl Watched {
+    /// A file whose search came to `summary`
+    fn searched(path: &str, summary: &FileSummary) -> Watched {
+        let metadata = fs::metadata(path).ok();
+        Watched {
+            path: String::from(path),
+            offset: summary.bytes_searched,
+            lines: summary.lines_searched,
+            identity: metadata.as_ref().and_then(search::identity),
+            stamp: metadata.as_ref().and_then(stamp),
+        }
+    }
+
+    /// A file that is not there yet, to search from the start once it is
+    fn missing(path: &str) -> Watched {
+        Watched { path: String::from(path), offset: 0, lines: 0, identity: None, stamp: None }
+    }
+
+    /// Forgets the file, so it is searched from the start when it is there again
+    fn forget(&mut self) {
+        *self = Watched::missing(&self.path);
+    }
+
+    /// Searches the lines added since the last time, returns the summary of that search, if there were any
+    fn search_new_lines(
+        &mut self,
+        searcher: &Searcher,
+        matcher: &dyn Matcher,
+        sink: &mut dyn Sink,
+    ) -> Result<Option<FileSummary>> {
+        let mut added = Vec::new();
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            // removed since it was looked at, a moment ago
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.forget();
+                return Ok(None);
+            }
+            Err(err) => return Err(Error::read(&self.path, err)),
+        };
+        file.seek(SeekFrom::Start(self.offset))
+            .and_then(|_| file.read_to_end(&mut added))
+            .map_err(|err| Error::read(&self.path, err))?;
+        let complete = match added.iter().rposition(|&byte| byte == b'\n') {
+            Some(end) => &added[..=end],
+            None => return Ok(None),
+        };
+        let mut sink = ContinuedSink { inner: sink, lines: self.lines as usize, offset: self.offset };
+        let summary = searcher.search_slice(matcher, complete, &self.path, &mut sink)?;
+        self.offset += complete.len() as u64;
+        self.lines += complete.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        Ok(Some(summary))
+    }
+}
+
+/// Size and modification time of a file, which change when it is written to
+fn stamp(metadata: &fs::Metadata) -> Option<(u64, SystemTime)> {
+    metadata.modified().ok().map(|modified| (metadata.len(), modified))
+}
+
+/// Whether the lines added to `path` are only found right by searching all of it again
+fn searched_whole(command_args: &CommandArgs, path: &str) -> bool {
+    command_args.all_match
+        || command_args.multiline
+        || command_args.only.is_some()
+        || !matches!(command_args.encoding, Encoding::Auto | Encoding::Utf8)
+        || search::extracts(command_args, path)
+}
+
+/// Passes everything on to the metrics of the command line, keeping where the search of every input ended, and
+/// the inputs that were not there, to watch them
+struct Recorder {
+    metrics: Option<Arc<dyn Metrics>>,
+    inputs: Mutex<Vec<Watched>>,
+}
+
+impl Recorder {
+    /// The inputs searched since the last call
+    fn take(&self) -> Vec<Watched> {
+        std::mem::take(&mut *self.inputs.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    fn record(&self, watched: Watched) {
+        self.inputs.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(watched);
+    }
+}
+
+impl Metrics for Recorder {
+    fn searched(&self, path: &str, summary: &FileSummary) {
+        self.record(Watched::searched(path, summary));
+        if let Some(metrics) = &self.metrics {
+            metrics.searched(path, summary);
+        }
+    }
+
+    fn interrupted(&self, path: &str, summary: &FileSummary) {
+        if let Some(metrics) = &self.metrics {
+            metrics.interrupted(path, summary);
+        }
+    }
+
+    fn failed(&self, error: &Error) {
+        if let Error::Io { path: Some(path), source } = error {
+            if source.kind() == io::ErrorKind::NotFound {
+                self.record(Watched::missing(&path.to_string_lossy()));
+            }
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.failed(error);
+        }
+    }
+}
+
+/// Passes results on with line numbers and offsets counted from the start of the file rather than from where
+/// the search of the added lines started
+struct ContinuedSink<'s> {
+    inner: &'s mut dyn Sink,
+    lines: usize,
+    offset: u64,
+}
+
+impl ContinuedSink<'_> {
+    fn continued<'a>(&self, line: &SinkLine<'a>) -> SinkLine<'a> {
+        SinkLine { line_number: line.line_number + self.lines, offset: line.offset + self.offset, ..*line }
+    }
+}
+
+impl Sink for ContinuedSink<'_> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = self.continued(line);
+        self.inner.matched(&line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = self.continued(line);
+        self.inner.context(&line)
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        let line = self.continued(line);
+        self.inner.enclosing(&line)
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        self.inner.binary_match(path)
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.inner.end(path, summary)
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+}
+
+/// Passes the results of a search on, without the lines of the input found before, but not that the search is
+/// over: it only is once watching stops. Counts what it passed on instead.
+struct UnseenSink<'s> {
+    inner: &'s mut dyn Sink,
+    /// Lines of the input searched before, which were passed on then
+    seen: u64,
+    /// Matched lines passed on for the input being searched
+    matched: u64,
+    /// What was passed on, and the counters of the search once it is over
+    stats: Stats,
+}
+
+impl UnseenSink<'_> {
+    fn unseen(&self, line: &SinkLine) -> bool {
+        line.line_number as u64 > self.seen
+    }
+}
+
+impl Sink for UnseenSink<'_> {
+    fn begin(&mut self, path: &str) -> io::Result<()> {
+        self.matched = 0;
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, line: &SinkLine) -> io::Result<()> {
+        if !self.unseen(line) {
+            return Ok(());
+        }
+        self.matched += 1;
+        self.inner.matched(line)
+    }
+
+    fn context(&mut self, line: &SinkLine) -> io::Result<()> {
+        if self.unseen(line) {
+            self.inner.context(line)?;
+        }
+        Ok(())
+    }
+
+    fn enclosing(&mut self, line: &SinkLine) -> io::Result<()> {
+        if self.unseen(line) {
+            self.inner.enclosing(line)?;
+        }
+        Ok(())
+    }
+
+    fn binary_match(&mut self, path: &str) -> io::Result<()> {
+        // reported when the file was first searched
+        if self.seen == 0 {
+            self.inner.binary_match(path)?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, path: &str, summary: &FileSummary) -> io::Result<()> {
+        self.stats.matched_lines += self.matched;
+        if self.matched > 0 {
+            self.stats.files_with_matches += 1;
+        }
+        self.inner.end(path, &FileSummary { matched_lines: self.matched, ..summary.clone() })
+    }
+
+    fn error(&mut self, error: &Error) -> io::Result<()> {
+        self.inner.error(error)
+    }
+
+    fn replaced(&mut self, path: &str, original: &[u8], replaced: &[u8]) -> io::Result<()> {
+        self.inner.replaced(path, original, replaced)
+    }
+
+    fn finish(&mut self, stats: &Stats, _status: i32) -> io::Result<()> {
+        self.stats.files_searched = stats.files_searched;
+        self.stats.bytes_searched = stats.bytes_searched;
+        self.stats.errors = stats.errors;
+        self.stats.files = stats.files.clone();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The files and directories watched, and what was found in them so far
+struct Watch<'a> {
+    /// The command line, with the watched files recorded and the counters left to the end
+    command_args: CommandArgs,
+    matcher: &'a dyn Matcher,
+    /// For the lines added to files
+    searcher: Searcher,
+    recorder: Arc<Recorder>,
+    files: Vec<Watched>,
+    /// The walk of the directories, with -d recurse, to walk what is created in them
+    walk: Option<Walk>,
+    /// Files the walk found that the search left out, e.g. with --newer-than or --dedupe, so they stay left out
+    left_out: HashSet<String>,
+    watcher: Watcher,
+    summary: Summary,
+    stats: Stats,
+}
+
+impl Watch<'_> {
+    /// Searches `inputs` the way the command line says, passing on only the lines after the first `seen`, of
+    /// `seen_bytes`; returns the inputs searched, which are watched from here on
+    fn search(&mut self, inputs: Vec<String>, seen: u64, seen_bytes: u64, sink: &mut dyn Sink) -> Result<Vec<String>> {
+        let command_args = CommandArgs { files: inputs, files_listed: true, ..self.command_args.clone() };
+        let mut unseen = UnseenSink { inner: sink, seen, matched: 0, stats: Stats::default() };
+        let summary = search::run_with_sink(&command_args, self.matcher, &mut unseen)?;
+        let stats = unseen.stats;
+        self.add(&stats, stats.bytes_searched.saturating_sub(seen_bytes));
+        self.stats.errors += stats.errors;
+        self.stats.files.extend(stats.files);
+        self.summary.had_errors |= summary.had_errors;
+        self.summary.skipped += summary.skipped;
+        self.summary.interrupted |= summary.interrupted;
+
+        let mut searched = Vec::new();
+        for watched in self.recorder.take() {
+            searched.push(watched.path.clone());
+            match self.files.iter_mut().find(|file| file.path == watched.path) {
+                Some(file) => *file = watched,
+                None => self.files.push(watched),
+            }
+        }
+        Ok(searched)
+    }
+
+    /// Counts the search of an input, or of what was added to it
+    fn add(&mut self, stats: &Stats, bytes_searched: u64) {
+        self.stats.files_searched += stats.files_searched;
+        self.stats.bytes_searched += bytes_searched;
+        self.stats.matched_lines += stats.matched_lines;
+        self.stats.files_with_matches += stats.files_with_matches;
+        self.summary.matched |= stats.matched_lines > 0;
+    }
+
+    /// Reports an error reading an input, and goes on
+    fn failed(&mut self, err: Error, sink: &mut dyn Sink) -> Result<()> {
+        match err {
+            Error::Io { path: Some(_), .. } => {
+                if !self.command_args.no_messages {
+                    sink.error(&err).map_err(Error::write)?;
+                }
+                self.summary.had_errors = true;
+                self.stats.errors += 1;
+                Ok(())
+            }
+            Error::Interrupted => {
+                self.summary.interrupted = true;
+                Ok(())
+            }
+            err => Err(err),
+        }
+    }
+
+    /// Searches what changed about the entry `name` of `directory`
+    fn changed(&mut self, event: &Event, sink: &mut dyn Sink) -> Result<()> {
+        let path = walk::join(&event.directory, &event.name);
+        if let Some(index) = self.files.iter().position(|file| Path::new(&file.path) == Path::new(&path)) {
+            return self.search_file(index, sink);
+        }
+        let walk = match &mut self.walk {
+            Some(walk) if event.recursive && !self.left_out.contains(&path) => walk,
+            _ => return Ok(()),
+        };
+        // created in a directory walked, so walked the way it would have been
+        walk.add_found(&path);
+        let mut found = Vec::new();
+        let mut errors = Vec::new();
+        for file in walk.by_ref() {
+            match file {
+                Ok(file) => found.push(file),
+                Err(err) => errors.push(err),
+            }
+        }
+        for directory in walk.take_entered() {
+            if let Err(err) = self.watcher.add(&directory, true) {
+                errors.push(Error::read(&directory, err));
+            }
+        }
+        for err in errors {
+            self.failed(err, sink)?;
+        }
+        if !found.is_empty() {
+            let searched = self.search(found.clone(), 0, 0, sink)?;
+            self.left_out.extend(found.into_iter().filter(|file| !searched.contains(file)));
+        }
+        if !is_known(&self.files, &path) && Path::new(&path).is_file() {
+            self.left_out.insert(path);
+        }
+        Ok(())
+    }
+
+    /// Searches the lines added to the file at `index` since the last time
+    fn search_file(&mut self, index: usize, sink: &mut dyn Sink) -> Result<()> {
+        let file = &mut self.files[index];
+        let metadata = match fs::metadata(&file.path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                file.forget();
+                return Ok(());
+            }
+            Err(err) => {
+                let err = Error::read(&file.path, err);
+                return self.failed(err, sink);
+            }
+        };
+        let identity = search::identity(&metadata);
+        let truncated = file.stamp.is_some_and(|(size, _)| metadata.len() < size);
+        if identity != file.identity || truncated || metadata.len() < file.offset {
+            debug!("{} was replaced or truncated, searching it from the start", file.path);
+            file.forget();
+        }
+        let now = stamp(&metadata);
+        if now.is_some() && now == file.stamp {
+            return Ok(());
+        }
+        if file.stamp.is_none() || searched_whole(&self.command_args, &file.path) {
+            let (path, seen, seen_bytes) = (file.path.clone(), file.lines, file.offset);
+            let searched = self.search(vec![path.clone()], seen, seen_bytes, sink)?;
+            // left out by what the search leaves out, e.g. --include or --newer-than
+            if !searched.contains(&path) {
+                self.files.retain(|file| file.path != path);
+                self.left_out.insert(path);
+            }
+            return Ok(());
+        }
+        file.stamp = now;
+        match file.search_new_lines(&self.searcher, self.matcher, sink) {
+            Ok(Some(file_summary)) => {
+                let stats = Stats {
+                    files_searched: 1,
+                    matched_lines: file_summary.matched_lines,
+                    files_with_matches: u64::from(file_summary.matched_lines > 0),
+                    ..Stats::default()
+                };
+                self.add(&stats, file_summary.bytes_searched);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(err) => self.failed(err, sink),
+        }
+    }
+}
+
+/// Whether `path` is one of the watched files
+fn is_known(files: &[Watched], path: &str) -> bool {
+    files.iter().any(|file| Path::new(&file.path) == Path::new(path))
+}
+
+/// The directory a file given on the command line is in, as the walk names directories
+fn parent(file: &str) -> &str {
+    Path::new(file).parent().and_then(|parent| parent.to_str()).unwrap_or(walk::CURRENT_DIRECTORY)
+}
+
+/// Searches like [`search::run`], then keeps searching the lines added to the files until Ctrl-C is pressed.
+/// --stats, the summary of --json and --summary are written at the end and count what was found by both.
+pub fn run<W: Write>(command_args: &CommandArgs, matcher: &dyn Matcher, out: W) -> Result<Summary> {
+    let started = Instant::now();
+    let mut sink = search::output_sink(command_args, matcher, out);
+    let recorder = Arc::new(Recorder { metrics: command_args.metrics.clone(), inputs: Mutex::new(Vec::new()) });
+    let watch_args =
+        CommandArgs { stats: false, summary: false, metrics: Some(recorder.clone()), ..command_args.clone() };
+
+    // watched before the search, so what is written while it goes on is not missed
+    let mut watcher = Watcher::new()?;
+    for file in command_args.files.iter().filter(|file| !walk::is_dir(file)) {
+        watcher.add(parent(file), false).map_err(|err| Error::read(file, err))?;
+    }
+    let mut walk = None;
+    let mut listed = Vec::new();
+    if command_args.directories == DirectoryAction::Recurse {
+        let mut listing = Walk::of(command_args);
+        // what cannot be read is reported by the search
+        listed = listing.by_ref().filter_map(|file| file.ok()).collect();
+        for directory in listing.take_entered() {
+            if let Err(err) = watcher.add(&directory, true) {
+                debug!("not watching {}: {}", directory, err);
+            }
+        }
+        walk = Some(listing);
+    }
+
+    let mut watch = Watch {
+        command_args: watch_args,
+        matcher,
+        searcher: search::searcher(command_args),
+        recorder,
+        files: Vec::new(),
+        walk,
+        left_out: HashSet::new(),
+        watcher,
+        summary: Summary { matched: false, had_errors: false, skipped: 0, interrupted: false },
+        stats: Stats::default(),
+    };
+    let mut unseen = UnseenSink { inner: sink.as_mut(), seen: 0, matched: 0, stats: Stats::default() };
+    watch.summary = search::run_with_sink(&watch.command_args, matcher, &mut unseen)?;
+    let stats = unseen.stats;
+    watch.add(&stats, stats.bytes_searched);
+    watch.stats.errors = stats.errors;
+    watch.stats.files = stats.files;
+    watch.files = watch.recorder.take();
+    watch.left_out = listed.into_iter().filter(|file| !is_known(&watch.files, file)).collect();
+
+    while !watch.summary.interrupted {
+        sink.flush().map_err(Error::write)?;
+        let events = watch.watcher.wait().map_err(Error::write)?;
+        if signal::interrupted() {
+            watch.summary.interrupted = true;
+            break;
+        }
+        for event in &events {
+            watch.changed(event, sink.as_mut())?;
+        }
+    }
+    let (summary, mut stats) = (watch.summary, watch.stats);
+    stats.elapsed = started.elapsed();
+    if command_args.stats {
+        sink.stats(&stats).map_err(Error::write)?;
+    }
+    sink.finish(&stats, summary.status()).map_err(Error::write)?;
+    sink.flush().map_err(Error::write)?;
+    if command_args.summary {
+        eprintln!("{}", stats.summary());
+    }
+    Ok(summary)
+}
+
+/// How long to wait for changes before looking whether Ctrl-C was pressed, in milliseconds
+const WAIT_MS: u64 = 250;
+
+/// Something may have happened to the entry `name` of `directory`
+#[derive(PartialEq)]
+struct Event {
+    directory: String,
+    name: String,
+    /// The directory is walked, so what is created in it is searched
+    recursive: bool,
+}
+
+/// The path to hand the system for a directory named like the walk names it
+fn system_path(directory: &str) -> &str {
+    if directory == walk::CURRENT_DIRECTORY {
+        "."
+    } else {
+        directory
+    }
+}
+
+/// Watches directories with inotify
+#[cfg(target_os = "linux")]
+struct Watcher {
+    inotify: File,
+    /// Watch descriptor of every directory, the directory, and whether it is walked
+    directories: Vec<(i32, String, bool)>,
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::os::raw::{c_char, c_int, c_short, c_uint};
+
+    pub const IN_CLOEXEC: c_int = 0o2000000;
+    pub const IN_MODIFY: u32 = 0x2;
+    pub const IN_CLOSE_WRITE: u32 = 0x8;
+    pub const IN_MOVED_TO: u32 = 0x80;
+    pub const IN_CREATE: u32 = 0x100;
+    pub const POLLIN: c_short = 1;
+
+    #[repr(C)]
+    pub struct PollFd {
+        pub fd: c_int,
+        pub events: c_short,
+        pub revents: c_short,
+    }
+
+    extern "C" {
+        pub fn inotify_init1(flags: c_int) -> c_int;
+        pub fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+        pub fn poll(fds: *mut PollFd, count: c_uint, timeout: c_int) -> c_int;
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    fn new() -> Result<Watcher> {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: inotify_init1 takes no pointers; the descriptor it returns is owned by the File from here on
+        let inotify = unsafe {
+            let fd = inotify::inotify_init1(inotify::IN_CLOEXEC);
+            if fd < 0 {
+                return Err(Error::write(io::Error::last_os_error()));
+            }
+            File::from_raw_fd(fd)
+        };
+        Ok(Watcher { inotify, directories: Vec::new() })
+    }
+
+    /// Watches `directory`, named like the walk names it
+    fn add(&mut self, directory: &str, recursive: bool) -> io::Result<()> {
+        use std::ffi::CString;
+        use std::os::unix::io::AsRawFd;
+
+        let path = CString::new(system_path(directory))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file name contains a NUL byte"))?;
+        let mask = inotify::IN_MODIFY | inotify::IN_CLOSE_WRITE | inotify::IN_MOVED_TO | inotify::IN_CREATE;
+        // SAFETY: the path is NUL-terminated and outlives the call
+        let descriptor = unsafe { inotify::inotify_add_watch(self.inotify.as_raw_fd(), path.as_ptr(), mask) };
+        if descriptor < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // the same directory is watched once, however it is named
+        match self.directories.iter_mut().find(|(known, name, _)| *known == descriptor && name == directory) {
+            Some((_, _, walked)) => *walked |= recursive,
+            None => self.directories.push((descriptor, String::from(directory), recursive)),
+        }
+        Ok(())
+    }
+
+    /// Waits a little for changes, returns what may have changed
+    fn wait(&mut self) -> io::Result<Vec<Event>> {
+        use std::os::unix::io::AsRawFd;
+
+        let mut poll_fd = inotify::PollFd { fd: self.inotify.as_raw_fd(), events: inotify::POLLIN, revents: 0 };
+        // SAFETY: poll only writes `revents` of the one descriptor it is given
+        let ready = unsafe { inotify::poll(&mut poll_fd, 1, WAIT_MS as i32) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            // Ctrl-C, which the caller looks for
+            return if err.kind() == io::ErrorKind::Interrupted { Ok(Vec::new()) } else { Err(err) };
+        }
+        if ready == 0 {
+            return Ok(Vec::new());
+        }
+
+        // struct inotify_event: wd, mask, cookie and len, then len bytes of NUL-padded name
+        let mut buffer = [0u8; 16 * 1024];
+        let read = self.inotify.read(&mut buffer)?;
+        let mut events = Vec::new();
+        let mut at = 0;
+        while at + 16 <= read {
+            let field = |index: usize| {
+                let start = at + 4 * index;
+                u32::from_ne_bytes([buffer[start], buffer[start + 1], buffer[start + 2], buffer[start + 3]])
+            };
+            let descriptor = field(0) as i32;
+            let name_len = field(3) as usize;
+            let name = &buffer[at + 16..(at + 16 + name_len).min(read)];
+            let name = &name[..name.iter().position(|&byte| byte == 0).unwrap_or(name.len())];
+            // the walk leaves names that are not UTF-8 out as well
+            if let Ok(name) = std::str::from_utf8(name) {
+                for (_, directory, recursive) in self.directories.iter().filter(|(known, ..)| *known == descriptor) {
+                    let event = Event { directory: directory.clone(), name: String::from(name), recursive: *recursive };
+                    if !events.contains(&event) {
+                        events.push(event);
+                    }
+                }
+            }
+            at += 16 + name_len;
+        }
+        Ok(events)
+    }
+}
+
+/// Looks at the directories a few times a second, where there is no notification API to use
+#[cfg(not(target_os = "linux"))]
+struct Watcher {
+    /// Every directory, and whether it is walked
+    directories: Vec<(String, bool)>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    fn new() -> Result<Watcher> {
+        Ok(Watcher { directories: Vec::new() })
+    }
+
+    fn add(&mut self, directory: &str, recursive: bool) -> io::Result<()> {
+        fs::read_dir(system_path(directory))?;
+        match self.directories.iter_mut().find(|(name, _)| name == directory) {
+            Some((_, walked)) => *walked |= recursive,
+            None => self.directories.push((String::from(directory), recursive)),
+        }
+        Ok(())
+    }
+
+    /// Waits a little, returns every entry of the directories, which may all have changed
+    fn wait(&mut self) -> io::Result<Vec<Event>> {
+        std::thread::sleep(std::time::Duration::from_millis(WAIT_MS));
+        let mut events = Vec::new();
+        for (directory, recursive) in &self.directories {
+            // gone or unreadable for now, what was in it is looked at again once it is back
+            let entries = match fs::read_dir(system_path(directory)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for name in entries.filter_map(|entry| entry.ok()?.file_name().into_string().ok()) {
+                events.push(Event { directory: directory.clone(), name, recursive: *recursive });
+            }
+        }
+        Ok(events)
+    }
+}
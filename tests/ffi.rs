@@ -0,0 +1,155 @@
+//! The C interface, called the way a C program calls it, and `include/rgrep.h` checked against the functions and
+//! types in `src/ffi.rs`, so the header cannot drift from what the library exports.
+#![cfg(feature = "ffi")]
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::c_char;
+use std::ptr;
+
+use rgrep::ffi::*;
+
+/// The matches of `pattern` with `flags` in `haystack`: their line numbers and text
+fn search(pattern: &str, flags: u32, haystack: &[u8]) -> Result<Vec<(u64, String)>, String> {
+    let pattern = CString::new(pattern).unwrap();
+    let mut error: *mut c_char = ptr::null_mut();
+    unsafe {
+        let matcher = rgrep_matcher_new(pattern.as_ptr(), flags, &mut error);
+        if matcher.is_null() {
+            let message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            rgrep_string_free(error);
+            return Err(message);
+        }
+        let matches = rgrep_search(matcher, haystack.as_ptr(), haystack.len(), &mut error);
+        assert!(!matches.is_null());
+        let mut found = Vec::new();
+        let mut next = FfiMatch { line_number: 0, offset: 0, line: ptr::null(), line_len: 0, start: 0, end: 0 };
+        while rgrep_matches_next(matches, &mut next) {
+            let line = std::slice::from_raw_parts(next.line, next.line_len);
+            found.push((next.line_number, String::from_utf8_lossy(&line[next.start..next.end]).into_owned()));
+        }
+        rgrep_matches_free(matches);
+        rgrep_matcher_free(matcher);
+        Ok(found)
+    }
+}
+
+#[test]
+fn searches_buffers() {
+    let found = search("fo+", RGREP_IGNORE_CASE, b"Foo\nbar\nfoo fo\n").unwrap();
+    assert_eq!(found, [(1, "Foo"), (3, "foo"), (3, "fo")].map(|(line, text)| (line, String::from(text))));
+    let found = search("a.b\nx", RGREP_FIXED_STRINGS, b"a.b\nayb\nx\0y\n").unwrap();
+    assert_eq!(found, [(1, "a.b"), (3, "x")].map(|(line, text)| (line, String::from(text))));
+    let found = search(r"b\nc", RGREP_MULTILINE, b"a\nb\nc\n").unwrap();
+    assert_eq!(found, [(2, "b"), (3, "c")].map(|(line, text)| (line, String::from(text))));
+}
+
+#[test]
+fn reports_errors() {
+    let error = search("(a", 0, b"").unwrap_err();
+    assert!(error.contains("unclosed group"), "{}", error);
+    unsafe {
+        let mut error: *mut c_char = ptr::null_mut();
+        assert!(rgrep_matcher_new(ptr::null(), 0, &mut error).is_null());
+        assert_eq!(CStr::from_ptr(error).to_str(), Ok("pattern is null"));
+        rgrep_string_free(error);
+        assert!(rgrep_search(ptr::null(), ptr::null(), 0, ptr::null_mut()).is_null());
+        // everything can be freed when it is null
+        rgrep_matcher_free(ptr::null_mut());
+        rgrep_matches_free(ptr::null_mut());
+        rgrep_string_free(ptr::null_mut());
+    }
+}
+
+/// `text` without its comments, with runs of white space made a single space
+fn without_comments(text: &str) -> String {
+    let mut rest = text;
+    let mut code = String::new();
+    while let Some(start) = rest.find("/*") {
+        code.push_str(&rest[..start]);
+        rest = &rest[start + rest[start..].find("*/").expect("comments are closed") + 2..];
+    }
+    code.push_str(rest);
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The C type of a Rust type of the interface
+fn c_type(rust: &str) -> String {
+    if let Some(pointee) = rust.strip_prefix("*const ") {
+        return format!("const {} *", c_type(pointee));
+    }
+    if let Some(pointee) = rust.strip_prefix("*mut ") {
+        return format!("{} *", c_type(pointee)).replace("* *", "**");
+    }
+    String::from(match rust {
+        "c_char" => "char",
+        "u8" => "uint8_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        "bool" => "bool",
+        "FfiMatcher" => "rgrep_matcher",
+        "FfiMatches" => "rgrep_matches",
+        "FfiMatch" => "rgrep_match",
+        other => panic!("no C type for {}", other),
+    })
+}
+
+/// `type name`, the way the header writes them: pointers are next to the name
+fn declaration(c_type: &str, name: &str) -> String {
+    if c_type.ends_with('*') {
+        format!("{}{}", c_type, name)
+    } else {
+        format!("{} {}", c_type, name)
+    }
+}
+
+#[test]
+fn header_matches_the_library() {
+    let header = without_comments(&fs::read_to_string("include/rgrep.h").unwrap());
+    let source = fs::read_to_string("src/ffi.rs").unwrap();
+    let source = source.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut functions = 0;
+    for signature in source.split("pub unsafe extern \"C\" fn ").skip(1) {
+        let signature = &signature[..signature.find(" {").unwrap()];
+        let (name, rest) = signature.split_once('(').unwrap();
+        let (parameters, returned) = rest.rsplit_once(')').unwrap();
+        let parameters: Vec<String> = parameters
+            .split(',')
+            .map(str::trim)
+            .filter(|parameter| !parameter.is_empty())
+            .map(|parameter| {
+                let (name, rust) = parameter.split_once(": ").unwrap();
+                declaration(&c_type(rust), name)
+            })
+            .collect();
+        let returned = returned.trim().strip_prefix("-> ").map_or_else(|| String::from("void"), c_type);
+        let prototype = format!("{}({});", declaration(&returned, name), parameters.join(", "));
+        assert!(header.contains(&prototype), "include/rgrep.h does not declare {}", prototype);
+        functions += 1;
+    }
+    // and it declares no others
+    assert_eq!(header.matches(");").count(), functions);
+
+    let fields = source.split("pub struct FfiMatch {").nth(1).unwrap();
+    let fields: Vec<String> = fields[..fields.find('}').unwrap()]
+        .split(',')
+        .filter_map(|field| field.split("pub ").nth(1))
+        .map(|field| {
+            let (name, rust) = field.split_once(": ").unwrap();
+            format!("{};", declaration(&c_type(rust.trim()), name))
+        })
+        .collect();
+    let structure = format!("typedef struct rgrep_match {{ {} }} rgrep_match;", fields.join(" "));
+    assert!(header.contains(&structure), "include/rgrep.h does not declare {}", structure);
+
+    for (name, value) in [
+        ("RGREP_IGNORE_CASE", RGREP_IGNORE_CASE),
+        ("RGREP_FIXED_STRINGS", RGREP_FIXED_STRINGS),
+        ("RGREP_MULTILINE", RGREP_MULTILINE),
+    ] {
+        let define = format!("#define {} (1u << {})", name, value.trailing_zeros());
+        assert!(header.contains(&define), "include/rgrep.h does not have {}", define);
+    }
+}
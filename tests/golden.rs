@@ -0,0 +1,310 @@
+//! Runs the rgrep binary against the fixture tree in `tests/golden/tree` and compares what it prints and its exit
+//! status with the golden files in `tests/golden/cases`.
+//!
+//! A case file starts with headers, then has sections that each run until the next one:
+//!
+//! ```text
+//! args: -A 1 fox poem.txt
+//! env: TERM=dumb
+//! status: 0
+//! --- stdin
+//! only read if the case has this section
+//! --- stdout
+//! poem.txt:1:The quick brown fox
+//! --- stderr
+//! ```
+//!
+//! `args` is split like a shell would. `env` is optional and can be repeated, it sets an environment variable.
+//! `interrupt: MS` is optional too, it sends the binary SIGINT, like Ctrl-C, after MS milliseconds, for modes that
+//! run until then like `--watch`; such cases only run on Unix. The binary runs in the fixture tree, without a
+//! config file, `RGREP_OPTIONS` or `GREP_COLORS`. Run with `RGREP_BLESS=1` to write the actual output to the case
+//! files instead of comparing, for new cases and intended changes; review the diff before committing it.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use rgrep::config::split_shell_words;
+use rgrep::daemon::MAX_CACHED_MATCHERS;
+
+const SECTIONS: &[&str] = &["stdin", "stdout", "stderr"];
+
+#[derive(Debug, Default, PartialEq)]
+struct Case {
+    args: String,
+    env: Vec<(String, String)>,
+    /// Milliseconds after which the binary gets SIGINT
+    interrupt: Option<u64>,
+    status: i32,
+    stdin: Option<String>,
+    stdout: String,
+    stderr: String,
+}
+
+impl Case {
+    fn parse(text: &str) -> Case {
+        let mut case = Case::default();
+        let mut section: Option<String> = None;
+        let mut body = String::new();
+        for line in text.split_inclusive('\n') {
+            if let Some(name) = line.trim_end().strip_prefix("--- ").filter(|name| SECTIONS.contains(name)) {
+                if let Some(previous) = section.replace(String::from(name)) {
+                    case.set(&previous, std::mem::take(&mut body));
+                }
+            } else if section.is_some() {
+                body.push_str(line);
+            } else if let Some(args) = line.strip_prefix("args:") {
+                case.args = String::from(args.trim());
+            } else if let Some(variable) = line.strip_prefix("env:") {
+                let (name, value) = variable.trim().split_once('=').expect("env is NAME=VALUE");
+                case.env.push((String::from(name), String::from(value)));
+            } else if let Some(interrupt) = line.strip_prefix("interrupt:") {
+                case.interrupt = Some(interrupt.trim().parse().expect("interrupt is a number of milliseconds"));
+            } else if let Some(status) = line.strip_prefix("status:") {
+                case.status = status.trim().parse().expect("status is a number");
+            } else if !line.trim().is_empty() {
+                panic!("unexpected line in case header: {:?}", line);
+            }
+        }
+        if let Some(section) = section {
+            case.set(&section, body);
+        }
+        case
+    }
+
+    fn set(&mut self, section: &str, body: String) {
+        match section {
+            "stdin" => self.stdin = Some(body),
+            "stdout" => self.stdout = body,
+            _ => self.stderr = body,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut text = format!("args: {}\n", self.args);
+        for (name, value) in &self.env {
+            text.push_str(&format!("env: {}={}\n", name, value));
+        }
+        if let Some(interrupt) = self.interrupt {
+            text.push_str(&format!("interrupt: {}\n", interrupt));
+        }
+        text.push_str(&format!("status: {}\n", self.status));
+        if let Some(stdin) = &self.stdin {
+            text.push_str(&format!("--- stdin\n{}", stdin));
+        }
+        text.push_str(&format!("--- stdout\n{}--- stderr\n{}", self.stdout, self.stderr));
+        text
+    }
+
+    /// Runs the binary with the arguments and input of this case, returns what it did
+    fn run(&self, tree: &Path) -> Case {
+        let args = split_shell_words(&self.args).expect("args can be split");
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rgrep"));
+        command
+            .args(&args)
+            .current_dir(tree)
+            .env("RGREP_CONFIG", "")
+            .env_remove("RGREP_OPTIONS")
+            .env_remove("GREP_COLORS")
+            .envs(self.env.iter().map(|(name, value)| (name, value)))
+            .stdin(if self.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command.spawn().expect("rgrep starts");
+        if let Some(stdin) = &self.stdin {
+            // rgrep may exit before reading it, e.g. on a usage error
+            let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+        }
+        if let Some(interrupt) = self.interrupt {
+            std::thread::sleep(Duration::from_millis(interrupt));
+            let killed = Command::new("kill").arg("-INT").arg(child.id().to_string()).status();
+            assert!(killed.is_ok_and(|status| status.success()), "kill -INT {} failed", child.id());
+        }
+        let output = child.wait_with_output().unwrap();
+        Case {
+            args: self.args.clone(),
+            env: self.env.clone(),
+            interrupt: self.interrupt,
+            status: output.status.code().unwrap_or(-1),
+            stdin: self.stdin.clone(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
+
+#[test]
+fn golden() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    let tree = root.join("tree");
+    let bless = env::var_os("RGREP_BLESS").is_some();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(root.join("cases")).unwrap().map(|entry| entry.unwrap().path()).collect();
+    paths.sort();
+    let mut failures = Vec::new();
+    for path in &paths {
+        let expected = Case::parse(&fs::read_to_string(path).unwrap());
+        if expected.interrupt.is_some() && !cfg!(unix) {
+            continue;
+        }
+        let actual = expected.run(&tree);
+        if actual == expected {
+            continue;
+        }
+        if bless {
+            fs::write(path, actual.render()).unwrap();
+        } else {
+            failures.push(format!("{}:\n--- expected\n{}--- actual\n{}", path.display(), expected.render(), actual.render()));
+        }
+    }
+    assert!(failures.is_empty(), "{} of {} cases differ\n\n{}", failures.len(), paths.len(), failures.join("\n"));
+}
+
+/// The compiled patterns the daemon keeps are bounded; it takes more requests than a case file should hold to see
+#[test]
+fn daemon_matcher_cache_starts_over() {
+    let tree = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("tree");
+    // one pattern more than are kept
+    let requests = (0..=MAX_CACHED_MATCHERS).map(|number| format!("pattern{} poem.txt\n", number)).collect();
+    let case = Case { args: String::from("--daemon --debug"), stdin: Some(requests), ..Case::default() };
+    let actual = case.run(&tree);
+    assert_eq!(actual.status, 0, "{}", actual.stderr);
+    assert_eq!(actual.stdout.matches("\"type\":\"done\"").count(), MAX_CACHED_MATCHERS + 1);
+    assert_eq!(actual.stderr.matches("matcher cache is full").count(), 1, "{}", actual.stderr);
+}
+
+/// The counts of `--heading-counts` are those `-c` prints, for every input with matches. Binary inputs are searched
+/// as text, they have no heading otherwise.
+#[test]
+fn heading_counts_agree_with_count() {
+    let tree = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("tree");
+    for pattern in ["o", "fox", "e", "TODO", "^$", "."] {
+        let run = |args: &str| {
+            let case = Case { args: format!("{} -a -r '{}' .", args, pattern), ..Case::default() };
+            let actual = case.run(&tree);
+            assert!(actual.status < 2, "{}: {}", case.args, actual.stderr);
+            actual.stdout
+        };
+        let mut headed: Vec<(String, String)> = run("--heading --heading-counts")
+            .lines()
+            .filter_map(|line| line.strip_suffix(" matching lines)").or_else(|| line.strip_suffix(" matching line)")))
+            .filter_map(|heading| heading.rsplit_once(" ("))
+            .map(|(path, count)| (String::from(path), String::from(count)))
+            .collect();
+        let mut counted: Vec<(String, String)> = run("-c")
+            .lines()
+            .filter_map(|line| line.rsplit_once(':'))
+            .filter(|(_, count)| *count != "0")
+            .map(|(path, count)| (String::from(path), String::from(count)))
+            .collect();
+        headed.sort();
+        counted.sort();
+        assert_eq!(headed, counted, "pattern {:?}", pattern);
+    }
+}
+
+/// What is added to a watched tree while `--watch` runs is searched once: lines appended to a file, and files
+/// created in a directory walked, but not what --include leaves out
+#[cfg(unix)]
+#[test]
+fn watch_searches_what_is_added() {
+    let tree = env::temp_dir().join(format!("rgrep-watch-{}", std::process::id()));
+    fs::create_dir_all(tree.join("sub")).unwrap();
+    fs::write(tree.join("a.txt"), "fox one\n").unwrap();
+    fs::write(tree.join("b.md"), "fox md\n").unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_rgrep"))
+        .args(["--watch", "-r", "-n", "--include", "*.txt", "fox", "."])
+        .current_dir(&tree)
+        .env("RGREP_CONFIG", "")
+        .env_remove("RGREP_OPTIONS")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("rgrep starts");
+    std::thread::sleep(Duration::from_millis(500));
+    let append = |name: &str, text: &str| {
+        let mut file = fs::OpenOptions::new().append(true).create(true).open(tree.join(name)).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    };
+    append("a.txt", "fox two\nno fox yet");
+    append("b.md", "fox md again\n");
+    append("sub/c.txt", "fox sub\n");
+    std::thread::sleep(Duration::from_millis(500));
+    append("a.txt", "\n");
+    std::thread::sleep(Duration::from_millis(500));
+    let killed = Command::new("kill").arg("-INT").arg(child.id().to_string()).status();
+    assert!(killed.is_ok_and(|status| status.success()), "kill -INT {} failed", child.id());
+    let output = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&tree).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(130), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(stdout, "./a.txt:1:fox one\n./a.txt:2:fox two\n./sub/c.txt:1:fox sub\n./a.txt:3:no fox yet\n");
+}
+
+/// --write-replace rewrites a copy of a fixture: with the replacements, a `.bak` of the original with --backup and
+/// the permissions it had, and with --max-shown or --stop-on-nonmatch, only the matches that were reported
+#[test]
+fn write_replace_rewrites_files() {
+    let tree = env::temp_dir().join(format!("rgrep-write-replace-{}", std::process::id()));
+    fs::create_dir_all(&tree).unwrap();
+    let poem = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/tree/poem.txt")).unwrap();
+    let run = |args: &str| {
+        fs::write(tree.join("poem.txt"), &poem).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(tree.join("poem.txt"), fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let case = Case { args: format!("--write-replace {} poem.txt", args), ..Case::default() };
+        let actual = case.run(&tree);
+        assert_eq!(actual.status, 0, "{}: {}", case.args, actual.stderr);
+        fs::read_to_string(tree.join("poem.txt")).unwrap()
+    };
+
+    assert_eq!(run("--replace slow quick"), poem.replace("quick", "slow"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(tree.join("poem.txt")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+    assert!(!tree.join("poem.txt.bak").exists());
+    assert_eq!(run("--backup --replace slow quick"), poem.replace("quick", "slow"));
+    assert_eq!(fs::read_to_string(tree.join("poem.txt.bak")).unwrap(), poem);
+    assert_eq!(run("--max-shown 1 --replace slow quick"), poem.replacen("quick", "slow", 1));
+    let (reported, rest) = poem.split_at(poem.find("the lazy").unwrap());
+    assert_eq!(run("--stop-on-nonmatch --replace U u"), reported.replace('u', "U") + rest);
+    fs::remove_dir_all(&tree).unwrap();
+}
+
+/// A run with --checkpoint that could not search everything is gone on with by the next one with the same
+/// patterns, which only searches what is left, and refused by one with others
+#[test]
+fn checkpoint_resumes_a_stopped_run() {
+    let tree = env::temp_dir().join(format!("rgrep-checkpoint-{}", std::process::id()));
+    fs::create_dir_all(&tree).unwrap();
+    fs::write(tree.join("a.txt"), "fox a\n").unwrap();
+    fs::write(tree.join("c.txt"), "no match\n").unwrap();
+    let run = |args: &str| {
+        let case = Case { args: format!("--checkpoint scan {} a.txt b.txt c.txt", args), ..Case::default() };
+        case.run(&tree)
+    };
+
+    // b.txt is not there yet, so the run is stopped short of searching everything
+    let stopped = run("fox");
+    assert_eq!((stopped.status, stopped.stdout.as_str()), (2, "a.txt:fox a\n"), "{}", stopped.stderr);
+    assert!(tree.join("scan").exists());
+    fs::write(tree.join("b.txt"), "fox b\n").unwrap();
+    let refused = run("-i fox");
+    assert_eq!(refused.status, 2);
+    assert!(refused.stderr.contains("other patterns or options"), "{}", refused.stderr);
+    let resumed = run("fox");
+    assert_eq!((resumed.status, resumed.stdout.as_str()), (0, "b.txt:fox b\n"), "{}", resumed.stderr);
+    assert!(!tree.join("scan").exists());
+    fs::remove_dir_all(&tree).unwrap();
+}
@@ -0,0 +1,4 @@
+// A fox in a comment
+fn fox<'a>(name: &'a str) -> String {
+    format!("a fox named {}", name) // the fox's name
+}
@@ -0,0 +1,2 @@
+// the fox app
+console.log("fox");
@@ -0,0 +1,394 @@
+//! Property tests for the hand-written parts: the argument parser, the regex engine and the searcher get
+//! random command lines, patterns and inputs, must not panic, and what they report has to hold up.
+//!
+//! Cases come from a fixed seed, so a failure happens again on the next run. `RGREP_PROPTEST_CASES` sets how
+//! many cases each property gets and `RGREP_PROPTEST_SEED` picks other ones. The fuzz targets in `fuzz/` check
+//! the same properties with coverage guided inputs.
+
+use std::env;
+
+use rgrep::args::{CommandArgs, OptionKind, CATEGORIES};
+use rgrep::glob::Glob;
+use rgrep::matcher::regex::{self, RegexMatcher};
+use rgrep::matcher::posix::{self, Syntax};
+use rgrep::matcher::{self, Engine, LiteralMatcher, Matcher, MatcherOptions, MatchesPerLine};
+use rgrep::search::{BinaryDetection, Input, SearcherBuilder};
+
+/// xorshift64*, good enough to spread cases around and needs no dependency
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    fn string(&mut self, alphabet: &[&str], max_len: usize) -> String {
+        (0..self.below(max_len + 1)).map(|_| *self.pick(alphabet)).collect()
+    }
+
+    fn bytes(&mut self, alphabet: &[u8], max_len: usize) -> Vec<u8> {
+        (0..self.below(max_len + 1)).map(|_| *self.pick(alphabet)).collect()
+    }
+}
+
+/// Runs `property` for every case, with the case number in the panic message of a failure
+fn check(name: &str, property: impl Fn(&mut Rng)) {
+    let cases = env::var("RGREP_PROPTEST_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(256);
+    let seed = env::var("RGREP_PROPTEST_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or(0x5eed);
+    for case in 0..cases {
+        // xorshift never leaves zero, so the seed is mixed with the case number instead of used as is
+        let mut rng = Rng((seed ^ (case as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)) | 1);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| property(&mut rng)));
+        if let Err(panic) = result {
+            eprintln!("property {} failed at case {} with seed {}", name, case, seed);
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+const PATTERN_ALPHABET: &[&str] = &[
+    "a", "b", "c", "A", "é", ".", "*", "+", "?", "|", "(", ")", "(?:", "[", "]", "[^", "-", "^", "$", "\\",
+    "\\d", "\\w", "\\s", "\\b", "\\B", "\\A", "\\z", "\\n", "{", "}", "{2}", "{1,3}", "{0,}", "(?i)", "(?P<n>",
+    "[=", "[.", "=]", ".]",
+];
+
+const HAYSTACK_ALPHABET: &[u8] = b"abcABC \n\r\t0\x00\xc3\xa9\xff";
+
+#[test]
+fn parser_does_not_panic() {
+    // options that run right away print and exit the process, so they are left out
+    let mut options = Vec::new();
+    for option in CATEGORIES.iter().flat_map(|category| category.options.iter()) {
+        if let OptionKind::Action(_) = option.kind {
+            continue;
+        }
+        options.push(option);
+    }
+    let values = ["", "0", "1", "-1", "99999999999", "x", "=", "-", "--", "utf-16le", "binary", "trace"];
+
+    check("parser_does_not_panic", |rng| {
+        let mut args = vec![String::from("rgrep")];
+        for _ in 0..rng.below(8) {
+            let option = rng.pick(&options);
+            let value = if !option.possible_values.is_empty() && rng.chance(70) {
+                *rng.pick(option.possible_values)
+            } else {
+                *rng.pick(&values)
+            };
+            match rng.below(7) {
+                0 if !option.short.is_empty() => args.push(format!("-{}", option.short)),
+                1 => args.push(format!("--no-{}", option.long)),
+                2 => args.push(format!("--{}={}", option.long, value)),
+                3 => args.push(String::from(value)),
+                4 => args.push(format!("--{}", rng.string(&["a", "-", "e", "x"], 6))),
+                _ => args.push(format!("--{}", option.long)),
+            }
+        }
+        if let Ok(command_args) = CommandArgs::parse_from(args.clone()) {
+            assert!(command_args.patterns.len() <= args.len(), "{:?}", args);
+        }
+    });
+}
+
+#[test]
+fn parse_errors_point_at_their_position() {
+    check("parse_errors_point_at_their_position", |rng| {
+        // past `u16::MAX` too, the largest width `format!` takes
+        let prefix = "a".repeat(rng.below(100_000));
+        let error = RegexMatcher::new(&format!("{})", prefix), false).err().expect("an unopened group is an error");
+        let message = error.to_string();
+        let marker = message.lines().nth(2).expect("the error has a marker line");
+        assert_eq!(marker.find('^'), Some("    ".len() + prefix.len()));
+    });
+}
+
+#[test]
+fn regex_matches_are_consistent() {
+    check("regex_matches_are_consistent", |rng| {
+        let pattern = rng.string(PATTERN_ALPHABET, 8);
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        let matcher = match RegexMatcher::new(&pattern, rng.chance(30)) {
+            Ok(matcher) => matcher,
+            Err(_) => return,
+        };
+        let context = format!("pattern {:?} haystack {:?}", pattern, haystack);
+        let at = rng.below(haystack.len() + 1);
+        if let Some(found) = matcher.find_at(&haystack, at) {
+            assert!(at <= found.start && found.start <= found.end && found.end <= haystack.len(), "{}", context);
+            let captures = matcher.captures_at(&haystack, at).expect(&context);
+            assert_eq!(captures.get(0), Some(found), "{}", context);
+            for group in 1..captures.len() {
+                if let Some(span) = captures.get(group) {
+                    assert!(span.start <= span.end && span.end <= haystack.len(), "{}", context);
+                }
+            }
+        } else {
+            assert!(matcher.captures_at(&haystack, at).is_none(), "{}", context);
+        }
+        assert_eq!(matcher.is_match(&haystack), matcher.find(&haystack).is_some(), "{}", context);
+
+        let mut previous_end = 0;
+        for found in matcher.find_iter(&haystack) {
+            assert!(found.start >= previous_end, "overlapping matches, {}", context);
+            previous_end = found.end;
+        }
+    });
+}
+
+#[test]
+fn backtracker_and_pike_vm_agree() {
+    check("backtracker_and_pike_vm_agree", |rng| {
+        let pattern = rng.string(PATTERN_ALPHABET, 8);
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        let ignore_case = rng.chance(30);
+        let pike_vm = match RegexMatcher::with_engine(&pattern, ignore_case, Engine::PikeVm) {
+            Ok(matcher) => matcher,
+            Err(_) => return,
+        };
+        let backtracker = RegexMatcher::with_engine(&pattern, ignore_case, Engine::Backtrack).unwrap();
+        let context = format!("pattern {:?} haystack {:?}", pattern, haystack);
+        for at in 0..=haystack.len() {
+            let captures = |matcher: &RegexMatcher| {
+                let captures = matcher.captures_at(&haystack, at)?;
+                Some((0..captures.len()).map(|group| captures.get(group)).collect::<Vec<_>>())
+            };
+            assert_eq!(captures(&backtracker), captures(&pike_vm), "at {}, {}", at, context);
+        }
+    });
+}
+
+#[test]
+fn backreferences_match_what_their_group_did() {
+    check("backreferences_match_what_their_group_did", |rng| {
+        let group = rng.string(&["a", "b", "A", ".", "[ab]", "a*", "b?", "é"], 3);
+        let ignore_case = rng.chance(30);
+        let pattern = format!("({})-\\1", group);
+        let matcher = RegexMatcher::new(&pattern, ignore_case).unwrap();
+        let haystack = rng.bytes(b"abA-\xc3\xa9", 12);
+        let context = format!("pattern {:?} haystack {:?}", pattern, haystack);
+        if let Some(captures) = matcher.captures(&haystack) {
+            let (group, whole) = (captures.get(1).expect(&context), captures.get(0).expect(&context));
+            let again = &haystack[group.end + 1..whole.end];
+            if ignore_case {
+                let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).to_lowercase();
+                assert_eq!(text(again), text(&haystack[group.start..group.end]), "{}", context);
+            } else {
+                assert_eq!(again, &haystack[group.start..group.end], "{}", context);
+            }
+        }
+    });
+}
+
+#[test]
+fn replacing_matches_with_themselves_changes_nothing() {
+    check("replacing_matches_with_themselves_changes_nothing", |rng| {
+        let pattern = rng.string(PATTERN_ALPHABET, 6);
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        let matcher = match RegexMatcher::new(&pattern, rng.chance(30)) {
+            Ok(matcher) => matcher,
+            Err(_) => return,
+        };
+        let mut replaced = Vec::new();
+        matcher::replace_all(&matcher, &haystack, "${0}", &mut replaced);
+        assert_eq!(replaced, haystack, "pattern {:?}", pattern);
+    });
+}
+
+#[test]
+fn literal_and_regex_agree() {
+    check("literal_and_regex_agree", |rng| {
+        let needle = rng.string(&["a", "b", "A", ".", "*", "\n", "é"], 4);
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        let ignore_case = needle.is_ascii() && rng.chance(50);
+        let literal = LiteralMatcher::new(needle.as_bytes(), ignore_case);
+        let regex = RegexMatcher::new(&regex::escape(&needle), ignore_case).unwrap();
+        for at in 0..=haystack.len() {
+            assert_eq!(
+                literal.find_at(&haystack, at),
+                regex.find_at(&haystack, at),
+                "needle {:?} haystack {:?} at {}",
+                needle,
+                haystack,
+                at
+            );
+        }
+    });
+}
+
+#[test]
+fn patterns_built_each_find_the_same_matches() {
+    check("patterns_built_each_find_the_same_matches", |rng| {
+        let patterns: Vec<String> =
+            (0..1 + rng.below(3)).map(|_| rng.string(&["a", "b", "ab", ".", "*", "é"], 3)).collect();
+        let options =
+            MatcherOptions { fixed_strings: rng.chance(30), ignore_case: rng.chance(30), ..MatcherOptions::default() };
+        let (together, each) = match (matcher::build(&patterns, &options), matcher::build_each(&patterns, &options)) {
+            (Ok(together), Ok(each)) => (together, matcher::MultiMatcher::new(each)),
+            _ => return,
+        };
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        for at in 0..=haystack.len() {
+            let found = each.find_pattern_at(&haystack, at);
+            assert_eq!(
+                together.find_at(&haystack, at),
+                found.map(|(_, found)| found),
+                "patterns {:?} haystack {:?} at {}",
+                patterns,
+                haystack,
+                at
+            );
+        }
+    });
+}
+
+#[test]
+fn first_matches_per_line_are_the_first_matches() {
+    check("first_matches_per_line_are_the_first_matches", |rng| {
+        let patterns = vec![rng.string(&["a", "b", "ab", ".", "*", "\\n", "é"], 3)];
+        let all = MatcherOptions::default();
+        let first = MatcherOptions { matches: MatchesPerLine::First, ..MatcherOptions::default() };
+        let (all, first) = match (matcher::build(&patterns, &all), matcher::build(&patterns, &first)) {
+            (Ok(all), Ok(first)) => (all, first),
+            _ => return,
+        };
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        // sinks look for the matches in every line on its own
+        for line in haystack.split(|byte| *byte == b'\n') {
+            let found: Vec<matcher::Match> = matcher::find_iter(first.as_ref(), line).collect();
+            let expected: Vec<matcher::Match> = all.find(line).into_iter().collect();
+            assert_eq!(found, expected, "patterns {:?} line {:?}", patterns, line);
+        }
+        assert_eq!(first.find(&haystack), all.find(&haystack), "patterns {:?} haystack {:?}", patterns, haystack);
+    });
+}
+
+#[test]
+fn builtin_patterns_match_their_examples() {
+    for builtin in matcher::BUILTIN_PATTERNS {
+        let regex = RegexMatcher::new(builtin.pattern, false).unwrap();
+        let example = builtin.example.as_bytes();
+        assert_eq!(regex.find(example), Some(matcher::Match::new(0, example.len())), "{}", builtin.name);
+    }
+}
+
+#[test]
+fn extended_regexes_mean_what_they_do_in_the_engine() {
+    check("extended_regexes_mean_what_they_do_in_the_engine", |rng| {
+        // the syntax both have, with `{` taken literally where it does not start a count in either
+        let alphabet = ["a", "b", ".", "*", "+", "?", "|", "(", ")", "{2}", "{1,}", "[ab]", "[^a]", "é"];
+        let pattern = rng.string(&alphabet, 8);
+        // lazy repetition and special groups in the engine, a repetition repeated again in extended regexes
+        if ["*?", "+?", "??", "}?", "(?"].iter().any(|engine_only| pattern.contains(engine_only)) {
+            return;
+        }
+        let haystack = rng.bytes(HAYSTACK_ALPHABET, 24);
+        let ignore_case = rng.chance(30);
+        let engine = match RegexMatcher::new(&pattern, ignore_case) {
+            Ok(engine) => engine,
+            Err(_) => return,
+        };
+        let translated = posix::translate(&pattern, Syntax::Extended).expect("what the engine compiles translates");
+        let extended = RegexMatcher::new(&translated, ignore_case).expect("translations compile");
+        let context = format!("pattern {:?} translated {:?} haystack {:?}", pattern, translated, haystack);
+        for at in 0..=haystack.len() {
+            assert_eq!(extended.find_at(&haystack, at), engine.find_at(&haystack, at), "at {}, {}", at, context);
+        }
+    });
+}
+
+#[test]
+fn posix_classes_are_their_char_properties() {
+    type Property = fn(char) -> bool;
+    let classes: [(&str, Property); 6] = [
+        ("alpha", char::is_alphabetic),
+        ("alnum", char::is_alphanumeric),
+        ("upper", char::is_uppercase),
+        ("space", char::is_whitespace),
+        ("cntrl", char::is_control),
+        ("xdigit", |c| c.is_ascii_hexdigit()),
+    ];
+    check("posix_classes_are_their_char_properties", |rng| {
+        let (name, property) = rng.pick(&classes);
+        let chars = ['a', 'F', 'g', 'Z', 'é', 'É', 'ß', '7', '٣', ' ', '\t', '\u{a0}', '\0', '\x7f', '!', '_', '€'];
+        let c = *rng.pick(&chars);
+        let negated = rng.chance(50);
+        let pattern = format!("[{}[:{}:]]", if negated { "^" } else { "" }, name);
+        let extended = posix::translate(&pattern, Syntax::Extended).unwrap();
+        for pattern in [&pattern, &extended] {
+            let matcher = RegexMatcher::new(pattern, false).unwrap();
+            assert_eq!(matcher.is_match(c.to_string().as_bytes()), property(c) != negated, "{} {:?}", pattern, c);
+        }
+    });
+}
+
+#[test]
+fn globs_and_regexes_agree() {
+    check("globs_and_regexes_agree", |rng| {
+        let translations = [("a", "a"), ("B", "B"), ("?", "."), ("*", ".*"), ("[ab]", "[ab]"), ("[!a]", "[^a]")];
+        let parts: Vec<(&str, &str)> = (0..rng.below(5)).map(|_| *rng.pick(&translations)).collect();
+        let glob: String = parts.iter().map(|(glob, _)| *glob).collect();
+        let regex: String = parts.iter().map(|(_, regex)| *regex).collect();
+        let name = rng.string(&["a", "b", "A", "B", "é"], 5);
+        let ignore_case = rng.chance(50);
+        let regex = RegexMatcher::new(&format!("^{}$", regex), ignore_case).unwrap();
+        assert_eq!(
+            Glob::new(&glob, ignore_case).is_match(&name),
+            regex.is_match(name.as_bytes()),
+            "glob {:?} name {:?} ignoring case {}",
+            glob,
+            name,
+            ignore_case
+        );
+    });
+}
+
+#[test]
+fn searcher_reports_what_matches() {
+    check("searcher_reports_what_matches", |rng| {
+        let pattern = rng.string(&["a", "b", "c", ".", "*", "^", "$", "\\w", "|"], 4);
+        let multi_line = rng.chance(20);
+        let options =
+            MatcherOptions { fixed_strings: rng.chance(20), ignore_case: rng.chance(30), ..MatcherOptions::default() };
+        let matcher = match matcher::build(std::slice::from_ref(&pattern), &options) {
+            Ok(matcher) => matcher,
+            Err(_) => return,
+        };
+        let input = rng.bytes(HAYSTACK_ALPHABET, 64);
+        let searcher = SearcherBuilder::new()
+            .binary_detection(BinaryDetection::Text)
+            .multi_line(multi_line)
+            .context(rng.below(3))
+            .build();
+        let context = format!("pattern {:?} input {:?} multi-line {}", pattern, input, multi_line);
+
+        let mut previous_line = 0;
+        for found in searcher.matches(matcher.as_ref(), Input::Reader("input", Box::new(&input[..]))) {
+            let found = found.expect(&context);
+            assert!(found.line_number > previous_line, "{}", context);
+            previous_line = found.line_number;
+            let offset = found.offset as usize;
+            assert_eq!(&input[offset..offset + found.line.len()], &found.line[..], "{}", context);
+            if !multi_line {
+                assert!(matcher.is_match(&found.line), "line {} does not match, {}", found.line_number, context);
+            }
+            for span in &found.spans {
+                assert_eq!(matcher.find_at(&found.line, span.start), Some(*span), "{}", context);
+            }
+        }
+    });
+}